@@ -0,0 +1,222 @@
+//! Changed-path Bloom filters, which allow to learn quickly whether a path was possibly changed by a commit compared to its
+//! first parent without diffing their trees.
+//!
+//! The implementation matches the one of git, which stores one filter per commit in the `BIDX` and `BDAT` chunks of
+//! commit-graph files.
+use std::collections::BTreeSet;
+
+use bstr::{BStr, ByteSlice};
+
+/// The maximum amount of changed paths for which a filter is computed. Commits with more changes receive a filter which
+/// claims to contain every path.
+pub const MAX_CHANGED_PATHS: usize = 512;
+
+const BITS_PER_WORD: u64 = 8;
+const SEED0: u32 = 0x293a_e76f;
+const SEED1: u32 = 0x7e64_6e2c;
+
+/// The parameters used to compute all filters of a commit-graph file, as stored in the header of its `BDAT` chunk.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Settings {
+    /// The version of the murmur3 hash to use, either 1 or 2.
+    ///
+    /// Version 1 is affected by a bug in git which treats bytes of paths as signed, but it's what git writes by default.
+    pub hash_version: u32,
+    /// The amount of hashes to compute per path, and thus the amount of bits set for each path.
+    pub num_hashes: u32,
+    /// The amount of bits in a filter per changed path.
+    pub bits_per_entry: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            hash_version: 1,
+            num_hashes: 7,
+            bits_per_entry: 10,
+        }
+    }
+}
+
+/// The hashes of a path, used to add it to a [`Filter`] or to check whether a filter may contain it.
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+pub struct Key {
+    hashes: Vec<u32>,
+}
+
+impl Key {
+    /// Compute the key for the slash-separated `path` relative to the repository root, without leading or trailing slashes.
+    pub fn new(path: &BStr, settings: &Settings) -> Self {
+        let hash0 = murmur3_seeded(SEED0, path, settings.hash_version);
+        let hash1 = murmur3_seeded(SEED1, path, settings.hash_version);
+        Key {
+            hashes: (0..settings.num_hashes)
+                .map(|i| hash0.wrapping_add(i.wrapping_mul(hash1)))
+                .collect(),
+        }
+    }
+}
+
+/// A borrowed Bloom filter of a single commit, as stored in a commit-graph file.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy)]
+pub struct FilterRef<'a> {
+    data: &'a [u8],
+}
+
+/// An owned Bloom filter of a single commit, usually created to be written into a commit-graph file.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Filter {
+    data: Vec<u8>,
+}
+
+impl<'a> FilterRef<'a> {
+    /// Create a filter from its raw `data`.
+    pub fn from_bytes(data: &'a [u8]) -> Self {
+        FilterRef { data }
+    }
+
+    /// Returns the raw bytes of this filter.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns true if this filter wasn't computed, which makes it unusable for queries.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns false if the path of `key` definitely wasn't changed, or true if it may have been changed.
+    ///
+    /// Note that [empty][FilterRef::is_empty()] filters claim to contain nothing.
+    pub fn contains(&self, key: &Key) -> bool {
+        if self.data.is_empty() {
+            return false;
+        }
+        let num_bits = self.data.len() as u64 * BITS_PER_WORD;
+        key.hashes.iter().all(|hash| {
+            let bit = u64::from(*hash) % num_bits;
+            self.data[(bit / BITS_PER_WORD) as usize] & bitmask(bit) != 0
+        })
+    }
+
+    /// Copy this filter into an owned version.
+    pub fn to_owned(&self) -> Filter {
+        Filter {
+            data: self.data.to_vec(),
+        }
+    }
+}
+
+impl Filter {
+    /// Compute the filter for a commit which changed all `paths` compared to its first parent, or to the empty tree if it
+    /// has no parents.
+    ///
+    /// Each path is slash-separated and relative to the repository root. All of its leading directories are added as well,
+    /// so it's sufficient to only pass the paths of changed files.
+    ///
+    /// The filter is computed with the [default settings][Settings::default()] as these are the ones written into
+    /// commit-graph files.
+    pub fn from_changed_paths(paths: impl IntoIterator<Item = impl AsRef<BStr>>) -> Self {
+        let settings = Settings::default();
+        let mut num_changes = 0;
+        let mut all_paths = BTreeSet::new();
+        for path in paths {
+            num_changes += 1;
+            let mut path: &[u8] = path.as_ref();
+            while let Some((b'/', rest)) = path.split_last() {
+                path = rest;
+            }
+            loop {
+                all_paths.insert(path.to_owned());
+                match path.rfind_byte(b'/') {
+                    Some(pos) => path = &path[..pos],
+                    None => break,
+                }
+            }
+        }
+        if num_changes > MAX_CHANGED_PATHS {
+            return Filter::too_large();
+        }
+        if all_paths.is_empty() {
+            return Filter { data: vec![0] };
+        }
+
+        let num_bits = all_paths.len() as u64 * u64::from(settings.bits_per_entry);
+        let mut data = vec![0u8; ((num_bits + BITS_PER_WORD - 1) / BITS_PER_WORD) as usize];
+        let num_bits = data.len() as u64 * BITS_PER_WORD;
+        for path in all_paths {
+            for hash in Key::new(path.as_bstr(), &settings).hashes {
+                let bit = u64::from(hash) % num_bits;
+                data[(bit / BITS_PER_WORD) as usize] |= bitmask(bit);
+            }
+        }
+        Filter { data }
+    }
+
+    /// A filter for commits with too many changes, which claims to contain every path.
+    pub fn too_large() -> Self {
+        Filter { data: vec![0xff] }
+    }
+
+    /// Return a borrowed version of this filter for queries.
+    pub fn to_ref(&self) -> FilterRef<'_> {
+        FilterRef { data: &self.data }
+    }
+
+    /// Returns the raw bytes of this filter.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+fn bitmask(bit: u64) -> u8 {
+    1 << (bit & (BITS_PER_WORD - 1))
+}
+
+/// The 32-bit murmur3 hash as implemented by git, which treats all bytes as signed in `version` 1.
+fn murmur3_seeded(seed: u32, data: &[u8], version: u32) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+    const R1: u32 = 15;
+    const R2: u32 = 13;
+    const M: u32 = 5;
+    const N: u32 = 0xe654_6b64;
+
+    let byte = |b: u8| -> u32 {
+        if version == 1 {
+            b as i8 as i32 as u32
+        } else {
+            u32::from(b)
+        }
+    };
+    let mut hash = seed;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let k = byte(chunk[0]) | (byte(chunk[1]) << 8) | (byte(chunk[2]) << 16) | (byte(chunk[3]) << 24);
+        hash ^= k.wrapping_mul(C1).rotate_left(R1).wrapping_mul(C2);
+        hash = hash.rotate_left(R2).wrapping_mul(M).wrapping_add(N);
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        let mut k = 0;
+        if tail.len() == 3 {
+            k ^= byte(tail[2]) << 16;
+        }
+        if tail.len() >= 2 {
+            k ^= byte(tail[1]) << 8;
+        }
+        k ^= byte(tail[0]);
+        hash ^= k.wrapping_mul(C1).rotate_left(R1).wrapping_mul(C2);
+    }
+
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^= hash >> 16;
+    hash
+}
@@ -4,9 +4,13 @@ use std::{
     path::Path,
 };
 
+use byteorder::{BigEndian, ByteOrder};
 use git_hash::SIZE_OF_SHA1_DIGEST as SHA1_SIZE;
 
-use crate::file::{self, commit::Commit, File, COMMIT_DATA_ENTRY_SIZE};
+use crate::{
+    bloom,
+    file::{self, commit::Commit, File, COMMIT_DATA_ENTRY_SIZE, GENERATION_DATA_OVERFLOW_MASK},
+};
 
 /// Access
 impl File {
@@ -15,6 +19,12 @@ impl File {
         self.base_graph_count
     }
 
+    /// The settings used to compute the changed-path [Bloom filters][crate::bloom] of all commits in this file,
+    /// or `None` if this file doesn't have (usable) Bloom filters.
+    pub fn bloom_filter_settings(&self) -> Option<&bloom::Settings> {
+        self.bloom_filter_settings.as_ref()
+    }
+
     /// Returns the commit data for the commit located at the given lexigraphical position.
     ///
     /// `pos` must range from 0 to self.num_commits().
@@ -26,6 +36,11 @@ impl File {
         Commit::new(self, pos)
     }
 
+    /// Returns true if this file stores the corrected commit date of each of its commits, also known as generation number v2.
+    pub fn has_corrected_commit_dates(&self) -> bool {
+        self.generation_data_offset.is_some()
+    }
+
     /// The kind of hash used in this File.
     ///
     /// Note that it is always conforming to the hash used in the owning repository.
@@ -127,6 +142,48 @@ impl File {
         &self.data[start..start + COMMIT_DATA_ENTRY_SIZE]
     }
 
+    /// Returns the changed-path Bloom filter for the given commit, or `None` if there is none or if the index is corrupt.
+    pub(crate) fn bloom_filter_at(&self, pos: file::Position) -> Option<bloom::FilterRef<'_>> {
+        let index_offset = self.bloom_filter_index_offset?;
+        let data_range = self.bloom_filter_data_range.clone()?;
+        let pos: usize = pos
+            .0
+            .try_into()
+            .expect("an architecture able to hold 32 bits of integer");
+        let read_end = |pos: usize| -> usize {
+            let start = index_offset + pos * 4;
+            BigEndian::read_u32(&self.data[start..start + 4]) as usize
+        };
+        let start = if pos == 0 { 0 } else { read_end(pos - 1) };
+        let end = read_end(pos);
+        if start > end || end > data_range.len() {
+            return None;
+        }
+        Some(bloom::FilterRef::from_bytes(
+            &self.data[data_range.start + start..data_range.start + end],
+        ))
+    }
+
+    /// Returns the offset of the corrected commit date to the commit date of the given commit, or `None` if this file has no
+    /// generation data or if the commit refers to a non-existing overflow entry.
+    pub(crate) fn corrected_commit_date_offset_at(&self, pos: file::Position) -> Option<u64> {
+        let pos: usize = pos
+            .0
+            .try_into()
+            .expect("an architecture able to hold 32 bits of integer");
+        let start = self.generation_data_offset? + pos * 4;
+        let offset = BigEndian::read_u32(&self.data[start..start + 4]);
+        if offset & GENERATION_DATA_OVERFLOW_MASK == 0 {
+            return Some(u64::from(offset));
+        }
+        let overflow = self.generation_data_overflow_range.clone()?;
+        let start = overflow.start + (offset & !GENERATION_DATA_OVERFLOW_MASK) as usize * 8;
+        if start + 8 > overflow.end {
+            return None;
+        }
+        Some(BigEndian::read_u64(&self.data[start..start + 8]))
+    }
+
     /// Returns the byte slice for this file's entire Extra Edge List (EDGE) chunk.
     pub(crate) fn extra_edges_data(&self) -> Option<&[u8]> {
         Some(&self.data[self.extra_edges_list_range.clone()?])
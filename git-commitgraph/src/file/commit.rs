@@ -9,6 +9,7 @@ use byteorder::{BigEndian, ByteOrder};
 use git_hash::SIZE_OF_SHA1_DIGEST as SHA1_SIZE;
 
 use crate::{
+    bloom,
     file::{self, File, EXTENDED_EDGES_MASK, LAST_EXTENDED_EDGE_MASK, NO_PARENT},
     graph,
 };
@@ -53,6 +54,11 @@ impl<'a> Commit<'a> {
         }
     }
 
+    /// Returns the changed-path [Bloom filter][crate::bloom] of this commit, or `None` if the owning [File] has none.
+    pub fn bloom_filter(&self) -> Option<bloom::FilterRef<'a>> {
+        self.file.bloom_filter_at(self.pos)
+    }
+
     /// Returns the committer timestamp of this commit.
     ///
     /// The value is the number of seconds since 1970-01-01 00:00:00 UTC.
@@ -60,6 +66,18 @@ impl<'a> Commit<'a> {
         self.commit_timestamp
     }
 
+    /// Returns the corrected commit date of this commit, also known as generation number v2, or `None` if the owning [File]
+    /// doesn't store it.
+    ///
+    /// It's the committer timestamp of this commit unless that is not larger than the corrected commit date of all of its
+    /// parents, in which case it is one more than the largest of these. Unlike the [topological generation][Commit::generation()],
+    /// it stays useful for ordering commits by time even if clocks were skewed when they were created.
+    pub fn corrected_commit_date(&self) -> Option<u64> {
+        self.file
+            .corrected_commit_date_offset_at(self.pos)
+            .map(|offset| self.commit_timestamp.saturating_add(offset))
+    }
+
     /// Returns the generation number of this commit.
     ///
     /// Commits without parents have generation number 1. Commits with parents have a generation
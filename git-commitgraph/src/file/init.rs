@@ -9,10 +9,14 @@ use byteorder::{BigEndian, ByteOrder};
 use filebuffer::FileBuffer;
 use git_hash::SIZE_OF_SHA1_DIGEST as SHA1_SIZE;
 
-use crate::file::{
-    ChunkId, File, BASE_GRAPHS_LIST_CHUNK_ID, CHUNK_LOOKUP_SIZE, COMMIT_DATA_CHUNK_ID, COMMIT_DATA_ENTRY_SIZE,
-    EXTENDED_EDGES_LIST_CHUNK_ID, FAN_LEN, HEADER_LEN, OID_FAN_CHUNK_ID, OID_LOOKUP_CHUNK_ID, OID_LOOKUP_ENTRY_SIZE,
-    SENTINEL_CHUNK_ID, SIGNATURE,
+use crate::{
+    bloom,
+    file::{
+        ChunkId, File, BASE_GRAPHS_LIST_CHUNK_ID, BLOOM_FILTER_DATA_CHUNK_ID, BLOOM_FILTER_DATA_HEADER_LEN,
+        BLOOM_FILTER_INDEX_CHUNK_ID, CHUNK_LOOKUP_SIZE, COMMIT_DATA_CHUNK_ID, COMMIT_DATA_ENTRY_SIZE,
+        EXTENDED_EDGES_LIST_CHUNK_ID, FAN_LEN, GENERATION_DATA_CHUNK_ID, GENERATION_DATA_OVERFLOW_CHUNK_ID, HEADER_LEN,
+        OID_FAN_CHUNK_ID, OID_LOOKUP_CHUNK_ID, OID_LOOKUP_ENTRY_SIZE, SENTINEL_CHUNK_ID, SIGNATURE,
+    },
 };
 
 /// The error used in [`File::at()`].
@@ -122,10 +126,15 @@ impl TryFrom<&Path> for File {
         }
 
         let mut base_graphs_list_offset: Option<usize> = None;
+        let mut bloom_filter_index_offset: Option<usize> = None;
+        let mut bloom_filter_index_count = 0u32;
+        let mut bloom_filter_data: Option<(Range<usize>, bloom::Settings)> = None;
         let mut commit_data_offset: Option<usize> = None;
         let mut commit_data_count = 0u32;
         let mut extra_edges_list_range: Option<Range<usize>> = None;
         let mut fan_offset: Option<usize> = None;
+        let mut generation_data: Option<Range<usize>> = None;
+        let mut generation_data_overflow: Option<Range<usize>> = None;
         let mut oid_lookup_offset: Option<usize> = None;
         let mut oid_lookup_count = 0u32;
 
@@ -188,6 +197,43 @@ impl TryFrom<&Path> for File {
                     }
                     base_graphs_list_offset = Some(chunk_offset);
                 }
+                BLOOM_FILTER_INDEX_CHUNK_ID => {
+                    if bloom_filter_index_offset.is_some() {
+                        return Err(Error::DuplicateChunk(chunk_id));
+                    }
+                    if chunk_size % 4 != 0 {
+                        return Err(Error::InvalidChunkSize {
+                            id: chunk_id,
+                            msg: format!("chunk size {} is not a multiple of 4", chunk_size),
+                        });
+                    }
+                    bloom_filter_index_offset = Some(chunk_offset);
+                    bloom_filter_index_count = (chunk_size / 4)
+                        .try_into()
+                        .expect("number of commits in BIDX chunk to fit in 32 bits");
+                }
+                BLOOM_FILTER_DATA_CHUNK_ID => {
+                    if bloom_filter_data.is_some() {
+                        return Err(Error::DuplicateChunk(chunk_id));
+                    }
+                    if chunk_size < BLOOM_FILTER_DATA_HEADER_LEN {
+                        return Err(Error::InvalidChunkSize {
+                            id: chunk_id,
+                            msg: format!(
+                                "chunk size {} is too small to hold the {} bytes header",
+                                chunk_size, BLOOM_FILTER_DATA_HEADER_LEN
+                            ),
+                        });
+                    }
+                    let header = &data[chunk_offset..chunk_offset + BLOOM_FILTER_DATA_HEADER_LEN];
+                    let settings = bloom::Settings {
+                        hash_version: BigEndian::read_u32(&header[..4]),
+                        num_hashes: BigEndian::read_u32(&header[4..8]),
+                        bits_per_entry: BigEndian::read_u32(&header[8..]),
+                    };
+                    bloom_filter_data =
+                        Some((chunk_offset + BLOOM_FILTER_DATA_HEADER_LEN..next_chunk_offset, settings));
+                }
                 COMMIT_DATA_CHUNK_ID => {
                     if commit_data_offset.is_some() {
                         return Err(Error::DuplicateChunk(chunk_id));
@@ -216,6 +262,22 @@ impl TryFrom<&Path> for File {
                         end: next_chunk_offset,
                     })
                 }
+                GENERATION_DATA_CHUNK_ID | GENERATION_DATA_OVERFLOW_CHUNK_ID => {
+                    let (range, entry_size) = match chunk_id {
+                        GENERATION_DATA_CHUNK_ID => (&mut generation_data, 4),
+                        _ => (&mut generation_data_overflow, 8),
+                    };
+                    if range.is_some() {
+                        return Err(Error::DuplicateChunk(chunk_id));
+                    }
+                    if chunk_size % entry_size != 0 {
+                        return Err(Error::InvalidChunkSize {
+                            id: chunk_id,
+                            msg: format!("chunk size {} is not a multiple of {}", chunk_size, entry_size),
+                        });
+                    }
+                    *range = Some(chunk_offset..next_chunk_offset);
+                }
                 OID_FAN_CHUNK_ID => {
                     if fan_offset.is_some() {
                         return Err(Error::DuplicateChunk(chunk_id));
@@ -301,13 +363,71 @@ impl TryFrom<&Path> for File {
                 chunk2_commits: commit_data_count,
             });
         }
+
+        let generation_data_offset = match generation_data {
+            Some(range) => {
+                let generation_data_count = (range.len() / 4) as u32;
+                if generation_data_count != fan[255] {
+                    return Err(Error::CommitCountMismatch {
+                        chunk1_id: OID_FAN_CHUNK_ID,
+                        chunk1_commits: fan[255],
+                        chunk2_id: GENERATION_DATA_CHUNK_ID,
+                        chunk2_commits: generation_data_count,
+                    });
+                }
+                Some(range.start)
+            }
+            None => None,
+        };
+
+        // Like git, ignore Bloom filters we can't use instead of failing.
+        let (bloom_filter_index_offset, bloom_filter_data_range, bloom_filter_settings) =
+            match (bloom_filter_index_offset, bloom_filter_data) {
+                (Some(index_offset), Some((data_range, settings)))
+                    if matches!(settings.hash_version, 1 | 2)
+                        && settings.num_hashes > 0
+                        && settings.bits_per_entry > 0 =>
+                {
+                    if bloom_filter_index_count != fan[255] {
+                        return Err(Error::CommitCountMismatch {
+                            chunk1_id: OID_FAN_CHUNK_ID,
+                            chunk1_commits: fan[255],
+                            chunk2_id: BLOOM_FILTER_INDEX_CHUNK_ID,
+                            chunk2_commits: bloom_filter_index_count,
+                        });
+                    }
+                    let last_filter_end = match fan[255] {
+                        0 => 0,
+                        num_commits => {
+                            let ofs = index_offset + (num_commits as usize - 1) * 4;
+                            BigEndian::read_u32(&data[ofs..ofs + 4]) as usize
+                        }
+                    };
+                    if last_filter_end > data_range.len() {
+                        return Err(Error::Corrupt(format!(
+                            "Commit-graph {:?} chunk refers to {} bytes of filters, but {:?} chunk only has {}",
+                            BLOOM_FILTER_INDEX_CHUNK_ID.as_bstr(),
+                            last_filter_end,
+                            BLOOM_FILTER_DATA_CHUNK_ID.as_bstr(),
+                            data_range.len()
+                        )));
+                    }
+                    (Some(index_offset), Some(data_range), Some(settings))
+                }
+                _ => (None, None, None),
+            };
         Ok(File {
             base_graph_count,
             base_graphs_list_offset,
+            bloom_filter_index_offset,
+            bloom_filter_data_range,
+            bloom_filter_settings,
             commit_data_offset,
             data,
             extra_edges_list_range,
             fan,
+            generation_data_offset,
+            generation_data_overflow_range: generation_data_overflow,
             oid_lookup_offset,
             path: path.to_owned(),
         })
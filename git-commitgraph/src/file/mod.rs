@@ -15,8 +15,10 @@ mod access;
 pub mod commit;
 mod init;
 pub mod verify;
+pub mod write;
 
 const CHUNK_LOOKUP_SIZE: usize = 12;
+const BLOOM_FILTER_DATA_HEADER_LEN: usize = 12;
 const COMMIT_DATA_ENTRY_SIZE: usize = SHA1_SIZE + 16;
 const FAN_LEN: usize = 256;
 const HEADER_LEN: usize = 8;
@@ -26,8 +28,13 @@ const SIGNATURE: &[u8] = b"CGPH";
 
 type ChunkId = [u8; 4];
 const BASE_GRAPHS_LIST_CHUNK_ID: ChunkId = *b"BASE";
+const BLOOM_FILTER_DATA_CHUNK_ID: ChunkId = *b"BDAT";
+const BLOOM_FILTER_INDEX_CHUNK_ID: ChunkId = *b"BIDX";
 const COMMIT_DATA_CHUNK_ID: ChunkId = *b"CDAT";
 const EXTENDED_EDGES_LIST_CHUNK_ID: ChunkId = *b"EDGE";
+// Like git, ignore the `GDAT` and `GDOV` chunks written by git v2.31 to v2.35 as their corrected commit dates may be wrong.
+const GENERATION_DATA_CHUNK_ID: ChunkId = *b"GDA2";
+const GENERATION_DATA_OVERFLOW_CHUNK_ID: ChunkId = *b"GDO2";
 const OID_FAN_CHUNK_ID: ChunkId = *b"OIDF";
 const OID_LOOKUP_CHUNK_ID: ChunkId = *b"OIDL";
 const SENTINEL_CHUNK_ID: ChunkId = [0u8; 4];
@@ -37,6 +44,7 @@ const SENTINEL_CHUNK_ID: ChunkId = [0u8; 4];
 const NO_PARENT: u32 = 0x7000_0000;
 const EXTENDED_EDGES_MASK: u32 = 0x8000_0000;
 const LAST_EXTENDED_EDGE_MASK: u32 = 0x8000_0000;
+const GENERATION_DATA_OVERFLOW_MASK: u32 = 0x8000_0000;
 
 /// A single commit-graph file.
 ///
@@ -45,10 +53,15 @@ const LAST_EXTENDED_EDGE_MASK: u32 = 0x8000_0000;
 pub struct File {
     base_graph_count: u8,
     base_graphs_list_offset: Option<usize>,
+    bloom_filter_index_offset: Option<usize>,
+    bloom_filter_data_range: Option<Range<usize>>,
+    bloom_filter_settings: Option<crate::bloom::Settings>,
     commit_data_offset: usize,
     data: FileBuffer,
     extra_edges_list_range: Option<Range<usize>>,
     fan: [u32; FAN_LEN],
+    generation_data_offset: Option<usize>,
+    generation_data_overflow_range: Option<Range<usize>>,
     oid_lookup_offset: usize,
     path: PathBuf,
 }
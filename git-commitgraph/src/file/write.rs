@@ -0,0 +1,271 @@
+//! Write a single commit-graph file.
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    io::{self, Write},
+};
+
+use git_hash::{oid, ObjectId};
+
+use crate::{
+    bloom,
+    file::{
+        ChunkId, File, BASE_GRAPHS_LIST_CHUNK_ID, BLOOM_FILTER_DATA_CHUNK_ID, BLOOM_FILTER_DATA_HEADER_LEN,
+        BLOOM_FILTER_INDEX_CHUNK_ID, CHUNK_LOOKUP_SIZE, COMMIT_DATA_CHUNK_ID, COMMIT_DATA_ENTRY_SIZE,
+        EXTENDED_EDGES_LIST_CHUNK_ID, EXTENDED_EDGES_MASK, FAN_LEN, HEADER_LEN, LAST_EXTENDED_EDGE_MASK, NO_PARENT,
+        OID_FAN_CHUNK_ID, OID_LOOKUP_CHUNK_ID, OID_LOOKUP_ENTRY_SIZE, SENTINEL_CHUNK_ID, SIGNATURE,
+    },
+    Graph, GENERATION_NUMBER_MAX, MAX_COMMITS,
+};
+
+const VERSION: u8 = 1;
+const HASH_VERSION_SHA1: u8 = 1;
+/// The largest commit timestamp that can be stored, using 34 bits.
+const MAX_TIMESTAMP: u64 = 0x0003_ffff_ffff;
+
+/// The error returned by [`File::write_to()`].
+#[derive(thiserror::Error, Debug)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("Parent {parent} of commit {id} is neither part of the commits to write nor of the base graph")]
+    MissingParent { id: ObjectId, parent: ObjectId },
+    #[error(
+        "Commit-graph files would contain {0} commits altogether, but only {} commits are allowed",
+        MAX_COMMITS
+    )]
+    TooManyCommits(u64),
+    #[error("A commit-graph file can have at most 255 base graphs, but {0} were given")]
+    TooManyBaseGraphs(usize),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// A commit to write into a commit-graph file, as obtained from the object database or an existing commit graph.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    /// The id of the commit itself.
+    pub id: ObjectId,
+    /// The id of the tree the commit points to.
+    pub tree: ObjectId,
+    /// The ids of all parents of the commit in the order they are listed in the commit.
+    pub parents: Vec<ObjectId>,
+    /// The committer time in seconds since the unix epoch.
+    pub committer_timestamp: u64,
+    /// The changed-path Bloom filter of the commit, computed with the [default settings][bloom::Settings::default()].
+    ///
+    /// If at least one entry has a Bloom filter, all filters will be written, with missing ones marked as not computed.
+    pub bloom_filter: Option<bloom::Filter>,
+}
+
+/// Writing
+impl File {
+    /// Write a commit-graph file containing all `entries` to `out` and return its checksum, which is also the last
+    /// thing written to `out`.
+    ///
+    /// If `base` is set, the written file becomes the next layer of a split commit-graph chain on top of `base`.
+    /// Parents of `entries` are then looked up in `base` if they aren't part of `entries` themselves, and `entries`
+    /// which are already present in `base` are skipped.
+    /// Otherwise, all parents of `entries` must be contained in `entries` as well.
+    ///
+    /// Generation numbers are computed from scratch and capped at [`GENERATION_NUMBER_MAX`].
+    pub fn write_to(
+        entries: impl IntoIterator<Item = Entry>,
+        base: Option<&Graph>,
+        out: impl io::Write,
+    ) -> Result<ObjectId, Error> {
+        let mut entries: Vec<_> = entries
+            .into_iter()
+            .filter(|e| base.map_or(true, |base| base.lookup(e.id).is_none()))
+            .collect();
+        entries.sort_by_key(|e| e.id);
+        entries.dedup_by(|a, b| a.id == b.id);
+
+        let base_commits = base.map_or(0, |base| base.num_commits());
+        let num_commits = u64::from(base_commits) + entries.len() as u64;
+        if num_commits > u64::from(MAX_COMMITS) {
+            return Err(Error::TooManyCommits(num_commits));
+        }
+        let base_graph_ids: Vec<ObjectId> = base
+            .map(|base| base.files.iter().map(|f| f.checksum().to_owned()).collect())
+            .unwrap_or_default();
+        let base_graph_count =
+            u8::try_from(base_graph_ids.len()).map_err(|_| Error::TooManyBaseGraphs(base_graph_ids.len()))?;
+
+        let local_pos: HashMap<&oid, usize> = entries
+            .iter()
+            .enumerate()
+            .map(|(idx, e)| (e.id.as_ref(), idx))
+            .collect();
+        let parent_positions = entries
+            .iter()
+            .map(|e| {
+                e.parents
+                    .iter()
+                    .map(|parent| match local_pos.get(parent.as_ref()) {
+                        Some(idx) => Ok(Parent::Local(*idx)),
+                        None => base
+                            .and_then(|base| base.lookup(parent))
+                            .map(|pos| Parent::Base(pos.0))
+                            .ok_or_else(|| Error::MissingParent {
+                                id: e.id,
+                                parent: *parent,
+                            }),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let generations = compute_generations(&entries, &parent_positions, base);
+
+        let to_graph_pos = |p: &Parent| match p {
+            Parent::Local(idx) => base_commits + *idx as u32,
+            Parent::Base(pos) => *pos,
+        };
+        let mut extra_edges = Vec::<u32>::new();
+        let mut commit_data = Vec::with_capacity(entries.len() * COMMIT_DATA_ENTRY_SIZE);
+        for ((entry, parents), generation) in entries.iter().zip(parent_positions.iter()).zip(generations) {
+            commit_data.extend_from_slice(entry.tree.as_bytes());
+            let parent1 = parents.first().map_or(NO_PARENT, to_graph_pos);
+            let parent2 = match parents.len() {
+                0 | 1 => NO_PARENT,
+                2 => to_graph_pos(&parents[1]),
+                _ => {
+                    let edge_index = extra_edges.len() as u32;
+                    extra_edges.extend(parents[1..].iter().map(to_graph_pos));
+                    *extra_edges.last_mut().expect("at least two extra edges") |= LAST_EXTENDED_EDGE_MASK;
+                    EXTENDED_EDGES_MASK | edge_index
+                }
+            };
+            let timestamp = entry.committer_timestamp.min(MAX_TIMESTAMP);
+            commit_data.extend_from_slice(&parent1.to_be_bytes());
+            commit_data.extend_from_slice(&parent2.to_be_bytes());
+            commit_data.extend_from_slice(&((generation << 2) | (timestamp >> 32) as u32).to_be_bytes());
+            commit_data.extend_from_slice(&(timestamp as u32).to_be_bytes());
+        }
+
+        let mut fan = [0u32; FAN_LEN];
+        for entry in &entries {
+            fan[usize::from(entry.id.first_byte())] += 1;
+        }
+        for idx in 1..FAN_LEN {
+            fan[idx] += fan[idx - 1];
+        }
+
+        let mut chunks: Vec<(ChunkId, usize)> = vec![
+            (OID_FAN_CHUNK_ID, FAN_LEN * 4),
+            (OID_LOOKUP_CHUNK_ID, entries.len() * OID_LOOKUP_ENTRY_SIZE),
+            (COMMIT_DATA_CHUNK_ID, commit_data.len()),
+        ];
+        if !extra_edges.is_empty() {
+            chunks.push((EXTENDED_EDGES_LIST_CHUNK_ID, extra_edges.len() * 4));
+        }
+        let bloom_filters = if entries.iter().any(|e| e.bloom_filter.is_some()) {
+            let mut index = Vec::with_capacity(entries.len());
+            let mut data = Vec::new();
+            for entry in &entries {
+                if let Some(filter) = &entry.bloom_filter {
+                    data.extend_from_slice(filter.as_bytes());
+                }
+                index.push(data.len() as u32);
+            }
+            chunks.push((BLOOM_FILTER_INDEX_CHUNK_ID, index.len() * 4));
+            chunks.push((BLOOM_FILTER_DATA_CHUNK_ID, BLOOM_FILTER_DATA_HEADER_LEN + data.len()));
+            Some((index, data))
+        } else {
+            None
+        };
+        if !base_graph_ids.is_empty() {
+            chunks.push((BASE_GRAPHS_LIST_CHUNK_ID, base_graph_ids.len() * OID_LOOKUP_ENTRY_SIZE));
+        }
+
+        let mut out = git_features::hash::Write::new(out, git_hash::Kind::Sha1);
+        out.write_all(SIGNATURE)?;
+        out.write_all(&[VERSION, HASH_VERSION_SHA1, chunks.len() as u8, base_graph_count])?;
+
+        let mut offset = (HEADER_LEN + (chunks.len() + 1) * CHUNK_LOOKUP_SIZE) as u64;
+        for (id, size) in &chunks {
+            out.write_all(id)?;
+            out.write_all(&offset.to_be_bytes())?;
+            offset += *size as u64;
+        }
+        out.write_all(&SENTINEL_CHUNK_ID)?;
+        out.write_all(&offset.to_be_bytes())?;
+
+        for count in fan.iter() {
+            out.write_all(&count.to_be_bytes())?;
+        }
+        for entry in &entries {
+            out.write_all(entry.id.as_bytes())?;
+        }
+        out.write_all(&commit_data)?;
+        for edge in extra_edges {
+            out.write_all(&edge.to_be_bytes())?;
+        }
+        if let Some((index, data)) = bloom_filters {
+            for end in index {
+                out.write_all(&end.to_be_bytes())?;
+            }
+            let settings = bloom::Settings::default();
+            for value in &[settings.hash_version, settings.num_hashes, settings.bits_per_entry] {
+                out.write_all(&value.to_be_bytes())?;
+            }
+            out.write_all(&data)?;
+        }
+        for id in &base_graph_ids {
+            out.write_all(id.as_bytes())?;
+        }
+
+        let checksum = ObjectId::from(out.hash.digest());
+        out.inner.write_all(checksum.as_bytes())?;
+        out.inner.flush()?;
+        Ok(checksum)
+    }
+}
+
+/// The position of a parent, either within the commits to write or within the base graph.
+enum Parent {
+    Local(usize),
+    Base(u32),
+}
+
+/// Compute the generation of each of `entries` without recursion, using the generations stored in `base` for
+/// parents that are part of it.
+fn compute_generations(entries: &[Entry], parents: &[Vec<Parent>], base: Option<&Graph>) -> Vec<u32> {
+    const UNKNOWN: u32 = 0;
+    let mut generations = vec![UNKNOWN; entries.len()];
+    let mut stack = Vec::new();
+    for start in 0..entries.len() {
+        if generations[start] != UNKNOWN {
+            continue;
+        }
+        stack.push(start);
+        while let Some(&idx) = stack.last() {
+            if generations[idx] != UNKNOWN {
+                stack.pop();
+                continue;
+            }
+            let mut max_parent_generation = 0;
+            let mut all_parents_known = true;
+            for parent in &parents[idx] {
+                let generation = match parent {
+                    Parent::Local(parent_idx) if generations[*parent_idx] == UNKNOWN => {
+                        all_parents_known = false;
+                        stack.push(*parent_idx);
+                        continue;
+                    }
+                    Parent::Local(parent_idx) => generations[*parent_idx],
+                    Parent::Base(pos) => base
+                        .expect("base parents only exist with a base")
+                        .commit_at(crate::graph::Position(*pos))
+                        .generation(),
+                };
+                max_parent_generation = max_parent_generation.max(generation);
+            }
+            if all_parents_known {
+                generations[idx] = (max_parent_generation + 1).min(GENERATION_NUMBER_MAX);
+                stack.pop();
+            }
+        }
+    }
+    generations
+}
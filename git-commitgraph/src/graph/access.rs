@@ -1,4 +1,7 @@
+use bstr::BStr;
+
 use crate::{
+    bloom,
     file::{self, Commit, File},
     graph::{self, Graph},
 };
@@ -20,6 +23,26 @@ impl Graph {
         Some(r.file.commit_at(r.file_pos))
     }
 
+    /// Returns the generation of the commit matching the given `id` for use in commit ordering and reachability queries.
+    ///
+    /// This is its [corrected commit date][Commit::corrected_commit_date()] if [all files][Graph::has_corrected_commit_dates()]
+    /// store these, or its [topological generation][Commit::generation()] otherwise, so generations of different commits
+    /// of the same graph can always be compared.
+    pub fn generation_of(&self, id: impl AsRef<git_hash::oid>) -> Option<u64> {
+        let commit = self.commit_by_id(id)?;
+        if self.has_corrected_commit_dates() {
+            commit.corrected_commit_date()
+        } else {
+            Some(commit.generation().into())
+        }
+    }
+
+    /// Returns true if all files of this graph store the corrected commit dates of their commits, which is when these are
+    /// used as [generation][Graph::generation_of()].
+    pub fn has_corrected_commit_dates(&self) -> bool {
+        self.files.iter().all(File::has_corrected_commit_dates)
+    }
+
     /// Returns the `hash` at the given position `pos`.
     ///
     /// # Panics
@@ -44,6 +67,25 @@ impl Graph {
         Some(self.lookup_by_id(id.as_ref())?.graph_pos)
     }
 
+    /// Returns `Some(false)` if the commit with `id` definitely didn't change the slash-separated `path` compared to its first
+    /// parent, or `Some(true)` if it may have changed it.
+    ///
+    /// `None` is returned if the commit isn't part of the graph, or if it has no usable [Bloom filter][crate::bloom]
+    /// in which case the trees of the commit and its parent need to be compared to find out.
+    pub fn maybe_contains_path(&self, id: impl AsRef<git_hash::oid>, path: &BStr) -> Option<bool> {
+        let r = self.lookup_by_id(id.as_ref())?;
+        let settings = r.file.bloom_filter_settings()?;
+        let filter = r.file.commit_at(r.file_pos).bloom_filter()?;
+        if filter.is_empty() {
+            return None;
+        }
+        let mut path: &[u8] = path;
+        while let Some((b'/', rest)) = path.split_last() {
+            path = rest;
+        }
+        Some(filter.contains(&bloom::Key::new(path.into(), settings)))
+    }
+
     /// Returns the number of commits stored in this file.
     pub fn num_commits(&self) -> u32 {
         self.files.iter().map(|f| f.num_commits()).sum()
@@ -26,6 +26,12 @@ pub enum Error<E: std::error::Error + 'static> {
     },
     #[error(transparent)]
     Commit(#[from] commit::Error),
+    #[error("Commit {id}'s corrected commit date should be at least {min_expected} but is {actual:?}")]
+    CorrectedCommitDate {
+        actual: Option<u64>,
+        min_expected: u64,
+        id: git_hash::ObjectId,
+    },
     #[error("{}: {err}", .path.display())]
     File {
         // Use zero-size error type. We will never return
@@ -131,6 +137,7 @@ impl Graph {
             let file_stats = file
                 .traverse(|commit| {
                     let mut max_parent_generation = 0u32;
+                    let mut min_corrected_commit_date = commit.committer_timestamp();
                     for parent_pos in commit.iter_parents() {
                         let parent_pos = parent_pos.map_err(Error::Commit)?;
                         if parent_pos >= next_file_start_pos {
@@ -142,6 +149,20 @@ impl Graph {
                         }
                         let parent = self.commit_at(parent_pos);
                         max_parent_generation = max(max_parent_generation, parent.generation());
+                        if let Some(date) = parent.corrected_commit_date() {
+                            min_corrected_commit_date = max(min_corrected_commit_date, date + 1);
+                        }
+                    }
+
+                    if file.has_corrected_commit_dates() {
+                        let actual = commit.corrected_commit_date();
+                        if actual.map_or(true, |date| date < min_corrected_commit_date) {
+                            return Err(Error::CorrectedCommitDate {
+                                actual,
+                                min_expected: min_corrected_commit_date,
+                                id: commit.id().into(),
+                            });
+                        }
                     }
 
                     // If the max parent generation is GENERATION_NUMBER_MAX, then this commit's
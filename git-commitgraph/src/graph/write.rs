@@ -0,0 +1,323 @@
+//! Write commit graphs consisting of one or more files.
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use git_tempfile::{AutoRemove, ContainingDirectory};
+
+use crate::{
+    bloom,
+    file::{self, write::Entry, File},
+    graph, Graph,
+};
+
+/// The error returned by [`Graph::write_to_info_dir()`].
+#[derive(thiserror::Error, Debug)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("The existing commit graph could not be opened")]
+    Open(#[from] graph::Error),
+    #[error("A commit of the existing commit graph could not be read")]
+    Commit(#[from] file::commit::Error),
+    #[error("Could not write commit-graph file")]
+    Write(#[from] file::write::Error),
+    #[error("Could not {message} '{path}'")]
+    Io {
+        source: io::Error,
+        message: &'static str,
+        path: PathBuf,
+    },
+    #[error(transparent)]
+    Lock(#[from] git_lock::acquire::Error),
+    #[error("Could not move the written file into place at '{target}'")]
+    Persist { source: io::Error, target: PathBuf },
+}
+
+/// Determines how commits are written into a commit graph, either as single file or as chain of files.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Split {
+    /// Write all commits of the existing commit graph along with the new ones into a single `info/commit-graph` file,
+    /// and remove a possibly existing chain of split commit-graph files, like `git commit-graph write`.
+    No,
+    /// Write new commits into a new file on top of the existing chain without merging any of its files,
+    /// like `git commit-graph write --split=no-merge`.
+    NoMerge,
+    /// Write new commits into a new file on top of the existing chain, and merge it with the files below it as long as
+    /// these contain at most `factor` times as many commits as the ones merged so far, or if more than `max_commits` would
+    /// be written otherwise, like `git commit-graph write --split --size-multiple=<factor> --max-commits=<max_commits>`.
+    SizeMultiple {
+        /// The factor by which the amount of commits in a file must exceed the ones in the files above it to not be merged.
+        factor: u32,
+        /// If set, files below the new file are merged into it as long as it would contain more than this amount of commits.
+        max_commits: Option<u32>,
+    },
+    /// Merge all files of the existing chain along with the new commits into a single file of a new chain,
+    /// like `git commit-graph write --split=replace`.
+    Replace,
+}
+
+impl Default for Split {
+    fn default() -> Self {
+        Split::No
+    }
+}
+
+/// The outcome of [`Graph::write_to_info_dir()`].
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The path of the newly written commit-graph file, or `None` if there were no new commits to write.
+    pub path: Option<PathBuf>,
+    /// The amount of commits written into the new file, including the ones of files merged into it.
+    pub num_commits: u32,
+    /// The amount of files the commit graph consists of after writing.
+    pub num_files: usize,
+}
+
+/// Writing
+impl Graph {
+    /// Add the commits in `entries` to the commit graph in the `objects/info` directory at `info_dir`, creating it if it doesn't
+    /// exist yet, and write the new or changed files according to `split`.
+    ///
+    /// Commits already present in the existing commit graph are ignored, and all parents of the commits in `entries` have to be
+    /// present in either `entries` or the existing commit graph.
+    ///
+    /// All files are written to temporary files and moved into place once complete, with the `commit-graph-chain` file being updated
+    /// after all files it refers to were written. Files which aren't part of the commit graph anymore are removed afterwards.
+    ///
+    /// Like git, the `commit-graph` and `commit-graph-chain` files are locked before the existing commit graph is read, and writing
+    /// fails immediately if another process holds one of the locks.
+    pub fn write_to_info_dir(
+        info_dir: impl AsRef<Path>,
+        entries: impl IntoIterator<Item = Entry>,
+        split: Split,
+    ) -> Result<Outcome, Error> {
+        let info_dir = info_dir.as_ref();
+        let graphs_dir = info_dir.join("commit-graphs");
+        let single_file_path = info_dir.join("commit-graph");
+        let chain_file_path = graphs_dir.join("commit-graph-chain");
+
+        let single_file_lock =
+            git_lock::File::acquire_to_update_resource(&single_file_path, git_lock::acquire::Fail::Immediately, None)?;
+        if split != Split::No {
+            fs::create_dir_all(&graphs_dir).map_err(|err| Error::Io {
+                source: err,
+                message: "create directory",
+                path: graphs_dir.clone(),
+            })?;
+        }
+        let chain_file_lock = if graphs_dir.is_dir() {
+            Some(git_lock::File::acquire_to_update_resource(
+                &chain_file_path,
+                git_lock::acquire::Fail::Immediately,
+                None,
+            )?)
+        } else {
+            None
+        };
+        let (single_file, chain) = if single_file_path.is_file() {
+            (Some(Graph::from_file(&single_file_path)?), None)
+        } else if chain_file_path.is_file() {
+            (None, Some(Graph::from_commit_graphs_dir(&graphs_dir)?))
+        } else {
+            (None, None)
+        };
+        let existing = single_file.as_ref().or(chain.as_ref());
+        let mut entries: Vec<_> = entries
+            .into_iter()
+            .filter(|e| existing.map_or(true, |g| g.lookup(e.id).is_none()))
+            .collect();
+        entries.sort_by_key(|e| e.id);
+        entries.dedup_by(|a, b| a.id == b.id);
+        let num_new_commits = entries.len();
+        if let Some(single_file) = &single_file {
+            entries.extend(entries_of(single_file, &single_file.files)?);
+        }
+
+        if let Split::No = split {
+            if let Some(chain) = &chain {
+                entries.extend(entries_of(chain, &chain.files)?);
+            }
+            if num_new_commits == 0 && single_file.is_some() && chain.is_none() {
+                return Ok(Outcome {
+                    path: None,
+                    num_commits: 0,
+                    num_files: 1,
+                });
+            }
+            let num_commits = entries.len() as u32;
+            let lock_path = single_file_lock.lock_path().to_owned();
+            let single_file_lock = write_file(single_file_lock, &lock_path, entries, None)?.0;
+            drop(single_file);
+            commit(single_file_lock)?;
+            if let Some(chain) = chain {
+                let obsolete_files: Vec<_> = chain.files.iter().map(|f| f.path().to_owned()).collect();
+                drop(chain);
+                remove_files(obsolete_files.iter().chain(Some(&chain_file_path)))?;
+            }
+            drop(chain_file_lock);
+            return Ok(Outcome {
+                path: Some(single_file_path),
+                num_commits,
+                num_files: 1,
+            });
+        }
+
+        let mut layers = chain.map(|g| g.files).unwrap_or_default();
+        let mut keep = layers.len();
+        match split {
+            Split::No => unreachable!("handled above"),
+            Split::NoMerge => {}
+            Split::Replace => keep = 0,
+            Split::SizeMultiple { factor, max_commits } => {
+                let mut num_commits = entries.len() as u64;
+                while let Some(layer) = keep.checked_sub(1).map(|idx| &layers[idx]) {
+                    let layer_commits = u64::from(layer.num_commits());
+                    if layer_commits <= u64::from(factor) * num_commits
+                        || max_commits.map_or(false, |max| num_commits > u64::from(max))
+                    {
+                        num_commits += layer_commits;
+                        keep -= 1;
+                    } else {
+                        break;
+                    }
+                }
+            }
+        }
+        if entries.is_empty() && keep == layers.len() {
+            return Ok(Outcome {
+                path: None,
+                num_commits: 0,
+                num_files: layers.len(),
+            });
+        }
+
+        let merged_layers = if keep < layers.len() {
+            let all = Graph::new(layers)?;
+            entries.extend(entries_of(&all, &all.files[keep..])?);
+            layers = all.files;
+            layers.split_off(keep)
+        } else {
+            Vec::new()
+        };
+        let base = if layers.is_empty() {
+            None
+        } else {
+            Some(Graph::new(layers)?)
+        };
+
+        let mut chain_file_lock =
+            chain_file_lock.expect("the directory exists as it was created for split commit graphs");
+        let num_commits = entries.len() as u32;
+        let file =
+            git_tempfile::new(&graphs_dir, ContainingDirectory::Exists, AutoRemove::Tempfile).map_err(|err| {
+                Error::Io {
+                    source: err,
+                    message: "create temporary file in",
+                    path: graphs_dir.clone(),
+                }
+            })?;
+        let (file, checksum) = write_file(file, &graphs_dir, entries, base.as_ref())?;
+        let new_file_path = graphs_dir.join(format!("graph-{}.graph", checksum));
+        file.persist(&new_file_path).map_err(|err| Error::Persist {
+            source: err.error,
+            target: new_file_path.clone(),
+        })?;
+
+        let base_ids = base
+            .iter()
+            .flat_map(|g| g.files.iter().map(|f| f.checksum().to_owned()));
+        for id in base_ids.chain(Some(checksum)) {
+            writeln!(chain_file_lock, "{}", id).map_err(|err| Error::Io {
+                source: err,
+                message: "write commit-graph chain",
+                path: chain_file_path.clone(),
+            })?;
+        }
+        commit(chain_file_lock)?;
+
+        let num_files = base.map_or(0, |g| g.files.len()) + 1;
+        let obsolete_files: Vec<_> = merged_layers
+            .iter()
+            .map(|f| f.path().to_owned())
+            .filter(|p| *p != new_file_path)
+            .chain(single_file.as_ref().map(|_| single_file_path))
+            .collect();
+        drop((merged_layers, single_file));
+        remove_files(obsolete_files.iter())?;
+        drop(single_file_lock);
+
+        Ok(Outcome {
+            path: Some(new_file_path),
+            num_commits,
+            num_files,
+        })
+    }
+}
+
+/// Turn all commits in `files` back into entries, using `graph` to resolve the ids of their parents.
+///
+/// Bloom filters are retained if they were computed with the settings we use for writing.
+fn entries_of(graph: &Graph, files: &[File]) -> Result<Vec<Entry>, file::commit::Error> {
+    files
+        .iter()
+        .flat_map(|file| {
+            let keep_bloom_filters = file.bloom_filter_settings() == Some(&bloom::Settings::default());
+            file.iter_commits().map(move |commit| {
+                Ok(Entry {
+                    id: commit.id().to_owned(),
+                    tree: commit.root_tree_id().to_owned(),
+                    parents: commit
+                        .iter_parents()
+                        .map(|pos| pos.map(|pos| graph.id_at(pos).to_owned()))
+                        .collect::<Result<_, _>>()?,
+                    committer_timestamp: commit.committer_timestamp(),
+                    bloom_filter: commit
+                        .bloom_filter()
+                        .filter(|filter| keep_bloom_filters && !filter.is_empty())
+                        .map(|filter| filter.to_owned()),
+                })
+            })
+        })
+        .collect()
+}
+
+/// Write `entries` into `out`, which is located at `path`, and return it along with the checksum of the written file.
+fn write_file<W: io::Write>(
+    out: W,
+    path: &Path,
+    entries: Vec<Entry>,
+    base: Option<&Graph>,
+) -> Result<(W, git_hash::ObjectId), Error> {
+    let mut out = BufWriter::new(out);
+    let checksum = File::write_to(entries, base, &mut out)?;
+    let out = out.into_inner().map_err(|err| Error::Io {
+        source: err.into_error(),
+        message: "flush",
+        path: path.to_owned(),
+    })?;
+    Ok((out, checksum))
+}
+
+fn commit(lock: git_lock::File) -> Result<(), Error> {
+    let target = lock.resource_path();
+    lock.commit().map_err(|err| Error::Persist {
+        source: err.error,
+        target,
+    })?;
+    Ok(())
+}
+
+fn remove_files<'a>(paths: impl Iterator<Item = &'a PathBuf>) -> Result<(), Error> {
+    for path in paths {
+        fs::remove_file(path).map_err(|err| Error::Io {
+            source: err,
+            message: "remove obsolete commit-graph file",
+            path: path.to_owned(),
+        })?;
+    }
+    Ok(())
+}
@@ -1,4 +1,4 @@
-//! Read, verify, and traverse git commit graphs.
+//! Read, write, verify, and traverse git commit graphs.
 //!
 //! A [commit graph][Graph] is an index of commits in the git commit history.
 //! The [Graph] stores commit data in a way that accelerates lookups considerably compared to
@@ -10,6 +10,7 @@
 #![forbid(unsafe_code)]
 #![deny(rust_2018_idioms, missing_docs)]
 
+pub mod bloom;
 pub mod file;
 pub mod graph;
 
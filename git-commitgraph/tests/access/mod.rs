@@ -69,3 +69,32 @@ fn two_parents() -> crate::Result {
 
     Ok(())
 }
+
+#[test]
+fn corrected_commit_dates() -> crate::Result {
+    let repo_dir = make_readonly_repo("generation_v2.sh");
+    let refs = inspect_refs(&repo_dir, &["root", "future", "skewed", "tip"]);
+    let cg = Graph::from_info_dir(repo_dir.join(".git").join("objects").join("info"))?;
+    check_common(&cg, &refs);
+    assert!(cg.has_corrected_commit_dates());
+
+    for (name, generation, committer_timestamp, corrected_commit_date) in &[
+        ("root", 1, 946_771_200, 946_771_200),
+        ("future", 2, 4_000_000_000, 4_000_000_000),
+        ("skewed", 3, 86_400, 4_000_000_001),
+        ("tip", 4, 946_771_200, 4_000_000_002),
+    ] {
+        let commit = cg.commit_at(refs[*name].pos());
+        assert_eq!(commit.generation(), *generation, "{}", name);
+        assert_eq!(commit.committer_timestamp(), *committer_timestamp, "{}", name);
+        assert_eq!(
+            commit.corrected_commit_date(),
+            Some(*corrected_commit_date),
+            "{}: the last two use the overflow chunk",
+            name
+        );
+        assert_eq!(cg.generation_of(refs[*name].id()), Some(*corrected_commit_date));
+    }
+    assert_eq!(cg.verify_integrity(|_| Ok::<_, std::fmt::Error>(()))?.num_commits, 4);
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use bstr::ByteSlice;
+use byteorder::ByteOrder;
+use git_commitgraph::{bloom, file::File, graph::write::Split, Graph};
+
+use crate::{inspect_refs, make_readonly_repo};
+
+const PATHS: &[&str] = &["a", "c", "d", "d/b", "x", "d/x", "b"];
+
+fn expected_changes() -> Vec<(&'static str, &'static [&'static str])> {
+    vec![
+        ("c1", &["a"][..]),
+        ("c2", &["d", "d/b"][..]),
+        ("c3", &["a", "c"][..]),
+        ("c4", &[][..]),
+    ]
+}
+
+fn assert_paths_match(cg: &Graph, refs: &std::collections::HashMap<String, crate::RefInfo>) {
+    for (name, changed) in expected_changes() {
+        let id = refs[name].id();
+        for path in PATHS {
+            assert_eq!(
+                cg.maybe_contains_path(id, path.as_bytes().as_bstr()),
+                Some(changed.contains(path)),
+                "{}: {}",
+                name,
+                path
+            );
+        }
+    }
+}
+
+#[test]
+fn filters_written_by_git_are_found_and_queried() -> crate::Result {
+    let repo_dir = make_readonly_repo("changed_paths.sh");
+    let refs = inspect_refs(&repo_dir, &["c1", "c2", "c3", "c4"]);
+    let info_dir = repo_dir.join(".git").join("objects").join("info");
+    assert_eq!(
+        File::at(info_dir.join("commit-graph"))?.bloom_filter_settings(),
+        Some(&bloom::Settings::default()),
+        "git writes the default settings"
+    );
+    let cg = Graph::from_info_dir(info_dir)?;
+    assert_paths_match(&cg, &refs);
+    Ok(())
+}
+
+#[test]
+fn filters_computed_from_changed_files_match_the_ones_of_git() -> crate::Result {
+    let repo_dir = make_readonly_repo("changed_paths.sh");
+    let refs = inspect_refs(&repo_dir, &["c1", "c2", "c3", "c4"]);
+    let cg = Graph::from_info_dir(repo_dir.join(".git").join("objects").join("info"))?;
+    for (name, changed_files) in &[
+        ("c1", &["a"][..]),
+        ("c2", &["d/b"][..]),
+        ("c3", &["a", "c"][..]),
+        ("c4", &[][..]),
+    ] {
+        let expected = cg
+            .commit_by_id(refs[*name].id())
+            .and_then(|c| c.bloom_filter())
+            .expect("filter present");
+        let actual = bloom::Filter::from_changed_paths(changed_files.iter().map(|p| p.as_bytes()));
+        assert_eq!(actual.as_bytes(), expected.as_bytes(), "{}", name);
+    }
+    Ok(())
+}
+
+#[test]
+fn too_many_changes_produce_a_filter_containing_everything() {
+    let paths: Vec<_> = (0..=bloom::MAX_CHANGED_PATHS).map(|i| format!("file{}", i)).collect();
+    let filter = bloom::Filter::from_changed_paths(paths.iter().map(|p| p.as_bytes()));
+    assert_eq!(filter, bloom::Filter::too_large());
+    let key = bloom::Key::new(b"anything".as_bstr(), &Default::default());
+    assert!(filter.to_ref().contains(&key));
+}
+
+#[test]
+fn filters_are_retained_when_rewriting_the_graph() -> crate::Result {
+    let repo_dir = make_readonly_repo("changed_paths.sh");
+    let refs = inspect_refs(&repo_dir, &["c1", "c2", "c3", "c4"]);
+    let expected = Graph::from_info_dir(repo_dir.join(".git").join("objects").join("info"))?;
+
+    let dir = git_testtools::tempfile::TempDir::new()?;
+    let (first, second): (Vec<_>, Vec<_>) = crate::write::entries_of(&expected)
+        .into_iter()
+        .partition(|e| e.id == refs["c1"].id().to_owned());
+    Graph::write_to_info_dir(dir.path(), first, Split::NoMerge)?;
+    Graph::write_to_info_dir(dir.path(), second, Split::NoMerge)?;
+    assert_paths_match(&Graph::from_info_dir(dir.path())?, &refs);
+
+    Graph::write_to_info_dir(dir.path(), None, Split::No)?;
+    assert_paths_match(&Graph::from_info_dir(dir.path())?, &refs);
+    Ok(())
+}
+
+#[test]
+fn filters_with_unusable_settings_are_ignored() -> crate::Result {
+    let repo_dir = make_readonly_repo("changed_paths.sh");
+    let expected = Graph::from_info_dir(repo_dir.join(".git").join("objects").join("info"))?;
+    let dir = git_testtools::tempfile::TempDir::new()?;
+    Graph::write_to_info_dir(dir.path(), crate::write::entries_of(&expected), Split::No)?;
+
+    let path = dir.path().join("commit-graph");
+    let mut data = std::fs::read(&path)?;
+    let chunk_table = &data[8..];
+    let bdat_offset = chunk_table
+        .chunks(12)
+        .find(|entry| &entry[..4] == b"BDAT")
+        .map(|entry| byteorder::BigEndian::read_u64(&entry[4..]) as usize)
+        .expect("BDAT chunk present");
+    for (field, value) in &[("num_hashes", 4), ("bits_per_entry", 8)] {
+        let mut data = data.clone();
+        data[bdat_offset + value..bdat_offset + value + 4].copy_from_slice(&0u32.to_be_bytes());
+        std::fs::write(&path, &data)?;
+        assert_eq!(File::at(&path)?.bloom_filter_settings(), None, "{} is 0", field);
+    }
+    data[bdat_offset..bdat_offset + 4].copy_from_slice(&3u32.to_be_bytes());
+    std::fs::write(&path, &data)?;
+    assert_eq!(File::at(&path)?.bloom_filter_settings(), None, "unknown hash version");
+    Ok(())
+}
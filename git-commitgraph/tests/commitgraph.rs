@@ -12,6 +12,8 @@ use git_commitgraph::{graph::Position as GraphPosition, Graph};
 type Result = std::result::Result<(), Box<dyn std::error::Error>>;
 
 mod access;
+mod bloom;
+mod write;
 
 pub fn check_common(cg: &Graph, expected: &HashMap<String, RefInfo, impl BuildHasher>) {
     assert_eq!(
@@ -0,0 +1,28 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q
+git config commit.gpgsign false
+
+echo a > a
+git add a
+git commit -q -m c1
+git tag c1
+
+mkdir d
+echo b > d/b
+git add d
+git commit -q -m c2
+git tag c2
+
+echo a2 > a
+echo c > c
+git add a c
+git commit -q -m c3
+git tag c3
+
+git commit -q --allow-empty -m c4
+git tag c4
+
+git commit-graph write --no-progress --reachable --changed-paths
+git repack -adq
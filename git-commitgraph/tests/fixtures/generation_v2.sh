@@ -0,0 +1,17 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q
+git config commit.gpgsign false
+
+git commit -q --allow-empty -m root
+git tag root
+GIT_COMMITTER_DATE="@4000000000 +0000" git commit -q --allow-empty -m future
+git tag future
+GIT_COMMITTER_DATE="@86400 +0000" git commit -q --allow-empty -m skewed
+git tag skewed
+git commit -q --allow-empty -m tip
+git tag tip
+
+git -c commitGraph.generationVersion=2 commit-graph write --no-progress --reachable
+git repack -adq
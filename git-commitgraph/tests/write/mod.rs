@@ -0,0 +1,282 @@
+use std::path::Path;
+
+use git_commitgraph::{file::write::Entry, graph::write::Split, Graph};
+
+use crate::{check_common, inspect_refs, make_readonly_repo};
+
+fn info_dir(repo_dir: &Path) -> std::path::PathBuf {
+    repo_dir.join(".git").join("objects").join("info")
+}
+
+pub fn entries_of(cg: &Graph) -> Vec<Entry> {
+    cg.iter_commits()
+        .map(|commit| Entry {
+            id: commit.id().to_owned(),
+            tree: commit.root_tree_id().to_owned(),
+            parents: commit
+                .iter_parents()
+                .map(|pos| cg.id_at(pos.expect("valid parent")).to_owned())
+                .collect(),
+            committer_timestamp: commit.committer_timestamp(),
+            bloom_filter: commit
+                .bloom_filter()
+                .filter(|filter| !filter.is_empty())
+                .map(|filter| filter.to_owned()),
+        })
+        .collect()
+}
+
+fn verify(cg: &Graph) -> Result<u32, Box<dyn std::error::Error>> {
+    Ok(cg.verify_integrity(|_| Ok::<_, std::fmt::Error>(()))?.num_commits)
+}
+
+fn assert_same_commits(actual: &Graph, expected: &Graph) {
+    let mut actual_entries = entries_of(actual);
+    let mut expected_entries = entries_of(expected);
+    actual_entries.sort();
+    expected_entries.sort();
+    assert_eq!(actual_entries, expected_entries);
+    for commit in expected.iter_commits() {
+        assert_eq!(
+            actual
+                .commit_by_id(commit.id())
+                .expect("all commits are present")
+                .generation(),
+            commit.generation(),
+            "generations are computed like git does"
+        );
+    }
+}
+
+mod single_file {
+    use git_testtools::tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn from_scratch_matches_the_graph_written_by_git() -> crate::Result {
+        for (script, refs) in &[
+            ("single_commit.sh", &["commit"][..]),
+            ("single_parent.sh", &["parent", "child"][..]),
+            ("two_parents.sh", &["parent1", "parent2", "child"][..]),
+            (
+                "octopus_merges.sh",
+                &[
+                    "root",
+                    "parent1",
+                    "parent2",
+                    "parent3",
+                    "parent4",
+                    "three_parents",
+                    "four_parents",
+                ][..],
+            ),
+        ] {
+            let repo_dir = make_readonly_repo(script);
+            let expected = Graph::from_info_dir(info_dir(&repo_dir))?;
+
+            let dir = TempDir::new()?;
+            let outcome = Graph::write_to_info_dir(dir.path(), entries_of(&expected), Split::No)?;
+            assert_eq!(outcome.path, Some(dir.path().join("commit-graph")));
+            assert_eq!(outcome.num_commits, expected.num_commits());
+            assert_eq!(outcome.num_files, 1);
+
+            let actual = Graph::from_info_dir(dir.path())?;
+            check_common(&actual, &inspect_refs(&repo_dir, refs));
+            assert_same_commits(&actual, &expected);
+            assert_eq!(verify(&actual)?, expected.num_commits());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn existing_commits_are_retained_and_nothing_is_written_without_new_commits() -> crate::Result {
+        let repo_dir = make_readonly_repo("octopus_merges.sh");
+        let expected = Graph::from_info_dir(info_dir(&repo_dir))?;
+        let mut entries = entries_of(&expected);
+        entries.sort_by_key(|e| e.parents.len());
+        let (roots_and_children, merges) = entries.split_at(5);
+
+        let dir = TempDir::new()?;
+        Graph::write_to_info_dir(dir.path(), roots_and_children.to_vec(), Split::No)?;
+        let outcome = Graph::write_to_info_dir(dir.path(), merges.to_vec(), Split::No)?;
+        assert_eq!(outcome.num_commits, expected.num_commits());
+
+        let actual = Graph::from_info_dir(dir.path())?;
+        assert_same_commits(&actual, &expected);
+        assert_eq!(verify(&actual)?, expected.num_commits());
+
+        let outcome = Graph::write_to_info_dir(dir.path(), merges.to_vec(), Split::No)?;
+        assert_eq!(outcome.path, None, "all commits are already present");
+        Ok(())
+    }
+
+    #[test]
+    fn missing_parents_are_an_error() -> crate::Result {
+        let repo_dir = make_readonly_repo("single_parent.sh");
+        let refs = inspect_refs(&repo_dir, &["child"]);
+        let expected = Graph::from_info_dir(info_dir(&repo_dir))?;
+        let child_only: Vec<_> = entries_of(&expected)
+            .into_iter()
+            .filter(|e| e.id == refs["child"].id().to_owned())
+            .collect();
+
+        let dir = TempDir::new()?;
+        assert!(Graph::write_to_info_dir(dir.path(), child_only, Split::No).is_err());
+        assert!(!dir.path().join("commit-graph").exists());
+        Ok(())
+    }
+}
+
+mod split {
+    use git_testtools::tempfile::TempDir;
+
+    use super::*;
+
+    fn commits_in_order() -> Result<(Graph, Vec<Entry>), Box<dyn std::error::Error>> {
+        let repo_dir = make_readonly_repo("split_chain.sh");
+        let refs = inspect_refs(&repo_dir, &["commit1", "commit2", "commit3"]);
+        let expected = Graph::from_info_dir(info_dir(&repo_dir))?;
+        let entries = entries_of(&expected);
+        let in_order = ["commit1", "commit2", "commit3"]
+            .iter()
+            .map(|name| {
+                entries
+                    .iter()
+                    .find(|e| e.id == refs[*name].id().to_owned())
+                    .expect("commit present")
+                    .clone()
+            })
+            .collect();
+        Ok((expected, in_order))
+    }
+
+    fn chain_len(info_dir: &Path) -> usize {
+        std::fs::read_to_string(info_dir.join("commit-graphs").join("commit-graph-chain"))
+            .expect("chain file exists")
+            .lines()
+            .count()
+    }
+
+    fn num_graph_files(info_dir: &Path) -> usize {
+        std::fs::read_dir(info_dir.join("commit-graphs"))
+            .expect("graphs dir exists")
+            .filter(|e| {
+                e.as_ref()
+                    .expect("valid entry")
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(".graph")
+            })
+            .count()
+    }
+
+    #[test]
+    fn no_merge_adds_one_file_per_write_and_replace_merges_them_all() -> crate::Result {
+        let (expected, commits) = commits_in_order()?;
+        let dir = TempDir::new()?;
+        for (idx, commit) in commits.iter().enumerate() {
+            let outcome = Graph::write_to_info_dir(dir.path(), Some(commit.clone()), Split::NoMerge)?;
+            assert_eq!(outcome.num_commits, 1);
+            assert_eq!(outcome.num_files, idx + 1);
+        }
+        assert_eq!(chain_len(dir.path()), 3);
+
+        let actual = Graph::from_info_dir(dir.path())?;
+        assert_same_commits(&actual, &expected);
+        assert_eq!(verify(&actual)?, 3);
+        drop(actual);
+
+        let outcome = Graph::write_to_info_dir(dir.path(), None, Split::NoMerge)?;
+        assert_eq!(outcome.path, None, "nothing to do");
+
+        let outcome = Graph::write_to_info_dir(dir.path(), None, Split::Replace)?;
+        assert_eq!(outcome.num_commits, 3);
+        assert_eq!(outcome.num_files, 1);
+        assert_eq!(chain_len(dir.path()), 1);
+        assert_eq!(num_graph_files(dir.path()), 1, "merged files are removed");
+
+        let actual = Graph::from_info_dir(dir.path())?;
+        assert_same_commits(&actual, &expected);
+        assert_eq!(verify(&actual)?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn size_multiple_merges_files_which_are_not_large_enough() -> crate::Result {
+        let (expected, commits) = commits_in_order()?;
+        let dir = TempDir::new()?;
+        for commit in &commits[..2] {
+            Graph::write_to_info_dir(dir.path(), Some(commit.clone()), Split::NoMerge)?;
+        }
+        assert_eq!(chain_len(dir.path()), 2);
+
+        let outcome = Graph::write_to_info_dir(
+            dir.path(),
+            Some(commits[2].clone()),
+            Split::SizeMultiple {
+                factor: 2,
+                max_commits: None,
+            },
+        )?;
+        assert_eq!(outcome.num_commits, 3);
+        assert_eq!(outcome.num_files, 1);
+        assert_eq!(num_graph_files(dir.path()), 1);
+
+        let actual = Graph::from_info_dir(dir.path())?;
+        assert_same_commits(&actual, &expected);
+        assert_eq!(verify(&actual)?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn locked_chain_files_are_not_written() -> crate::Result {
+        let (_expected, commits) = commits_in_order()?;
+        let dir = TempDir::new()?;
+        Graph::write_to_info_dir(dir.path(), Some(commits[0].clone()), Split::NoMerge)?;
+        let lock_path = dir.path().join("commit-graphs").join("commit-graph-chain.lock");
+        std::fs::write(&lock_path, b"")?;
+
+        assert!(
+            Graph::write_to_info_dir(dir.path(), Some(commits[1].clone()), Split::NoMerge).is_err(),
+            "another process holds the lock"
+        );
+        assert_eq!(chain_len(dir.path()), 1);
+        assert_eq!(num_graph_files(dir.path()), 1, "nothing was written");
+
+        std::fs::remove_file(lock_path)?;
+        let outcome = Graph::write_to_info_dir(dir.path(), Some(commits[1].clone()), Split::NoMerge)?;
+        assert_eq!(outcome.num_files, 2);
+        assert!(
+            std::fs::read_dir(dir.path().join("commit-graphs"))?.all(|e| !e
+                .expect("valid entry")
+                .file_name()
+                .to_string_lossy()
+                .ends_with(".lock")),
+            "locks are released"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn switching_to_a_single_file_removes_the_chain() -> crate::Result {
+        let (expected, commits) = commits_in_order()?;
+        let dir = TempDir::new()?;
+        Graph::write_to_info_dir(dir.path(), commits[..2].to_vec(), Split::NoMerge)?;
+        let outcome = Graph::write_to_info_dir(dir.path(), Some(commits[2].clone()), Split::No)?;
+        assert_eq!(outcome.num_commits, 3);
+        assert!(!dir.path().join("commit-graphs").join("commit-graph-chain").exists());
+        assert_eq!(num_graph_files(dir.path()), 0);
+
+        let actual = Graph::from_info_dir(dir.path())?;
+        assert_same_commits(&actual, &expected);
+        check_common(
+            &actual,
+            &inspect_refs(
+                &make_readonly_repo("split_chain.sh"),
+                &["commit1", "commit2", "commit3"],
+            ),
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,316 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use bstr::{BString, ByteSlice};
+use git_hash::ObjectId;
+
+use crate::{Entry, File};
+
+const SIGNATURE: &[u8] = b"DIRC";
+const HASH_LEN: usize = 20;
+const PATH_LEN_MASK: u16 = 0xfff;
+const FLAG_EXTENDED: u16 = 0x4000;
+const EXTENDED_FLAG_INTENT_TO_ADD: u16 = 0x2000;
+
+/// The error returned by [`File::at()`] and [`File::from_bytes()`].
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("Could not read the index file at '{}'", .path.display())]
+    Io { source: std::io::Error, path: PathBuf },
+    #[error("The index is corrupt: {message}")]
+    Corrupt { message: &'static str },
+    #[error("Index version {version} isn't supported")]
+    UnsupportedVersion { version: u32 },
+    #[error("The index uses the extension '{signature}' which is required to understand it, but isn't supported")]
+    UnsupportedExtension { signature: BString },
+    #[error("The index is a split index, but its shared index couldn't be read")]
+    SharedIndex(#[source] Box<Error>),
+}
+
+impl File {
+    /// Read the index file at `path`. If it is a split index, the shared index it's based on is read from the same directory
+    /// and merged into it.
+    pub fn at(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|source| Error::Io {
+            source,
+            path: path.to_owned(),
+        })?;
+        Self::from_bytes(&data, path.parent().unwrap_or_else(|| Path::new(".")))
+    }
+
+    /// Decode an index file from `data`. If it is a split index, the shared index it's based on is read from `shared_index_dir`,
+    /// which is usually the directory containing the index file.
+    pub fn from_bytes(data: &[u8], shared_index_dir: &Path) -> Result<Self, Error> {
+        let Decoded { mut file, shared_index } = decode(data)?;
+        let link = match shared_index {
+            Some(link) => link,
+            None => return Ok(file),
+        };
+        let shared = File::at(shared_index_dir.join(format!("sharedindex.{}", link.id)))
+            .map_err(|err| Error::SharedIndex(Box::new(err)))?;
+        if shared.entries.iter().any(|e| e.path.is_empty()) {
+            return Err(corrupt(
+                "the shared index of a split index contains entries without path",
+            ));
+        }
+
+        // Replaced entries of the shared index are stored as entries without path at the beginning of the split index,
+        // in order. All other entries of the split index are added to the shared index or replace entries with the same path.
+        let num_replacements = link.replaced.len();
+        if file.entries.len() < num_replacements || file.entries[..num_replacements].iter().any(|e| !e.path.is_empty())
+        {
+            return Err(corrupt(
+                "the split index has fewer replacements than the shared index needs",
+            ));
+        }
+        let mut replacements = file.entries.drain(..num_replacements);
+        let mut entries = BTreeMap::new();
+        for (pos, entry) in shared.entries.into_iter().enumerate() {
+            if link.deleted.binary_search(&pos).is_ok() {
+                continue;
+            }
+            let entry = match link.replaced.binary_search(&pos) {
+                Ok(_) => Entry {
+                    path: entry.path,
+                    ..replacements.next().expect("checked amount of replacements")
+                },
+                Err(_) => entry,
+            };
+            entries.insert((entry.path.clone(), entry.stage), entry);
+        }
+        drop(replacements);
+        for entry in file.entries.drain(..) {
+            entries.insert((entry.path.clone(), entry.stage), entry);
+        }
+        file.entries = entries.into_iter().map(|(_, entry)| entry).collect();
+        file.tree_ids.extend(shared.tree_ids);
+        Ok(file)
+    }
+}
+
+struct Decoded {
+    file: File,
+    shared_index: Option<Link>,
+}
+
+/// The content of the `link` extension of a split index.
+struct Link {
+    /// The id of the shared index, which is also part of its file name.
+    id: ObjectId,
+    /// The sorted positions of the entries in the shared index which are deleted.
+    deleted: Vec<usize>,
+    /// The sorted positions of the entries in the shared index which are replaced by entries in the split index.
+    replaced: Vec<usize>,
+}
+
+fn corrupt(message: &'static str) -> Error {
+    Error::Corrupt { message }
+}
+
+/// A cursor into the data of an index file, failing if reads go past its end.
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| corrupt("unexpected end of data"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        Ok((u64::from(self.u32()?) << 32) | u64::from(self.u32()?))
+    }
+
+    fn id(&mut self) -> Result<ObjectId, Error> {
+        Ok(ObjectId::from_20_bytes(self.bytes(HASH_LEN)?))
+    }
+
+    /// Return all bytes up to the next NUL byte, and skip the NUL byte as well.
+    fn nul_terminated(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.data[self.pos.min(self.data.len())..]
+            .find_byte(0)
+            .ok_or_else(|| corrupt("missing NUL byte"))?;
+        let bytes = self.bytes(len)?;
+        self.pos += 1;
+        Ok(bytes)
+    }
+
+    /// Decode a variable length integer as used for the prefix compression of paths in index version 4.
+    fn varint(&mut self) -> Result<usize, Error> {
+        let mut byte = self.bytes(1)?[0];
+        let mut value = usize::from(byte & 0x7f);
+        while byte & 0x80 != 0 {
+            byte = self.bytes(1)?[0];
+            value = ((value + 1) << 7) | usize::from(byte & 0x7f);
+        }
+        Ok(value)
+    }
+}
+
+fn decode(data: &[u8]) -> Result<Decoded, Error> {
+    let mut cursor = Cursor { data, pos: 0 };
+    if cursor.bytes(SIGNATURE.len())? != SIGNATURE {
+        return Err(corrupt("invalid signature"));
+    }
+    let version = cursor.u32()?;
+    if !(2..=4).contains(&version) {
+        return Err(Error::UnsupportedVersion { version });
+    }
+    let num_entries = cursor.u32()?;
+
+    let mut file = File::default();
+    let mut previous_path = BString::default();
+    for _ in 0..num_entries {
+        let start = cursor.pos;
+        // ctime, mtime, dev and ino
+        cursor.bytes(6 * 4)?;
+        let mode = cursor.u32()?;
+        // uid, gid and size
+        cursor.bytes(3 * 4)?;
+        let id = cursor.id()?;
+        let flags = cursor.u16()?;
+        let mut is_intent_to_add = false;
+        if flags & FLAG_EXTENDED != 0 {
+            if version < 3 {
+                return Err(corrupt("extended flags are only supported in version 3 and later"));
+            }
+            is_intent_to_add = cursor.u16()? & EXTENDED_FLAG_INTENT_TO_ADD != 0;
+        }
+        let path = if version == 4 {
+            // The path is stored as the amount of bytes to remove from the end of the previous path, and a suffix to append.
+            let strip = cursor.varint()?;
+            let prefix_len = previous_path
+                .len()
+                .checked_sub(strip)
+                .ok_or_else(|| corrupt("a path removes more than the previous path"))?;
+            let mut path: BString = previous_path[..prefix_len].into();
+            path.extend_from_slice(cursor.nul_terminated()?);
+            previous_path = path.clone();
+            path
+        } else {
+            let len = flags & PATH_LEN_MASK;
+            let path = if len == PATH_LEN_MASK {
+                cursor.nul_terminated()?
+            } else {
+                let path = cursor.bytes(len.into())?;
+                cursor.pos += 1;
+                path
+            };
+            // Entries are padded with 1 to 8 NUL bytes to a multiple of 8 bytes, including the one terminating the path.
+            cursor.pos = start + ((cursor.pos - 1 - start + 8) & !7);
+            path.into()
+        };
+        file.entries.push(Entry {
+            path,
+            mode,
+            id,
+            stage: ((flags >> 12) & 0b11) as u8,
+            is_intent_to_add,
+        });
+    }
+
+    let mut shared_index = None;
+    let extensions_end = data
+        .len()
+        .checked_sub(HASH_LEN)
+        .ok_or_else(|| corrupt("missing checksum"))?;
+    while cursor.pos + 8 <= extensions_end {
+        let signature = cursor.bytes(4)?;
+        let size = cursor.u32()? as usize;
+        let mut extension = Cursor {
+            data: cursor.bytes(size)?,
+            pos: 0,
+        };
+        match signature {
+            b"TREE" => decode_tree(&mut extension, &mut file.tree_ids)?,
+            b"link" => {
+                let id = extension.id()?;
+                let (deleted, replaced) = if extension.pos < extension.data.len() {
+                    (decode_ewah(&mut extension)?, decode_ewah(&mut extension)?)
+                } else {
+                    Default::default()
+                };
+                if !id.is_null() {
+                    shared_index = Some(Link { id, deleted, replaced });
+                }
+            }
+            // Extensions starting with an uppercase letter are optional and can be ignored.
+            _ if signature[0].is_ascii_uppercase() => {}
+            _ => {
+                return Err(Error::UnsupportedExtension {
+                    signature: signature.into(),
+                })
+            }
+        }
+    }
+    Ok(Decoded { file, shared_index })
+}
+
+/// Add the ids of all valid trees in the cache-tree `extension` to `tree_ids`.
+fn decode_tree(extension: &mut Cursor<'_>, tree_ids: &mut Vec<ObjectId>) -> Result<(), Error> {
+    while extension.pos < extension.data.len() {
+        // Each tree is recorded as `<path>\0<entry count> <subtree count>\n<id>`.
+        extension.nul_terminated()?;
+        let counts_len = extension.data[extension.pos..]
+            .find_byte(b'\n')
+            .ok_or_else(|| corrupt("a cached tree has no entry count"))?;
+        let entry_count = extension.bytes(counts_len)?;
+        extension.pos += 1;
+        // Invalidated trees have a negative entry count and no id.
+        if entry_count.first() != Some(&b'-') {
+            tree_ids.push(extension.id()?);
+        }
+    }
+    Ok(())
+}
+
+/// Decode an EWAH compressed bitmap and return the sorted positions of all set bits.
+fn decode_ewah(cursor: &mut Cursor<'_>) -> Result<Vec<usize>, Error> {
+    let num_bits = cursor.u32()? as usize;
+    let num_words = cursor.u32()? as usize;
+    let mut words = Vec::with_capacity(num_words.min(cursor.data.len() / 8));
+    for _ in 0..num_words {
+        words.push(cursor.u64()?);
+    }
+    // The position of the last run length word, which is only needed to append to the bitmap.
+    cursor.u32()?;
+
+    let mut set_bits = Vec::new();
+    let mut bit = 0;
+    let mut words = words.into_iter();
+    // Each run length word denotes a run of words with all bits set or unset, followed by the amount of literal words.
+    while let Some(marker) = words.next() {
+        let run_len = ((marker >> 1) & 0xffff_ffff) as usize * 64;
+        if marker & 1 == 1 {
+            set_bits.extend(bit..bit + run_len);
+        }
+        bit += run_len;
+        for _ in 0..(marker >> 33) {
+            let word = words.next().ok_or_else(|| corrupt("a bitmap ends prematurely"))?;
+            set_bits.extend((0..64).filter(|n| word & (1 << n) != 0).map(|n| bit + n));
+            bit += 64;
+        }
+    }
+    set_bits.retain(|bit| *bit < num_bits);
+    Ok(set_bits)
+}
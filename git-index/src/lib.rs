@@ -1 +1,39 @@
+//! Read and write the git index file, which records the state of the files in a work tree to be committed next.
 #![forbid(unsafe_code, rust_2018_idioms)]
+#![deny(missing_docs)]
+
+use bstr::BString;
+use git_hash::ObjectId;
+
+///
+pub mod decode;
+///
+pub mod write;
+
+/// An entry of the index, which is a file in the work tree along with the object id of its content.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Entry {
+    /// The slash-separated path of the file relative to the root of the work tree.
+    pub path: BString,
+    /// The mode of the file as it would be recorded in a tree, like `0o100644` for files, `0o100755` for executables,
+    /// `0o120000` for symbolic links and `0o160000` for submodules.
+    pub mode: u32,
+    /// The id of the blob with the file's content, or the commit checked out in a submodule.
+    pub id: ObjectId,
+    /// The stage of the entry, which is 0 unless it is one side of a merge conflict, with 1 being the common ancestor,
+    /// 2 being ours and 3 being theirs.
+    pub stage: u8,
+    /// If true, the entry was added with `git add --intent-to-add` and its `id` is the one of an empty blob which
+    /// isn't necessarily stored in the object database.
+    pub is_intent_to_add: bool,
+}
+
+/// The content of an index file, along with the content of the shared index it's based on if it is a split index.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default)]
+pub struct File {
+    /// All entries, sorted by path and stage.
+    pub entries: Vec<Entry>,
+    /// The ids of all valid trees recorded in the cache-tree extension, which are the trees that would be written for
+    /// the entries in their directories.
+    pub tree_ids: Vec<ObjectId>,
+}
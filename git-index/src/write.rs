@@ -0,0 +1,63 @@
+use std::{io, io::Write};
+
+use crate::Entry;
+
+const SIGNATURE: &[u8] = b"DIRC";
+/// Paths as long or longer than this are recorded as this length in the flags of an entry.
+const PATH_LEN_MASK: usize = 0xfff;
+const FLAG_EXTENDED: u16 = 0x4000;
+const EXTENDED_FLAG_INTENT_TO_ADD: u16 = 0x2000;
+
+/// Write `entries` in index format version 2 to `out`, followed by the checksum of everything written, without any extensions.
+/// Version 3 is written instead if one of the entries [is intended to be added][Entry::is_intent_to_add].
+///
+/// `entries` are sorted by path and stage as required by the format, and must not contain the same path and stage twice.
+/// The stat information of all entries is zero, which causes git to compare the content of each file with its entry
+/// before refreshing the index.
+pub fn entries(entries: &mut [Entry], out: impl Write) -> io::Result<()> {
+    entries.sort_by(|lhs, rhs| (&lhs.path, lhs.stage).cmp(&(&rhs.path, rhs.stage)));
+    if let Some(pair) = entries
+        .windows(2)
+        .find(|pair| (&pair[0].path, pair[0].stage) == (&pair[1].path, pair[1].stage))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("The path '{}' was added to the index twice", pair[0].path),
+        ));
+    }
+    let version: u32 = if entries.iter().any(|e| e.is_intent_to_add) {
+        3
+    } else {
+        2
+    };
+
+    let mut out = git_features::hash::Write::new(out, git_hash::Kind::Sha1);
+    out.write_all(SIGNATURE)?;
+    out.write_all(&version.to_be_bytes())?;
+    out.write_all(&(entries.len() as u32).to_be_bytes())?;
+    for entry in entries.iter() {
+        // ctime, mtime, dev, ino
+        out.write_all(&[0; 6 * 4])?;
+        out.write_all(&entry.mode.to_be_bytes())?;
+        // uid, gid, size
+        out.write_all(&[0; 3 * 4])?;
+        out.write_all(entry.id.as_bytes())?;
+        let mut flags = entry.path.len().min(PATH_LEN_MASK) as u16 | (u16::from(entry.stage & 0b11) << 12);
+        if entry.is_intent_to_add {
+            flags |= FLAG_EXTENDED;
+        }
+        out.write_all(&flags.to_be_bytes())?;
+        let mut len = 40 + entry.id.as_bytes().len() + 2;
+        if entry.is_intent_to_add {
+            out.write_all(&EXTENDED_FLAG_INTENT_TO_ADD.to_be_bytes())?;
+            len += 2;
+        }
+        out.write_all(&entry.path)?;
+        len += entry.path.len();
+        // Entries are padded with 1 to 8 NUL bytes to a multiple of 8 bytes.
+        out.write_all(&[0; 8][..8 - len % 8])?;
+    }
+    let checksum = out.hash.digest();
+    out.inner.write_all(&checksum)?;
+    out.inner.flush()
+}
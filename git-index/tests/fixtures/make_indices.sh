@@ -0,0 +1,48 @@
+#!/bin/bash
+set -eu -o pipefail
+
+function init_repo() {
+  git init -q "$1"
+  cd "$1"
+  git config commit.gpgsign false
+  mkdir -p dir/sub
+  for n in $(seq 10); do echo $n > file$n; done
+  echo b > dir/b
+  echo c > dir/sub/c
+  echo x > exe && chmod +x exe
+  ln -s dir/b link
+  git add .
+  git commit -q -m c1
+}
+
+(init_repo v2)
+
+(init_repo v3
+  echo new > intent
+  git add --intent-to-add intent
+)
+
+(init_repo v4
+  git update-index --index-version 4
+  echo new > dir/sub/d
+  git add dir/sub/d
+)
+
+(init_repo split
+  git config splitIndex.maxPercentChange 100
+  git update-index --split-index
+  echo changed > file1
+  echo new > file11
+  git add file1 file11
+  git rm -q --cached file2
+)
+
+(init_repo conflict
+  git checkout -q -b other
+  echo other > file1
+  git commit -q -am other
+  git checkout -q -
+  echo main > file1
+  git commit -q -am main
+  git merge -q other || :
+)
@@ -0,0 +1,126 @@
+use std::{path::Path, process::Command};
+
+use bstr::ByteSlice;
+use git_hash::ObjectId;
+use git_index::{decode, File};
+
+type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+
+fn fixture(name: &str) -> std::result::Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+    Ok(git_testtools::scripted_fixture_repo_read_only("make_indices.sh")?
+        .join(name)
+        .canonicalize()?)
+}
+
+/// Return the output of `git ls-files --stage` for the index at `index_path` in the repository at `repo`.
+fn ls_files(repo: &Path, index_path: &Path) -> std::result::Result<String, Box<dyn std::error::Error>> {
+    let out = Command::new("git")
+        .args(&["ls-files", "--stage"])
+        .env("GIT_INDEX_FILE", index_path)
+        .current_dir(repo)
+        .output()?;
+    assert!(out.status.success(), "{}", out.stderr.as_bstr());
+    Ok(String::from_utf8(out.stdout)?)
+}
+
+fn to_ls_files(file: &File) -> String {
+    file.entries
+        .iter()
+        .map(|e| format!("{:06o} {} {}\t{}\n", e.mode, e.id, e.stage, e.path))
+        .collect()
+}
+
+fn head_tree(repo: &Path) -> std::result::Result<ObjectId, Box<dyn std::error::Error>> {
+    let out = Command::new("git")
+        .args(&["rev-parse", "HEAD^{tree}"])
+        .current_dir(repo)
+        .output()?;
+    Ok(ObjectId::from_hex(out.stdout.trim())?)
+}
+
+#[test]
+fn all_versions_split_indices_and_conflicts_decode_like_git_sees_them() -> Result {
+    for name in &["v2", "v3", "v4", "split", "conflict"] {
+        let repo = fixture(name)?;
+        let index_path = repo.join(".git").join("index");
+        let file = File::at(&index_path)?;
+        assert_eq!(to_ls_files(&file), ls_files(&repo, &index_path)?, "{}", name);
+    }
+    Ok(())
+}
+
+#[test]
+fn intent_to_add_entries_are_marked() -> Result {
+    let file = File::at(fixture("v3")?.join(".git").join("index"))?;
+    let marked: Vec<_> = file
+        .entries
+        .iter()
+        .filter(|e| e.is_intent_to_add)
+        .map(|e| e.path.clone())
+        .collect();
+    assert_eq!(marked, vec!["intent"]);
+    Ok(())
+}
+
+#[test]
+fn the_cache_tree_provides_the_ids_of_valid_trees() -> Result {
+    let repo = fixture("v2")?;
+    let file = File::at(repo.join(".git").join("index"))?;
+    assert_eq!(file.tree_ids.len(), 3, "the root tree, dir and dir/sub");
+    assert_eq!(file.tree_ids[0], head_tree(&repo)?);
+
+    let file = File::at(fixture("v4")?.join(".git").join("index"))?;
+    assert!(
+        file.tree_ids.is_empty(),
+        "adding dir/sub/d invalidated all trees containing it"
+    );
+    Ok(())
+}
+
+#[test]
+fn the_shared_index_of_a_split_index_is_required() -> Result {
+    let repo = fixture("split")?;
+    let data = std::fs::read(repo.join(".git").join("index"))?;
+    let err = File::from_bytes(&data, Path::new("does-not-exist")).unwrap_err();
+    assert!(matches!(err, decode::Error::SharedIndex(_)));
+    Ok(())
+}
+
+#[test]
+fn unknown_lowercase_extensions_are_rejected_while_uppercase_ones_are_ignored() -> Result {
+    let repo = fixture("v2")?;
+    let data = std::fs::read(repo.join(".git").join("index"))?;
+    let with_extension = |signature: &[u8]| {
+        let (content, checksum) = data.split_at(data.len() - 20);
+        let mut data = content.to_vec();
+        data.extend_from_slice(signature);
+        data.extend_from_slice(&4u32.to_be_bytes());
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(checksum);
+        data
+    };
+
+    let err = File::from_bytes(&with_extension(b"abcd"), &repo).unwrap_err();
+    assert!(matches!(err, decode::Error::UnsupportedExtension { signature } if signature == "abcd"));
+    assert_eq!(
+        File::from_bytes(&with_extension(b"ABCD"), &repo)?,
+        File::from_bytes(&data, &repo)?
+    );
+    Ok(())
+}
+
+#[test]
+fn written_indices_are_read_by_git_and_round_trip() -> Result {
+    for name in &["v2", "v3", "v4", "conflict"] {
+        let repo = fixture(name)?;
+        let mut file = File::at(repo.join(".git").join("index"))?;
+        let dir = tempfile::tempdir()?;
+        let index_path = dir.path().join("index");
+        git_index::write::entries(&mut file.entries, std::fs::File::create(&index_path)?)?;
+
+        let written = File::at(&index_path)?;
+        assert_eq!(written.entries, file.entries, "{}", name);
+        assert_eq!(to_ls_files(&written), ls_files(&repo, &index_path)?, "{}", name);
+    }
+    Ok(())
+}
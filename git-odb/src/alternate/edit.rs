@@ -0,0 +1,237 @@
+use std::{
+    borrow::Cow,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use git_object::bstr::{BStr, BString, ByteSlice};
+
+use crate::{
+    alternate::{self, parse},
+    store::transfer::{self, io_err, read_dir},
+};
+
+/// The error returned by [`add()`], [`remove()`] and [`dissociate()`].
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("Could not {message} '{}'", .path.display())]
+    Io {
+        source: io::Error,
+        message: &'static str,
+        path: PathBuf,
+    },
+    #[error(transparent)]
+    Lock(#[from] git_lock::acquire::Error),
+    #[error("Could not write the alternates file")]
+    Commit(#[from] git_lock::commit::Error<git_lock::File>),
+    #[error(transparent)]
+    Parse(#[from] parse::Error),
+    #[error(transparent)]
+    Resolve(#[from] alternate::Error),
+    #[error("The alternate object directory at '{}' does not exist", .0.display())]
+    NotADirectory(PathBuf),
+    #[error("The objects directory at '{}' cannot be its own alternate", .0.display())]
+    SelfReference(PathBuf),
+    #[error("The path '{}' cannot be represented in an alternates file", .0.display())]
+    PathConversion(PathBuf),
+}
+
+impl From<transfer::Error> for Error {
+    fn from(transfer::Error { source, message, path }: transfer::Error) -> Self {
+        Error::Io { source, message, path }
+    }
+}
+
+/// Add `alternate` to the `./info/alternates` file of `objects_directory` to be able to read its objects, creating the file if needed.
+///
+/// Relative paths are relative to `objects_directory`. Return `false` if `alternate` was present already, leaving the file unchanged.
+/// Note that stores need to be refreshed to see the objects of the new alternate.
+pub fn add(objects_directory: impl AsRef<Path>, alternate: impl AsRef<Path>) -> Result<bool, Error> {
+    let (objects_directory, alternate) = (objects_directory.as_ref(), alternate.as_ref());
+    let alternate_canonicalized = objects_directory
+        .join(alternate)
+        .canonicalize()
+        .ok()
+        .filter(|path| path.is_dir())
+        .ok_or_else(|| Error::NotADirectory(alternate.to_owned()))?;
+    if objects_directory
+        .canonicalize()
+        .map_err(|err| io_err(err, "canonicalize objects directory", objects_directory))?
+        == alternate_canonicalized
+    {
+        return Err(Error::SelfReference(objects_directory.to_owned()));
+    }
+    let entry = <[u8]>::from_path(alternate)
+        .map(|path| quote_if_needed(path.as_bstr()))
+        .ok_or_else(|| Error::PathConversion(alternate.to_owned()))?;
+
+    let lock = lock_alternates_file(objects_directory)?;
+    let mut content = read_alternates_file(objects_directory)?;
+    for existing in parse::content(&content)? {
+        if existing == alternate
+            || canonicalized(objects_directory, &existing).as_ref() == Some(&alternate_canonicalized)
+        {
+            return Ok(false);
+        }
+    }
+    if !content.is_empty() && !content.ends_with(b"\n") {
+        content.push(b'\n');
+    }
+    content.extend_from_slice(&entry);
+    content.push(b'\n');
+    write_and_commit(lock, &content)?;
+    Ok(true)
+}
+
+/// Remove all entries of `alternate` from the `./info/alternates` file of `objects_directory`, or the entire file if no other entries
+/// or comments remain.
+///
+/// Relative paths are relative to `objects_directory`. Return `false` if `alternate` wasn't present.
+/// Note that stores need to be refreshed to stop using the removed alternate.
+pub fn remove(objects_directory: impl AsRef<Path>, alternate: impl AsRef<Path>) -> Result<bool, Error> {
+    let (objects_directory, alternate) = (objects_directory.as_ref(), alternate.as_ref());
+    let alternate_canonicalized = canonicalized(objects_directory, alternate);
+
+    let lock = lock_alternates_file(objects_directory)?;
+    let content = read_alternates_file(objects_directory)?;
+    let mut removed = false;
+    let mut remaining = Vec::with_capacity(content.len());
+    for line in content.lines_with_terminator() {
+        let entry = line.trim_end_with(|c| c == '\n').as_bstr();
+        if !entry.is_empty() && !entry.starts_with(b"#") {
+            let path = parse::path(entry)?;
+            if path == alternate
+                || (alternate_canonicalized.is_some()
+                    && canonicalized(objects_directory, &path) == alternate_canonicalized)
+            {
+                removed = true;
+                continue;
+            }
+        }
+        remaining.extend_from_slice(line);
+    }
+    if !removed {
+        return Ok(false);
+    }
+    if remaining.trim().is_empty() {
+        let path = lock.resource_path();
+        fs::remove_file(&path).map_err(|err| io_err(err, "remove alternates file", &path))?;
+    } else {
+        write_and_commit(lock, &remaining)?;
+    }
+    Ok(true)
+}
+
+/// Copy all objects of the alternates of `objects_directory`, including their alternates in turn, into `objects_directory`
+/// and remove its `./info/alternates` file to make it independent of them, similar to `git clone --dissociate`.
+///
+/// Packs are copied as a whole with their index copied last, while loose objects and packs which are present already are skipped.
+/// Note that stores need to be refreshed to stop using the alternates.
+pub fn dissociate(objects_directory: impl AsRef<Path>) -> Result<(), Error> {
+    let objects_directory = objects_directory.as_ref();
+    let lock = lock_alternates_file(objects_directory)?;
+    for alternate in alternate::resolve(objects_directory)? {
+        let pack_dir = alternate.join("pack");
+        let mut pack_files: Vec<_> = if pack_dir.is_dir() {
+            read_dir(&pack_dir)?
+        } else {
+            Vec::new()
+        }
+        .into_iter()
+        .filter(|(path, name)| name.starts_with("pack-") && pack_copy_priority(path).is_some())
+        .collect();
+        if !pack_files.is_empty() {
+            pack_files.sort_by_key(|(path, _)| pack_copy_priority(path));
+            let destination_dir = objects_directory.join("pack");
+            fs::create_dir_all(&destination_dir).map_err(|err| io_err(err, "create directory", &destination_dir))?;
+            for (path, name) in pack_files {
+                copy_unless_present(&path, &destination_dir.join(name))?;
+            }
+        }
+
+        transfer::loose_objects(&alternate, objects_directory, copy_unless_present)?;
+    }
+
+    let path = lock.resource_path();
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(io_err(err, "remove alternates file", &path).into()),
+    }
+}
+
+fn lock_alternates_file(objects_directory: &Path) -> Result<git_lock::File, Error> {
+    Ok(git_lock::File::acquire_to_update_resource(
+        objects_directory.join("info").join("alternates"),
+        git_lock::acquire::Fail::Immediately,
+        Some(objects_directory.to_owned()),
+    )?)
+}
+
+fn read_alternates_file(objects_directory: &Path) -> Result<Vec<u8>, Error> {
+    let path = objects_directory.join("info").join("alternates");
+    match fs::read(&path) {
+        Ok(content) => Ok(content),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(io_err(err, "read alternates file", &path).into()),
+    }
+}
+
+fn write_and_commit(mut lock: git_lock::File, content: &[u8]) -> Result<(), Error> {
+    use std::io::Write;
+    let lock_path = lock.lock_path().to_owned();
+    lock.write_all(content)
+        .map_err(|err| io_err(err, "write alternates lock file", &lock_path))?;
+    lock.commit()?;
+    Ok(())
+}
+
+fn canonicalized(objects_directory: &Path, alternate: &Path) -> Option<PathBuf> {
+    objects_directory.join(alternate).canonicalize().ok()
+}
+
+/// Quote `path` like git would if it couldn't otherwise be read back from an alternates file.
+fn quote_if_needed(path: &BStr) -> Cow<'_, BStr> {
+    if !(path.starts_with(b"\"") || path.starts_with(b"#") || path.contains(&b'\n')) {
+        return path.into();
+    }
+    let mut out = BString::from("\"");
+    for &byte in path.iter() {
+        match byte {
+            b'"' => out.extend_from_slice(b"\\\""),
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            b'\t' => out.extend_from_slice(b"\\t"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            byte if byte < 0x20 || byte == 0x7f => out.extend_from_slice(format!("\\{:03o}", byte).as_bytes()),
+            byte => out.push(byte),
+        }
+    }
+    out.push(b'"');
+    out.into()
+}
+
+/// Copy pack data first and indices last so packs are complete once they can be discovered, or return `None` for files
+/// which don't need to be copied.
+fn pack_copy_priority(path: &Path) -> Option<usize> {
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+    ["pack", "rev", "promisor", "idx"]
+        .iter()
+        .position(|candidate| *candidate == extension)
+}
+
+fn copy_unless_present(source: &Path, destination: &Path) -> Result<(), Error> {
+    if transfer::is_present(destination) {
+        return Ok(());
+    }
+    let destination_dir = destination.parent().expect("destination is within a directory");
+    let mut tempfile = tempfile::NamedTempFile::new_in(destination_dir)
+        .map_err(|err| io_err(err, "create file in", destination_dir))?;
+    let mut input = fs::File::open(source).map_err(|err| io_err(err, "open", source))?;
+    io::copy(&mut input, tempfile.as_file_mut()).map_err(|err| io_err(err, "copy file to", destination))?;
+    tempfile
+        .persist(destination)
+        .map_err(|err| io_err(err.error, "move copied file to", destination))?;
+    Ok(())
+}
@@ -16,15 +16,29 @@
 //! ```
 //!
 //! Based on the [canonical implementation](https://github.com/git/git/blob/master/sha1-file.c#L598:L609).
-use std::{fs, io, path::PathBuf};
+//!
+//! Alternates can be [listed][list()], [added][add()] and [removed][remove()], and objects borrowed from alternates can be copied
+//! into the local object database to [dissociate][dissociate()] from them.
+//! Additional alternates can be provided in the [`GIT_ALTERNATE_OBJECT_DIRECTORIES`][ENVIRONMENT_VARIABLE] environment variable,
+//! which is [read][from_environment()] by the caller and passed to the object stores explicitly.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
 
 use crate::store::compound;
 
+///
+pub mod edit;
+pub use edit::{add, dissociate, remove};
 ///
 pub mod parse;
 #[allow(missing_docs)]
 pub mod unquote;
 
+/// The environment variable with additional alternate object directories, separated by `:` (or `;` on windows).
+pub const ENVIRONMENT_VARIABLE: &str = "GIT_ALTERNATE_OBJECT_DIRECTORIES";
+
 /// Returned by [`resolve()`]
 #[derive(thiserror::Error, Debug)]
 #[allow(missing_docs)]
@@ -45,16 +59,53 @@ pub enum Error {
 /// if there are no alternates).
 /// It is an error once a repository is seen again as it would lead to a cycle.
 pub fn resolve(objects_directory: impl Into<PathBuf>) -> Result<Vec<PathBuf>, Error> {
+    resolve_with(objects_directory, None)
+}
+
+/// Like [`resolve()`], but with `additional` alternate object directories which are used before the ones in the `./info/alternates`
+/// file, typically those obtained [from the environment][from_environment()].
+///
+/// The alternates of `additional` directories are resolved as well, and directories which were seen already are ignored.
+/// Like git, `additional` directories which don't exist are skipped with a warning.
+pub fn resolve_with(
+    objects_directory: impl Into<PathBuf>,
+    additional: impl IntoIterator<Item = PathBuf>,
+) -> Result<Vec<PathBuf>, Error> {
     let relative_base = objects_directory.into();
     let mut dirs = vec![(0, relative_base.clone())];
     let mut out = Vec::new();
     let mut seen = vec![relative_base.canonicalize()?];
+    let mut additional_dirs = Vec::new();
+    for path in additional {
+        let path_canonicalized = match path.canonicalize() {
+            Ok(path) => path,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                log::warn!(
+                    "Ignoring alternate object directory '{}' as it doesn't exist",
+                    path.display()
+                );
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if seen.contains(&path_canonicalized) {
+            continue;
+        }
+        seen.push(path_canonicalized);
+        additional_dirs.push((1, path));
+    }
+    let num_additional = seen.len() - 1;
+    // directories are processed last to first
+    dirs.extend(additional_dirs.into_iter().rev());
     while let Some((depth, dir)) = dirs.pop() {
         match fs::read(dir.join("info").join("alternates")) {
             Ok(input) => {
                 for path in parse::content(&input)?.into_iter() {
                     let path = relative_base.join(path);
                     let path_canonicalized = path.canonicalize()?;
+                    if seen[1..=num_additional].contains(&path_canonicalized) {
+                        continue;
+                    }
                     if seen.contains(&path_canonicalized) {
                         return Err(Error::Cycle(seen));
                     }
@@ -71,3 +122,28 @@ pub fn resolve(objects_directory: impl Into<PathBuf>) -> Result<Vec<PathBuf>, Er
     }
     Ok(out)
 }
+
+/// Return the alternate object directories listed in the `./info/alternates` file of `objects_directory` as written, without following
+/// their alternates in turn. Relative paths are relative to `objects_directory`.
+///
+/// If there is no such file, the resulting `Vec` is empty.
+pub fn list(objects_directory: impl AsRef<Path>) -> Result<Vec<PathBuf>, Error> {
+    match fs::read(objects_directory.as_ref().join("info").join("alternates")) {
+        Ok(input) => Ok(parse::content(&input)?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Return the alternate object directories in the [`ENVIRONMENT_VARIABLE`], or an empty `Vec` if it is not set.
+pub fn from_environment() -> Result<Vec<PathBuf>, Error> {
+    use git_object::bstr::ByteVec;
+    match std::env::var_os(ENVIRONMENT_VARIABLE) {
+        Some(value) => {
+            let value = Vec::from_os_string(value)
+                .map_err(|value| parse::Error::PathConversion(value.to_string_lossy().into_owned().into_bytes()))?;
+            Ok(parse::environment(&value)?)
+        }
+        None => Ok(Vec::new()),
+    }
+}
@@ -1,6 +1,6 @@
 use std::{borrow::Cow, path::PathBuf};
 
-use git_object::bstr::ByteSlice;
+use git_object::bstr::{BStr, ByteSlice};
 
 use crate::alternate::unquote;
 
@@ -14,6 +14,12 @@ pub enum Error {
     Unquote(#[from] unquote::Error),
 }
 
+/// The separator of paths in the value of [`ENVIRONMENT_VARIABLE`][crate::alternate::ENVIRONMENT_VARIABLE].
+#[cfg(not(windows))]
+const ENVIRONMENT_SEPARATOR: u8 = b':';
+#[cfg(windows)]
+const ENVIRONMENT_SEPARATOR: u8 = b';';
+
 pub(crate) fn content(input: &[u8]) -> Result<Vec<PathBuf>, Error> {
     let mut out = Vec::new();
     for line in input.split(|b| *b == b'\n') {
@@ -21,16 +27,51 @@ pub(crate) fn content(input: &[u8]) -> Result<Vec<PathBuf>, Error> {
         if line.is_empty() || line.starts_with(b"#") {
             continue;
         }
-        out.push(
-            if line.starts_with(b"\"") {
-                unquote::ansi_c(line)?
-            } else {
-                Cow::Borrowed(line)
+        out.push(path(line)?)
+    }
+    Ok(out)
+}
+
+/// Parse the value of the [`ENVIRONMENT_VARIABLE`][crate::alternate::ENVIRONMENT_VARIABLE] into the paths it contains.
+///
+/// Paths are separated like in the `PATH` variable and may be ansi-c quoted to contain the separator. Empty paths are ignored.
+pub fn environment(input: &[u8]) -> Result<Vec<PathBuf>, Error> {
+    let mut out = Vec::new();
+    let mut input = input.as_bstr();
+    while !input.is_empty() {
+        let quote_end = if input.starts_with(b"\"") {
+            let mut pos = 1;
+            loop {
+                match input.get(pos) {
+                    Some(b'\\') => pos += 2,
+                    Some(b'"') => break pos + 1,
+                    Some(_) => pos += 1,
+                    None => break input.len(),
+                }
             }
-            .to_path()
-            .map(ToOwned::to_owned)
-            .map_err(|_| Error::PathConversion(line.to_vec()))?,
-        )
+            .min(input.len())
+        } else {
+            0
+        };
+        let end = input[quote_end..]
+            .find_byte(ENVIRONMENT_SEPARATOR)
+            .map_or(input.len(), |pos| pos + quote_end);
+        let entry = input[..end].as_bstr();
+        if !entry.is_empty() {
+            out.push(path(entry)?);
+        }
+        input = input.get(end + 1..).unwrap_or_default().as_bstr();
     }
     Ok(out)
 }
+
+pub(crate) fn path(entry: &BStr) -> Result<PathBuf, Error> {
+    if entry.starts_with(b"\"") {
+        unquote::ansi_c(entry)?
+    } else {
+        Cow::Borrowed(entry)
+    }
+    .to_path()
+    .map(ToOwned::to_owned)
+    .map_err(|_| Error::PathConversion(entry.to_vec()))
+}
@@ -63,3 +63,58 @@ pub struct Entry<'a> {
     /// The version of the pack file containing `data`
     pub version: crate::pack::data::Version,
 }
+
+pub use git_pack::find::Header;
+
+/// A reader for the data of an object which doesn't hold the entire object in memory, as returned by the `try_find_stream()`
+/// methods of the [loose][crate::loose::Store::try_find_stream()], [compound][crate::compound::Store::try_find_stream()]
+/// and [linked][crate::linked::Store::try_find_stream()] stores.
+pub struct Stream<'a> {
+    /// The kind of the object.
+    pub kind: git_object::Kind,
+    /// The size of the object in bytes, which is the amount of bytes this stream will yield.
+    pub size: u64,
+    inner: stream::Inner<'a>,
+}
+
+pub(crate) mod stream {
+    use std::{fs, io};
+
+    use git_features::zlib;
+
+    pub(crate) type Loose = io::Chain<io::Cursor<Vec<u8>>, zlib::stream::inflate::ReadBoxed<io::BufReader<fs::File>>>;
+
+    pub(crate) enum Inner<'a> {
+        Loose(Loose),
+        Packed(git_pack::data::decode_stream::Stream<'a>),
+    }
+
+    impl super::Stream<'static> {
+        pub(crate) fn from_loose(kind: git_object::Kind, size: u64, read: Loose) -> Self {
+            super::Stream {
+                kind,
+                size,
+                inner: Inner::Loose(read),
+            }
+        }
+    }
+
+    impl<'a> From<git_pack::data::decode_stream::Stream<'a>> for super::Stream<'a> {
+        fn from(s: git_pack::data::decode_stream::Stream<'a>) -> Self {
+            super::Stream {
+                kind: s.kind,
+                size: s.size,
+                inner: Inner::Packed(s),
+            }
+        }
+    }
+
+    impl io::Read for super::Stream<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match &mut self.inner {
+                Inner::Loose(r) => r.read(buf),
+                Inner::Packed(r) => r.read(buf),
+            }
+        }
+    }
+}
@@ -15,10 +15,17 @@
 //!   * This is the database closely resembling the object database in a git repository, and probably what most people would want to use.
 //! * [`linked::Store`]
 //!   * A database containing various [`compound::Stores`][compound::Store] as gathered from `alternates` files.
+//! * [`general::Store`]
+//!   * A database like the [`linked::Store`] which picks up new, changed and removed packs and alternates automatically,
+//!     loading packs only when needed.
+//!   * Meant for long-running processes which share one instance per repository among all threads using handles.
+//! * [`memory::Proxy`]
+//!   * A database keeping written objects in memory while reading all other objects from the database it wraps.
+//!   * Useful for tests and to compute objects like commits without writing them to disk.
 pub use git_pack as pack;
 
 mod store;
-pub use store::{compound, handle, linked, loose, sink, Handle, Sink};
+pub use store::{compound, general, handle, linked, loose, memory, sink, Handle, Sink};
 
 pub mod alternate;
 
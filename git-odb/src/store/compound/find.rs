@@ -11,6 +11,8 @@ pub enum Error {
     Loose(#[from] loose::find::Error),
     #[error("An error occurred while obtaining an object from the packed object store")]
     Pack(#[from] pack::data::decode_entry::Error),
+    #[error("An error occurred while streaming an object from the packed object store")]
+    PackStream(#[from] pack::data::decode_stream::Error),
 }
 
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
@@ -42,6 +44,34 @@ impl compound::Store {
         Ok(None)
     }
 
+    /// Like [`try_find()`][compound::Store::try_find()], but return a reader for the object's data instead of placing it into
+    /// a buffer, which keeps memory usage bounded even for very large objects.
+    pub fn try_find_stream(&self, id: impl AsRef<git_hash::oid>) -> Result<Option<crate::find::Stream<'_>>, Error> {
+        let id = id.as_ref();
+        for bundle in &self.bundles {
+            if let Some(idx) = find_pack_index(bundle, id) {
+                return Ok(Some(bundle.get_object_stream_by_index(idx)?.into()));
+            }
+        }
+        self.loose.try_find_stream(id).map_err(Into::into)
+    }
+
+    /// Like [`try_find()`][compound::Store::try_find()], but only return the kind and size of the object without decoding
+    /// its data.
+    pub fn try_header(&self, id: impl AsRef<git_hash::oid>) -> Result<Option<crate::find::Header>, Error> {
+        let id = id.as_ref();
+        for bundle in &self.bundles {
+            if let Some(idx) = find_pack_index(bundle, id) {
+                let outcome = bundle.get_object_header_by_index(idx)?;
+                return Ok(Some(crate::find::Header {
+                    kind: outcome.kind,
+                    size: outcome.object_size,
+                }));
+            }
+        }
+        self.loose.try_header(id).map_err(Into::into)
+    }
+
     /// Internal-use function to look up a packed object index or loose object.
     /// Used to avoid double-lookups in linked::Store::locate.
     /// (The polonius borrow-checker would support this via the locate
@@ -9,6 +9,9 @@ mod write;
 
 /// An object database with tiered lookup packs and loose objects.
 /// This is a typical git database as used in git repositories, sans 'alternates'.
+///
+/// All packs are loaded when it is created, see the [`general::Store`][crate::general::Store] to load them on demand with
+/// a limit on the amount of loaded packs.
 pub struct Store {
     /// A loose object database into which new objects are written
     pub loose: loose::Store,
@@ -0,0 +1,287 @@
+use std::{convert::TryInto, sync::atomic::Ordering};
+
+use git_features::threading::OwnShared;
+use git_hash::{oid, ObjectId};
+use git_object::Data;
+
+use crate::{
+    pack,
+    store::{general, loose},
+};
+
+/// Returned by [`general::Handle::try_find()`][git_pack::Find::try_find()]
+#[derive(thiserror::Error, Debug)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("An error occurred while obtaining an object from the loose object store")]
+    Loose(#[from] loose::find::Error),
+    #[error("An error occurred while obtaining an object from the packed object store")]
+    Pack(#[from] pack::data::decode_entry::Error),
+    #[error("An error occurred while loading a pack or refreshing the store")]
+    Load(#[from] general::load::Error),
+    #[error("Could not fetch the missing object {id} from the promisor remote")]
+    Promisor {
+        id: ObjectId,
+        source: general::promisor::FetchError,
+    },
+}
+
+/// Where an object was found.
+enum Location {
+    Packed {
+        pack: OwnShared<pack::data::File>,
+        index: OwnShared<pack::index::File>,
+        entry_index: u32,
+    },
+    Loose(loose::Store),
+}
+
+impl general::Handle {
+    /// Find the location of the object with `id`, refreshing the store and trying again if it couldn't be found.
+    fn locate(&self, id: &oid) -> Result<Option<Location>, general::load::Error> {
+        loop {
+            if let Some(location) = self.locate_in_snapshot(id)? {
+                return Ok(Some(location));
+            }
+            if !self.refresh_after_miss()? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Like [`locate()`][Self::locate()], but fetch the object from the promisor remote if it can't be found and try once more.
+    fn locate_or_fetch(&self, id: &oid) -> Result<Option<Location>, Error> {
+        if let Some(location) = self.locate(id)? {
+            return Ok(Some(location));
+        }
+        let fetch = match self.store.promisor_fetch() {
+            Some(fetch) => fetch,
+            None => return Ok(None),
+        };
+        fetch(&[id.to_owned()]).map_err(|source| Error::Promisor {
+            id: id.to_owned(),
+            source,
+        })?;
+        self.store.refresh()?;
+        Ok(self.locate(id)?)
+    }
+
+    fn locate_in_snapshot(&self, id: &oid) -> Result<Option<Location>, general::load::Error> {
+        let mut snapshot = self.snapshot();
+        for lookup in snapshot.indices.iter_mut() {
+            let entry_index = match lookup.index.lookup(id) {
+                Some(idx) => idx,
+                None => continue,
+            };
+            let pack = match &lookup.data {
+                Some(pack) => OwnShared::clone(pack),
+                None => match self.store.load_pack(lookup.pack_id)? {
+                    Some(pack) => {
+                        lookup.data = Some(OwnShared::clone(&pack));
+                        pack
+                    }
+                    // The pack disappeared, maybe the object is available elsewhere.
+                    None => continue,
+                },
+            };
+            lookup
+                .last_used
+                .store(self.store.clock.fetch_add(1, Ordering::Relaxed) + 1, Ordering::Relaxed);
+            return Ok(Some(Location::Packed {
+                pack,
+                index: OwnShared::clone(&lookup.index),
+                entry_index,
+            }));
+        }
+        Ok(snapshot
+            .loose
+            .iter()
+            .find(|db| db.contains(id))
+            .map(|db| Location::Loose(db.clone())))
+    }
+}
+
+fn decode_packed(
+    pack: &pack::data::File,
+    index: &pack::index::File,
+    entry_index: u32,
+    out: &mut Vec<u8>,
+    cache: &mut impl pack::cache::DecodeEntry,
+) -> Result<(git_object::Kind, pack::bundle::Location), pack::data::decode_entry::Error> {
+    let pack_offset = index.pack_offset_at_index(entry_index);
+    let entry = pack.entry(pack_offset);
+    let header_size = entry.header_size();
+    pack.decode_entry(
+        entry,
+        out,
+        |id, _out| {
+            index
+                .lookup(id)
+                .map(|idx| pack::data::ResolvedBase::InPack(pack.entry(index.pack_offset_at_index(idx))))
+        },
+        cache,
+    )
+    .map(|r| {
+        (
+            r.kind,
+            pack::bundle::Location {
+                pack_id: pack.id,
+                pack_offset,
+                index_file_id: entry_index,
+                entry_size: r.compressed_size + header_size,
+            },
+        )
+    })
+}
+
+impl crate::pack::Find for general::Handle {
+    type Error = Error;
+
+    /// Return true if the given object `id` is contained in the store, refreshing it if needed.
+    ///
+    /// Objects are never fetched from a promisor remote, which only happens when they are read.
+    fn contains(&self, id: impl AsRef<oid>) -> bool {
+        let id = id.as_ref();
+        loop {
+            {
+                let snapshot = self.snapshot();
+                if snapshot.indices.iter().any(|lookup| lookup.index.lookup(id).is_some())
+                    || snapshot.loose.iter().any(|db| db.contains(id))
+                {
+                    return true;
+                }
+            }
+            if !self.refresh_after_miss().unwrap_or(false) {
+                return false;
+            }
+        }
+    }
+
+    fn try_find_cached<'a>(
+        &self,
+        id: impl AsRef<oid>,
+        buffer: &'a mut Vec<u8>,
+        pack_cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Result<Option<(Data<'a>, Option<pack::bundle::Location>)>, Self::Error> {
+        let id = id.as_ref();
+        let (kind, location) = loop {
+            match self.locate_or_fetch(id)? {
+                Some(Location::Packed {
+                    pack,
+                    index,
+                    entry_index,
+                }) => {
+                    let (kind, location) = decode_packed(&pack, &index, entry_index, buffer, pack_cache)?;
+                    break (kind, Some(location));
+                }
+                Some(Location::Loose(db)) => match db.try_find(id, buffer)? {
+                    Some(obj) => break (obj.kind, None),
+                    // The object was packed and deleted in the mean time, so try again with refreshed packs.
+                    None => {
+                        if !self.refresh_after_miss()? {
+                            return Ok(None);
+                        }
+                    }
+                },
+                None => return Ok(None),
+            }
+        };
+        Ok(Some((Data::new(kind, buffer), location)))
+    }
+
+    /// Return the kind and size of the object with `id` while only decoding pack entry headers or the loose object header.
+    ///
+    /// Like reading objects, this fetches missing objects from the promisor remote.
+    fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<pack::find::Header>, Self::Error> {
+        let id = id.as_ref();
+        loop {
+            match self.locate_or_fetch(id)? {
+                Some(Location::Packed {
+                    pack,
+                    index,
+                    entry_index,
+                }) => {
+                    let entry = pack.entry(index.pack_offset_at_index(entry_index));
+                    let outcome = pack.decode_header(entry, |id| {
+                        index.lookup(id).map(|idx| {
+                            pack::data::decode_header::ResolvedBase::InPack(pack.entry(index.pack_offset_at_index(idx)))
+                        })
+                    })?;
+                    return Ok(Some(pack::find::Header {
+                        kind: outcome.kind,
+                        size: outcome.object_size,
+                    }));
+                }
+                Some(Location::Loose(db)) => match db.try_header(id)? {
+                    Some(header) => return Ok(Some(header)),
+                    // The object was packed and deleted in the mean time, so try again with refreshed packs.
+                    None => {
+                        if !self.refresh_after_miss()? {
+                            return Ok(None);
+                        }
+                    }
+                },
+                None => return Ok(None),
+            }
+        }
+    }
+
+    fn location_by_oid(&self, id: impl AsRef<oid>, buf: &mut Vec<u8>) -> Option<pack::bundle::Location> {
+        let (pack, index, entry_index) = match self.locate(id.as_ref()).ok()?? {
+            Location::Packed {
+                pack,
+                index,
+                entry_index,
+            } => (pack, index, entry_index),
+            Location::Loose(_) => return None,
+        };
+        let pack_offset = index.pack_offset_at_index(entry_index);
+        let entry = pack.entry(pack_offset);
+
+        buf.resize(entry.decompressed_size.try_into().expect("representable size"), 0);
+        pack.decompress_entry(&entry, buf)
+            .ok()
+            .map(|entry_size_past_header| pack::bundle::Location {
+                pack_id: pack.id,
+                pack_offset,
+                index_file_id: entry_index,
+                entry_size: entry.header_size() + entry_size_past_header,
+            })
+    }
+
+    fn index_iter_by_pack_id(&self, pack_id: u32) -> Option<Box<dyn Iterator<Item = pack::index::Entry> + '_>> {
+        let index = self
+            .snapshot()
+            .indices
+            .iter()
+            .find(|lookup| lookup.pack_id == pack_id)
+            .map(|lookup| OwnShared::clone(&lookup.index))?;
+        Some(Box::new((0..index.num_objects()).map(move |idx| pack::index::Entry {
+            oid: index.oid_at_index(idx).to_owned(),
+            pack_offset: index.pack_offset_at_index(idx),
+            crc32: index.crc32_at_index(idx),
+        })))
+    }
+
+    /// Always returns `None` as packs may be unloaded at any time and can't be borrowed from, which causes pack generation
+    /// to re-encode objects instead of copying their pack entries.
+    fn entry_by_location(&self, _location: &pack::bundle::Location) -> Option<pack::find::Entry<'_>> {
+        None
+    }
+}
+
+impl crate::Find for general::Handle {
+    type Error = Error;
+
+    fn contains(&self, id: impl AsRef<oid>) -> bool {
+        pack::Find::contains(self, id)
+    }
+
+    fn try_find<'a>(&self, id: impl AsRef<oid>, buffer: &'a mut Vec<u8>) -> Result<Option<Data<'a>>, Self::Error> {
+        pack::Find::try_find(self, id, buffer).map(|t| t.map(|t| t.0))
+    }
+
+    fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<crate::find::Header>, Self::Error> {
+        pack::Find::try_header(self, id)
+    }
+}
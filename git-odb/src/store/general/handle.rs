@@ -0,0 +1,108 @@
+use std::cell::{RefCell, RefMut};
+
+use git_features::threading::{get_mut, OwnShared};
+
+use crate::store::general::{self, load};
+
+/// Determines what happens to packs which are removed from disk while a handle exists.
+pub(crate) enum Mode {
+    DeletedPacksAreInaccessible,
+    /// Keep packs loaded even if they are removed from disk, as pack ids we handed out must remain valid.
+    KeepDeletedPacksAvailable,
+}
+
+impl general::Store {
+    /// Create a new handle to find objects in this instance.
+    pub fn to_handle(self: &OwnShared<Self>) -> general::Handle {
+        let mode = self.register_handle();
+        general::Handle {
+            store: OwnShared::clone(self),
+            refresh_mode: Default::default(),
+            mode: Some(mode),
+            snapshot: RefCell::new(self.snapshot()),
+        }
+    }
+
+    fn register_handle(&self) -> Mode {
+        get_mut(&self.state).num_handles_unstable += 1;
+        Mode::DeletedPacksAreInaccessible
+    }
+
+    fn remove_handle(&self, mode: Mode) {
+        let mut state = get_mut(&self.state);
+        match mode {
+            Mode::KeepDeletedPacksAvailable => state.num_handles_stable -= 1,
+            Mode::DeletedPacksAreInaccessible => state.num_handles_unstable -= 1,
+        }
+    }
+
+    fn upgrade_handle(&self, mode: Mode) -> Mode {
+        if let Mode::DeletedPacksAreInaccessible = mode {
+            let mut state = get_mut(&self.state);
+            state.num_handles_unstable -= 1;
+            state.num_handles_stable += 1;
+        }
+        Mode::KeepDeletedPacksAvailable
+    }
+}
+
+impl general::Handle {
+    /// Call once if pack locations or ids are stored and used later, which requires all packs seen by this handle to remain
+    /// available even if they are deleted on disk, for instance by `git gc` running while a pack is being generated.
+    ///
+    /// Removed packs will be unloaded once the last handle requiring them is dropped.
+    pub fn prevent_pack_unload(&mut self) {
+        self.mode = self.mode.take().map(|mode| self.store.upgrade_handle(mode));
+    }
+
+    /// Configure if the store should be checked for changes if an object can't be found.
+    pub fn set_refresh_mode(&mut self, mode: general::RefreshMode) {
+        self.refresh_mode = mode;
+    }
+
+    /// Return the store this handle belongs to.
+    pub fn store(&self) -> &OwnShared<general::Store> {
+        &self.store
+    }
+
+    /// Return our view of the store, after updating it if the store changed since we last looked.
+    pub(crate) fn snapshot(&self) -> RefMut<'_, load::Snapshot> {
+        let mut snapshot = self.snapshot.borrow_mut();
+        if snapshot.generation != self.store.generation() {
+            *snapshot = self.store.snapshot();
+        }
+        snapshot
+    }
+
+    /// Check the store for changes after an object couldn't be found, and return true if the lookup should be retried as
+    /// our view of the store changed.
+    pub(crate) fn refresh_after_miss(&self) -> Result<bool, load::Error> {
+        match self.refresh_mode {
+            general::RefreshMode::Never => Ok(false),
+            general::RefreshMode::AfterObjectMiss => {
+                self.store.refresh()?;
+                // Others may have refreshed the store already, so we would be the only ones to not see the changes.
+                Ok(self.store.generation() != self.snapshot.borrow().generation)
+            }
+        }
+    }
+}
+
+impl Clone for general::Handle {
+    fn clone(&self) -> Self {
+        let mut handle = self.store.to_handle();
+        handle.refresh_mode = self.refresh_mode;
+        if let Some(Mode::KeepDeletedPacksAvailable) = self.mode {
+            handle.prevent_pack_unload();
+        }
+        handle
+    }
+}
+
+impl Drop for general::Handle {
+    fn drop(&mut self) {
+        if let Some(mode) = self.mode.take() {
+            self.store.remove_handle(mode)
+        }
+    }
+}
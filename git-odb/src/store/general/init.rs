@@ -0,0 +1,68 @@
+use std::{path::PathBuf, sync::atomic::AtomicUsize};
+
+use git_features::threading::{MutableOnDemand, OwnShared};
+
+use crate::store::{
+    general::{self, load},
+    loose,
+};
+
+/// Configuration for [`general::Store::at_opts()`].
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone)]
+pub struct Options {
+    /// The maximum amount of pack data files to keep memory-mapped at the same time, or `None` for no limit.
+    ///
+    /// Once the limit is reached, the least recently used pack is unloaded to make room for the one to load next.
+    /// Pack indices are always kept loaded as they are needed for lookups.
+    ///
+    /// Note that this is a soft limit as handles keep using the packs they already loaded until their next object access.
+    /// The memory map of an unloaded pack is only released once all handles let go of it, so more packs than configured
+    /// can remain mapped while handles are idle or busy reading from them.
+    pub max_open_packs: Option<usize>,
+    /// Alternate object directories to use before those listed in `alternates` files, typically obtained
+    /// [from the environment][crate::alternate::from_environment()]. Directories which don't exist are skipped.
+    pub alternates: Vec<PathBuf>,
+}
+
+/// Returned by [`general::Store::at()`]
+#[derive(thiserror::Error, Debug)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("The objects directory at '{0}' is not an accessible directory")]
+    Inaccessible(PathBuf),
+    #[error(transparent)]
+    Load(#[from] load::Error),
+}
+
+/// Instantiation
+impl general::Store {
+    /// Create a new shared instance from the given git `objects_directory`, commonly `.git/objects`, and load the indices
+    /// of all packs in it and its alternates.
+    ///
+    /// Use [`to_handle()`][general::Store::to_handle()] to find objects. Alternates in
+    /// [`GIT_ALTERNATE_OBJECT_DIRECTORIES`][crate::alternate::ENVIRONMENT_VARIABLE] are not used unless passed with
+    /// [`at_opts()`][general::Store::at_opts()].
+    pub fn at(objects_directory: impl Into<PathBuf>) -> Result<OwnShared<Self>, Error> {
+        Self::at_opts(objects_directory, Options::default())
+    }
+
+    /// Like [`at()`][general::Store::at()], but configurable with `options`.
+    pub fn at_opts(objects_directory: impl Into<PathBuf>, options: Options) -> Result<OwnShared<Self>, Error> {
+        let objects_directory = objects_directory.into();
+        if !objects_directory.is_dir() {
+            return Err(Error::Inaccessible(objects_directory));
+        }
+        let store = general::Store {
+            loose: loose::Store::at(objects_directory),
+            alternates: options.alternates,
+            state: MutableOnDemand::new(general::State {
+                max_open_packs: options.max_open_packs.map(|max| max.max(1)),
+                ..Default::default()
+            }),
+            generation: AtomicUsize::new(0),
+            clock: AtomicUsize::new(0),
+        };
+        store.refresh()?;
+        Ok(OwnShared::new(store))
+    }
+}
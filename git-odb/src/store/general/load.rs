@@ -0,0 +1,330 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::SystemTime,
+};
+
+use git_features::threading::{get_mut, get_ref, get_ref_upgradeable, upgrade_ref_to_mut, OwnShared};
+
+use crate::{
+    alternate, pack,
+    store::{
+        general::{self, Db, IndexAndPack, OnDiskFile, State},
+        loose,
+    },
+};
+
+/// The error returned when loading or refreshing a [`general::Store`].
+#[derive(thiserror::Error, Debug)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error(transparent)]
+    Alternate(#[from] alternate::Error),
+    #[error("Could not read the pack directory at '{}'", .path.display())]
+    PackDirectory { source: io::Error, path: PathBuf },
+    #[error(transparent)]
+    Index(#[from] pack::index::init::Error),
+    #[error(transparent)]
+    Pack(#[from] pack::data::header::decode::Error),
+}
+
+/// A pack index as seen by a handle, possibly along with its pack data file.
+pub(crate) struct IndexLookup {
+    pub(crate) pack_id: u32,
+    pub(crate) index: OwnShared<pack::index::File>,
+    pub(crate) data: Option<OwnShared<pack::data::File>>,
+    pub(crate) last_used: OwnShared<AtomicUsize>,
+    pub(crate) is_promisor: bool,
+}
+
+/// The view of a handle onto the store at a given generation, which is kept until the store changes.
+#[derive(Default)]
+pub(crate) struct Snapshot {
+    pub(crate) generation: usize,
+    pub(crate) loose: Vec<loose::Store>,
+    pub(crate) indices: Vec<IndexLookup>,
+}
+
+impl general::Store {
+    /// Check the pack directories and `alternates` files for changes and update our state accordingly, returning true
+    /// if there were changes.
+    ///
+    /// New pack indices are loaded right away, and removed packs are unloaded unless a handle prevents it, in which
+    /// case they are kept until the last such handle is dropped.
+    ///
+    /// Note that this happens automatically whenever an object can't be found unless the handle's
+    /// [refresh mode][general::RefreshMode] is configured otherwise.
+    pub fn refresh(&self) -> Result<bool, Error> {
+        let mut state = get_mut(&self.state);
+        let changed = state.refresh(&self.loose.path, &self.alternates)?;
+        if changed {
+            self.generation.fetch_add(1, Ordering::SeqCst);
+        }
+        Ok(changed)
+    }
+
+    /// Return information about the resources currently in use.
+    pub fn metrics(&self) -> general::Metrics {
+        let state = get_ref(&self.state);
+        let mut metrics = general::Metrics {
+            num_handles: state.num_handles_stable + state.num_handles_unstable,
+            num_dbs: state.dbs.len(),
+            ..Default::default()
+        };
+        for file in &state.files {
+            if file.index.loaded().is_some() {
+                metrics.open_indices += 1;
+            }
+            if file.data.loaded().is_some() {
+                metrics.open_packs += 1;
+            }
+            if let OnDiskFile::Garbage(_) = file.index {
+                metrics.garbage_packs += 1;
+            }
+        }
+        metrics
+    }
+
+    pub(crate) fn generation(&self) -> usize {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        let state = get_ref(&self.state);
+        let mut indices: Vec<_> = state
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(pack_id, file)| {
+                file.index.loaded().map(|index| {
+                    (
+                        matches!(file.index, OnDiskFile::Garbage(_)),
+                        file.modified,
+                        IndexLookup {
+                            pack_id: pack_id as u32,
+                            index: OwnShared::clone(index),
+                            data: file.data.loaded().cloned(),
+                            last_used: OwnShared::clone(&file.last_used),
+                            is_promisor: file.is_promisor,
+                        },
+                    )
+                })
+            })
+            .collect();
+        // Like libgit2, look into the newest packs first, and only consider removed packs last.
+        indices.sort_by(|l, r| l.0.cmp(&r.0).then(l.1.cmp(&r.1).reverse()));
+        Snapshot {
+            generation: self.generation(),
+            loose: state.dbs.iter().map(|db| db.loose.clone()).collect(),
+            indices: indices.into_iter().map(|t| t.2).collect(),
+        }
+    }
+
+    /// Return the pack data file for `pack_id` and load it if needed, or `None` if it was removed from disk.
+    pub(crate) fn load_pack(&self, pack_id: u32) -> Result<Option<OwnShared<pack::data::File>>, Error> {
+        let state = get_ref_upgradeable(&self.state);
+        match state.files.get(pack_id as usize) {
+            Some(file) => match (&file.index, &file.data) {
+                (_, OnDiskFile::Loaded(pack) | OnDiskFile::Garbage(pack)) => return Ok(Some(OwnShared::clone(pack))),
+                (OnDiskFile::Loaded(_) | OnDiskFile::Garbage(_), OnDiskFile::Unloaded) => {}
+                _ => return Ok(None),
+            },
+            None => return Ok(None),
+        }
+
+        let mut state = upgrade_ref_to_mut(state, &self.state);
+        if let OnDiskFile::Unloaded = state.files[pack_id as usize].data {
+            if state.evict_least_recently_used_pack() {
+                // Handles have to drop the evicted pack as well.
+                self.generation.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        let file = &mut state.files[pack_id as usize];
+        if let OnDiskFile::Unloaded = file.data {
+            file.data = match pack::data::File::at(file.index_path.with_extension("pack")) {
+                Ok(mut pack) => {
+                    // Pack caches identify packs by their id, which thus must be unique within this store.
+                    pack.id = pack_id;
+                    OnDiskFile::Loaded(OwnShared::new(pack))
+                }
+                Err(pack::data::header::decode::Error::Io { source, .. })
+                    if source.kind() == io::ErrorKind::NotFound =>
+                {
+                    OnDiskFile::Missing
+                }
+                Err(err) => return Err(err.into()),
+            };
+        }
+        Ok(file.data.loaded().cloned())
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|md| md.modified()).ok()
+}
+
+/// Return `modified` unless it's so recent that changes made right after it might not alter the modification time, as its
+/// granularity can be coarse. Without a time, the directory will be checked again next time.
+fn non_racy(modified: Option<SystemTime>) -> Option<SystemTime> {
+    modified.filter(|modified| {
+        SystemTime::now()
+            .duration_since(*modified)
+            .map_or(false, |age| age > std::time::Duration::from_secs(1))
+    })
+}
+
+impl State {
+    /// If the maximum amount of packs is loaded, unload the one which was used least recently and return true.
+    ///
+    /// It can be loaded again when needed as its id remains valid.
+    fn evict_least_recently_used_pack(&mut self) -> bool {
+        let max_open_packs = match self.max_open_packs {
+            Some(max) => max,
+            None => return false,
+        };
+        let loaded = || {
+            self.files
+                .iter()
+                .enumerate()
+                .filter(|(_, f)| matches!(f.data, OnDiskFile::Loaded(_)))
+        };
+        if loaded().count() < max_open_packs {
+            return false;
+        }
+        match loaded()
+            .min_by_key(|(_, f)| f.last_used.load(Ordering::Relaxed))
+            .map(|(idx, _)| idx)
+        {
+            Some(idx) => {
+                self.files[idx].data = OnDiskFile::Unloaded;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn refresh(&mut self, objects_directory: &Path, alternates: &[PathBuf]) -> Result<bool, Error> {
+        let mut changed = self.refresh_dbs(objects_directory, alternates)?;
+
+        let is_loaded = |f: &IndexAndPack| matches!(f.index, OnDiskFile::Loaded(_));
+        let mut seen_indices = Vec::new();
+        for db_index in 0..self.dbs.len() {
+            let pack_dir = self.dbs[db_index].loose.path.join("pack");
+            let pack_dir_modified = modified(&pack_dir);
+            if pack_dir_modified.is_some() && pack_dir_modified == self.dbs[db_index].pack_dir_modified {
+                seen_indices.extend(
+                    self.files
+                        .iter()
+                        .filter(|f| is_loaded(f) && f.index_path.parent() == Some(pack_dir.as_path()))
+                        .map(|f| (f.index_path.clone(), f.modified)),
+                );
+                continue;
+            }
+
+            let entries = match std::fs::read_dir(&pack_dir) {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    self.dbs[db_index].pack_dir_modified = None;
+                    continue;
+                }
+                Err(source) => return Err(Error::PackDirectory { source, path: pack_dir }),
+            };
+            let indices_on_disk = entries
+                .filter_map(Result::ok)
+                .filter_map(|e| e.metadata().map(|md| (e.path(), md)).ok())
+                .filter(|(p, md)| md.file_type().is_file() && p.extension().unwrap_or_default() == "idx")
+                .filter_map(|(p, md)| md.modified().ok().map(|modified| (p, modified)));
+            for (index_path, index_modified) in indices_on_disk {
+                let is_known = self
+                    .files
+                    .iter()
+                    .any(|f| is_loaded(f) && f.index_path == index_path && f.modified == index_modified);
+                if !is_known {
+                    let index = match pack::index::File::at(&index_path) {
+                        Ok(index) => index,
+                        // It was deleted in the mean time.
+                        Err(pack::index::init::Error::Io { source, .. })
+                            if source.kind() == io::ErrorKind::NotFound =>
+                        {
+                            continue
+                        }
+                        Err(err) => return Err(err.into()),
+                    };
+                    self.files.push(IndexAndPack {
+                        index_path: index_path.clone(),
+                        modified: index_modified,
+                        index: OnDiskFile::Loaded(OwnShared::new(index)),
+                        data: OnDiskFile::Unloaded,
+                        last_used: Default::default(),
+                        is_promisor: index_path.with_extension("promisor").is_file(),
+                    });
+                    changed = true;
+                }
+                seen_indices.push((index_path, index_modified));
+            }
+            self.dbs[db_index].pack_dir_modified = non_racy(pack_dir_modified);
+        }
+
+        let keep_removed_packs = self.num_handles_stable > 0;
+        for file in self.files.iter_mut() {
+            let was_loaded = is_loaded(file);
+            if was_loaded
+                && seen_indices
+                    .iter()
+                    .any(|(path, modified)| *path == file.index_path && *modified == file.modified)
+            {
+                continue;
+            }
+            match std::mem::replace(&mut file.index, OnDiskFile::Missing) {
+                OnDiskFile::Loaded(index) | OnDiskFile::Garbage(index) if keep_removed_packs => {
+                    file.index = OnDiskFile::Garbage(index);
+                    // A pack that isn't loaded yet must not be loaded anymore as it might have been replaced on disk.
+                    file.data = match std::mem::replace(&mut file.data, OnDiskFile::Missing) {
+                        OnDiskFile::Loaded(data) | OnDiskFile::Garbage(data) => OnDiskFile::Garbage(data),
+                        OnDiskFile::Unloaded | OnDiskFile::Missing => OnDiskFile::Missing,
+                    };
+                    changed |= was_loaded;
+                }
+                OnDiskFile::Loaded(_) | OnDiskFile::Garbage(_) => {
+                    file.data = OnDiskFile::Missing;
+                    changed = true;
+                }
+                OnDiskFile::Unloaded | OnDiskFile::Missing => {}
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Resolve alternates again if any of the `alternates` files changed, and return true if that happened.
+    fn refresh_dbs(&mut self, objects_directory: &Path, alternates: &[PathBuf]) -> Result<bool, Error> {
+        let alternates_file = |db: &Path| db.join("info").join("alternates");
+        if !self.dbs.is_empty()
+            && self
+                .dbs
+                .iter()
+                .all(|db| modified(&alternates_file(&db.loose.path)) == db.alternates_modified)
+        {
+            return Ok(false);
+        }
+
+        let mut db_paths = alternate::resolve_with(objects_directory, alternates.iter().cloned())?;
+        db_paths.insert(0, objects_directory.to_owned());
+        let mut previous_dbs = std::mem::take(&mut self.dbs);
+        self.dbs = db_paths
+            .into_iter()
+            .map(|path| match previous_dbs.iter().position(|db| db.loose.path == path) {
+                Some(pos) => previous_dbs.remove(pos),
+                None => Db {
+                    loose: loose::Store::at(path),
+                    pack_dir_modified: None,
+                    alternates_modified: None,
+                },
+            })
+            .collect();
+        for db in self.dbs.iter_mut() {
+            db.alternates_modified = modified(&alternates_file(&db.loose.path));
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,152 @@
+//! An object database which keeps track of changes to packs and alternates on disk, loading packs only when they are needed.
+use std::{cell::RefCell, path::PathBuf, sync::atomic::AtomicUsize, time::SystemTime};
+
+use git_features::threading::{MutableOnDemand, OwnShared};
+
+use crate::{pack, store::loose};
+
+///
+pub mod init;
+
+///
+pub mod load;
+
+mod handle;
+
+///
+pub mod find;
+
+mod write;
+
+///
+pub mod promisor;
+
+/// An object database shared by any amount of [handles][Handle], which picks up new packs and alternates on disk
+/// automatically and drops packs which were removed.
+///
+/// Whenever an object can't be found, the pack directories as well as `alternates` files are checked for changes using
+/// their modification time, and new pack indices are loaded before the lookup is tried once more. Pack data files are only
+/// memory-mapped when an object is actually read from them, and the amount of mapped packs can be
+/// [limited][init::Options::max_open_packs].
+///
+/// In partial clones, objects which are still missing after a refresh can be fetched on demand from a promisor remote
+/// using a [fetch function][Store::set_promisor_fetch()].
+///
+/// Packs which were deleted on disk are released unless a handle [needs them to remain available][Handle::prevent_pack_unload()].
+/// Either way, memory maps are only dropped once the last handle stopped using them, making it safe to unload packs while
+/// other threads are reading objects.
+///
+/// For long-running processes, the idea is to create one instance per repository, share it among all threads and connections
+/// using handles, and drop it along with the last of them.
+pub struct Store {
+    /// The loose object database of the objects directory, which receives all written objects.
+    pub(crate) loose: loose::Store,
+    /// Alternate object directories to use in addition to those in `alternates` files.
+    pub(crate) alternates: Vec<PathBuf>,
+    pub(crate) state: MutableOnDemand<State>,
+    /// Incremented whenever the loaded packs or object databases change, allowing handles to cheaply determine if their
+    /// view of the store is outdated.
+    pub(crate) generation: AtomicUsize,
+    /// Incremented whenever a handle uses a pack, to know which pack was least recently used.
+    pub(crate) clock: AtomicUsize,
+}
+
+/// A way to find objects in a [`Store`] with a thread-local view of the loaded packs, or write loose objects.
+///
+/// Handles are cheap to create and clone, but must not be shared across threads.
+pub struct Handle {
+    pub(crate) store: OwnShared<Store>,
+    pub(crate) refresh_mode: RefreshMode,
+    pub(crate) mode: Option<handle::Mode>,
+    pub(crate) snapshot: RefCell<load::Snapshot>,
+}
+
+/// Define if the store is checked for changes on disk when an object couldn't be found.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum RefreshMode {
+    /// Check for new, changed or removed packs and alternates whenever an object couldn't be found. This is the default.
+    AfterObjectMiss,
+    /// Never check for changes on disk, which is useful if many objects are expected to be missing, at the risk of not
+    /// seeing new packs until [`Store::refresh()`] is called explicitly.
+    Never,
+}
+
+impl Default for RefreshMode {
+    fn default() -> Self {
+        RefreshMode::AfterObjectMiss
+    }
+}
+
+/// Information about the resources used by a [`Store`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct Metrics {
+    /// The amount of handles currently in use.
+    pub num_handles: usize,
+    /// The amount of object databases, which is one for the objects directory plus one per alternate.
+    pub num_dbs: usize,
+    /// The amount of loaded pack indices.
+    pub open_indices: usize,
+    /// The amount of memory-mapped pack data files.
+    pub open_packs: usize,
+    /// The amount of packs which are kept loaded even though they were removed from disk.
+    pub garbage_packs: usize,
+}
+
+pub(crate) enum OnDiskFile<T> {
+    /// The file is on disk but wasn't loaded yet.
+    Unloaded,
+    Loaded(T),
+    /// The file was loaded, but disappeared from disk. It's kept as handles require the pack ids they saw to remain valid.
+    Garbage(T),
+    /// The file is gone from disk and was unloaded, or it couldn't be loaded in the first place.
+    Missing,
+}
+
+impl<T> OnDiskFile<T> {
+    pub(crate) fn loaded(&self) -> Option<&T> {
+        match self {
+            OnDiskFile::Loaded(v) | OnDiskFile::Garbage(v) => Some(v),
+            OnDiskFile::Unloaded | OnDiskFile::Missing => None,
+        }
+    }
+}
+
+/// A pack index and its pack data file. Its position in [`State::files`] is its pack id, which is never reused.
+pub(crate) struct IndexAndPack {
+    /// The path to the index file, with the pack data file next to it.
+    pub(crate) index_path: PathBuf,
+    /// The modification time of the index file when it was loaded, to detect packs that were rewritten in place.
+    pub(crate) modified: SystemTime,
+    pub(crate) index: OnDiskFile<OwnShared<pack::index::File>>,
+    pub(crate) data: OnDiskFile<OwnShared<pack::data::File>>,
+    /// The [clock][Store::clock] time at which the pack was last used by any handle.
+    pub(crate) last_used: OwnShared<AtomicUsize>,
+    /// True if the pack was received from a promisor remote, as indicated by a `.promisor` file next to it.
+    pub(crate) is_promisor: bool,
+}
+
+/// An object database directory, either the objects directory itself or one of its alternates.
+pub(crate) struct Db {
+    pub(crate) loose: loose::Store,
+    /// The modification time of the `pack` directory when we last looked at its contents, or `None` if it must be looked at
+    /// next time.
+    pub(crate) pack_dir_modified: Option<SystemTime>,
+    /// The modification time of the `info/alternates` file when alternates were last resolved.
+    pub(crate) alternates_modified: Option<SystemTime>,
+}
+
+#[derive(Default)]
+pub(crate) struct State {
+    /// The objects directory, followed by all of its alternates.
+    pub(crate) dbs: Vec<Db>,
+    pub(crate) files: Vec<IndexAndPack>,
+    /// The maximum amount of loaded pack data files, or `None` if there is no limit.
+    pub(crate) max_open_packs: Option<usize>,
+    /// The function to fetch objects that couldn't be found from a promisor remote, if this is a partial clone.
+    pub(crate) promisor_fetch: Option<OwnShared<promisor::FetchFn>>,
+
+    /// The amount of handles which require packs to remain available even if they are deleted on disk.
+    pub(crate) num_handles_stable: usize,
+    /// The amount of handles which don't mind if packs are unloaded once they are removed on disk.
+    pub(crate) num_handles_unstable: usize,
+}
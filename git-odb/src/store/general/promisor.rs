@@ -0,0 +1,42 @@
+use git_features::threading::{get_mut, get_ref, OwnShared};
+use git_hash::{oid, ObjectId};
+
+use crate::store::general;
+
+/// The error returned by a [`FetchFn`], which is free to fail in any way.
+pub type FetchError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// A function to obtain the given missing objects from a promisor remote and place them into the store, usually as pack with
+/// a `.promisor` file next to it.
+///
+/// It's called with the ids of objects that couldn't be found even after refreshing the store, and the lookup is retried
+/// once it returns successfully.
+pub type FetchFn = dyn Fn(&[ObjectId]) -> Result<(), FetchError> + Send + Sync;
+
+impl general::Store {
+    /// Set the function to call whenever an object can't be found to fetch it from a promisor remote, or unset it with `None`.
+    ///
+    /// This should be done if the repository is a partial clone, i.e. `extensions.partialClone` is set to the name of the remote
+    /// to fetch missing objects from.
+    pub fn set_promisor_fetch(&self, fetch: Option<Box<FetchFn>>) {
+        get_mut(&self.state).promisor_fetch = fetch.map(OwnShared::from);
+    }
+
+    pub(crate) fn promisor_fetch(&self) -> Option<OwnShared<FetchFn>> {
+        get_ref(&self.state).promisor_fetch.clone()
+    }
+}
+
+impl general::Handle {
+    /// Return true if the object with `id` is contained in a pack received from a promisor remote, i.e. one with a `.promisor`
+    /// file next to it.
+    ///
+    /// Objects referenced by such objects are promised to be available on the remote, so they are allowed to be missing.
+    pub fn is_in_promisor_pack(&self, id: impl AsRef<oid>) -> bool {
+        let id = id.as_ref();
+        self.snapshot()
+            .indices
+            .iter()
+            .any(|lookup| lookup.is_promisor && lookup.index.lookup(id).is_some())
+    }
+}
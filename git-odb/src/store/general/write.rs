@@ -0,0 +1,27 @@
+use std::io::Read;
+
+use git_object::Kind;
+
+use crate::store::{general, loose};
+
+impl crate::traits::Write for general::Handle {
+    type Error = loose::write::Error;
+
+    fn write(&self, object: impl git_object::WriteTo, hash: git_hash::Kind) -> Result<git_hash::ObjectId, Self::Error> {
+        self.store.loose.write(object, hash)
+    }
+
+    fn write_buf(&self, object: Kind, from: &[u8], hash: git_hash::Kind) -> Result<git_hash::ObjectId, Self::Error> {
+        self.store.loose.write_buf(object, from, hash)
+    }
+
+    fn write_stream(
+        &self,
+        kind: Kind,
+        size: u64,
+        from: impl Read,
+        hash: git_hash::Kind,
+    ) -> Result<git_hash::ObjectId, Self::Error> {
+        self.store.loose.write_stream(kind, size, from, hash)
+    }
+}
@@ -119,6 +119,10 @@ mod impls {
         fn try_find<'a>(&self, id: impl AsRef<oid>, buffer: &'a mut Vec<u8>) -> Result<Option<Data<'a>>, Self::Error> {
             git_pack::Find::try_find(self, id, buffer).map(|t| t.map(|t| t.0))
         }
+
+        fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<crate::find::Header>, Self::Error> {
+            self.store.try_header(id)
+        }
     }
 
     impl<S> crate::pack::Find for Handle<S>
@@ -162,6 +166,10 @@ mod impls {
             Ok(possibly_obj)
         }
 
+        fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<git_pack::find::Header>, Self::Error> {
+            self.store.try_header(id)
+        }
+
         fn location_by_oid(&self, id: impl AsRef<oid>, buf: &mut Vec<u8>) -> Option<git_pack::bundle::Location> {
             self.store.location_by_oid(id, buf)
         }
@@ -55,6 +55,16 @@ impl crate::pack::Find for linked::Store {
         Ok(None)
     }
 
+    fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<pack::find::Header>, Self::Error> {
+        let id = id.as_ref();
+        for db in self.dbs.iter() {
+            if let Some(header) = db.try_header(id)? {
+                return Ok(Some(header));
+            }
+        }
+        Ok(None)
+    }
+
     fn location_by_oid(&self, id: impl AsRef<oid>, buf: &mut Vec<u8>) -> Option<pack::bundle::Location> {
         let id = id.as_ref();
         for db in self.dbs.iter() {
@@ -118,4 +128,27 @@ impl crate::Find for linked::Store {
     fn try_find<'a>(&self, id: impl AsRef<oid>, buffer: &'a mut Vec<u8>) -> Result<Option<Data<'a>>, Self::Error> {
         pack::Find::try_find(self, id, buffer).map(|t| t.map(|t| t.0))
     }
+
+    fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<crate::find::Header>, Self::Error> {
+        pack::Find::try_header(self, id)
+    }
+}
+
+impl linked::Store {
+    /// Find the object identified by `id` in all of our databases and return a reader for its data, keeping memory usage
+    /// bounded even for very large objects.
+    ///
+    /// See [`compound::Store::try_find_stream()`] for details.
+    pub fn try_find_stream(
+        &self,
+        id: impl AsRef<oid>,
+    ) -> Result<Option<crate::find::Stream<'_>>, compound::find::Error> {
+        let id = id.as_ref();
+        for db in self.dbs.iter() {
+            if let Some(stream) = db.try_find_stream(id)? {
+                return Ok(Some(stream));
+            }
+        }
+        Ok(None)
+    }
 }
@@ -5,6 +5,14 @@ use crate::{
     store::{compound, linked},
 };
 
+/// Configuration for [`linked::Store::at_opts()`].
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone)]
+pub struct Options {
+    /// Alternate object directories to use before those listed in `alternates` files, typically obtained
+    /// [from the environment][alternate::from_environment()]. Directories which don't exist are skipped.
+    pub alternates: Vec<PathBuf>,
+}
+
 /// The error returned by [`linked::Store::at()`]
 #[derive(Debug, thiserror::Error)]
 #[allow(missing_docs)]
@@ -19,13 +27,21 @@ impl linked::Store {
     /// Instantiate an instance at the given `objects_directory`, commonly `.git/objects`.
     ///
     /// _git alternate_ files will be traversed to build a chain of [`compound::Store`] instances.
+    ///
+    /// Note that alternates in [`GIT_ALTERNATE_OBJECT_DIRECTORIES`][alternate::ENVIRONMENT_VARIABLE] are not used unless
+    /// passed with [`at_opts()`][linked::Store::at_opts()].
     pub fn at(objects_directory: impl Into<PathBuf>) -> Result<Self, Error> {
+        Self::at_opts(objects_directory, Options::default())
+    }
+
+    /// Like [`at()`][linked::Store::at()], but configurable with `options`.
+    pub fn at_opts(objects_directory: impl Into<PathBuf>, Options { alternates }: Options) -> Result<Self, Error> {
         let mut dbs = vec![compound::Store::at(objects_directory.into(), 0)?];
 
         let compute_ofs = |db: &compound::Store| db.bundles.iter().map(|p| p.pack.id).max().map(|ofs| ofs + 1);
         let mut ofs = compute_ofs(&dbs[0]).unwrap_or(0);
 
-        for object_path in alternate::resolve(dbs[0].loose.path.clone())?.into_iter() {
+        for object_path in alternate::resolve_with(dbs[0].loose.path.clone(), alternates.iter().cloned())?.into_iter() {
             let store = compound::Store::at(object_path, ofs)?;
             ofs = compute_ofs(&store).unwrap_or(ofs);
             dbs.push(store);
@@ -34,7 +50,7 @@ impl linked::Store {
             !dbs.is_empty(),
             "we can rely on at least one compound database to be present"
         );
-        Ok(linked::Store { dbs })
+        Ok(linked::Store { dbs, alternates })
     }
 
     /// Efficiently refresh the stable data like memory maps of packs or linked repositories to reflect the changed state on disk.
@@ -43,7 +59,12 @@ impl linked::Store {
         //       to the compound db to deal with pack refreshing.
         let first_db = self.dbs.remove(0);
         let base_path = first_db.loose.path;
-        *self = Self::at(base_path)?;
+        *self = Self::at_opts(
+            base_path,
+            Options {
+                alternates: std::mem::take(&mut self.alternates),
+            },
+        )?;
         Ok(self)
     }
 }
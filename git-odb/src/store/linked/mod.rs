@@ -7,11 +7,28 @@ use crate::store::compound;
 pub struct Store {
     /// The compound databases containing the actual objects.
     pub dbs: Vec<compound::Store>,
+    /// Alternate object directories to use in addition to those in `alternates` files, kept to use them again when refreshing.
+    alternates: Vec<std::path::PathBuf>,
+}
+
+/// A temporary object directory to receive objects into, which uses the objects directory it was created for as alternate.
+///
+/// Objects written to its [store][Quarantine::store()] only become visible in the original objects directory once
+/// [migrated][Quarantine::migrate()]. Dropping it removes the quarantine directory along with all objects it received.
+pub struct Quarantine {
+    store: Store,
+    objects_directory: std::path::PathBuf,
+    path: std::path::PathBuf,
+    /// Removed along with all received objects on drop or when the process receives a termination signal.
+    directory: git_tempfile::Handle<git_tempfile::handle::Directory>,
 }
 
 ///
 pub mod init;
 
+///
+pub mod quarantine;
+
 mod handle;
 
 mod find;
@@ -0,0 +1,177 @@
+//! Receive objects into a temporary directory first to only make them visible once they are known to be complete.
+//!
+//! This is what `git receive-pack` does to prevent partial or rejected objects to ever enter the object database,
+//! see [its documentation](https://github.com/git/git/blob/master/Documentation/git-receive-pack.txt#L223:L223).
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use git_object::bstr::ByteSlice;
+use git_tempfile::ContainingDirectory;
+
+use crate::store::{
+    linked,
+    transfer::{self, io_err, read_dir},
+};
+
+/// The prefix of all quarantine directories within an objects directory.
+const PREFIX: &str = "incoming-";
+
+/// The error returned by [`linked::Store::quarantine()`] and [`linked::Quarantine::migrate()`].
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("Could not {message} '{path}'")]
+    Io {
+        source: io::Error,
+        message: &'static str,
+        path: PathBuf,
+    },
+    #[error("The objects directory at '{}' cannot be represented in an alternates file", .0.display())]
+    PathConversion(PathBuf),
+    #[error(transparent)]
+    Init(#[from] linked::init::Error),
+}
+
+impl From<transfer::Error> for Error {
+    fn from(transfer::Error { source, message, path }: transfer::Error) -> Self {
+        Error::Io { source, message, path }
+    }
+}
+
+impl linked::Store {
+    /// Create a new [quarantine][linked::Quarantine] within the objects directory of our first database to receive objects into,
+    /// for example while receiving a pack from a remote.
+    ///
+    /// The quarantine directory is removed when the process receives a termination signal. Directories of processes which were
+    /// killed without a chance to clean up are left alone as they can't be told apart from those still in use.
+    pub fn quarantine(&self) -> Result<linked::Quarantine, Error> {
+        linked::Quarantine::new(&self.dbs[0].loose.path, self.alternates.clone())
+    }
+}
+
+/// Access
+impl linked::Quarantine {
+    /// The store to write received objects to, which can also read all objects of the objects directory it was created for.
+    pub fn store(&self) -> &linked::Store {
+        &self.store
+    }
+
+    /// Mutable access to the store, mostly for [refreshing][linked::Store::refresh()] it after writing packs.
+    pub fn store_mut(&mut self) -> &mut linked::Store {
+        &mut self.store
+    }
+
+    /// The objects directory of the quarantine, suitable for `GIT_QUARANTINE_PATH` and `GIT_OBJECT_DIRECTORY` of hooks
+    /// which need to see the received objects.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The objects directory this quarantine was created for and which will receive all objects on [`migrate()`][Self::migrate()].
+    pub fn objects_directory(&self) -> &Path {
+        &self.objects_directory
+    }
+}
+
+impl linked::Quarantine {
+    fn new(objects_directory: &Path, additional_alternates: Vec<PathBuf>) -> Result<Self, Error> {
+        let objects_directory = objects_directory
+            .canonicalize()
+            .map_err(|err| io_err(err, "canonicalize objects directory", objects_directory))?;
+        let directory = git_tempfile::new_directory(&objects_directory, PREFIX, ContainingDirectory::Exists)
+            .map_err(|err| io_err(err, "create quarantine directory in", &objects_directory))?;
+        let path = directory.path().ok_or_else(|| {
+            io_err(
+                io::ErrorKind::Interrupted.into(),
+                "create quarantine directory in",
+                &objects_directory,
+            )
+        })?;
+
+        let pack_dir = path.join("pack");
+        fs::create_dir(&pack_dir).map_err(|err| io_err(err, "create directory", &pack_dir))?;
+        let info_dir = path.join("info");
+        fs::create_dir(&info_dir).map_err(|err| io_err(err, "create directory", &info_dir))?;
+        let alternates = {
+            let mut buf = <[u8]>::from_path(&objects_directory)
+                .filter(|path| !path.contains(&b'\n') && !path.starts_with(b"\""))
+                .ok_or_else(|| Error::PathConversion(objects_directory.clone()))?
+                .to_owned();
+            buf.push(b'\n');
+            buf
+        };
+        let alternates_path = info_dir.join("alternates");
+        fs::write(&alternates_path, alternates)
+            .map_err(|err| io_err(err, "write alternates file", &alternates_path))?;
+
+        Ok(linked::Quarantine {
+            store: linked::Store::at_opts(
+                &path,
+                linked::init::Options {
+                    alternates: additional_alternates,
+                },
+            )?,
+            objects_directory,
+            path,
+            directory,
+        })
+    }
+
+    /// Move all received loose objects and packs into the objects directory this quarantine was created for
+    /// and remove the quarantine directory afterwards.
+    ///
+    /// Objects which already exist in the destination are not overwritten. Pack indices are moved only after their
+    /// pack data files to not make incomplete packs visible to concurrent readers.
+    ///
+    /// Note that existing stores of the destination need to be [refreshed][linked::Store::refresh()] to see packs moved here.
+    pub fn migrate(self) -> Result<(), Error> {
+        let linked::Quarantine {
+            store,
+            objects_directory,
+            path: directory_path,
+            directory,
+        } = self;
+        drop(store);
+
+        transfer::loose_objects(&directory_path, &objects_directory, move_unless_present)?;
+
+        let mut pack_files: Vec<_> = read_dir(&directory_path.join("pack"))?
+            .into_iter()
+            .filter(|(_, name)| name.starts_with("pack-"))
+            .collect();
+        if !pack_files.is_empty() {
+            pack_files.sort_by_key(|(path, _)| pack_move_priority(path));
+            let destination_dir = objects_directory.join("pack");
+            fs::create_dir_all(&destination_dir).map_err(|err| io_err(err, "create directory", &destination_dir))?;
+            for (path, name) in pack_files {
+                move_unless_present(&path, &destination_dir.join(name))?;
+            }
+        }
+
+        match directory.take() {
+            Some(directory) => directory
+                .close()
+                .map_err(|err| io_err(err, "remove quarantine directory", &directory_path).into()),
+            // It was removed by a signal handler, and the process is about to terminate.
+            None => Ok(()),
+        }
+    }
+}
+
+/// Like git, move `.keep` files first and indices last so packs are complete once they can be discovered.
+fn pack_move_priority(path: &Path) -> usize {
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+    ["keep", "pack", "rev", "idx"]
+        .iter()
+        .position(|candidate| *candidate == extension)
+        .unwrap_or(4)
+}
+
+fn move_unless_present(source: &Path, destination: &Path) -> Result<(), Error> {
+    if transfer::is_present(destination) {
+        return Ok(());
+    }
+    fs::rename(source, destination).map_err(|err| io_err(err, "move received file to", destination).into())
+}
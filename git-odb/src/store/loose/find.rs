@@ -1,4 +1,8 @@
-use std::{fs, io::Read, path::PathBuf};
+use std::{
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+};
 
 use git_features::zlib;
 
@@ -65,6 +69,84 @@ impl Store {
         }
     }
 
+    /// Return a reader for the object identified by the given [`ObjectId`][git_hash::ObjectId] if present in this database,
+    /// which decompresses the object while reading instead of loading it into memory at once.
+    ///
+    /// Returns `Err` if there was an error opening the object or decoding its header. Returns `Ok<None>` if
+    /// there was no such object.
+    pub fn try_find_stream(
+        &self,
+        id: impl AsRef<git_hash::oid>,
+    ) -> Result<Option<crate::find::Stream<'static>>, Error> {
+        let mut header = [0u8; HEADER_READ_UNCOMPRESSED_BYTES];
+        Ok(self.read_header(id.as_ref(), &mut header)?.map(
+            |Header {
+                 kind,
+                 size,
+                 data_start,
+                 data_end,
+                 inflate,
+             }| {
+                let data_in_header = io::Cursor::new(header[data_start..data_end].to_vec());
+                crate::find::Stream::from_loose(kind, size as u64, data_in_header.chain(inflate))
+            },
+        ))
+    }
+
+    /// Return the kind and size of the object identified by the given [`ObjectId`][git_hash::ObjectId] if present in this
+    /// database, decompressing only its header.
+    pub fn try_header(&self, id: impl AsRef<git_hash::oid>) -> Result<Option<crate::find::Header>, Error> {
+        // enough for the longest possible header, 'commit <u64::MAX>\0'
+        let mut header = [0u8; 32];
+        Ok(self
+            .read_header(id.as_ref(), &mut header)?
+            .map(|Header { kind, size, .. }| crate::find::Header {
+                kind,
+                size: size as u64,
+            }))
+    }
+
+    /// Open the object with `id` and decompress it into `buf` until its header was read.
+    fn read_header(&self, id: &git_hash::oid, buf: &mut [u8]) -> Result<Option<Header>, Error> {
+        let path = sha1_path(id, self.path.clone());
+        let file = match fs::File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(Error::Io {
+                    source: err,
+                    action: Self::OPEN_ACTION,
+                    path,
+                })
+            }
+        };
+        let mut inflate = zlib::stream::inflate::ReadBoxed {
+            inner: io::BufReader::new(file),
+            decompressor: Box::new(zlib::Decompress::new(true)),
+        };
+
+        let mut filled = 0;
+        while filled < buf.len() && !buf[..filled].contains(&0) {
+            let bytes_read = inflate.read(&mut buf[filled..]).map_err(|e| Error::Io {
+                source: e,
+                action: "deflate",
+                path: path.to_owned(),
+            })?;
+            if bytes_read == 0 {
+                break;
+            }
+            filled += bytes_read;
+        }
+        let (kind, size, header_size) = git_object::decode::loose_header(&buf[..filled])?;
+        Ok(Some(Header {
+            kind,
+            size,
+            data_start: header_size,
+            data_end: filled,
+            inflate,
+        }))
+    }
+
     fn find_inner<'a>(&self, id: &git_hash::oid, buf: &'a mut Vec<u8>) -> Result<git_object::Data<'a>, Error> {
         let path = sha1_path(id, self.path.clone());
 
@@ -139,3 +221,13 @@ impl Store {
         Ok(git_object::Data { kind, data: buf })
     }
 }
+
+/// A decoded loose object header, along with the decompressor positioned right after the data following it.
+struct Header {
+    kind: git_object::Kind,
+    size: usize,
+    /// The range of object data that was decompressed along with the header.
+    data_start: usize,
+    data_end: usize,
+    inflate: zlib::stream::inflate::ReadBoxed<io::BufReader<fs::File>>,
+}
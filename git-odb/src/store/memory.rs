@@ -0,0 +1,183 @@
+//! An in-memory object database layered on top of another one.
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    convert::TryInto,
+    io::{self, Read},
+};
+
+use git_hash::{oid, ObjectId};
+use git_object::{Data, Kind};
+
+use crate::pack;
+
+/// An object database which keeps all written objects in memory while reading objects it doesn't have from the
+/// store it wraps.
+///
+/// This allows to compute and inspect objects, like commits resulting from a merge, without touching the disk,
+/// and to [persist][Proxy::persist()] them only if desired.
+pub struct Proxy<T> {
+    inner: T,
+    memory: RefCell<BTreeMap<ObjectId, (Kind, Vec<u8>)>>,
+}
+
+impl<T> Proxy<T> {
+    /// Create a new instance which reads objects from `inner` if they are not held in memory.
+    pub fn new(inner: T) -> Self {
+        Proxy {
+            inner,
+            memory: Default::default(),
+        }
+    }
+
+    /// Return the store we read objects from that we don't have in memory.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Return the store we read objects from, discarding all objects held in memory.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Return the amount of objects held in memory.
+    pub fn len(&self) -> usize {
+        self.memory.borrow().len()
+    }
+
+    /// Return true if no object was written yet.
+    pub fn is_empty(&self) -> bool {
+        self.memory.borrow().is_empty()
+    }
+
+    /// Return the ids of all objects held in memory, in order.
+    pub fn ids(&self) -> Vec<ObjectId> {
+        self.memory.borrow().keys().cloned().collect()
+    }
+
+    /// Discard all objects held in memory.
+    pub fn clear(&mut self) {
+        self.memory.get_mut().clear();
+    }
+
+    /// Write all objects held in memory into `store` using the given kind of `hash`, keeping them in memory.
+    ///
+    /// Typically `store` is the same as our [inner][Proxy::inner()] store.
+    pub fn persist<W: crate::Write>(&self, store: W, hash: git_hash::Kind) -> Result<(), W::Error> {
+        for (kind, data) in self.memory.borrow().values() {
+            store.write_buf(*kind, data, hash)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> crate::Write for Proxy<T> {
+    type Error = io::Error;
+
+    fn write_stream(
+        &self,
+        kind: Kind,
+        size: u64,
+        mut from: impl Read,
+        hash: git_hash::Kind,
+    ) -> Result<ObjectId, Self::Error> {
+        let size: usize = size.try_into().expect("object size to fit into usize");
+        let mut data = Vec::with_capacity(size);
+        from.by_ref().take(size as u64).read_to_end(&mut data)?;
+        if data.len() != size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the stream ended before all object data was read",
+            ));
+        }
+        let id: ObjectId = match hash {
+            git_hash::Kind::Sha1 => {
+                let mut hasher = git_features::hash::Sha1::default();
+                hasher.update(&git_object::encode::loose_header(kind, size));
+                hasher.update(&data);
+                hasher.digest().into()
+            }
+        };
+        self.memory.borrow_mut().entry(id).or_insert((kind, data));
+        Ok(id)
+    }
+}
+
+impl<T> pack::Find for Proxy<T>
+where
+    T: pack::Find,
+{
+    type Error = T::Error;
+
+    fn contains(&self, id: impl AsRef<oid>) -> bool {
+        let id = id.as_ref();
+        self.memory.borrow().contains_key(id) || self.inner.contains(id)
+    }
+
+    fn try_find_cached<'a>(
+        &self,
+        id: impl AsRef<oid>,
+        buffer: &'a mut Vec<u8>,
+        pack_cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Result<Option<(Data<'a>, Option<pack::bundle::Location>)>, Self::Error> {
+        let id = id.as_ref();
+        if let Some((kind, data)) = self.memory.borrow().get(id) {
+            buffer.clear();
+            buffer.extend_from_slice(data);
+            return Ok(Some((
+                Data {
+                    kind: *kind,
+                    data: buffer,
+                },
+                None,
+            )));
+        }
+        self.inner.try_find_cached(id, buffer, pack_cache)
+    }
+
+    fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<pack::find::Header>, Self::Error> {
+        let id = id.as_ref();
+        if let Some((kind, data)) = self.memory.borrow().get(id) {
+            return Ok(Some(pack::find::Header {
+                kind: *kind,
+                size: data.len() as u64,
+            }));
+        }
+        self.inner.try_header(id)
+    }
+
+    fn location_by_oid(&self, id: impl AsRef<oid>, buf: &mut Vec<u8>) -> Option<pack::bundle::Location> {
+        let id = id.as_ref();
+        if self.memory.borrow().contains_key(id) {
+            return None;
+        }
+        self.inner.location_by_oid(id, buf)
+    }
+
+    fn index_iter_by_pack_id(&self, pack_id: u32) -> Option<Box<dyn Iterator<Item = pack::index::Entry> + '_>> {
+        self.inner.index_iter_by_pack_id(pack_id)
+    }
+
+    fn entry_by_location(&self, location: &pack::bundle::Location) -> Option<pack::find::Entry<'_>> {
+        self.inner.entry_by_location(location)
+    }
+}
+
+impl<T> crate::Find for Proxy<T>
+where
+    T: pack::Find,
+{
+    type Error = T::Error;
+
+    fn contains(&self, id: impl AsRef<oid>) -> bool {
+        pack::Find::contains(self, id)
+    }
+
+    fn try_find<'a>(&self, id: impl AsRef<oid>, buffer: &'a mut Vec<u8>) -> Result<Option<Data<'a>>, Self::Error> {
+        pack::Find::try_find(self, id, buffer).map(|t| t.map(|t| t.0))
+    }
+
+    fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<crate::find::Header>, Self::Error> {
+        pack::Find::try_header(self, id)
+    }
+}
@@ -3,14 +3,18 @@ use std::{cell::RefCell, sync::Arc};
 pub use sink::{sink, Sink};
 
 pub mod compound;
+pub mod general;
 ///
 pub mod handle;
 pub mod linked;
 pub mod loose;
+pub mod memory;
 
 ///
 pub mod sink;
 
+pub(crate) mod transfer;
+
 /// A way to access objects along with pre-configured thread-local caches for packed base objects as well as objects themselves.
 ///
 /// By default, no cache will be used.
@@ -0,0 +1,65 @@
+//! Utilities to copy or move objects and packs from one objects directory to another.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// An IO error along with the operation that failed and the path it failed on.
+pub(crate) struct Error {
+    pub source: io::Error,
+    pub message: &'static str,
+    pub path: PathBuf,
+}
+
+pub(crate) fn io_err(source: io::Error, message: &'static str, path: &Path) -> Error {
+    Error {
+        source,
+        message,
+        path: path.to_owned(),
+    }
+}
+
+/// Return the paths and names of all entries in `dir`.
+pub(crate) fn read_dir(dir: &Path) -> Result<Vec<(PathBuf, String)>, Error> {
+    fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|e| (e.path(), e.file_name().to_string_lossy().into_owned())))
+                .collect()
+        })
+        .map_err(|err| io_err(err, "read directory", dir))
+}
+
+/// Call `transfer(source, destination)` for each loose object in the fan-out directories of `source_dir`, with `destination`
+/// being its path in `destination_dir` whose fan-out directory is created as needed.
+///
+/// Only files named like objects are passed, leaving out temporary files like `tmp_obj_*` which may still be written to.
+pub(crate) fn loose_objects<E: From<Error>>(
+    source_dir: &Path,
+    destination_dir: &Path,
+    mut transfer: impl FnMut(&Path, &Path) -> Result<(), E>,
+) -> Result<(), E> {
+    for (path, name) in read_dir(source_dir)? {
+        if !(is_hex(&name, 2) && path.is_dir()) {
+            continue;
+        }
+        let destination_dir = destination_dir.join(name);
+        fs::create_dir_all(&destination_dir).map_err(|err| io_err(err, "create directory", &destination_dir))?;
+        for (object_path, object_name) in read_dir(&path)? {
+            if is_hex(&object_name, 38) {
+                transfer(&object_path, &destination_dir.join(object_name))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Return true if `destination` exists and thus doesn't need to be written.
+pub(crate) fn is_present(destination: &Path) -> bool {
+    // Objects and packs are named after their content, hence the existing file is what we would put there.
+    destination.exists()
+}
+
+fn is_hex(name: &str, len: usize) -> bool {
+    name.len() == len && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
@@ -60,6 +60,18 @@ pub trait Find {
         id: impl AsRef<git_hash::oid>,
         buffer: &'a mut Vec<u8>,
     ) -> Result<Option<git_object::Data<'a>>, Self::Error>;
+
+    /// Find the kind and size of the object matching `id` without decoding its data, or `None` if it doesn't exist.
+    ///
+    /// The default implementation decodes the entire object, which is why implementors should provide a more efficient
+    /// implementation if they can.
+    fn try_header(&self, id: impl AsRef<git_hash::oid>) -> Result<Option<crate::find::Header>, Self::Error> {
+        let mut buf = Vec::new();
+        Ok(self.try_find(id, &mut buf)?.map(|obj| crate::find::Header {
+            kind: obj.kind,
+            size: obj.data.len() as u64,
+        }))
+    }
 }
 
 mod _impls {
@@ -156,6 +168,10 @@ mod _impls {
         fn try_find<'a>(&self, id: impl AsRef<oid>, buffer: &'a mut Vec<u8>) -> Result<Option<Data<'a>>, Self::Error> {
             (*self).try_find(id, buffer)
         }
+
+        fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<crate::find::Header>, Self::Error> {
+            (*self).try_header(id)
+        }
     }
 
     impl<T> crate::Find for Rc<T>
@@ -171,6 +187,10 @@ mod _impls {
         fn try_find<'a>(&self, id: impl AsRef<oid>, buffer: &'a mut Vec<u8>) -> Result<Option<Data<'a>>, Self::Error> {
             self.deref().try_find(id, buffer)
         }
+
+        fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<crate::find::Header>, Self::Error> {
+            self.deref().try_header(id)
+        }
     }
 
     impl<T> crate::Find for Arc<T>
@@ -186,6 +206,10 @@ mod _impls {
         fn try_find<'a>(&self, id: impl AsRef<oid>, buffer: &'a mut Vec<u8>) -> Result<Option<Data<'a>>, Self::Error> {
             self.deref().try_find(id, buffer)
         }
+
+        fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<crate::find::Header>, Self::Error> {
+            self.deref().try_header(id)
+        }
     }
 }
 
@@ -100,3 +100,196 @@ fn no_alternate_in_first_objects_dir() -> crate::Result {
     assert!(alternate::resolve(tmp.path())?.is_empty());
     Ok(())
 }
+
+#[test]
+fn add_list_and_remove() -> crate::Result {
+    let tmp = git_testtools::tempfile::TempDir::new()?;
+    let (at, b, c) = (tmp.path().join("a"), tmp.path().join("b"), tmp.path().join("c"));
+    for dir in &[&at, &b, &c] {
+        fs::create_dir(dir)?;
+    }
+
+    assert!(alternate::list(&at)?.is_empty());
+    assert!(alternate::add(&at, &b)?);
+    assert!(
+        !alternate::add(&at, &b)?,
+        "adding an existing alternate again has no effect"
+    );
+    assert!(
+        alternate::add(&at, Path::new("..").join("c"))?,
+        "relative paths are fine too"
+    );
+    assert!(
+        !alternate::add(&at, &c)?,
+        "paths are compared after resolving them relative to the objects directory"
+    );
+    assert_eq!(alternate::list(&at)?, vec![b.clone(), Path::new("..").join("c")]);
+    assert_eq!(alternate::resolve(&at)?.len(), 2);
+
+    assert!(alternate::remove(&at, &c)?);
+    assert!(!alternate::remove(&at, &c)?, "it's not present anymore");
+    assert_eq!(alternate::list(&at)?, vec![b.clone()]);
+    assert!(alternate::remove(&at, &b)?);
+    assert!(
+        !at.join("info").join("alternates").exists(),
+        "the file is removed once it's empty"
+    );
+    Ok(())
+}
+
+#[test]
+fn remove_keeps_comments_and_other_entries() -> crate::Result {
+    let tmp = git_testtools::tempfile::TempDir::new()?;
+    let (from, to) = alternate_with(tmp.path().join("a"), tmp.path().join("b"), Some("# comment\n"))?;
+    assert!(alternate::remove(&from, &to)?);
+    assert_eq!(fs::read(from.join("info").join("alternates"))?, b"\n# comment\n");
+    Ok(())
+}
+
+#[test]
+fn add_quotes_paths_which_would_be_misinterpreted_otherwise() -> crate::Result {
+    let tmp = git_testtools::tempfile::TempDir::new()?;
+    let at = tmp.path().join("a");
+    fs::create_dir_all(at.join("#not-a-comment"))?;
+
+    assert!(alternate::add(&at, "#not-a-comment")?);
+    assert_eq!(fs::read(at.join("info").join("alternates"))?, b"\"#not-a-comment\"\n");
+    assert_eq!(alternate::list(&at)?, vec![PathBuf::from("#not-a-comment")]);
+    assert_eq!(alternate::resolve(&at)?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn add_refuses_missing_directories_and_self_references() -> crate::Result {
+    let tmp = git_testtools::tempfile::TempDir::new()?;
+    assert!(matches!(
+        alternate::add(tmp.path(), tmp.path().join("missing")),
+        Err(alternate::edit::Error::NotADirectory(_))
+    ));
+    assert!(matches!(
+        alternate::add(tmp.path(), "."),
+        Err(alternate::edit::Error::SelfReference(_))
+    ));
+    assert!(alternate::list(tmp.path())?.is_empty());
+    Ok(())
+}
+
+#[test]
+#[cfg(not(windows))]
+fn environment_variable_values_are_split_and_unquoted() -> crate::Result {
+    assert_eq!(
+        alternate::parse::environment(br#"/a::"/b:\tc":relative/d:"#)?,
+        vec![
+            PathBuf::from("/a"),
+            PathBuf::from("/b:\tc"),
+            PathBuf::from("relative/d")
+        ]
+    );
+    assert!(alternate::parse::environment(b"")?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn additional_alternates_are_resolved_first_and_deduplicated() -> crate::Result {
+    let tmp = git_testtools::tempfile::TempDir::new()?;
+    let (from, to) = alternate(tmp.path().join("a"), tmp.path().join("b"))?;
+    let (additional, additional_to) = alternate(tmp.path().join("c"), tmp.path().join("d"))?;
+
+    assert_eq!(
+        alternate::resolve_with(&from, vec![additional.clone(), to.clone()])?,
+        vec![additional, additional_to, to],
+        "an alternate which is also in the alternates file is only used once"
+    );
+    Ok(())
+}
+
+#[test]
+fn additional_alternates_which_do_not_exist_are_skipped() -> crate::Result {
+    let tmp = git_testtools::tempfile::TempDir::new()?;
+    let (from, to) = alternate(tmp.path().join("a"), tmp.path().join("b"))?;
+
+    assert_eq!(
+        alternate::resolve_with(&from, vec![tmp.path().join("missing")])?,
+        vec![to],
+        "like git, missing directories only cause a warning"
+    );
+    Ok(())
+}
+
+#[test]
+fn additional_alternates_are_used_by_linked_stores_and_kept_when_refreshing() -> crate::Result {
+    let tmp = git_testtools::tempfile::TempDir::new()?;
+    let at = tmp.path().join("objects");
+    fs::create_dir(&at)?;
+    let additional = crate::fixture_path("objects").canonicalize()?;
+
+    assert_eq!(
+        git_odb::linked::Store::at(&at)?.dbs.len(),
+        1,
+        "the environment isn't read"
+    );
+    let mut store = git_odb::linked::Store::at_opts(
+        &at,
+        git_odb::linked::init::Options {
+            alternates: vec![additional.clone(), tmp.path().join("missing")],
+        },
+    )?;
+    assert_eq!(store.dbs.len(), 2);
+    assert_eq!(store.dbs[1].loose.path, additional);
+
+    store.refresh()?;
+    assert_eq!(store.dbs.len(), 2, "additional alternates are used again");
+    Ok(())
+}
+
+#[test]
+fn dissociate_copies_all_objects_of_alternates_and_removes_them() -> crate::Result {
+    let tmp = git_testtools::tempfile::TempDir::new()?;
+    let borrowed_dir = crate::fixture_path("objects").canonicalize()?;
+    let borrowed = git_odb::linked::Store::at(&borrowed_dir)?;
+    let at = tmp.path().join("objects");
+    fs::create_dir(&at)?;
+    assert!(alternate::add(&at, &borrowed_dir)?);
+
+    let store = git_odb::linked::Store::at(&at)?;
+    assert_eq!(store.dbs.len(), 2);
+    assert!(store.dbs[0].bundles.is_empty());
+
+    alternate::dissociate(&at)?;
+    assert!(alternate::list(&at)?.is_empty());
+
+    let store = git_odb::linked::Store::at(&at)?;
+    assert_eq!(store.dbs.len(), 1, "the alternate is gone");
+    assert_eq!(store.dbs[0].bundles.len(), borrowed.dbs[0].bundles.len());
+    let mut buf = Vec::new();
+    let mut count = 0;
+    for id in borrowed.iter() {
+        let id = id?;
+        assert!(git_odb::pack::Find::try_find(&store, id, &mut buf)?.is_some());
+        count += 1;
+    }
+    assert!(count > 0);
+    Ok(())
+}
+
+#[test]
+fn dissociate_only_copies_files_named_like_loose_objects() -> crate::Result {
+    let tmp = git_testtools::tempfile::TempDir::new()?;
+    let borrowed_dir = tmp.path().join("borrowed");
+    let fan_out_dir = borrowed_dir.join("ab");
+    fs::create_dir_all(&fan_out_dir)?;
+    let object_name = "cdef0123456789abcdef0123456789abcdef01";
+    fs::write(fan_out_dir.join(object_name), b"object")?;
+    fs::write(fan_out_dir.join("tmp_obj_AbCdEf"), b"partially written object")?;
+    let at = tmp.path().join("objects");
+    fs::create_dir(&at)?;
+    assert!(alternate::add(&at, &borrowed_dir)?);
+
+    alternate::dissociate(&at)?;
+    assert!(at.join("ab").join(object_name).is_file());
+    assert!(
+        !at.join("ab").join("tmp_obj_AbCdEf").exists(),
+        "temporary files are left alone"
+    );
+    Ok(())
+}
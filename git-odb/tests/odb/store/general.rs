@@ -0,0 +1,312 @@
+use std::path::Path;
+
+use git_odb::{general, pack, Find, Write};
+
+use crate::{fixture_path, hex_to_id};
+
+const PACK: &str = "pack-11fdfa9e156ab73caae3b6da867192221f2089c2";
+const PACKED_ID: &str = "501b297447a8255d3533c6858bb692575cdefaa0";
+const LOOSE_ID: &str = "37d4e6c5c48ba0d245164c4e10d5f41140cab980";
+
+fn copy_pack(name: &str, objects_dir: &Path) -> std::io::Result<()> {
+    let pack_dir = objects_dir.join("pack");
+    std::fs::create_dir_all(&pack_dir)?;
+    for ext in &["pack", "idx"] {
+        let file_name = format!("{}.{}", name, ext);
+        std::fs::copy(fixture_path("objects/pack").join(&file_name), pack_dir.join(file_name))?;
+    }
+    Ok(())
+}
+
+fn remove_pack(name: &str, objects_dir: &Path) -> std::io::Result<()> {
+    for ext in &["pack", "idx"] {
+        std::fs::remove_file(objects_dir.join("pack").join(format!("{}.{}", name, ext)))?;
+    }
+    Ok(())
+}
+
+fn can_find(handle: &general::Handle, hex_id: &str) -> bool {
+    let mut buf = Vec::new();
+    handle
+        .try_find(hex_to_id(hex_id), &mut buf)
+        .expect("no read error")
+        .is_some()
+}
+
+#[test]
+fn finds_loose_and_packed_objects_and_loads_packs_on_demand() -> crate::Result {
+    let store = general::Store::at(fixture_path("objects"))?;
+    assert_eq!(
+        store.metrics(),
+        general::Metrics {
+            num_handles: 0,
+            num_dbs: 1,
+            open_indices: 3,
+            open_packs: 0,
+            garbage_packs: 0,
+        }
+    );
+
+    let handle = store.to_handle();
+    assert!(can_find(&handle, LOOSE_ID));
+    assert_eq!(store.metrics().open_packs, 0, "loose objects don't need packs");
+    assert!(can_find(&handle, PACKED_ID));
+    assert!(can_find(&handle, "4dac9989f96bc5b5b1263b582c08f0c5f0b58542"));
+    assert_eq!(
+        store.metrics().open_packs,
+        2,
+        "only packs containing objects are loaded"
+    );
+    assert!(!handle.contains(hex_to_id("0000000000000000000000000000000000000001")));
+    assert!(!can_find(&handle, "0000000000000000000000000000000000000001"));
+
+    let mut buf = Vec::new();
+    let (obj, location) = pack::Find::try_find(&handle, hex_to_id(PACKED_ID), &mut buf)?.expect("present");
+    assert_eq!(obj.kind, git_object::Kind::Commit);
+    let location = location.expect("packed objects have a location");
+    assert_eq!(
+        pack::Find::location_by_oid(&handle, hex_to_id(PACKED_ID), &mut buf),
+        Some(location.clone())
+    );
+    assert_eq!(
+        pack::Find::index_iter_by_pack_id(&handle, location.pack_id).map(|iter| iter.count()),
+        Some(30)
+    );
+
+    let clone = handle.clone();
+    assert_eq!(store.metrics().num_handles, 2);
+    drop((handle, clone));
+    assert_eq!(store.metrics().num_handles, 0);
+    Ok(())
+}
+
+#[test]
+fn headers_of_loose_and_packed_objects_match_their_decoded_counterpart() -> crate::Result {
+    let store = general::Store::at(fixture_path("objects"))?;
+    let handle = store.to_handle();
+    let mut buf = Vec::new();
+    for hex_id in &[LOOSE_ID, PACKED_ID, "4dac9989f96bc5b5b1263b582c08f0c5f0b58542"] {
+        let id = hex_to_id(hex_id);
+        let header = handle.try_header(id)?.expect("present");
+        let obj = handle.try_find(id, &mut buf)?.expect("present");
+        assert_eq!(header.kind, obj.kind);
+        assert_eq!(header.size, obj.data.len() as u64);
+    }
+    assert!(handle
+        .try_header(hex_to_id("0000000000000000000000000000000000000001"))?
+        .is_none());
+    Ok(())
+}
+
+#[test]
+fn new_and_removed_packs_are_picked_up_on_object_miss() -> crate::Result {
+    let dir = git_testtools::tempfile::tempdir()?;
+    let objects_dir = dir.path().join("objects");
+    std::fs::create_dir(&objects_dir)?;
+    let store = general::Store::at(&objects_dir)?;
+    let handle = store.to_handle();
+    assert!(!can_find(&handle, PACKED_ID));
+
+    copy_pack(PACK, &objects_dir)?;
+    assert!(can_find(&handle, PACKED_ID), "the new pack is found after a refresh");
+    assert_eq!(store.metrics().open_indices, 1);
+    assert!(!store.refresh()?, "nothing changed since the last refresh");
+
+    remove_pack(PACK, &objects_dir)?;
+    assert!(
+        can_find(&handle, PACKED_ID),
+        "the pack is still loaded as it isn't known to be gone"
+    );
+    assert!(store.refresh()?);
+    assert!(!can_find(&handle, PACKED_ID), "removed packs are unloaded");
+    assert_eq!(
+        store.metrics(),
+        general::Metrics {
+            num_handles: 1,
+            num_dbs: 1,
+            open_indices: 0,
+            open_packs: 0,
+            garbage_packs: 0,
+        }
+    );
+
+    let mut never_refreshing = store.to_handle();
+    never_refreshing.set_refresh_mode(general::RefreshMode::Never);
+    copy_pack(PACK, &objects_dir)?;
+    assert!(!can_find(&never_refreshing, PACKED_ID));
+    assert!(can_find(&handle, PACKED_ID));
+    assert!(
+        can_find(&never_refreshing, PACKED_ID),
+        "packs are still seen once the store was refreshed"
+    );
+    Ok(())
+}
+
+#[test]
+fn removed_packs_remain_available_to_handles_which_need_them() -> crate::Result {
+    let dir = git_testtools::tempfile::tempdir()?;
+    let objects_dir = dir.path().join("objects");
+    copy_pack(PACK, &objects_dir)?;
+    let store = general::Store::at(&objects_dir)?;
+    let mut handle = store.to_handle();
+    handle.prevent_pack_unload();
+    assert!(can_find(&handle, PACKED_ID));
+
+    remove_pack(PACK, &objects_dir)?;
+    assert!(store.refresh()?);
+    assert!(
+        can_find(&handle, PACKED_ID),
+        "the pack is kept as it might still be used"
+    );
+    assert_eq!(store.metrics().garbage_packs, 1);
+
+    let unstable = store.to_handle();
+    drop(handle);
+    assert!(
+        store.refresh()?,
+        "removed packs are unloaded once no handle needs them anymore"
+    );
+    assert!(!can_find(&unstable, PACKED_ID));
+    assert_eq!(store.metrics().garbage_packs, 0);
+    Ok(())
+}
+
+#[test]
+fn alternates_are_picked_up_and_writes_go_to_the_objects_directory() -> crate::Result {
+    let dir = git_testtools::tempfile::tempdir()?;
+    let objects_dir = dir.path().join("objects");
+    let alternate_dir = dir.path().join("alternate");
+    std::fs::create_dir_all(objects_dir.join("info"))?;
+    copy_pack(PACK, &alternate_dir)?;
+
+    let store = general::Store::at(&objects_dir)?;
+    let handle = store.to_handle();
+    assert!(!can_find(&handle, PACKED_ID));
+
+    std::fs::write(
+        objects_dir.join("info/alternates"),
+        alternate_dir.to_str().expect("valid UTF-8"),
+    )?;
+    assert!(can_find(&handle, PACKED_ID), "the alternate was picked up");
+    assert_eq!(store.metrics().num_dbs, 2);
+
+    let id = handle.write_buf(git_object::Kind::Blob, b"hello", git_hash::Kind::Sha1)?;
+    assert!(objects_dir.join(id.to_hex(2).to_string()).is_dir());
+    assert!(handle.contains(id));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "internal-testing-git-features-parallel")]
+fn handles_can_be_used_from_multiple_threads() -> crate::Result {
+    let store = general::Store::at(fixture_path("objects"))?;
+    let handle = store.to_handle();
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let handle = handle.clone();
+            std::thread::spawn(move || can_find(&handle, PACKED_ID))
+        })
+        .collect();
+    for thread in threads {
+        assert!(thread.join().expect("no panic"));
+    }
+    assert_eq!(store.metrics().open_packs, 1, "packs are loaded only once");
+    Ok(())
+}
+
+#[test]
+fn the_least_recently_used_pack_is_unloaded_once_the_maximum_amount_of_packs_is_loaded() -> crate::Result {
+    let dir = git_testtools::tempfile::tempdir()?;
+    let objects_dir = dir.path().join("objects");
+    let (first, second, third) = (
+        (PACK, PACKED_ID),
+        (
+            "pack-a2bf8e71d8c18879e499335762dd95119d93d9f1",
+            "4dac9989f96bc5b5b1263b582c08f0c5f0b58542",
+        ),
+        (
+            "pack-c0438c19fb16422b6bbcce24387b3264416d485b",
+            "dd25c539efbb0ab018caa4cda2d133285634e9b5",
+        ),
+    );
+    for (pack, _) in &[first, second, third] {
+        copy_pack(pack, &objects_dir)?;
+    }
+    let store = general::Store::at_opts(
+        &objects_dir,
+        general::init::Options {
+            max_open_packs: Some(2),
+            ..Default::default()
+        },
+    )?;
+    let handle = store.to_handle();
+    for (_, id) in &[first, second, first, third] {
+        assert!(can_find(&handle, id));
+    }
+    assert_eq!(store.metrics().open_packs, 2, "the limit is respected");
+    assert_eq!(store.metrics().open_indices, 3, "indices are always loaded");
+
+    for (pack, _) in &[first, second] {
+        std::fs::remove_file(objects_dir.join("pack").join(format!("{}.pack", pack)))?;
+    }
+    assert!(
+        can_find(&handle, first.1),
+        "the first pack was used recently and is still loaded"
+    );
+    assert!(
+        !can_find(&handle, second.1),
+        "the second pack was unloaded and can't be loaded anymore"
+    );
+    Ok(())
+}
+
+#[test]
+fn missing_objects_are_fetched_from_the_promisor_remote_when_read() -> crate::Result {
+    let dir = git_testtools::tempfile::tempdir()?;
+    let objects_dir = dir.path().join("objects");
+    std::fs::create_dir(&objects_dir)?;
+    let store = general::Store::at(&objects_dir)?;
+    let handle = store.to_handle();
+
+    let fetched = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    store.set_promisor_fetch(Some(Box::new({
+        let objects_dir = objects_dir.clone();
+        let fetched = fetched.clone();
+        move |ids: &[git_hash::ObjectId]| {
+            fetched.fetch_add(ids.len(), std::sync::atomic::Ordering::SeqCst);
+            if ids.iter().any(|id| *id == hex_to_id(PACKED_ID)) {
+                copy_pack(PACK, &objects_dir)?;
+                std::fs::write(objects_dir.join("pack").join(format!("{}.promisor", PACK)), b"")?;
+            }
+            Ok(())
+        }
+    })));
+
+    assert!(!handle.contains(hex_to_id(PACKED_ID)));
+    assert_eq!(
+        fetched.load(std::sync::atomic::Ordering::SeqCst),
+        0,
+        "checking for existence doesn't fetch"
+    );
+    assert!(can_find(&handle, PACKED_ID), "the object was fetched on demand");
+    assert_eq!(fetched.load(std::sync::atomic::Ordering::SeqCst), 1);
+    assert!(handle.is_in_promisor_pack(hex_to_id(PACKED_ID)));
+    assert!(!handle.is_in_promisor_pack(hex_to_id(LOOSE_ID)));
+
+    assert!(!can_find(&handle, "0000000000000000000000000000000000000001"));
+    assert_eq!(
+        fetched.load(std::sync::atomic::Ordering::SeqCst),
+        2,
+        "objects the remote doesn't have stay missing"
+    );
+
+    store.set_promisor_fetch(Some(Box::new(|_: &[git_hash::ObjectId]| {
+        Err("remote unreachable".into())
+    })));
+    let mut buf = Vec::new();
+    assert!(matches!(
+        handle.try_find(hex_to_id("0000000000000000000000000000000000000001"), &mut buf),
+        Err(general::find::Error::Promisor { .. })
+    ));
+    Ok(())
+}
@@ -53,6 +53,71 @@ mod iter {
     }
 }
 
+mod try_header {
+    use git_pack::Find;
+
+    use crate::odb::store::linked::db;
+
+    #[test]
+    fn all_loose_and_packed_objects_match_their_decoded_counterpart() -> crate::Result {
+        let db = db();
+        let mut buf = Vec::new();
+        for id in db.iter() {
+            let id = id?;
+            let obj = db.try_find(id, &mut buf)?.expect("object present").0;
+            let header = db.try_header(id)?.expect("object present");
+            assert_eq!(header.kind, obj.kind, "{}", id);
+            assert_eq!(header.size, obj.data.len() as u64, "{}", id);
+        }
+        assert!(db.try_header(git_hash::ObjectId::null_sha1())?.is_none());
+        Ok(())
+    }
+}
+
+mod find_stream {
+    use std::io::Read;
+
+    use git_odb::{linked, Write};
+    use git_pack::Find;
+
+    use crate::odb::store::linked::db;
+
+    #[test]
+    fn all_loose_and_packed_objects_match_their_buffered_counterpart() -> crate::Result {
+        let db = db();
+        let mut buf = Vec::new();
+        let mut streamed = Vec::new();
+        for id in db.iter() {
+            let id = id?;
+            let obj = db.try_find(id, &mut buf)?.expect("object present").0;
+            let mut stream = db.try_find_stream(id)?.expect("object present");
+            assert_eq!(stream.kind, obj.kind);
+            assert_eq!(stream.size, obj.data.len() as u64);
+            streamed.clear();
+            stream.read_to_end(&mut streamed)?;
+            assert_eq!(streamed, obj.data, "{}", id);
+        }
+        assert!(db.try_find_stream(git_hash::ObjectId::null_sha1())?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn large_loose_objects() -> crate::Result {
+        let tmp = git_testtools::tempfile::TempDir::new()?;
+        let db = linked::Store::at(tmp.path())?;
+        let data: Vec<u8> = (0..3 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+        let id = db.write_buf(git_object::Kind::Blob, &data, git_hash::Kind::Sha1)?;
+
+        let mut stream = db.try_find_stream(id)?.expect("object present");
+        assert_eq!(stream.kind, git_object::Kind::Blob);
+        assert_eq!(stream.size, data.len() as u64);
+        let mut streamed = Vec::new();
+        stream.read_to_end(&mut streamed)?;
+        assert!(streamed == data, "the streamed data matches what was written");
+        Ok(())
+    }
+}
+
 mod locate {
     use git_odb::linked::Store;
     use git_pack::Find;
@@ -114,3 +179,98 @@ mod init {
         assert_eq!(db.dbs[0].bundles.len(), 3)
     }
 }
+
+mod quarantine {
+    use std::path::Path;
+
+    use git_odb::{linked, Write};
+    use git_pack::Find;
+
+    use crate::fixture_path;
+
+    fn quarantine_dirs(objects_dir: &Path) -> std::io::Result<usize> {
+        Ok(std::fs::read_dir(objects_dir)?
+            .filter_map(Result::ok)
+            .filter(|e| e.file_name().to_string_lossy().starts_with("incoming-"))
+            .count())
+    }
+
+    #[test]
+    fn received_objects_are_only_visible_after_migration() -> crate::Result {
+        let tmp = git_testtools::tempfile::TempDir::new()?;
+        let db = linked::Store::at(tmp.path())?;
+        let existing = db.write_buf(git_object::Kind::Blob, b"existing", git_hash::Kind::Sha1)?;
+
+        let mut quarantine = db.quarantine()?;
+        assert!(quarantine.path().starts_with(tmp.path().canonicalize()?));
+        let received = quarantine
+            .store()
+            .write_buf(git_object::Kind::Blob, b"received", git_hash::Kind::Sha1)?;
+        assert!(
+            quarantine.store().contains(existing),
+            "the original objects directory is an alternate"
+        );
+        assert!(quarantine.store().contains(received));
+        assert!(
+            !db.contains(received),
+            "received objects are invisible to the original store"
+        );
+
+        let pack_name = "pack-11fdfa9e156ab73caae3b6da867192221f2089c2";
+        for ext in &["idx", "pack"] {
+            let file_name = format!("{}.{}", pack_name, ext);
+            std::fs::copy(
+                fixture_path("objects/pack").join(&file_name),
+                quarantine.path().join("pack").join(file_name),
+            )?;
+        }
+        quarantine.store_mut().refresh()?;
+        let packed = git_testtools::hex_to_id("501b297447a8255d3533c6858bb692575cdefaa0");
+        assert!(quarantine.store().contains(packed));
+        assert!(!db.contains(packed));
+
+        quarantine.migrate()?;
+        assert_eq!(quarantine_dirs(tmp.path())?, 0, "the quarantine directory is removed");
+        let db = linked::Store::at(tmp.path())?;
+        assert!(db.contains(received), "loose objects were moved");
+        assert!(db.contains(packed), "packs were moved");
+        assert!(db.contains(existing));
+        assert_eq!(db.dbs.len(), 1, "no alternate was added");
+        Ok(())
+    }
+
+    #[test]
+    fn dropping_the_quarantine_discards_all_received_objects() -> crate::Result {
+        let tmp = git_testtools::tempfile::TempDir::new()?;
+        let db = linked::Store::at(tmp.path())?;
+        let quarantine = db.quarantine()?;
+        let received = quarantine
+            .store()
+            .write_buf(git_object::Kind::Blob, b"received", git_hash::Kind::Sha1)?;
+        assert_eq!(quarantine_dirs(tmp.path())?, 1);
+
+        drop(quarantine);
+        assert_eq!(quarantine_dirs(tmp.path())?, 0);
+        assert!(!linked::Store::at(tmp.path())?.contains(received));
+        Ok(())
+    }
+
+    #[test]
+    fn quarantines_of_other_processes_are_left_alone() -> crate::Result {
+        let tmp = git_testtools::tempfile::TempDir::new()?;
+        let foreign = tmp.path().join("incoming-foreign");
+        std::fs::create_dir_all(foreign.join("ab"))?;
+        std::fs::write(foreign.join("ab").join("partial"), b"")?;
+
+        let db = linked::Store::at(tmp.path())?;
+        let first = db.quarantine()?;
+        let second = db.quarantine()?;
+        assert!(
+            foreign.join("ab").join("partial").is_file(),
+            "they might still be in use"
+        );
+        assert_ne!(first.path(), second.path());
+        assert_eq!(quarantine_dirs(tmp.path())?, 3);
+        Ok(())
+    }
+}
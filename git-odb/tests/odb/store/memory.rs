@@ -0,0 +1,77 @@
+use git_odb::{linked, memory, Find, FindExt, Write};
+
+use crate::{fixture_path, hex_to_id};
+
+fn db() -> memory::Proxy<linked::Store> {
+    memory::Proxy::new(linked::Store::at(fixture_path("objects")).expect("valid object path"))
+}
+
+#[test]
+fn writes_are_kept_in_memory_while_reads_fall_back_to_the_inner_store() -> crate::Result {
+    let db = db();
+    assert!(db.is_empty());
+    let id = db.write_buf(git_object::Kind::Blob, b"hello world", git_hash::Kind::Sha1)?;
+    assert_eq!(id, hex_to_id("95d09f2b10159347eece71399a7e2e907ea3df4f"));
+    assert_eq!(db.len(), 1);
+    assert_eq!(db.ids(), vec![id]);
+
+    let mut buf = Vec::new();
+    assert!(db.contains(id));
+    assert_eq!(db.find_blob(id, &mut buf)?.data, b"hello world");
+    assert!(!db.inner().contains(id), "nothing was written to the inner store");
+
+    let packed = hex_to_id("501b297447a8255d3533c6858bb692575cdefaa0");
+    assert!(db.contains(packed));
+    assert!(db.try_find(packed, &mut buf)?.is_some(), "objects on disk can be read");
+
+    let header = db.try_header(id)?.expect("present");
+    assert_eq!(header.kind, git_object::Kind::Blob);
+    assert_eq!(header.size, 11);
+    assert_eq!(
+        db.try_header(packed)?.map(|h| h.kind),
+        Some(git_object::Kind::Commit),
+        "headers are obtained from the inner store as well"
+    );
+
+    let again = db.write_buf(git_object::Kind::Blob, b"hello world", git_hash::Kind::Sha1)?;
+    assert_eq!(again, id);
+    assert_eq!(db.len(), 1, "objects are only stored once");
+    Ok(())
+}
+
+#[test]
+fn persist_writes_all_objects_into_another_store() -> crate::Result {
+    let mut db = db();
+    let blob = db.write_buf(git_object::Kind::Blob, b"content", git_hash::Kind::Sha1)?;
+    let tree = db.write(
+        &git_object::Tree {
+            entries: vec![git_object::tree::Entry {
+                mode: git_object::tree::EntryMode::Blob,
+                filename: "file".into(),
+                oid: blob,
+            }],
+        },
+        git_hash::Kind::Sha1,
+    )?;
+
+    let tmp = git_testtools::tempfile::TempDir::new()?;
+    let target = linked::Store::at(tmp.path())?;
+    db.persist(&target, git_hash::Kind::Sha1)?;
+    assert!(target.contains(blob));
+    assert!(target.contains(tree));
+    assert_eq!(db.len(), 2, "objects are still in memory");
+
+    db.clear();
+    assert!(db.is_empty());
+    assert!(!db.contains(blob), "the inner store never saw the objects");
+    Ok(())
+}
+
+#[test]
+fn incomplete_streams_are_an_error() {
+    let db = db();
+    assert!(db
+        .write_stream(git_object::Kind::Blob, 10, &b"short"[..], git_hash::Kind::Sha1)
+        .is_err());
+    assert!(db.is_empty());
+}
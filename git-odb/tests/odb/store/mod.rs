@@ -1,5 +1,7 @@
 pub mod compound;
+pub mod general;
 pub mod handle;
 pub mod linked;
 pub mod loose;
+pub mod memory;
 pub mod sink;
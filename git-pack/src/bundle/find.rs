@@ -59,4 +59,64 @@ impl crate::Bundle {
                 )
             })
     }
+
+    /// Like [`find()`][Self::find()], but return a reader for the object's data instead of decoding it into memory,
+    /// see [`data::File::decode_entry_stream()`][crate::data::File::decode_entry_stream()] for details.
+    pub fn find_stream(
+        &self,
+        id: impl AsRef<git_hash::oid>,
+    ) -> Result<Option<crate::data::decode_stream::Stream<'_>>, crate::data::decode_stream::Error> {
+        let idx = match self.index.lookup(id) {
+            Some(idx) => idx,
+            None => return Ok(None),
+        };
+        self.get_object_stream_by_index(idx).map(Some)
+    }
+
+    /// Like [`get_object_by_index()`][Self::get_object_by_index()], but return a reader for the object's data.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn get_object_stream_by_index(
+        &self,
+        idx: u32,
+    ) -> Result<crate::data::decode_stream::Stream<'_>, crate::data::decode_stream::Error> {
+        let ofs = self.index.pack_offset_at_index(idx);
+        self.pack.decode_entry_stream(self.pack.entry(ofs), |id, _out| {
+            self.index
+                .lookup(id)
+                .map(|idx| crate::data::ResolvedBase::InPack(self.pack.entry(self.index.pack_offset_at_index(idx))))
+        })
+    }
+
+    /// Find the kind and size of the object with the given [`ObjectId`][git_hash::ObjectId] without decoding it,
+    /// see [`data::File::decode_header()`][crate::data::File::decode_header()] for details.
+    pub fn find_header(
+        &self,
+        id: impl AsRef<git_hash::oid>,
+    ) -> Result<Option<crate::data::decode_header::Outcome>, crate::data::decode_entry::Error> {
+        let idx = match self.index.lookup(id) {
+            Some(idx) => idx,
+            None => return Ok(None),
+        };
+        self.get_object_header_by_index(idx).map(Some)
+    }
+
+    /// Like [`get_object_by_index()`][Self::get_object_by_index()], but only decode the kind and size of the object.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn get_object_header_by_index(
+        &self,
+        idx: u32,
+    ) -> Result<crate::data::decode_header::Outcome, crate::data::decode_entry::Error> {
+        let ofs = self.index.pack_offset_at_index(idx);
+        self.pack.decode_header(self.pack.entry(ofs), |id| {
+            self.index.lookup(id).map(|idx| {
+                crate::data::decode_header::ResolvedBase::InPack(self.pack.entry(self.index.pack_offset_at_index(idx)))
+            })
+        })
+    }
 }
@@ -0,0 +1,100 @@
+use std::convert::TryInto;
+
+use git_features::zlib;
+
+use crate::data::{decode_entry::Error, delta, File};
+
+/// A return value of a resolve function, which given an [`ObjectId`][git_hash::ObjectId] determines where an object can be found.
+#[derive(Debug, PartialEq, Eq, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResolvedBase {
+    /// Indicate an object is within this pack, at the given entry, and thus can be looked up locally.
+    InPack(crate::data::Entry),
+    /// Indicates the object of `kind` was found outside of the pack.
+    #[allow(missing_docs)]
+    OutOfPack { kind: git_object::Kind },
+}
+
+/// Additional information about an object whose header was decoded by [`File::decode_header()`].
+#[derive(Debug, PartialEq, Eq, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The kind of resolved object.
+    pub kind: git_object::Kind,
+    /// The decompressed size of the object.
+    pub object_size: u64,
+    /// The amount of deltas in the chain of objects that had to be followed to learn the kind of object.
+    pub num_deltas: u32,
+}
+
+/// The maximum amount of bytes needed to decode the base and result size of a delta, each being a variable-length integer.
+const DELTA_SIZES_MAX_LEN: usize = 20;
+
+/// Obtain object information quickly.
+impl File {
+    /// Resolve the kind and size of the object at `entry` without decoding it.
+    ///
+    /// The size of deltified objects is obtained by decompressing only the first bytes of the delta at `entry`, while the kind
+    /// is determined by following the delta chain to its base, reading only pack entry headers along the way.
+    ///
+    /// `resolve` is a function to lookup objects with the given [`ObjectId`][git_hash::ObjectId], in case the full object id is
+    /// used to refer to a base object, instead of an in-pack offset.
+    pub fn decode_header(
+        &self,
+        mut entry: crate::data::Entry,
+        resolve: impl Fn(&git_hash::oid) -> Option<ResolvedBase>,
+    ) -> Result<Outcome, Error> {
+        use crate::data::entry::Header::*;
+        let object_size = match entry.header {
+            Tree | Blob | Commit | Tag => {
+                return Ok(Outcome {
+                    kind: entry.header.as_kind().expect("a non-delta entry"),
+                    object_size: entry.decompressed_size,
+                    num_deltas: 0,
+                })
+            }
+            OfsDelta { .. } | RefDelta { .. } => self.decode_delta_object_size(&entry)?,
+        };
+
+        let mut num_deltas = 0;
+        loop {
+            num_deltas += 1;
+            entry = match entry.header {
+                OfsDelta { base_distance } => self.entry(entry.base_pack_offset(base_distance)),
+                RefDelta { base_id } => match resolve(base_id.as_ref()) {
+                    Some(ResolvedBase::InPack(base_entry)) => base_entry,
+                    Some(ResolvedBase::OutOfPack { kind }) => {
+                        return Ok(Outcome {
+                            kind,
+                            object_size,
+                            num_deltas,
+                        })
+                    }
+                    None => return Err(Error::DeltaBaseUnresolved(base_id)),
+                },
+                _ => unreachable!("only deltas are followed"),
+            };
+            if let Some(kind) = entry.header.as_kind() {
+                return Ok(Outcome {
+                    kind,
+                    object_size,
+                    num_deltas,
+                });
+            }
+        }
+    }
+
+    /// Decompress just enough of the delta at `entry` to learn the size of the object it produces.
+    fn decode_delta_object_size(&self, entry: &crate::data::Entry) -> Result<u64, Error> {
+        let offset: usize = entry.data_offset.try_into().expect("offset representable by machine");
+        assert!(offset < self.data.len(), "entry offset out of bounds");
+        let mut buf = [0u8; DELTA_SIZES_MAX_LEN];
+        let len = (entry.decompressed_size as usize).min(buf.len());
+        let (_status, _consumed_in, consumed_out) =
+            zlib::Inflate::default().once(&self.data[offset..], &mut buf[..len])?;
+        let sizes = &buf[..consumed_out];
+        let (_base_size, consumed) = delta::decode_header_size(sizes);
+        let (result_size, _consumed) = delta::decode_header_size(&sizes[consumed..]);
+        Ok(result_size)
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    convert::TryInto,
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+};
+
+use git_features::zlib;
+use smallvec::SmallVec;
+
+use super::ResolvedBase;
+use crate::data::{delta, File};
+
+/// Returned by [`File::decode_entry_stream()`]
+#[derive(thiserror::Error, Debug)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("Failed to decompress pack entry")]
+    ZlibInflate(#[from] zlib::inflate::Error),
+    #[error("A delta chain could not be applied as the ref base with id {0} could not be found")]
+    DeltaBaseUnresolved(git_hash::ObjectId),
+    #[error("Could not write an intermediate delta result to a temporary file")]
+    Io(#[from] io::Error),
+}
+
+/// A reader yielding the data of a decoded pack entry without holding the entire object in memory,
+/// as created by [`File::decode_entry_stream()`].
+pub struct Stream<'a> {
+    /// The kind of the decoded object.
+    pub kind: git_object::Kind,
+    /// The size of the decoded object in bytes, which is the amount of bytes this stream will yield.
+    pub size: u64,
+    inner: Inner<'a>,
+}
+
+enum Inner<'a> {
+    Base(zlib::stream::inflate::ReadBoxed<&'a [u8]>),
+    Delta(ApplyDelta),
+}
+
+impl io::Read for Stream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            Inner::Base(r) => r.read(buf),
+            Inner::Delta(r) => r.read(buf),
+        }
+    }
+}
+
+/// Decoding of objects with bounded memory
+impl File {
+    /// Return a reader yielding the decompressed data of the given `entry`.
+    ///
+    /// _Note_ that this method does not resolve deltified objects, but merely decompresses their content.
+    pub fn decompress_entry_stream(&self, entry: &crate::data::Entry) -> zlib::stream::inflate::ReadBoxed<&'_ [u8]> {
+        let offset: usize = entry.data_offset.try_into().expect("offset representable by machine");
+        assert!(offset < self.data.len(), "entry offset out of bounds");
+        zlib::stream::inflate::ReadBoxed {
+            inner: &self.data[offset..],
+            decompressor: Box::new(zlib::Decompress::new(true)),
+        }
+    }
+
+    /// Decode an entry like [`decode_entry()`][File::decode_entry()], but return a reader for its data to avoid
+    /// holding the entire object in memory, which is useful for very large blobs.
+    ///
+    /// Base objects are decompressed while reading. For deltified objects, all but the last delta of the chain are applied
+    /// up-front with their results written to anonymous temporary files, while the last delta is applied while reading.
+    /// Memory usage is thus bounded by the size of the largest delta of the chain, whereas the size of the object only
+    /// affects the amount of temporary disk space used.
+    ///
+    /// `resolve` is a function to lookup objects with the given [`ObjectId`][git_hash::ObjectId], in case the full object id
+    /// is used to refer to a base object, instead of an in-pack offset. Out-of-pack bases are held in memory.
+    pub fn decode_entry_stream(
+        &self,
+        entry: crate::data::Entry,
+        resolve: impl Fn(&git_hash::oid, &mut Vec<u8>) -> Option<ResolvedBase>,
+    ) -> Result<Stream<'_>, Error> {
+        use crate::data::entry::Header;
+        if let Some(kind) = entry.header.as_kind() {
+            return Ok(Stream {
+                kind,
+                size: entry.decompressed_size,
+                inner: Inner::Base(self.decompress_entry_stream(&entry)),
+            });
+        }
+
+        // all deltas, from the one that produces the desired object (first) to the oldest at the end of the chain
+        let mut chain = SmallVec::<[crate::data::Entry; 10]>::default();
+        let mut cursor = entry;
+        let (kind, mut base) = loop {
+            let next = match cursor.header {
+                Header::OfsDelta { base_distance } => self.entry(cursor.base_pack_offset(base_distance)),
+                Header::RefDelta { base_id } => {
+                    let mut out = Vec::new();
+                    match resolve(base_id.as_ref(), &mut out) {
+                        Some(ResolvedBase::InPack(entry)) => entry,
+                        Some(ResolvedBase::OutOfPack { kind, end }) => {
+                            out.truncate(end);
+                            chain.push(cursor);
+                            break (kind, Base::Memory(io::Cursor::new(out)));
+                        }
+                        None => return Err(Error::DeltaBaseUnresolved(base_id)),
+                    }
+                }
+                _ => unreachable!("only deltas are followed"),
+            };
+            chain.push(cursor);
+            match next.header.as_kind() {
+                Some(kind) => {
+                    let mut file = tempfile::tempfile()?;
+                    io::copy(&mut self.decompress_entry_stream(&next), &mut file)?;
+                    break (kind, Base::File(file));
+                }
+                None => cursor = next,
+            }
+        };
+
+        let last = chain.remove(0);
+        for delta in chain.iter().rev() {
+            let mut intermediate = ApplyDelta::new(self.decompress_delta(delta)?, base)?;
+            let mut file = tempfile::tempfile()?;
+            let written = io::copy(&mut intermediate, &mut file)?;
+            if written != intermediate.result_size {
+                return Err(invalid_data("delta produced a result of unexpected size").into());
+            }
+            base = Base::File(file);
+        }
+        let last = ApplyDelta::new(self.decompress_delta(&last)?, base)?;
+        Ok(Stream {
+            kind,
+            size: last.result_size,
+            inner: Inner::Delta(last),
+        })
+    }
+
+    fn decompress_delta(&self, entry: &crate::data::Entry) -> Result<Vec<u8>, Error> {
+        let offset: usize = entry.data_offset.try_into().expect("offset representable by machine");
+        let mut out = vec![
+            0;
+            entry
+                .decompressed_size
+                .try_into()
+                .expect("a single delta size small enough to fit a usize")
+        ];
+        zlib::Inflate::default().once(&self.data[offset..], &mut out)?;
+        Ok(out)
+    }
+}
+
+enum Base {
+    File(fs::File),
+    Memory(io::Cursor<Vec<u8>>),
+}
+
+impl Base {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Base::File(f) => f.read(buf),
+            Base::Memory(c) => c.read(buf),
+        }
+    }
+
+    fn seek(&mut self, ofs: u64) -> io::Result<u64> {
+        match self {
+            Base::File(f) => f.seek(SeekFrom::Start(ofs)),
+            Base::Memory(c) => c.seek(SeekFrom::Start(ofs)),
+        }
+    }
+}
+
+enum Op {
+    None,
+    Copy { remaining: u64 },
+    Insert { start: usize, end: usize },
+}
+
+/// Apply delta instructions while reading, copying from a seekable base.
+struct ApplyDelta {
+    instructions: Vec<u8>,
+    /// The position of the next instruction to decode.
+    pos: usize,
+    base: Base,
+    base_size: u64,
+    result_size: u64,
+    /// The amount of bytes produced so far, which must match `result_size` once all instructions are applied.
+    produced: u64,
+    op: Op,
+}
+
+impl ApplyDelta {
+    fn new(instructions: Vec<u8>, base: Base) -> io::Result<Self> {
+        let (base_size, base_size_len) = delta::decode_header_size(&instructions);
+        let (result_size, result_size_len) = delta::decode_header_size(&instructions[base_size_len..]);
+        Ok(ApplyDelta {
+            pos: base_size_len + result_size_len,
+            instructions,
+            base,
+            base_size,
+            result_size,
+            produced: 0,
+            op: Op::None,
+        })
+    }
+
+    fn next_byte(&mut self) -> io::Result<u8> {
+        let byte = self
+            .instructions
+            .get(self.pos)
+            .copied()
+            .ok_or_else(|| invalid_data("delta instructions ended unexpectedly"))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    /// Decode the next instruction, or return `false` if there is none.
+    /// Equivalent to the instruction decoding in [`delta::apply()`].
+    fn next_op(&mut self) -> io::Result<bool> {
+        let cmd = match self.instructions.get(self.pos) {
+            Some(cmd) => *cmd,
+            None => return Ok(false),
+        };
+        self.pos += 1;
+        if cmd & 0b1000_0000 != 0 {
+            let (mut ofs, mut size) = (0u64, 0u64);
+            for (bit, shift) in [(0b0000_0001, 0), (0b0000_0010, 8), (0b0000_0100, 16), (0b0000_1000, 24)].iter() {
+                if cmd & bit != 0 {
+                    ofs |= (self.next_byte()? as u64) << shift;
+                }
+            }
+            for (bit, shift) in [(0b0001_0000, 0), (0b0010_0000, 8), (0b0100_0000, 16)].iter() {
+                if cmd & bit != 0 {
+                    size |= (self.next_byte()? as u64) << shift;
+                }
+            }
+            if size == 0 {
+                size = 0x10000;
+            }
+            if ofs + size > self.base_size {
+                return Err(invalid_data("delta copies data beyond the end of its base object"));
+            }
+            self.base.seek(ofs)?;
+            self.op = Op::Copy { remaining: size };
+        } else if cmd == 0 {
+            return Err(invalid_data("encountered unsupported delta command code: 0"));
+        } else {
+            let end = self.pos + cmd as usize;
+            if end > self.instructions.len() {
+                return Err(invalid_data("delta inserts more data than it contains"));
+            }
+            self.op = Op::Insert { start: self.pos, end };
+            self.pos = end;
+        }
+        Ok(true)
+    }
+}
+
+impl io::Read for ApplyDelta {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let read = loop {
+            match &mut self.op {
+                Op::Copy { remaining } if *remaining != 0 => {
+                    let max = (*remaining).min(buf.len() as u64) as usize;
+                    let read = self.base.read(&mut buf[..max])?;
+                    if read == 0 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "base object ended before delta copy was complete",
+                        ));
+                    }
+                    *remaining -= read as u64;
+                    break read;
+                }
+                Op::Insert { start, end } if start != end => {
+                    let len = (*end - *start).min(buf.len());
+                    buf[..len].copy_from_slice(&self.instructions[*start..*start + len]);
+                    *start += len;
+                    break len;
+                }
+                _ => {
+                    if !self.next_op()? {
+                        if self.produced != self.result_size {
+                            return Err(invalid_data("delta produced less data than its declared result size"));
+                        }
+                        return Ok(0);
+                    }
+                }
+            }
+        };
+        self.produced += read as u64;
+        if self.produced > self.result_size {
+            return Err(invalid_data("delta produced more data than its declared result size"));
+        }
+        Ok(read)
+    }
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    /// A delta without base inserting `abc`, declaring a result of `result_size` bytes.
+    fn insert_abc(result_size: u8) -> ApplyDelta {
+        let instructions = vec![0, result_size, 3, b'a', b'b', b'c'];
+        ApplyDelta::new(instructions, Base::Memory(io::Cursor::new(Vec::new()))).expect("valid header")
+    }
+
+    #[test]
+    fn results_of_the_declared_size_are_produced() {
+        let mut out = Vec::new();
+        insert_abc(3).read_to_end(&mut out).expect("sizes match");
+        assert_eq!(out, b"abc");
+    }
+
+    #[test]
+    fn results_smaller_than_declared_are_invalid() {
+        let err = insert_abc(4).read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn results_larger_than_declared_are_invalid() {
+        let err = insert_abc(2).read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
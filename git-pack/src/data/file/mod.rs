@@ -1,5 +1,9 @@
 ///
 pub mod decode_entry;
+///
+pub mod decode_header;
+///
+pub mod decode_stream;
 mod init;
 ///
 pub mod verify;
@@ -17,7 +17,7 @@ pub struct Entry {
 }
 
 mod file;
-pub use file::{decode_entry, verify, ResolvedBase};
+pub use file::{decode_entry, decode_header, decode_stream, verify, ResolvedBase};
 ///
 pub mod header;
 
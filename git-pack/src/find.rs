@@ -49,6 +49,16 @@ pub mod existing_iter {
     }
 }
 
+/// The kind and size of an object as returned by [`Find::try_header()`][crate::Find::try_header()].
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Header {
+    /// The kind of the object.
+    pub kind: git_object::Kind,
+    /// The size of the object's data in bytes, once decompressed and with all deltas applied.
+    pub size: u64,
+}
+
 /// An Entry in a pack providing access to its data.
 ///
 /// Its commonly retrieved by reading from a pack index file followed by a read from a pack data file.
@@ -42,6 +42,18 @@ pub trait Find {
         pack_cache: &mut impl crate::cache::DecodeEntry,
     ) -> Result<Option<(git_object::Data<'a>, Option<crate::bundle::Location>)>, Self::Error>;
 
+    /// Find the kind and size of the object matching `id` without decoding its data, or `None` if it doesn't exist.
+    ///
+    /// The default implementation decodes the entire object, which is why implementors should provide a more efficient
+    /// implementation if they can.
+    fn try_header(&self, id: impl AsRef<git_hash::oid>) -> Result<Option<find::Header>, Self::Error> {
+        let mut buf = Vec::new();
+        Ok(self.try_find(id, &mut buf)?.map(|(obj, _location)| find::Header {
+            kind: obj.kind,
+            size: obj.data.len() as u64,
+        }))
+    }
+
     /// Find the packs location where an object with `id` can be found in the database, or `None` if there is no pack
     /// holding the object.
     ///
@@ -180,6 +192,10 @@ mod find_impls {
             (*self).try_find_cached(id, buffer, pack_cache)
         }
 
+        fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<find::Header>, Self::Error> {
+            (*self).try_header(id)
+        }
+
         fn location_by_oid(&self, id: impl AsRef<oid>, buf: &mut Vec<u8>) -> Option<crate::bundle::Location> {
             (*self).location_by_oid(id, buf)
         }
@@ -212,6 +228,10 @@ mod find_impls {
             self.deref().try_find_cached(id, buffer, pack_cache)
         }
 
+        fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<find::Header>, Self::Error> {
+            self.deref().try_header(id)
+        }
+
         fn location_by_oid(&self, id: impl AsRef<oid>, buf: &mut Vec<u8>) -> Option<Location> {
             self.deref().location_by_oid(id, buf)
         }
@@ -244,6 +264,10 @@ mod find_impls {
             self.deref().try_find_cached(id, buffer, pack_cache)
         }
 
+        fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<find::Header>, Self::Error> {
+            self.deref().try_header(id)
+        }
+
         fn location_by_oid(&self, id: impl AsRef<oid>, buf: &mut Vec<u8>) -> Option<Location> {
             self.deref().location_by_oid(id, buf)
         }
@@ -276,6 +300,10 @@ mod find_impls {
             self.deref().try_find_cached(id, buffer, pack_cache)
         }
 
+        fn try_header(&self, id: impl AsRef<oid>) -> Result<Option<find::Header>, Self::Error> {
+            self.deref().try_header(id)
+        }
+
         fn location_by_oid(&self, id: impl AsRef<oid>, buf: &mut Vec<u8>) -> Option<Location> {
             self.deref().location_by_oid(id, buf)
         }
@@ -38,6 +38,63 @@ mod locate {
         }
     }
 
+    mod find_header {
+        use git_odb::pack;
+
+        use crate::{fixture_path, pack::PACKS_AND_INDICES};
+
+        #[test]
+        fn all_objects_match_their_decoded_counterpart() -> Result<(), Box<dyn std::error::Error>> {
+            for (index_path, _data_path) in PACKS_AND_INDICES {
+                let bundle = pack::Bundle::at(fixture_path(index_path))?;
+
+                let mut buf = Vec::new();
+                for entry in bundle.index.iter() {
+                    let (obj, _location) = bundle
+                        .find(entry.oid, &mut buf, &mut pack::cache::Never)?
+                        .expect("id present");
+                    let header = bundle.find_header(entry.oid)?.expect("id present");
+                    assert_eq!(header.kind, obj.kind, "{}", entry.oid);
+                    assert_eq!(header.object_size, obj.data.len() as u64, "{}", entry.oid);
+                }
+                assert!(bundle.find_header(git_hash::ObjectId::null_sha1())?.is_none());
+            }
+            Ok(())
+        }
+    }
+
+    mod find_stream {
+        use std::io::Read;
+
+        use git_odb::pack;
+
+        use crate::{fixture_path, pack::PACKS_AND_INDICES};
+
+        #[test]
+        fn all_objects_match_their_decoded_counterpart() -> Result<(), Box<dyn std::error::Error>> {
+            for (index_path, _data_path) in PACKS_AND_INDICES {
+                let bundle = pack::Bundle::at(fixture_path(index_path))?;
+
+                let mut buf = Vec::new();
+                let mut streamed = Vec::new();
+                for entry in bundle.index.iter() {
+                    let (obj, _location) = bundle
+                        .find(entry.oid, &mut buf, &mut pack::cache::Never)?
+                        .expect("id present");
+                    let mut stream = bundle.find_stream(entry.oid)?.expect("id present");
+                    assert_eq!(stream.kind, obj.kind);
+                    assert_eq!(stream.size, obj.data.len() as u64);
+
+                    streamed.clear();
+                    stream.read_to_end(&mut streamed)?;
+                    assert_eq!(streamed, obj.data, "{}", entry.oid);
+                }
+                assert!(bundle.find_stream(git_hash::ObjectId::null_sha1())?.is_none());
+            }
+            Ok(())
+        }
+    }
+
     #[test]
     fn blob() -> Result<(), Box<dyn std::error::Error>> {
         let mut out = Vec::new();
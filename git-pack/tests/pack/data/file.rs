@@ -112,6 +112,121 @@ mod decode_entry {
     }
 }
 
+mod decode_header {
+    use git_pack::data::decode_header::{Outcome, ResolvedBase};
+
+    use crate::pack::{data::file::pack_at, SMALL_PACK};
+
+    fn decode_header_at_offset(offset: u64) -> Outcome {
+        fn resolve_with_panic(_oid: &git_hash::oid) -> Option<ResolvedBase> {
+            panic!("should not want to resolve an id here")
+        }
+
+        let p = pack_at(SMALL_PACK);
+        p.decode_header(p.entry(offset), resolve_with_panic)
+            .expect("valid offset provides valid entry")
+    }
+
+    #[test]
+    fn commit() {
+        assert_eq!(
+            decode_header_at_offset(1968),
+            Outcome {
+                kind: git_object::Kind::Commit,
+                object_size: 187,
+                num_deltas: 0
+            }
+        );
+    }
+
+    #[test]
+    fn blob_ofs_delta_two_links() {
+        assert_eq!(
+            decode_header_at_offset(3033),
+            Outcome {
+                kind: git_object::Kind::Blob,
+                object_size: 173,
+                num_deltas: 2
+            }
+        );
+    }
+
+    #[test]
+    fn blob_ofs_delta_single_link() {
+        assert_eq!(
+            decode_header_at_offset(3569),
+            Outcome {
+                kind: git_object::Kind::Blob,
+                object_size: 1163,
+                num_deltas: 1
+            }
+        );
+    }
+}
+
+mod decode_entry_stream {
+    use std::io::Read;
+
+    use bstr::ByteSlice;
+    use git_pack::{cache, data::ResolvedBase};
+
+    use crate::pack::{data::file::pack_at, SMALL_PACK};
+
+    fn resolve_with_panic(_oid: &git_hash::oid, _out: &mut Vec<u8>) -> Option<ResolvedBase> {
+        panic!("should not want to resolve an id here")
+    }
+
+    /// Read the stream in small chunks to exercise instructions spanning multiple reads.
+    fn stream_entry_at_offset(offset: u64) -> (git_object::Kind, u64, Vec<u8>) {
+        let p = pack_at(SMALL_PACK);
+        let mut stream = p
+            .decode_entry_stream(p.entry(offset), resolve_with_panic)
+            .expect("valid offset provides valid entry");
+        let mut out = Vec::new();
+        let mut chunk = [0u8; 7];
+        loop {
+            let read = stream.read(&mut chunk).expect("reading works");
+            if read == 0 {
+                break;
+            }
+            out.extend_from_slice(&chunk[..read]);
+        }
+        (stream.kind, stream.size, out)
+    }
+
+    fn decode_entry_at_offset(offset: u64) -> Vec<u8> {
+        let p = pack_at(SMALL_PACK);
+        let mut buf = Vec::new();
+        p.decode_entry(p.entry(offset), &mut buf, resolve_with_panic, &mut cache::Never)
+            .expect("valid offset provides valid entry");
+        buf
+    }
+
+    #[test]
+    fn commit() {
+        let (kind, size, buf) = stream_entry_at_offset(1968);
+        assert_eq!(kind, git_object::Kind::Commit);
+        assert_eq!(size, 187);
+        assert_eq!(buf.as_bstr(), decode_entry_at_offset(1968).as_bstr());
+    }
+
+    #[test]
+    fn blob_ofs_delta_two_links() {
+        let (kind, size, buf) = stream_entry_at_offset(3033);
+        assert_eq!(kind, git_object::Kind::Blob);
+        assert_eq!(size, 173, "the size is the actual object size");
+        assert_eq!(buf.as_bstr(), decode_entry_at_offset(3033).as_bstr());
+    }
+
+    #[test]
+    fn blob_ofs_delta_single_link() {
+        let (kind, size, buf) = stream_entry_at_offset(3569);
+        assert_eq!(kind, git_object::Kind::Blob);
+        assert_eq!(size, 1163, "the size is the actual object size");
+        assert_eq!(buf.as_bstr(), decode_entry_at_offset(3569).as_bstr());
+    }
+}
+
 mod decompress_entry {
     use git_object::bstr::ByteSlice;
 
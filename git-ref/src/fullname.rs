@@ -72,6 +72,14 @@ impl FullName {
         self.0.to_path().expect("UTF-8 conversion always succeeds").into()
     }
 
+    /// Return the shortest name that still refers to this reference when expanded with git's lookup rules, like `main`
+    /// for `refs/heads/main` or `origin` for `refs/remotes/origin/HEAD`, for display.
+    ///
+    /// See [`FullNameRef::shorten()`] for details.
+    pub fn shorten(&self) -> &BStr {
+        self.to_ref().shorten()
+    }
+
     /// Dissolve this instance and return the buffer.
     pub fn into_inner(self) -> BString {
         self.0
@@ -106,4 +114,28 @@ impl<'a> FullNameRef<'a> {
     pub fn to_owned(&self) -> FullName {
         FullName(self.0.to_owned())
     }
+
+    /// Return the shortest name that still refers to this reference when expanded with git's lookup rules, like `main`
+    /// for `refs/heads/main`, `v1.0` for `refs/tags/v1.0` or `origin` for `refs/remotes/origin/HEAD`, for display.
+    ///
+    /// The most specific rule is tried first, and names matching no rule like `HEAD` are returned unchanged.
+    ///
+    /// ### Note
+    ///
+    /// Existing references aren't checked, so the shortened name may be ambiguous if, for example, a tag and a branch
+    /// share the same name.
+    pub fn shorten(&self) -> &'a BStr {
+        let name = self.0;
+        if let Some(remote) = name
+            .strip_prefix(b"refs/remotes/")
+            .and_then(|name| name.strip_suffix(b"/HEAD"))
+        {
+            return remote.as_bstr();
+        }
+        ["refs/remotes/", "refs/heads/", "refs/tags/", "refs/"]
+            .iter()
+            .find_map(|prefix| name.strip_prefix(prefix.as_bytes()))
+            .unwrap_or(name)
+            .as_bstr()
+    }
 }
@@ -27,7 +27,7 @@ use git_object::bstr::{BStr, BString};
 
 #[path = "store/mod.rs"]
 mod store_impl;
-pub use store_impl::{file, packed};
+pub use store_impl::{file, packed, reftable};
 
 mod fullname;
 ///
@@ -50,6 +50,8 @@ pub mod log;
 ///
 pub mod peel;
 
+pub mod pseudo;
+
 ///
 pub mod store {
     /// The way a file store handles the reflog
@@ -71,6 +73,7 @@ pub mod store {
 
     pub(crate) enum State {
         Loose { store: file::Store },
+        Reftable { store: reftable::Store },
     }
 
     #[path = "general/mod.rs"]
@@ -80,9 +83,10 @@ pub mod store {
     #[path = "general/handle/mod.rs"]
     mod handle;
 
-    pub use handle::find;
+    pub use general::init;
+    pub use handle::{find, iter, reflog, transaction};
 
-    use crate::file;
+    use crate::{file, reftable};
 }
 
 /// The git reference store.
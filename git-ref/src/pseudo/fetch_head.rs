@@ -0,0 +1,62 @@
+use git_hash::ObjectId;
+use git_object::bstr::{BString, ByteSlice};
+
+use crate::pseudo::Error;
+
+/// The marker for entries in `FETCH_HEAD` which are not supposed to be merged by `git pull`.
+const NOT_FOR_MERGE: &[u8] = b"not-for-merge";
+
+/// A line in `FETCH_HEAD`, describing an object obtained from a remote.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    /// The object that was fetched.
+    pub id: ObjectId,
+    /// If true, this entry is not supposed to be merged into the current branch, as it wasn't the first reference fetched
+    /// or is only a remote-tracking reference.
+    pub not_for_merge: bool,
+    /// A human-readable description of what was fetched from where, like `branch 'main' of https://example.com/repo`.
+    pub description: BString,
+}
+
+impl Entry {
+    /// Serialize this instance to `out` in the format used in `FETCH_HEAD`.
+    pub fn write_to(&self, mut out: impl std::io::Write) -> std::io::Result<()> {
+        write!(out, "{}\t", self.id)?;
+        if self.not_for_merge {
+            out.write_all(NOT_FOR_MERGE)?;
+        }
+        out.write_all(b"\t")?;
+        out.write_all(&self.description)?;
+        out.write_all(b"\n")
+    }
+}
+
+/// Parse all entries of a `FETCH_HEAD` file from `data`, failing on the first line that can't be parsed.
+pub fn decode(data: &[u8]) -> Result<Vec<Entry>, Error> {
+    data.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(line_number, line)| {
+            parse_line(line).ok_or_else(|| Error::Decode {
+                line_number: line_number + 1,
+                line: line.into(),
+            })
+        })
+        .collect()
+}
+
+fn parse_line(line: &[u8]) -> Option<Entry> {
+    let mut tokens = line.splitn_str(3, b"\t");
+    let id = ObjectId::from_hex(tokens.next()?).ok()?;
+    let not_for_merge = match tokens.next()? {
+        b"" => false,
+        NOT_FOR_MERGE => true,
+        _ => return None,
+    };
+    Some(Entry {
+        id,
+        not_for_merge,
+        description: tokens.next().unwrap_or_default().into(),
+    })
+}
@@ -0,0 +1,26 @@
+use git_hash::ObjectId;
+use git_object::bstr::ByteSlice;
+
+use crate::pseudo::Error;
+
+/// Parse the object ids of all commits listed in a `MERGE_HEAD` file from `data`.
+pub fn decode(data: &[u8]) -> Result<Vec<ObjectId>, Error> {
+    data.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(line_number, line)| {
+            ObjectId::from_hex(line).map_err(|_| Error::Decode {
+                line_number: line_number + 1,
+                line: line.into(),
+            })
+        })
+        .collect()
+}
+
+/// Serialize `ids` to `out` in the format used in `MERGE_HEAD`, one object id per line.
+pub fn write_to<'a>(ids: impl IntoIterator<Item = &'a ObjectId>, mut out: impl std::io::Write) -> std::io::Result<()> {
+    for id in ids {
+        writeln!(out, "{}", id)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! Pseudo-references like `FETCH_HEAD` or `MERGE_HEAD` which live next to `HEAD` in the git directory.
+//!
+//! Unlike references, some of them can contain more than one object id along with additional information, which is why they
+//! have their own parsers and writers. Others, like `ORIG_HEAD`, `CHERRY_PICK_HEAD` or `REVERT_HEAD`, contain a single object
+//! id just like peeled references do.
+//! Pseudo-references are never stored in `packed-refs` or ref-tables, nor are they affected by namespaces.
+use git_object::bstr::BStr;
+
+/// The name of the pseudo-reference written by `git fetch`.
+pub const FETCH_HEAD: &str = "FETCH_HEAD";
+/// The name of the pseudo-reference listing the commits to merge while a merge is in progress.
+pub const MERGE_HEAD: &str = "MERGE_HEAD";
+/// The name of the pseudo-reference keeping the previous value of `HEAD` before drastic changes like resets or rebases.
+pub const ORIG_HEAD: &str = "ORIG_HEAD";
+/// The name of the pseudo-reference pointing to the commit being cherry-picked.
+pub const CHERRY_PICK_HEAD: &str = "CHERRY_PICK_HEAD";
+/// The name of the pseudo-reference pointing to the commit being reverted.
+pub const REVERT_HEAD: &str = "REVERT_HEAD";
+
+/// Returns true if `name` looks like a pseudo-reference, that is it consists of uppercase ascii letters and underscores,
+/// and ends with `HEAD` without being `HEAD` itself.
+pub fn is_pseudo_ref_name(name: &BStr) -> bool {
+    name.len() > 4 && name.ends_with(b"HEAD") && name.iter().all(|b| b.is_ascii_uppercase() || *b == b'_')
+}
+
+///
+pub mod fetch_head;
+///
+pub mod merge_head;
+
+mod error {
+    use std::path::PathBuf;
+
+    use git_object::bstr::BString;
+    use quick_error::quick_error;
+
+    quick_error! {
+        /// The error returned when reading or writing pseudo-references.
+        #[derive(Debug)]
+        #[allow(missing_docs)]
+        pub enum Error {
+            InvalidName { name: BString } {
+                display("'{}' is not a valid name for a pseudo-reference", name)
+            }
+            Decode { line_number: usize, line: BString } {
+                display("Line {} could not be parsed: {:?}", line_number, line)
+            }
+            Io { err: std::io::Error, path: PathBuf } {
+                display("Could not read or write pseudo-reference at '{}'", path.display())
+                source(err)
+            }
+            LockAcquire { err: git_lock::acquire::Error, path: PathBuf } {
+                display("The lock for the pseudo-reference at '{}' could not be obtained", path.display())
+                source(err)
+            }
+        }
+    }
+}
+pub use error::Error;
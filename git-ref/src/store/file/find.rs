@@ -1,4 +1,5 @@
 use std::{
+    borrow::Cow,
     convert::TryInto,
     io::{self, Read},
     path::{Path, PathBuf},
@@ -72,37 +73,79 @@ impl file::Store {
         self.find_one_with_verified_input(path.to_partial_path().as_ref(), packed)
     }
 
+    /// Find all references `partial` could refer to in order of precedence, following the same lookup rules as
+    /// [`try_find()`][file::Store::try_find()] which would return the first of them.
+    ///
+    /// If more than one reference is returned, `partial` is ambiguous.
+    pub fn try_find_all<'a, Name, E>(&self, partial: Name) -> Result<Vec<Reference>, Error>
+    where
+        Name: TryInto<PartialNameRef<'a>, Error = E>,
+        Error: From<E>,
+    {
+        let path = partial.try_into()?;
+        let packed = self.assure_packed_refs_uptodate()?;
+        let mut found = Vec::new();
+        self.find_with_verified_input(path.to_partial_path().as_ref(), packed.as_deref(), |r| {
+            found.push(r);
+            true
+        })?;
+        Ok(found)
+    }
+
     pub(crate) fn find_one_with_verified_input(
         &self,
         relative_path: &Path,
         packed: Option<&packed::Buffer>,
     ) -> Result<Option<Reference>, Error> {
+        let mut found = None;
+        self.find_with_verified_input(relative_path, packed, |r| {
+            found = Some(r);
+            false
+        })?;
+        Ok(found)
+    }
+
+    /// Call `on_match` with each reference `relative_path` refers to in order of precedence, until it returns false.
+    fn find_with_verified_input(
+        &self,
+        relative_path: &Path,
+        packed: Option<&packed::Buffer>,
+        mut on_match: impl FnMut(Reference) -> bool,
+    ) -> Result<(), Error> {
+        let mut lookup =
+            |inbetween: &str, relative_path: &Path, packed: Option<&packed::Buffer>, transform: Transform| {
+                self.find_inner(inbetween, relative_path, packed, transform)
+                    .map(|r| r.map_or(true, &mut on_match))
+            };
         let is_all_uppercase = relative_path
             .to_string_lossy()
             .as_ref()
             .chars()
             .all(|c| c.is_ascii_uppercase());
-        if relative_path.components().count() == 1 && is_all_uppercase {
-            if let Some(r) = self.find_inner("", relative_path, None, Transform::None)? {
-                return Ok(Some(r));
-            }
+        if (relative_path.starts_with("main-worktree") || relative_path.starts_with("worktrees"))
+            && !lookup("", relative_path, None, Transform::None)?
+        {
+            return Ok(());
+        }
+        if relative_path.components().count() == 1
+            && is_all_uppercase
+            && !lookup("", relative_path, None, Transform::None)?
+        {
+            return Ok(());
         }
 
         for inbetween in &["", "tags", "heads", "remotes"] {
-            match self.find_inner(*inbetween, relative_path, packed, Transform::EnforceRefsPrefix) {
-                Ok(Some(r)) => return Ok(Some(r)),
-                Ok(None) => {
-                    continue;
-                }
-                Err(err) => return Err(err),
+            if !lookup(*inbetween, relative_path, packed, Transform::EnforceRefsPrefix)? {
+                return Ok(());
             }
         }
-        self.find_inner(
+        lookup(
             "remotes",
             &relative_path.join("HEAD"),
             None,
             Transform::EnforceRefsPrefix,
         )
+        .map(|_| ())
     }
 
     fn find_inner(
@@ -165,12 +208,59 @@ impl file::Store {
 impl file::Store {
     /// Implements the logic required to transform a fully qualified refname into a filesystem path
     pub(crate) fn reference_path(&self, name: &Path) -> PathBuf {
+        let (base, relative_name) = self.to_base_dir_and_relative_name(name);
         match &self.namespace {
-            None => self.base.join(name),
-            Some(namespace) => self.base.join(namespace.to_path()).join(name),
+            None => base.join(relative_name),
+            Some(namespace) => base.join(namespace.to_path()).join(relative_name),
+        }
+    }
+
+    /// Return the directory in which the reference with the given full `name` is stored along with its name relative to it.
+    ///
+    /// Without a common directory, that's always our `base` and `name` itself. Otherwise per-worktree references are
+    /// kept in `base` while all others are shared in the common directory. Names prefixed with `main-worktree/` or
+    /// `worktrees/<id>/` refer to the per-worktree references of the main worktree or the linked worktree `<id>` respectively.
+    pub(in crate::store_impl::file) fn to_base_dir_and_relative_name<'a>(
+        &self,
+        name: &'a Path,
+    ) -> (Cow<'_, Path>, &'a Path) {
+        let common_dir = self.common_dir_resolved();
+        if let Ok(relative_name) = name.strip_prefix("main-worktree") {
+            return (common_dir.into(), relative_name);
+        }
+        if let Ok(worktree_and_name) = name.strip_prefix("worktrees") {
+            let mut components = worktree_and_name.components();
+            if let Some(id) = components.next() {
+                let relative_name = components.as_path();
+                if !relative_name.as_os_str().is_empty() {
+                    return (common_dir.join("worktrees").join(id).into(), relative_name);
+                }
+            }
+        }
+        match &self.common_dir {
+            Some(common_dir) if !is_per_worktree_ref_name(name) => (common_dir.into(), name),
+            _ => (self.base.as_path().into(), name),
         }
     }
+}
+
+/// Returns true if the reference with the full `name` is private to each worktree, like `HEAD` or `refs/bisect/bad`.
+pub(in crate::store_impl::file) fn is_per_worktree_ref_name(name: &Path) -> bool {
+    let mut components = name.components();
+    let is_pseudo_ref = components.next().map_or(false, |first| {
+        components.next().is_none()
+            && first
+                .as_os_str()
+                .to_str()
+                .map_or(false, |name| name.bytes().all(|b| b.is_ascii_uppercase() || b == b'_'))
+    });
+    is_pseudo_ref
+        || name.starts_with("refs/bisect")
+        || name.starts_with("refs/worktree")
+        || name.starts_with("refs/rewritten")
+}
 
+impl file::Store {
     /// Read the file contents with a verified full reference path and return it in the given vector if possible.
     pub(crate) fn ref_contents(&self, relative_path: &Path) -> std::io::Result<Option<Vec<u8>>> {
         let mut buf = Vec::new();
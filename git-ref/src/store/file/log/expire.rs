@@ -0,0 +1,459 @@
+use std::{collections::HashSet, convert::TryInto, io::Read};
+
+use git_hash::ObjectId;
+use git_object::bstr::{BStr, ByteSlice};
+
+use crate::{
+    log::Line,
+    peel,
+    store_impl::{file, file::log},
+    FullNameRef,
+};
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+/// The amount of days after which all reflog entries expire if `gc.reflogExpire` is unset.
+pub const DEFAULT_EXPIRE_DAYS: u32 = 90;
+/// The amount of days after which unreachable reflog entries expire if `gc.reflogExpireUnreachable` is unset.
+pub const DEFAULT_EXPIRE_UNREACHABLE_DAYS: u32 = 30;
+
+/// Configure which entries are removed by [`file::Store::reflog_expire()`].
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub struct Options {
+    /// Entries older than this time in seconds since unix epoch are removed, or none at all if `None`.
+    ///
+    /// This is the cut-off configured by `gc.reflogExpire`, see [`parse_cutoff()`].
+    pub expire: Option<u32>,
+    /// Entries older than this time in seconds since unix epoch are removed if their previous or new object isn't reachable
+    /// from the current value of the reference, or none at all if `None`.
+    ///
+    /// This is the cut-off configured by `gc.reflogExpireUnreachable`, see [`parse_cutoff()`].
+    pub expire_unreachable: Option<u32>,
+    /// If true, remove entries whose previous or new object is missing, isn't a commit or lacks its tree,
+    /// similar to `git reflog expire --stale-fix`.
+    pub stale_fix: bool,
+    /// If true, the previous object of an entry following removed entries is set to the new object of the entry now
+    /// preceding it, similar to `git reflog expire --rewrite`.
+    pub rewrite: bool,
+    /// If true, the reflog is not changed and the [outcome][Outcome] merely reports which entries would have been removed.
+    pub dry_run: bool,
+}
+
+impl Options {
+    /// Return options with the cut-offs git uses if neither `gc.reflogExpire` nor `gc.reflogExpireUnreachable` are set,
+    /// relative to `now` in seconds since unix epoch.
+    pub fn with_default_cutoffs(now: u32) -> Self {
+        Options {
+            expire: Some(now.saturating_sub(DEFAULT_EXPIRE_DAYS * SECONDS_PER_DAY)),
+            expire_unreachable: Some(now.saturating_sub(DEFAULT_EXPIRE_UNREACHABLE_DAYS * SECONDS_PER_DAY)),
+            stale_fix: false,
+            rewrite: false,
+            dry_run: false,
+        }
+    }
+}
+
+/// The outcome of [`file::Store::reflog_expire()`] and [`file::Store::reflog_delete()`].
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Outcome {
+    /// The removed entries, from oldest to most recent.
+    pub removed: Vec<Line>,
+    /// The amount of entries remaining in the reflog.
+    pub kept: usize,
+}
+
+/// Parse `value` of `gc.reflogExpire` or `gc.reflogExpireUnreachable` into a cut-off in seconds since unix epoch relative
+/// to `now`, for use in [`Options`].
+///
+/// `never` and `false` yield `None` to never expire entries, `now` and `all` expire all of them, relative dates like
+/// `90.days.ago` or `2 weeks` count back from `now`, and absolute dates like `2023-01-01` or `2023-01-01 12:30:00` are
+/// interpreted as UTC. Other formats git's approximate date parser understands, like `yesterday` or `last friday`, are
+/// rejected.
+///
+/// Values of `gc.<pattern>.reflogExpire` and `gc.<pattern>.reflogExpireUnreachable` aren't selected here: callers pick the value
+/// of the first section whose pattern matches the name of the reference to expire, and fall back to `gc.reflogExpire` or
+/// `gc.reflogExpireUnreachable` otherwise, before passing it to this function.
+pub fn parse_cutoff(value: &BStr, now: u32) -> Result<Option<u32>, Error> {
+    let invalid = || Error::InvalidCutoff {
+        value: value.to_owned(),
+    };
+    let value = value.trim().to_str().map_err(|_| invalid())?.to_ascii_lowercase();
+    Ok(match value.as_str() {
+        "never" | "false" => None,
+        "now" | "all" => Some(u32::MAX),
+        absolute if absolute.starts_with(|c: char| c.is_ascii_digit()) && absolute.contains('-') => {
+            Some(parse_absolute_date(absolute).ok_or_else(invalid)?)
+        }
+        relative => {
+            let mut tokens = relative.split(&['.', ' '][..]).filter(|t| !t.is_empty());
+            let amount: u32 = tokens.next().and_then(|t| t.parse().ok()).ok_or_else(invalid)?;
+            let unit = match tokens.next().map(|unit| unit.strip_suffix('s').unwrap_or(unit)) {
+                Some("second") => 1,
+                Some("minute") => 60,
+                Some("hour") => 60 * 60,
+                Some("day") => SECONDS_PER_DAY,
+                Some("week") => 7 * SECONDS_PER_DAY,
+                Some("month") => 30 * SECONDS_PER_DAY,
+                Some("year") => 365 * SECONDS_PER_DAY,
+                _ => return Err(invalid()),
+            };
+            match tokens.next() {
+                None | Some("ago") if tokens.next().is_none() => {}
+                _ => return Err(invalid()),
+            }
+            Some(now.saturating_sub(amount.saturating_mul(unit)))
+        }
+    })
+}
+
+/// Parse `YYYY-MM-DD`, optionally followed by `HH:MM` or `HH:MM:SS` separated by a space or `T`, as UTC into seconds since unix epoch.
+fn parse_absolute_date(value: &str) -> Option<u32> {
+    let (date, time) = match value.find(&[' ', 't'][..]) {
+        Some(pos) => (&value[..pos], Some(value[pos + 1..].trim_start())),
+        None => (value, None),
+    };
+    let mut date = date.split('-').map(|n| n.parse::<u32>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    if date.next().is_some() || !(1970..=2105).contains(&year) || !(1..=12).contains(&month) {
+        return None;
+    }
+    let is_leap_year = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_in_month = [
+        31,
+        if is_leap_year { 29 } else { 28 },
+        31,
+        30,
+        31,
+        30,
+        31,
+        31,
+        30,
+        31,
+        30,
+        31,
+    ];
+    if !(1..=days_in_month[month as usize - 1]).contains(&day) {
+        return None;
+    }
+    let days_before_year: u32 = (1970..year)
+        .map(|year| {
+            if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 {
+                366
+            } else {
+                365
+            }
+        })
+        .sum();
+    let days = days_before_year + days_in_month[..month as usize - 1].iter().sum::<u32>() + day - 1;
+
+    let seconds_of_day = match time {
+        None => 0,
+        Some(time) => {
+            let mut time = time.split(':').map(|n| n.parse::<u32>().ok());
+            let (hours, minutes, seconds) = (time.next()??, time.next()??, time.next().unwrap_or(Some(0))?);
+            if time.next().is_some() || hours > 23 || minutes > 59 || seconds > 60 {
+                return None;
+            }
+            hours * 60 * 60 + minutes * 60 + seconds
+        }
+    };
+    (days as u64 * SECONDS_PER_DAY as u64 + seconds_of_day as u64)
+        .try_into()
+        .ok()
+}
+
+impl file::Store {
+    /// Remove entries from the reflog of the reference `name` according to `options`, while holding the lock of the reference
+    /// acquired with `lock_fail_mode` so no entries can be appended in the mean time. The reflog is rewritten atomically.
+    ///
+    /// `find` is used to look up commits to determine if they are reachable from the current value of the reference and if
+    /// [stale entries][Options::stale_fix] should be removed. It receives an object id and a buffer to fill, returning the kind
+    /// of object along with its data or `None` if it doesn't exist.
+    ///
+    /// Return `Ok(None)` if there is no reflog.
+    pub fn reflog_expire<'a, Name, E, FindErr>(
+        &self,
+        name: Name,
+        options: Options,
+        lock_fail_mode: git_lock::acquire::Fail,
+        mut find: impl FnMut(ObjectId, &mut Vec<u8>) -> Result<Option<(git_object::Kind, &[u8])>, FindErr>,
+    ) -> Result<Option<Outcome>, Error>
+    where
+        Name: TryInto<FullNameRef<'a>, Error = E>,
+        crate::name::Error: From<E>,
+        FindErr: std::error::Error + Send + Sync + 'static,
+    {
+        let name: FullNameRef<'_> = name.try_into().map_err(|err| Error::RefnameValidation(err.into()))?;
+        let mut reachable = None;
+        let mut complete = HashSet::new();
+        let mut buf = Vec::new();
+        self.rewrite_reflog(name, lock_fail_mode, options.rewrite, options.dry_run, |_, line| {
+            let time = line.signature.time.time;
+            if options.expire.map_or(false, |cutoff| time < cutoff) {
+                return Ok(true);
+            }
+            let (previous_oid, new_oid) = (line.previous_oid(), line.new_oid());
+            if options.stale_fix {
+                for id in [previous_oid, new_oid] {
+                    if !is_complete_commit(id, &mut complete, &mut buf, &mut find)? {
+                        return Ok(true);
+                    }
+                }
+            }
+            if options.expire_unreachable.map_or(false, |cutoff| time < cutoff) {
+                if reachable.is_none() {
+                    reachable = Some(self.reachable_from_tip(name, &mut buf, &mut find)?);
+                }
+                let reachable = reachable.as_ref().expect("just initialized");
+                return Ok([previous_oid, new_oid]
+                    .iter()
+                    .any(|id| !id.is_null() && !reachable.contains(id)));
+            }
+            Ok(false)
+        })
+    }
+
+    /// Remove the given `entries` from the reflog of the reference `name` while holding the lock of the reference, acquired
+    /// with `lock_fail_mode`. Entries are indexed from most recent to oldest, with `0` being the most recent entry just like in
+    /// `main@{0}`. If `rewrite` is true, the previous object of the entry following a removed one is adjusted just like
+    /// [`Options::rewrite`] does.
+    ///
+    /// Return `Ok(None)` if there is no reflog.
+    pub fn reflog_delete<'a, Name, E>(
+        &self,
+        name: Name,
+        entries: impl IntoIterator<Item = usize>,
+        rewrite: bool,
+        lock_fail_mode: git_lock::acquire::Fail,
+    ) -> Result<Option<Outcome>, Error>
+    where
+        Name: TryInto<FullNameRef<'a>, Error = E>,
+        crate::name::Error: From<E>,
+    {
+        let name: FullNameRef<'_> = name.try_into().map_err(|err| Error::RefnameValidation(err.into()))?;
+        let entries: HashSet<usize> = entries.into_iter().collect();
+        let no_such_entry = |index: usize| Error::NoSuchEntry {
+            index,
+            name: name.as_bstr().to_owned(),
+        };
+        let outcome = self.rewrite_reflog(name, lock_fail_mode, rewrite, false, |(index, total), _| match entries
+            .iter()
+            .find(|entry| **entry >= total)
+        {
+            Some(entry) => Err(no_such_entry(*entry)),
+            None => Ok(entries.contains(&(total - 1 - index))),
+        })?;
+        match (outcome, entries.iter().min()) {
+            (Some(outcome), Some(entry)) if outcome.kept == 0 && outcome.removed.is_empty() => {
+                Err(no_such_entry(*entry))
+            }
+            (outcome, _) => Ok(outcome),
+        }
+    }
+
+    /// Call `should_remove` with the index of each line in the reflog along with the total amount of lines, and rewrite the
+    /// reflog without the lines it returned true for unless `dry_run` is set.
+    fn rewrite_reflog(
+        &self,
+        name: FullNameRef<'_>,
+        lock_fail_mode: git_lock::acquire::Fail,
+        rewrite: bool,
+        dry_run: bool,
+        mut should_remove: impl FnMut((usize, usize), &log::LineRef<'_>) -> Result<bool, Error>,
+    ) -> Result<Option<Outcome>, Error> {
+        let lock = git_lock::Marker::acquire_to_hold_resource(
+            self.reference_path(&name.to_path()),
+            lock_fail_mode,
+            Some(self.to_base_dir_and_relative_name(&name.to_path()).0.into_owned()),
+        )
+        .map_err(|err| Error::LockAcquire {
+            err,
+            full_name: name.as_bstr().to_owned(),
+        })?;
+        let reflog_path = self.reflog_path_namespaced(name);
+        let mut buf = Vec::new();
+        match std::fs::File::open(&reflog_path).and_then(|mut f| f.read_to_end(&mut buf)) {
+            Ok(_) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::Io { err, reflog_path }),
+        }
+        let lines = log::iter::forward(&buf).collect::<Result<Vec<_>, _>>()?;
+
+        let total = lines.len();
+        let mut kept = Vec::with_capacity(total);
+        let mut removed = Vec::new();
+        let mut previous_removed = false;
+        for (index, line) in lines.into_iter().enumerate() {
+            if should_remove((index, total), &line)? {
+                removed.push(line.to_owned());
+                previous_removed = true;
+                continue;
+            }
+            let mut line = line.to_owned();
+            if rewrite && previous_removed {
+                line.previous_oid = kept.last().map_or_else(
+                    || ObjectId::null(line.new_oid.kind()),
+                    |previous: &Line| previous.new_oid,
+                );
+            }
+            previous_removed = false;
+            kept.push(line);
+        }
+
+        if !dry_run && !removed.is_empty() {
+            let mut file =
+                git_lock::File::acquire_to_update_resource(&reflog_path, lock_fail_mode, None).map_err(|err| {
+                    Error::LockAcquire {
+                        err,
+                        full_name: name.as_bstr().to_owned(),
+                    }
+                })?;
+            for line in &kept {
+                line.write_to(&mut file).map_err(|err| Error::Io {
+                    err,
+                    reflog_path: reflog_path.clone(),
+                })?;
+            }
+            file.commit().map_err(|err| Error::Io {
+                err: err.error,
+                reflog_path,
+            })?;
+        }
+        drop(lock);
+        Ok(Some(Outcome {
+            removed,
+            kept: kept.len(),
+        }))
+    }
+
+    /// Return all commits reachable from the peeled value of the reference `name`, which is empty if the reference or its
+    /// object doesn't exist.
+    fn reachable_from_tip<FindErr>(
+        &self,
+        name: FullNameRef<'_>,
+        buf: &mut Vec<u8>,
+        mut find: impl FnMut(ObjectId, &mut Vec<u8>) -> Result<Option<(git_object::Kind, &[u8])>, FindErr>,
+    ) -> Result<HashSet<ObjectId>, Error>
+    where
+        FindErr: std::error::Error + Send + Sync + 'static,
+    {
+        use crate::file::ReferenceExt;
+        let mut reachable = HashSet::new();
+        let tip = match self.try_find(name)? {
+            Some(mut reference) => match reference.peel_to_id_in_place(self, &mut find) {
+                Ok(id) => id,
+                Err(peel::to_id::Error::NotFound { .. }) => return Ok(reachable),
+                Err(err) => return Err(err.into()),
+            },
+            None => return Ok(reachable),
+        };
+        let mut queue = vec![tip];
+        while let Some(id) = queue.pop() {
+            if !reachable.insert(id) {
+                continue;
+            }
+            match find(id, buf).map_err(|err| Error::Find(Box::new(err)))? {
+                Some((git_object::Kind::Commit, data)) => queue.extend(
+                    git_object::CommitRefIter::from_bytes(data)
+                        .filter_map(Result::ok)
+                        .skip(1)
+                        .take_while(|token| matches!(token, git_object::commit::ref_iter::Token::Parent { .. }))
+                        .filter_map(git_object::commit::ref_iter::Token::into_id),
+                ),
+                _ => {
+                    reachable.remove(&id);
+                }
+            }
+        }
+        Ok(reachable)
+    }
+}
+
+/// Return true if `id` is null or a commit which exists along with its tree, caching positive results in `complete`.
+fn is_complete_commit<FindErr>(
+    id: ObjectId,
+    complete: &mut HashSet<ObjectId>,
+    buf: &mut Vec<u8>,
+    mut find: impl FnMut(ObjectId, &mut Vec<u8>) -> Result<Option<(git_object::Kind, &[u8])>, FindErr>,
+) -> Result<bool, Error>
+where
+    FindErr: std::error::Error + Send + Sync + 'static,
+{
+    if id.is_null() || complete.contains(&id) {
+        return Ok(true);
+    }
+    let tree = match find(id, buf).map_err(|err| Error::Find(Box::new(err)))? {
+        Some((git_object::Kind::Commit, data)) => git_object::CommitRefIter::from_bytes(data).tree_id(),
+        _ => return Ok(false),
+    };
+    let is_complete = match tree {
+        Some(tree) => matches!(
+            find(tree, buf).map_err(|err| Error::Find(Box::new(err)))?,
+            Some((git_object::Kind::Tree, _))
+        ),
+        None => false,
+    };
+    if is_complete {
+        complete.insert(id);
+    }
+    Ok(is_complete)
+}
+
+mod error {
+    use std::path::PathBuf;
+
+    use git_object::bstr::BString;
+    use quick_error::quick_error;
+
+    use crate::{
+        peel,
+        store_impl::{file, file::log},
+    };
+
+    quick_error! {
+        /// The error returned by [crate::file::Store::reflog_expire()] and [crate::file::Store::reflog_delete()].
+        #[derive(Debug)]
+        #[allow(missing_docs)]
+        pub enum Error {
+            RefnameValidation(err: crate::name::Error) {
+                display("The reflog name or path is not a valid ref name")
+                from()
+                source(err)
+            }
+            InvalidCutoff { value: BString } {
+                display("'{}' is not a valid expiry date", value)
+            }
+            LockAcquire { err: git_lock::acquire::Error, full_name: BString } {
+                display("The lock for the reference '{}' or its reflog could not be obtained", full_name)
+                source(err)
+            }
+            Io { err: std::io::Error, reflog_path: PathBuf } {
+                display("Could not read or write the reflog at '{}'", reflog_path.display())
+                source(err)
+            }
+            Decode(err: log::iter::decode::Error) {
+                display("A line of the reflog could not be parsed")
+                from()
+                source(err)
+            }
+            FindReference(err: file::find::Error) {
+                display("The reference whose reflog is expired could not be read")
+                from()
+                source(err)
+            }
+            Peel(err: peel::to_id::Error) {
+                display("Could not peel the reference to the commit its reflog entries must be reachable from")
+                from()
+                source(err)
+            }
+            Find(err: Box<dyn std::error::Error + Send + Sync + 'static>) {
+                display("An object needed to determine reachability could not be looked up")
+                source(&**err)
+            }
+            NoSuchEntry { index: usize, name: BString } {
+                display("The reflog of '{}' has no entry with index {}", name, index)
+            }
+        }
+    }
+}
+pub use error::Error;
@@ -2,6 +2,8 @@ use git_object::bstr::BStr;
 
 pub use super::loose::reflog::{create_or_update, Error};
 
+///
+pub mod expire;
 ///
 pub mod iter;
 mod line;
@@ -1,6 +1,7 @@
 use std::{
     ffi::OsString,
     io::Read,
+    iter::Peekable,
     path::{Path, PathBuf},
 };
 
@@ -9,7 +10,7 @@ use git_object::bstr::ByteSlice;
 use os_str_bytes::OsStrBytes;
 
 use crate::{
-    store_impl::file::{self, loose::Reference},
+    store_impl::file::{self, find::is_per_worktree_ref_name, loose::Reference},
     FullName,
 };
 
@@ -18,6 +19,8 @@ pub(in crate::store_impl::file) struct SortedLoosePaths {
     pub(crate) base: PathBuf,
     filename_prefix: Option<OsString>,
     file_walk: DirEntryIter,
+    /// If set, only paths relative to `base` for which it returns true are considered.
+    filter: Option<fn(&Path) -> bool>,
 }
 
 impl SortedLoosePaths {
@@ -31,8 +34,14 @@ impl SortedLoosePaths {
             base: base.into(),
             filename_prefix,
             file_walk,
+            filter: None,
         }
     }
+
+    fn with_filter(mut self, filter: fn(&Path) -> bool) -> Self {
+        self.filter = Some(filter);
+        self
+    }
 }
 
 impl Iterator for SortedLoosePaths {
@@ -55,10 +64,15 @@ impl Iterator for SortedLoosePaths {
                             continue;
                         }
                     }
-                    let full_name = full_path
+                    let relative_path = full_path
                         .strip_prefix(&self.base)
-                        .expect("prefix-stripping cannot fail as prefix is our root")
-                        .to_raw_bytes();
+                        .expect("prefix-stripping cannot fail as prefix is our root");
+                    if let Some(filter) = self.filter {
+                        if !filter(relative_path) {
+                            continue;
+                        }
+                    }
+                    let full_name = relative_path.to_raw_bytes();
                     #[cfg(windows)]
                     let full_name: Vec<u8> = full_name.into_owned().replace(b"\\", b"/");
 
@@ -79,9 +93,50 @@ impl Iterator for SortedLoosePaths {
     }
 }
 
+/// An iterator over sorted loose reference paths which merges the shared references of the common directory with the
+/// per-worktree references in the private git directory of a linked worktree, if there is one.
+pub(in crate::store_impl::file) struct MergedLoosePaths {
+    common_dir: Option<Peekable<SortedLoosePaths>>,
+    git_dir: Option<Peekable<SortedLoosePaths>>,
+}
+
+impl From<SortedLoosePaths> for MergedLoosePaths {
+    fn from(paths: SortedLoosePaths) -> Self {
+        MergedLoosePaths {
+            common_dir: Some(paths.peekable()),
+            git_dir: None,
+        }
+    }
+}
+
+impl Iterator for MergedLoosePaths {
+    type Item = std::io::Result<(PathBuf, FullName)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match (self.common_dir.as_mut(), self.git_dir.as_mut()) {
+            (None, None) => None,
+            (Some(shared), None) => shared.next(),
+            (None, Some(private)) => private.next(),
+            (Some(shared), Some(private)) => {
+                let take_private = match (private.peek(), shared.peek()) {
+                    (None, _) => false,
+                    (Some(_), None) | (Some(Err(_)), _) => true,
+                    (Some(Ok(_)), Some(Err(_))) => false,
+                    (Some(Ok(private)), Some(Ok(shared))) => private.1.as_bstr() < shared.1.as_bstr(),
+                };
+                if take_private {
+                    private.next()
+                } else {
+                    shared.next()
+                }
+            }
+        }
+    }
+}
+
 /// An iterator over all loose references as seen from a particular base directory.
 pub struct Loose {
-    ref_paths: SortedLoosePaths,
+    ref_paths: MergedLoosePaths,
     buf: Vec<u8>,
 }
 
@@ -90,7 +145,7 @@ impl Loose {
     /// path to which resulting reference names should be relative to.
     pub fn at_root(root: impl AsRef<Path>, base: impl Into<PathBuf>) -> Self {
         Loose {
-            ref_paths: SortedLoosePaths::at_root_with_names(root, base, None),
+            ref_paths: SortedLoosePaths::at_root_with_names(root, base, None).into(),
             buf: Vec::new(),
         }
     }
@@ -103,7 +158,7 @@ impl Loose {
         prefix: Option<OsString>,
     ) -> Self {
         Loose {
-            ref_paths: SortedLoosePaths::at_root_with_names(root, base, prefix),
+            ref_paths: SortedLoosePaths::at_root_with_names(root, base, prefix).into(),
             buf: Vec::new(),
         }
     }
@@ -122,13 +177,9 @@ impl Iterator for Loose {
                     })
                     .map_err(loose::Error::ReadFileContents)
                     .and_then(|_| {
-                        let relative_path = validated_path
-                            .strip_prefix(&self.ref_paths.base)
-                            .expect("root contains path");
-                        Reference::try_from_path(name, &self.buf).map_err(|err| loose::Error::ReferenceCreation {
-                            err,
-                            relative_path: relative_path.into(),
-                        })
+                        let relative_path = name.to_path().into_owned();
+                        Reference::try_from_path(name, &self.buf)
+                            .map_err(|err| loose::Error::ReferenceCreation { err, relative_path })
                     })
             })
         })
@@ -148,27 +199,66 @@ impl file::Store {
     ///
     /// See [`Store::packed()`][file::Store::open_packed_buffer()] for interacting with packed references.
     pub fn loose_iter(&self) -> std::io::Result<Loose> {
-        let refs = self.refs_dir();
-        if !refs.is_dir() {
+        if !self.refs_dir().is_dir() {
             return Err(std::io::ErrorKind::NotFound.into());
         }
-        Ok(Loose::at_root(refs, self.base.clone()))
+        Ok(Loose {
+            ref_paths: self.loose_paths(Path::new("refs"), None),
+            buf: Vec::new(),
+        })
     }
 
     /// Return an iterator over all loose references that start with the given `prefix`.
     ///
     /// Otherwise it's similar to [`loose_iter()`][file::Store::loose_iter()].
     pub fn loose_iter_prefixed(&self, prefix: impl AsRef<Path>) -> std::io::Result<Loose> {
-        let (root, remainder) = self.validate_prefix(&self.base, prefix.as_ref())?;
-        Ok(Loose::at_root_with_filename_prefix(root, self.base.clone(), remainder))
+        let (root, remainder) = self.validate_prefix(prefix.as_ref())?;
+        Ok(Loose {
+            ref_paths: self.loose_paths(&root, remainder),
+            buf: Vec::new(),
+        })
     }
 
+    /// Return the directory containing all shared loose references.
     pub(in crate::store_impl::file) fn refs_dir(&self) -> PathBuf {
-        self.base.join("refs")
+        self.common_dir_resolved().join("refs")
+    }
+
+    /// Return sorted paths to all loose references in the directory `relative_root` whose file names start with `filename_prefix`.
+    ///
+    /// In linked worktrees, shared references are taken from the common directory and merged with the per-worktree
+    /// references of our private git directory.
+    pub(in crate::store_impl::file) fn loose_paths(
+        &self,
+        relative_root: &Path,
+        filename_prefix: Option<OsString>,
+    ) -> MergedLoosePaths {
+        let common_dir = self.common_dir_resolved();
+        let shared_root = common_dir.join(relative_root);
+        match &self.common_dir {
+            None => SortedLoosePaths::at_root_with_names(shared_root, common_dir.to_owned(), filename_prefix).into(),
+            Some(_) => {
+                let private_root = self.base.join(relative_root);
+                MergedLoosePaths {
+                    git_dir: private_root.is_dir().then(|| {
+                        SortedLoosePaths::at_root_with_names(private_root, self.base.clone(), filename_prefix.clone())
+                            .with_filter(is_per_worktree_ref_name)
+                            .peekable()
+                    }),
+                    common_dir: shared_root.is_dir().then(|| {
+                        SortedLoosePaths::at_root_with_names(shared_root, common_dir.to_owned(), filename_prefix)
+                            .with_filter(|name| !is_per_worktree_ref_name(name))
+                            .peekable()
+                    }),
+                }
+            }
+        }
     }
+
+    /// Validate `prefix` and return the directory to iterate, relative to the directory holding the references with that prefix,
+    /// along with the prefix that all file names in it must have.
     pub(in crate::store_impl::file) fn validate_prefix(
         &self,
-        base: &Path,
         prefix: &Path,
     ) -> std::io::Result<(PathBuf, Option<OsString>)> {
         if prefix.is_absolute() {
@@ -184,13 +274,19 @@ impl file::Store {
                 "Refusing to handle prefixes with relative path components",
             ));
         }
-        let base = base.join(prefix);
-        if base.is_dir() {
-            Ok((base, None))
+        let base = match &self.common_dir {
+            Some(_) if is_per_worktree_ref_name(prefix) => &self.base,
+            _ => self.common_dir_resolved(),
+        };
+        if base.join(prefix).is_dir() {
+            Ok((prefix.to_owned(), None))
         } else {
             Ok((
-                base.parent().expect("a parent is always there unless empty").to_owned(),
-                base.file_name().map(ToOwned::to_owned),
+                prefix
+                    .parent()
+                    .expect("a parent is always there unless empty")
+                    .to_owned(),
+                prefix.file_name().map(ToOwned::to_owned),
             ))
         }
     }
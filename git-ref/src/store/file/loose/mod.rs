@@ -35,6 +35,26 @@ mod init {
         pub fn at(git_dir: impl Into<PathBuf>, write_reflog: crate::file::WriteReflog) -> Self {
             file::Store {
                 base: git_dir.into(),
+                common_dir: None,
+                write_reflog,
+                namespace: None,
+                packed: Default::default(),
+            }
+        }
+
+        /// Create a new instance for the linked worktree whose private `git_dir` is typically `.git/worktrees/<id>`,
+        /// and whose shared references are stored in `common_dir`, typically the `.git` directory of the main worktree.
+        ///
+        /// Per-worktree references like `HEAD` or those in `refs/bisect/` are read from and written to `git_dir`,
+        /// whereas all other references, including `packed-refs`, are kept in `common_dir`.
+        pub fn for_linked_worktree(
+            git_dir: impl Into<PathBuf>,
+            common_dir: impl Into<PathBuf>,
+            write_reflog: crate::file::WriteReflog,
+        ) -> Self {
+            file::Store {
+                base: git_dir.into(),
+                common_dir: Some(common_dir.into()),
                 write_reflog,
                 namespace: None,
                 packed: Default::default(),
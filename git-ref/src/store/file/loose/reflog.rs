@@ -1,4 +1,8 @@
-use std::{convert::TryInto, io::Read, path::PathBuf};
+use std::{
+    convert::TryInto,
+    io::Read,
+    path::{Path, PathBuf},
+};
 
 use crate::{
     store_impl::{file, file::log},
@@ -78,39 +82,61 @@ impl file::Store {
 impl file::Store {
     /// Implements the logic required to transform a fully qualified refname into its log name
     pub(crate) fn reflog_path(&self, name: FullNameRef<'_>) -> PathBuf {
-        self.reflog_path_inner(&name.to_path())
+        self.reflog_root_and_path(&name.to_path()).1
+    }
+
+    /// Like [`reflog_path()`][file::Store::reflog_path()], but places the log into our namespace if one is set,
+    /// similar to how [references are placed][file::Store::reference_path()].
+    pub(in crate::store_impl::file) fn reflog_path_namespaced(&self, name: FullNameRef<'_>) -> PathBuf {
+        let full_name = name.to_path();
+        let (base, relative_name) = self.to_base_dir_and_relative_name(&full_name);
+        match &self.namespace {
+            None => base.join("logs").join(relative_name),
+            Some(namespace) => base.join("logs").join(namespace.to_path()).join(relative_name),
+        }
+    }
+
+    /// Returns the directory holding all reflogs next to the one of `full_name`, along with the full path to the latter.
+    pub(in crate::store_impl::file) fn reflog_root_and_path(&self, full_name: &Path) -> (PathBuf, PathBuf) {
+        let (base, relative_name) = self.to_base_dir_and_relative_name(full_name);
+        let root = base.join("logs");
+        let path = root.join(relative_name);
+        (root, path)
     }
 }
 
 ///
 pub mod create_or_update {
-    use std::{
-        io::Write,
-        path::{Path, PathBuf},
-    };
+    use std::{io::Write, path::Path};
 
     use git_hash::{oid, ObjectId};
     use git_object::bstr::BStr;
 
-    use crate::store_impl::{file, file::WriteReflog};
+    use crate::{
+        store_impl::{file, file::WriteReflog},
+        FullNameRef,
+    };
 
     impl file::Store {
         pub(crate) fn reflog_create_or_append(
             &self,
-            lock: &git_lock::Marker,
+            name: FullNameRef<'_>,
             previous_oid: Option<ObjectId>,
             new: &oid,
             committer: &git_actor::Signature,
             message: &BStr,
             force_create_reflog: bool,
         ) -> Result<(), Error> {
-            let full_name = self.reflock_resource_full_name(lock);
             match self.write_reflog {
                 WriteReflog::Normal => {
                     let mut options = std::fs::OpenOptions::new();
                     options.append(true).read(false);
-                    let log_path = self.reflock_resource_to_log_path(lock);
+                    let log_path = self.reflog_path_namespaced(name);
 
+                    let full_name = match &self.namespace {
+                        None => name.to_path().into_owned(),
+                        Some(namespace) => namespace.to_owned().into_namespaced_prefix(name.to_path()),
+                    };
                     if force_create_reflog || self.should_autocreate_reflog(&full_name) {
                         let parent_dir = log_path.parent().expect("always with parent directory");
                         git_tempfile::create_dir::all(parent_dir, Default::default()).map_err(|err| {
@@ -133,7 +159,7 @@ pub mod create_or_update {
                                     .map(Some)
                                     .map_err(|_| Error::Append {
                                         err,
-                                        reflog_path: self.reflock_resource_to_log_path(lock),
+                                        reflog_path: log_path.clone(),
                                     })?
                             } else {
                                 return Err(Error::Append {
@@ -145,23 +171,9 @@ pub mod create_or_update {
                     };
 
                     if let Some(mut file) = file_for_appending {
-                        write!(
-                            file,
-                            "{} {} ",
-                            previous_oid.unwrap_or_else(|| ObjectId::null(new.kind())),
-                            new
-                        )
-                        .and_then(|_| committer.write_to(&mut file))
-                        .and_then(|_| {
-                            if !message.is_empty() {
-                                writeln!(file, "\t{}", message)
-                            } else {
-                                writeln!(file)
-                            }
-                        })
-                        .map_err(|err| Error::Append {
+                        write_line(&mut file, previous_oid, new, committer, message).map_err(|err| Error::Append {
                             err,
-                            reflog_path: self.reflock_resource_to_log_path(lock),
+                            reflog_path: log_path,
                         })?;
                     }
                     Ok(())
@@ -170,38 +182,88 @@ pub mod create_or_update {
             }
         }
 
+        /// Copy the reflog of `source` to the reflog of `target` through a lock file, replacing it once the lock is committed,
+        /// and append a line for the change to `new` if it is set.
+        /// Nothing happens if `source` has no reflog or if reflogs are disabled, and `false` is returned.
+        pub(crate) fn reflog_copy_and_append(
+            &self,
+            source: FullNameRef<'_>,
+            target: FullNameRef<'_>,
+            new: Option<&oid>,
+            committer: &git_actor::Signature,
+            message: &BStr,
+        ) -> Result<bool, Error> {
+            if self.write_reflog == WriteReflog::Disable {
+                return Ok(false);
+            }
+            let source_path = self.reflog_path_namespaced(source);
+            let mut source_file = match std::fs::File::open(&source_path) {
+                Ok(file) if source_path.is_file() => file,
+                Ok(_) => return Ok(false),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+                Err(err) => {
+                    return Err(Error::Copy {
+                        err,
+                        source_path,
+                        reflog_path: self.reflog_path_namespaced(target),
+                    })
+                }
+            };
+            let log_path = self.reflog_path_namespaced(target);
+            let (reflog_root, _) = self.reflog_root_and_path(&target.to_path());
+            let mut lock = git_lock::File::acquire_to_update_resource(
+                &log_path,
+                git_lock::acquire::Fail::Immediately,
+                Some(reflog_root),
+            )
+            .map_err(|err| Error::LockAcquire {
+                err,
+                reflog_path: log_path.clone(),
+            })?;
+            std::io::copy(&mut source_file, &mut lock).map_err(|err| Error::Copy {
+                err,
+                source_path,
+                reflog_path: log_path.clone(),
+            })?;
+            if let Some(new) = new {
+                write_line(&mut lock, Some(new.to_owned()), new, committer, message).map_err(|err| Error::Append {
+                    err,
+                    reflog_path: log_path.clone(),
+                })?;
+            }
+            lock.commit().map_err(|err| Error::LockCommit {
+                err: err.error,
+                reflog_path: log_path,
+            })?;
+            Ok(true)
+        }
+
         fn should_autocreate_reflog(&self, full_name: &Path) -> bool {
             full_name.starts_with("refs/heads/")
                 || full_name.starts_with("refs/remotes/")
                 || full_name.starts_with("refs/notes/")
                 || full_name == Path::new("HEAD")
         }
+    }
 
-        fn reflock_resource_full_name(&self, reflock: &git_lock::Marker) -> PathBuf {
-            reflock
-                .resource_path()
-                .strip_prefix(&self.base)
-                .expect("lock must be held within this store")
-                .to_owned()
-        }
-
-        fn reflock_resource_to_log_path(&self, reflock: &git_lock::Marker) -> PathBuf {
-            self.reflog_path_inner(
-                reflock
-                    .resource_path()
-                    .strip_prefix(&self.base)
-                    .expect("lock must be held within this store"),
-            )
-        }
-
-        /// Returns the base and a full path (including the base) to the reflog for a ref of the given `full_name`
-        pub(in crate::store_impl::file::loose::reflog) fn reflog_path_inner(&self, full_name: &Path) -> PathBuf {
-            self.reflog_root().join(full_name)
-        }
-
-        /// Returns the base paths for all reflogs
-        pub(in crate::store_impl::file) fn reflog_root(&self) -> PathBuf {
-            self.base.join("logs")
+    fn write_line(
+        mut out: impl Write,
+        previous_oid: Option<ObjectId>,
+        new: &oid,
+        committer: &git_actor::Signature,
+        message: &BStr,
+    ) -> std::io::Result<()> {
+        write!(
+            out,
+            "{} {} ",
+            previous_oid.unwrap_or_else(|| ObjectId::null(new.kind())),
+            new
+        )?;
+        committer.write_to(&mut out)?;
+        if !message.is_empty() {
+            writeln!(out, "\t{}", message)
+        } else {
+            writeln!(out)
         }
     }
 
@@ -226,6 +288,18 @@ pub mod create_or_update {
                     display("Could not open reflog file at '{}' for appending", reflog_path.display())
                     source(err)
                 }
+                Copy { err: std::io::Error, source_path: PathBuf, reflog_path: PathBuf } {
+                    display("Could not copy reflog file at '{}' to '{}'", source_path.display(), reflog_path.display())
+                    source(err)
+                }
+                LockAcquire { err: git_lock::acquire::Error, reflog_path: PathBuf } {
+                    display("Could not lock reflog file at '{}' to copy another reflog into it", reflog_path.display())
+                    source(err)
+                }
+                LockCommit { err: std::io::Error, reflog_path: PathBuf } {
+                    display("Could not move the lock file of the reflog at '{}' into place", reflog_path.display())
+                    source(err)
+                }
                 MessageWithNewlines {
                     display("tbd")
                 }
@@ -1,7 +1,6 @@
 use std::{convert::TryInto, path::Path};
 
 use git_actor::{Sign, Signature, Time};
-use git_lock::acquire::Fail;
 use git_object::bstr::ByteSlice;
 use git_testtools::hex_to_id;
 use tempfile::TempDir;
@@ -17,16 +16,6 @@ fn empty_store(writemode: WriteReflog) -> Result<(TempDir, file::Store)> {
     Ok((dir, store))
 }
 
-fn reflock(store: &file::Store, full_name: &str) -> Result<git_lock::Marker> {
-    let full_name: FullNameRef<'_> = full_name.try_into()?;
-    git_lock::Marker::acquire_to_hold_resource(
-        store.reference_path(&full_name.to_path()),
-        Fail::Immediately,
-        Some(store.base.clone()),
-    )
-    .map_err(Into::into)
-}
-
 fn reflog_lines(store: &file::Store, name: &str, buf: &mut Vec<u8>) -> Result<Vec<crate::log::Line>> {
     store
         .reflog_iter(name, buf)?
@@ -39,12 +28,24 @@ fn reflog_lines(store: &file::Store, name: &str, buf: &mut Vec<u8>) -> Result<Ve
 const WRITE_MODES: &[WriteReflog] = &[WriteReflog::Normal, WriteReflog::Disable];
 
 #[test]
-fn reflock_resource_to_log_path() -> Result {
-    let (_keep, store) = empty_store(WriteReflog::Normal)?;
-    for name in &["HEAD", "refs/heads/main"] {
+fn reflog_path_of_linked_worktree_depends_on_the_reference() -> Result {
+    let dir = TempDir::new()?;
+    let common_dir = dir.path().join("common");
+    let git_dir = common_dir.join("worktrees").join("wt");
+    let store = file::Store::for_linked_worktree(&git_dir, &common_dir, WriteReflog::Normal);
+    for (name, expected) in &[
+        ("HEAD", git_dir.join("logs").join("HEAD")),
+        ("refs/bisect/bad", git_dir.join("logs/refs/bisect/bad")),
+        ("refs/heads/main", common_dir.join("logs/refs/heads/main")),
+        ("main-worktree/HEAD", common_dir.join("logs").join("HEAD")),
+        ("worktrees/other/HEAD", common_dir.join("worktrees/other/logs/HEAD")),
+    ] {
+        let name: FullNameRef<'_> = (*name).try_into()?;
+        assert_eq!(&store.reflog_path(name), expected);
         assert_eq!(
-            store.reflock_resource_to_log_path(&reflock(&store, name).unwrap()),
-            store.reflog_path_inner(Path::new(name))
+            &store.reflog_path_namespaced(name),
+            expected,
+            "without namespace, both are equal"
         );
     }
     Ok(())
@@ -67,7 +68,6 @@ fn missing_reflog_creates_it_even_if_similarly_named_empty_dir_exists_and_append
     for mode in WRITE_MODES {
         let (_keep, store) = empty_store(*mode)?;
         let full_name = "refs/heads/main";
-        let lock = reflock(&store, full_name)?;
         let new = hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242");
         let committer = Signature {
             name: "committer".into(),
@@ -78,7 +78,14 @@ fn missing_reflog_creates_it_even_if_similarly_named_empty_dir_exists_and_append
                 sign: Sign::Plus,
             },
         };
-        store.reflog_create_or_append(&lock, None, &new, &committer, b"the message".as_bstr(), false)?;
+        store.reflog_create_or_append(
+            full_name.try_into()?,
+            None,
+            &new,
+            &committer,
+            b"the message".as_bstr(),
+            false,
+        )?;
 
         let mut buf = Vec::new();
         match mode {
@@ -94,7 +101,7 @@ fn missing_reflog_creates_it_even_if_similarly_named_empty_dir_exists_and_append
                 );
                 let previous = hex_to_id("0000000000000000000000111111111111111111");
                 store.reflog_create_or_append(
-                    &lock,
+                    full_name.try_into()?,
                     Some(previous),
                     &new,
                     &committer,
@@ -124,13 +131,12 @@ fn missing_reflog_creates_it_even_if_similarly_named_empty_dir_exists_and_append
 
         // create onto existing directory
         let full_name = "refs/heads/other";
-        let lock = reflock(&store, full_name)?;
-        let reflog_path = store.reflog_path_inner(Path::new(full_name));
+        let reflog_path = store.reflog_root_and_path(Path::new(full_name)).1;
         let directory_in_place_of_reflog = reflog_path.join("empty-a").join("empty-b");
         std::fs::create_dir_all(&directory_in_place_of_reflog)?;
 
         store.reflog_create_or_append(
-            &lock,
+            full_name.try_into()?,
             None,
             &new,
             &committer,
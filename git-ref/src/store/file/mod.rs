@@ -10,8 +10,12 @@ use git_features::threading::{MutableOnDemand, OwnShared};
 pub struct Store {
     /// The location at which loose references can be found as per conventions of a typical git repository.
     ///
-    /// Typical base paths are `.git` repository folders.
+    /// Typical base paths are `.git` repository folders, or `.git/worktrees/<id>` folders of linked worktrees.
     base: PathBuf,
+    /// The directory containing all references shared among worktrees, if it differs from `base`.
+    ///
+    /// It is only set for stores of linked worktrees, whose `base` then only contains per-worktree references like `HEAD`.
+    common_dir: Option<PathBuf>,
     /// The way to handle reflog edits
     pub write_reflog: WriteReflog,
     /// The namespace to use for edits and reads
@@ -32,6 +36,17 @@ mod access {
         pub fn base(&self) -> &Path {
             &self.base
         }
+
+        /// Return the directory containing all shared references if this store belongs to a linked worktree, or `None` otherwise.
+        pub fn common_dir(&self) -> Option<&Path> {
+            self.common_dir.as_deref()
+        }
+
+        /// Return the directory containing all shared references, which is the [`base()`][file::Store::base()] unless
+        /// this store belongs to a linked worktree.
+        pub fn common_dir_resolved(&self) -> &Path {
+            self.common_dir.as_deref().unwrap_or(&self.base)
+        }
     }
 }
 
@@ -41,9 +56,10 @@ pub struct Transaction<'s> {
     packed_transaction: Option<crate::store_impl::packed::Transaction>,
     updates: Option<Vec<transaction::Edit>>,
     packed_refs: transaction::PackedRefs,
+    hook: Option<Box<transaction::HookFn>>,
 }
 
-pub(in crate::store_impl::file) fn path_to_name(path: impl Into<PathBuf>) -> git_object::bstr::BString {
+pub(crate) fn path_to_name(path: impl Into<PathBuf>) -> git_object::bstr::BString {
     use os_str_bytes::OsStringBytes;
     let path = path.into().into_raw_vec();
     #[cfg(windows)]
@@ -83,6 +99,11 @@ pub mod transaction;
 ///
 pub mod packed;
 
+///
+pub mod pack_refs;
+
+mod pseudo;
+
 mod raw_ext;
 pub use raw_ext::ReferenceExt;
 
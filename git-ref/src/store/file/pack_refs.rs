@@ -0,0 +1,156 @@
+use git_object::bstr::ByteSlice;
+
+use crate::{
+    store_impl::file::{
+        self,
+        find::is_per_worktree_ref_name,
+        transaction::{FindObjectFn, PackedRefs},
+    },
+    transaction::{Change, LogChange, PreviousValue, RefEdit},
+    FullName, Target,
+};
+
+/// Options for [`file::Store::pack_refs()`].
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// If true, pack all references, otherwise only pack tags and those references which are already packed.
+    pub all: bool,
+    /// If true, delete loose references once they were written into the `packed-refs` file.
+    pub prune: bool,
+    /// How to handle references or the `packed-refs` file which are locked by someone else.
+    pub lock_mode: git_lock::acquire::Fail,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            all: false,
+            prune: true,
+            lock_mode: git_lock::acquire::Fail::Immediately,
+        }
+    }
+}
+
+impl file::Store {
+    /// Write loose references into the `packed-refs` file as configured by `options`, like `git pack-refs` does, and return
+    /// the names of all packed references.
+    ///
+    /// `find` is used to peel tags, which are written on their own line after the reference pointing to them.
+    /// Symbolic and broken references are never packed, neither are the ones private to a worktree like `refs/bisect/*`.
+    /// Namespaces are ignored, and all references are handled as seen on disk.
+    pub fn pack_refs(&self, options: Options, find: Box<FindObjectFn>) -> Result<Vec<FullName>, Error> {
+        let mut store = self.clone();
+        store.namespace = None;
+
+        let packed = store.assure_packed_refs_uptodate()?;
+        let loose = match store.loose_iter() {
+            Ok(loose) => loose,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut edits = Vec::new();
+        for reference in loose {
+            let reference = match reference {
+                Ok(reference) => reference,
+                // Broken references aren't packed, just like git does.
+                Err(file::iter::loose::Error::ReferenceCreation { .. }) => continue,
+                Err(err) => return Err(err.into()),
+            };
+            let id = match reference.target {
+                Target::Peeled(id) => id,
+                Target::Symbolic(_) => continue,
+            };
+            if is_per_worktree_ref_name(reference.name.to_path().as_ref()) {
+                continue;
+            }
+            let is_packed = match &packed {
+                Some(packed) => packed.try_find(reference.name.to_ref())?.is_some(),
+                None => false,
+            };
+            if !(options.all || is_packed || reference.name.as_bstr().starts_with_str("refs/tags/")) {
+                continue;
+            }
+            edits.push(RefEdit {
+                change: Change::Update {
+                    // Reflogs are left untouched as the value doesn't change.
+                    log: LogChange::default(),
+                    expected: PreviousValue::MustExistAndMatch(Target::Peeled(id)),
+                    new: Target::Peeled(id),
+                },
+                name: reference.name,
+                deref: false,
+            });
+        }
+        drop(packed);
+        if edits.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let names = edits.iter().map(|edit| edit.name.clone()).collect();
+        let packed_refs = if options.prune {
+            PackedRefs::DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(find)
+        } else {
+            PackedRefs::DeletionsAndNonSymbolicUpdates(find)
+        };
+        let unused_committer = git_actor::Signature {
+            name: Default::default(),
+            email: Default::default(),
+            time: git_actor::Time {
+                time: 0,
+                offset: 0,
+                sign: git_actor::Sign::Plus,
+            },
+        };
+        store
+            .transaction()
+            .packed_refs(packed_refs)
+            .prepare(edits, options.lock_mode)?
+            .commit(&unused_committer)?;
+        Ok(names)
+    }
+}
+
+mod error {
+    use quick_error::quick_error;
+
+    use crate::store_impl::{file, packed};
+
+    quick_error! {
+        /// The error returned by [`file::Store::pack_refs()`].
+        #[derive(Debug)]
+        #[allow(missing_docs)]
+        pub enum Error {
+            PackedOpen(err: packed::buffer::open::Error) {
+                display("The packed-refs file could not be opened")
+                from()
+                source(err)
+            }
+            PackedFind(err: packed::find::Error) {
+                display("The packed-refs file could not be parsed")
+                from()
+                source(err)
+            }
+            Traversal(err: std::io::Error) {
+                display("The loose references could not be traversed")
+                from()
+                source(err)
+            }
+            LooseReference(err: file::iter::loose::Error) {
+                display("A loose reference could not be read")
+                from()
+                source(err)
+            }
+            Prepare(err: file::transaction::prepare::Error) {
+                display("The transaction to pack references could not be prepared")
+                from()
+                source(err)
+            }
+            Commit(err: file::transaction::commit::Error) {
+                display("The transaction to pack references could not be committed")
+                from()
+                source(err)
+            }
+        }
+    }
+}
+pub use error::Error;
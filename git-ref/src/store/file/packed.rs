@@ -40,9 +40,9 @@ impl file::Store {
         self.assure_packed_refs_uptodate()
     }
 
-    /// Return the path at which packed-refs would usually be stored
+    /// Return the path at which packed-refs would usually be stored, which is within the common directory for linked worktrees.
     pub fn packed_refs_path(&self) -> PathBuf {
-        self.base.join("packed-refs")
+        self.common_dir_resolved().join("packed-refs")
     }
 }
 
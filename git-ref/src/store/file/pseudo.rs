@@ -0,0 +1,99 @@
+use std::{io::Write, path::PathBuf};
+
+use git_hash::ObjectId;
+use git_object::bstr::{BStr, ByteSlice};
+
+use crate::{
+    pseudo::{self, fetch_head, merge_head, Error},
+    store_impl::file,
+};
+
+/// Pseudo-references
+impl file::Store {
+    /// Read and parse all entries of `FETCH_HEAD`, or return `None` if it doesn't exist.
+    pub fn fetch_head(&self) -> Result<Option<Vec<fetch_head::Entry>>, Error> {
+        self.read_pseudo_ref(pseudo::FETCH_HEAD.into())?
+            .map(|data| fetch_head::decode(&data))
+            .transpose()
+    }
+
+    /// Read and parse all commits listed in `MERGE_HEAD`, or return `None` if it doesn't exist.
+    pub fn merge_head(&self) -> Result<Option<Vec<ObjectId>>, Error> {
+        self.read_pseudo_ref(pseudo::MERGE_HEAD.into())?
+            .map(|data| merge_head::decode(&data))
+            .transpose()
+    }
+
+    /// Return the object id stored in the pseudo-reference `name`, like [`ORIG_HEAD`][pseudo::ORIG_HEAD], or `None` if it
+    /// doesn't exist. Pseudo-references with multiple entries, like `FETCH_HEAD`, yield their first one.
+    pub fn pseudo_ref_id(&self, name: &BStr) -> Result<Option<ObjectId>, Error> {
+        Ok(match self.read_pseudo_ref(name)? {
+            Some(data) => {
+                let line = data.lines().next().unwrap_or_default();
+                let hex = line.split_str(b"\t").next().unwrap_or_default().trim();
+                Some(ObjectId::from_hex(hex).map_err(|_| Error::Decode {
+                    line_number: 1,
+                    line: line.into(),
+                })?)
+            }
+            None => None,
+        })
+    }
+
+    /// Atomically replace `FETCH_HEAD` with the given `entries`, acquiring its lock with `lock_fail_mode`.
+    pub fn write_fetch_head<'a>(
+        &self,
+        entries: impl IntoIterator<Item = &'a fetch_head::Entry>,
+        lock_fail_mode: git_lock::acquire::Fail,
+    ) -> Result<(), Error> {
+        self.write_pseudo_ref(pseudo::FETCH_HEAD, lock_fail_mode, |out| {
+            entries.into_iter().try_for_each(|entry| entry.write_to(&mut *out))
+        })
+    }
+
+    /// Atomically replace `MERGE_HEAD` with the given commit `ids`, acquiring its lock with `lock_fail_mode`.
+    pub fn write_merge_head<'a>(
+        &self,
+        ids: impl IntoIterator<Item = &'a ObjectId>,
+        lock_fail_mode: git_lock::acquire::Fail,
+    ) -> Result<(), Error> {
+        self.write_pseudo_ref(pseudo::MERGE_HEAD, lock_fail_mode, |out| merge_head::write_to(ids, out))
+    }
+
+    fn pseudo_ref_path(&self, name: &BStr) -> Result<PathBuf, Error> {
+        if !pseudo::is_pseudo_ref_name(name) {
+            return Err(Error::InvalidName { name: name.to_owned() });
+        }
+        Ok(self.base.join(name.to_str().expect("validated ascii")))
+    }
+
+    fn read_pseudo_ref(&self, name: &BStr) -> Result<Option<Vec<u8>>, Error> {
+        let path = self.pseudo_ref_path(name)?;
+        match std::fs::read(&path) {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(Error::Io { err, path }),
+        }
+    }
+
+    fn write_pseudo_ref(
+        &self,
+        name: &str,
+        lock_fail_mode: git_lock::acquire::Fail,
+        write: impl FnOnce(&mut dyn Write) -> std::io::Result<()>,
+    ) -> Result<(), Error> {
+        let path = self.pseudo_ref_path(name.into())?;
+        let mut lock = git_lock::File::acquire_to_update_resource(&path, lock_fail_mode, None).map_err(|err| {
+            Error::LockAcquire {
+                err,
+                path: path.clone(),
+            }
+        })?;
+        write(&mut lock).map_err(|err| Error::Io {
+            err,
+            path: path.clone(),
+        })?;
+        lock.commit().map_err(|err| Error::Io { err: err.error, path })?;
+        Ok(())
+    }
+}
@@ -1,5 +1,8 @@
 use crate::{
-    store_impl::file::{transaction::PackedRefs, Transaction},
+    store_impl::file::{
+        transaction::{hook, PackedRefs},
+        Transaction,
+    },
     transaction::{Change, LogChange, RefEdit, RefLog},
     Target,
 };
@@ -23,8 +26,8 @@ impl<'s> Transaction<'s> {
     ///   along with empty parent directories
     ///
     /// Note that transactions will be prepared automatically as needed.
-    pub fn commit(self, committer: &git_actor::Signature) -> Result<Vec<RefEdit>, Error> {
-        let mut updates = self.updates.expect("BUG: must call prepare before commit");
+    pub fn commit(mut self, committer: &git_actor::Signature) -> Result<Vec<RefEdit>, Error> {
+        let mut updates = self.updates.take().expect("BUG: must call prepare before commit");
         let delete_loose_refs = matches!(
             self.packed_refs,
             PackedRefs::DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(_)
@@ -36,24 +39,57 @@ impl<'s> Transaction<'s> {
             match &change.update.change {
                 // reflog first, then reference
                 Change::Update { log, new, expected } => {
-                    let lock = change.lock.take().expect("each ref is locked");
+                    let lock = match change.lock.take() {
+                        Some(lock) => lock,
+                        None => {
+                            // Initial transactions write peeled references only into packed-refs without locking them.
+                            if let (Target::Peeled(new_oid), true) = (new, log.force_create_reflog) {
+                                self.store.reflog_create_or_append(
+                                    change.update.name.to_ref(),
+                                    None,
+                                    new_oid,
+                                    committer,
+                                    log.message.as_ref(),
+                                    true,
+                                )?;
+                            }
+                            continue;
+                        }
+                    };
                     let (update_ref, update_reflog) = match log.mode {
                         RefLog::Only => (false, true),
                         RefLog::AndReference => (true, true),
                     };
                     if update_reflog {
+                        let reflog_was_copied = match &change.relocated_from {
+                            Some(source) => self.store.reflog_copy_and_append(
+                                source.to_ref(),
+                                change.update.name.to_ref(),
+                                match new {
+                                    Target::Peeled(new_oid) => Some(new_oid),
+                                    Target::Symbolic(_) => None, // no reflog for symref changes
+                                },
+                                committer,
+                                log.message.as_ref(),
+                            )?,
+                            None => false,
+                        };
                         match new {
+                            _ if reflog_was_copied => {}
                             Target::Symbolic(_) => {} // no reflog for symref changes
                             Target::Peeled(new_oid) => {
                                 let previous = match expected {
                                     PreviousValue::MustExistAndMatch(Target::Peeled(oid)) => Some(oid.to_owned()),
+                                    // renamed or copied references keep their value
+                                    _ if change.relocated_from.is_some() => Some(new_oid.to_owned()),
                                     _ => None,
                                 }
                                 .or(change.leaf_referent_previous_oid);
-                                let do_update = previous.as_ref().map_or(true, |previous| previous != new_oid);
+                                let do_update = change.relocated_from.is_some()
+                                    || previous.as_ref().map_or(true, |previous| previous != new_oid);
                                 if do_update {
                                     self.store.reflog_create_or_append(
-                                        &lock,
+                                        change.update.name.to_ref(),
                                         previous,
                                         new_oid,
                                         committer,
@@ -93,17 +129,20 @@ impl<'s> Transaction<'s> {
                     }
                 }
                 Change::Delete { .. } => {}
+                Change::Rename { .. } | Change::Copy { .. } => {
+                    unreachable!("BUG: renames and copies are split during pre-processing")
+                }
             }
         }
 
-        let reflog_root = self.store.reflog_root();
         for change in updates.iter_mut() {
             match &change.update.change {
-                Change::Update { .. } => {}
+                Change::Update { .. } | Change::Rename { .. } | Change::Copy { .. } => {}
                 Change::Delete { .. } => {
                     // Reflog deletion happens first in case it fails a ref without log is less terrible than
                     // a log without a reference.
-                    let reflog_path = self.store.reflog_path(change.update.name.to_ref());
+                    let (reflog_root, reflog_path) =
+                        self.store.reflog_root_and_path(change.update.name.to_path().as_ref());
                     if let Err(err) = std::fs::remove_file(&reflog_path) {
                         if err.kind() != std::io::ErrorKind::NotFound {
                             return Err(Error::DeleteReflog {
@@ -122,7 +161,7 @@ impl<'s> Transaction<'s> {
             }
         }
 
-        if let Some(t) = self.packed_transaction {
+        if let Some(t) = self.packed_transaction.take() {
             t.commit().map_err(Error::PackedTransactionCommit)?;
             // Always refresh ourselves right away to avoid races. We ignore errors as there may be many reasons this fails, and it's not
             // critical to be done here. In other words, the pack may be refreshed at a later time and then it might work.
@@ -136,6 +175,7 @@ impl<'s> Transaction<'s> {
                     ..
                 } => delete_loose_refs && *mode == RefLog::AndReference,
                 Change::Delete { log: mode, .. } => *mode == RefLog::AndReference,
+                Change::Rename { .. } | Change::Copy { .. } => false,
             };
             if take_lock_and_delete {
                 let lock = change.lock.take().expect("lock must still be present in delete mode");
@@ -151,7 +191,12 @@ impl<'s> Transaction<'s> {
                 drop(lock)
             }
         }
-        Ok(updates.into_iter().map(|edit| edit.update).collect())
+        let edits: Vec<_> = updates.into_iter().map(|edit| edit.update).collect();
+        if let Some(hook) = self.hook.as_mut() {
+            // It's too late to change anything, so errors are ignored just like git does.
+            hook(hook::State::Committed, &edits).ok();
+        }
+        Ok(edits)
     }
 }
 mod error {
@@ -0,0 +1,127 @@
+//! Support for the `reference-transaction` hook which is informed about the state of a transaction and may reject it.
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use git_object::bstr::{BString, ByteVec};
+
+use crate::{
+    store_impl::file::transaction::HookFn,
+    transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog},
+    Target,
+};
+
+/// The state of a transaction when the hook is called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum State {
+    /// All references are locked and the transaction is about to be committed, unless the hook fails.
+    Prepared,
+    /// The transaction was committed, which can't be undone by the hook anymore.
+    Committed,
+    /// The prepared transaction was dropped without being committed and all locks are released.
+    Aborted,
+}
+
+impl State {
+    /// Return the state as passed as only argument to the `reference-transaction` hook.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            State::Prepared => "prepared",
+            State::Committed => "committed",
+            State::Aborted => "aborted",
+        }
+    }
+}
+
+/// Produce the lines passed to the `reference-transaction` hook via stdin, one `<old-value> SP <new-value> SP <ref-name> LF`
+/// line per edit.
+///
+/// Values are hex object ids or `ref:<name>` for symbolic references, and unknown or non-existing values are the null id.
+/// Edits that only affect the reflog are skipped.
+pub fn to_input(edits: &[RefEdit]) -> BString {
+    fn push_target(buf: &mut BString, target: Option<&Target>) {
+        match target {
+            Some(Target::Peeled(oid)) => buf.push_str(oid.to_string()),
+            Some(Target::Symbolic(name)) => {
+                buf.push_str("ref:");
+                buf.push_str(name.as_bstr());
+            }
+            None => buf.push_str(git_hash::ObjectId::null_sha1().to_string()),
+        }
+    }
+    let previous = |expected: &PreviousValue| match expected {
+        PreviousValue::MustExistAndMatch(target) | PreviousValue::ExistingMustMatch(target) => Some(target.clone()),
+        PreviousValue::Any | PreviousValue::MustExist | PreviousValue::MustNotExist => None,
+    };
+
+    let mut buf = BString::default();
+    for edit in edits {
+        let (previous, new) = match &edit.change {
+            Change::Update {
+                log: LogChange { mode: RefLog::Only, .. },
+                ..
+            }
+            | Change::Delete { log: RefLog::Only, .. } => continue,
+            Change::Update { expected, new, .. } => (previous(expected), Some(new.clone())),
+            Change::Delete { expected, .. } => (previous(expected), None),
+            Change::Rename { .. } | Change::Copy { .. } => {
+                unreachable!("BUG: renames and copies are split during pre-processing")
+            }
+        };
+        push_target(&mut buf, previous.as_ref());
+        buf.push_byte(b' ');
+        push_target(&mut buf, new.as_ref());
+        buf.push_byte(b' ');
+        buf.push_str(edit.name.as_bstr());
+        buf.push_byte(b'\n');
+    }
+    buf
+}
+
+/// Return a hook function which runs the executable at `path` in `working_dir` like git does, passing the [`State`] as
+/// only argument and the [edits][to_input()] via stdin.
+///
+/// If it exits with a non-zero status in the [prepared][State::Prepared] state the transaction is aborted, otherwise its
+/// exit status is ignored.
+pub fn executable(path: impl Into<PathBuf>, working_dir: impl Into<PathBuf>) -> Box<HookFn> {
+    let path = path.into();
+    let working_dir = working_dir.into();
+    Box::new(move |state, edits| {
+        let mut child = Command::new(&path)
+            .arg(state.as_str())
+            .current_dir(&working_dir)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        let stdin_result = child
+            .stdin
+            .take()
+            .expect("configured")
+            .write_all(to_input(edits).as_slice());
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(Box::new(Error::Status { status }));
+        }
+        match stdin_result {
+            Err(err) if err.kind() != std::io::ErrorKind::BrokenPipe => Err(err.into()),
+            _ => Ok(()),
+        }
+    })
+}
+
+mod error {
+    use quick_error::quick_error;
+
+    quick_error! {
+        /// The error returned by [executable hooks][super::executable()].
+        #[derive(Debug)]
+        #[allow(missing_docs)]
+        pub enum Error {
+            Status { status: std::process::ExitStatus } {
+                display("The reference-transaction hook failed with {}", status)
+            }
+        }
+    }
+}
+pub use error::Error;
@@ -4,6 +4,7 @@ use git_object::bstr::BString;
 use crate::{
     store_impl::{file, file::Transaction},
     transaction::RefEdit,
+    FullName,
 };
 
 /// A function receiving an object id to resolve, returning its decompressed bytes.
@@ -15,6 +16,10 @@ pub type FindObjectFn =
         &mut Vec<u8>,
     ) -> Result<Option<git_object::Kind>, Box<dyn std::error::Error + Send + Sync + 'static>>;
 
+/// A function called with the [state][hook::State] of a transaction along with all of its edits, usually to run the
+/// `reference-transaction` hook. If it fails in the [prepared][hook::State::Prepared] state, the transaction is aborted.
+pub type HookFn = dyn FnMut(hook::State, &[RefEdit]) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>;
+
 /// How to handle packed refs during a transaction
 pub enum PackedRefs {
     /// Only propagate deletions of references. This is the default
@@ -24,6 +29,13 @@ pub enum PackedRefs {
     /// Propagate deletions as well as updates to references which are peeled, that is contain an object id. Furthermore delete the
     /// reference which is originally updated if it exists. If it doesn't, the new value will be written into the packed ref right away.
     DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(Box<FindObjectFn>),
+    /// Write all updates to references which are peeled, that is contain an object id, straight into a new packed-refs file
+    /// without creating or locking their loose counterparts, which is much faster when creating a lot of references like
+    /// when cloning. Their reflogs are only written if [forced][crate::transaction::LogChange::force_create_reflog], while all
+    /// other edits are handled as usual.
+    ///
+    /// This is only valid if the store has no references yet, otherwise the transaction fails to prepare.
+    InitialTransaction(Box<FindObjectFn>),
 }
 
 impl Default for PackedRefs {
@@ -42,6 +54,8 @@ pub(in crate::store_impl::file) struct Edit {
     /// For symbolic refs, this is the previous OID to put into the reflog instead of our own previous value. It's the
     /// peeled value of the leaf referent.
     leaf_referent_previous_oid: Option<ObjectId>,
+    /// Set if this update creates a reference renamed or copied from the given reference, whose reflog is to be copied.
+    relocated_from: Option<FullName>,
 }
 
 impl Edit {
@@ -62,6 +76,16 @@ impl std::borrow::BorrowMut<RefEdit> for Edit {
     }
 }
 
+impl crate::store_impl::prepare::Edit for Edit {
+    fn parent_index(&self) -> Option<usize> {
+        self.parent_index
+    }
+
+    fn set_leaf_referent_previous_oid(&mut self, oid: ObjectId) {
+        self.leaf_referent_previous_oid = Some(oid);
+    }
+}
+
 /// Edits
 impl file::Store {
     /// Open a transaction with the given `edits`, and determine how to fail if a `lock` cannot be obtained.
@@ -76,6 +100,7 @@ impl file::Store {
             packed_transaction: None,
             updates: None,
             packed_refs: PackedRefs::default(),
+            hook: None,
         }
     }
 }
@@ -86,8 +111,30 @@ impl<'s> Transaction<'s> {
         self.packed_refs = packed_refs;
         self
     }
+
+    /// Call `hook` once all references are locked, after the transaction was committed, or when the prepared transaction is
+    /// dropped without committing it or rejected by the hook itself. Use [`hook::executable()`] to run the `reference-transaction` hook like git does.
+    pub fn hook(mut self, hook: Box<HookFn>) -> Self {
+        self.hook = Some(hook);
+        self
+    }
+}
+
+impl<'s> Drop for Transaction<'s> {
+    fn drop(&mut self) {
+        if let (Some(hook), Some(updates)) = (self.hook.as_mut(), self.updates.take()) {
+            // Release all locks before telling the hook, as git does.
+            self.packed_transaction.take();
+            let edits: Vec<_> = updates.into_iter().map(|edit| edit.update).collect();
+            // The transaction is rolled back no matter what the hook has to say.
+            hook(hook::State::Aborted, &edits).ok();
+        }
+    }
 }
 
+///
+pub mod hook;
+
 ///
 pub mod prepare;
 
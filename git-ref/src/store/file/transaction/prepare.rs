@@ -1,3 +1,5 @@
+use git_object::bstr::{BStr, BString};
+
 use crate::{
     packed,
     store_impl::{
@@ -7,8 +9,12 @@ use crate::{
             transaction::{Edit, PackedRefs},
             Transaction,
         },
+        prepare::{
+            check_and_update_expected, is_quarantined, propagate_leaf_referent_previous_oid, run_prepared_hook,
+            Mismatch,
+        },
     },
-    transaction::{Change, LogChange, RefEdit, RefEditsExt, RefLog},
+    transaction::{Change, LogChange, PreviousValue, RefEdit, RefEditsExt, RefLog},
     Reference, Target,
 };
 
@@ -50,115 +56,51 @@ impl<'s> Transaction<'s> {
                 (None, None) => Ok(None),
                 (maybe_loose, _) => Ok(maybe_loose),
             });
-        let lock = match &mut change.update.change {
-            Change::Delete { expected, .. } => {
+        let lock = match change.update.change {
+            Change::Delete { .. } => {
                 let lock = git_lock::Marker::acquire_to_hold_resource(
                     store.reference_path(&relative_path),
                     lock_fail_mode,
-                    Some(store.base.to_owned()),
+                    Some(store.to_base_dir_and_relative_name(&relative_path).0.into_owned()),
                 )
                 .map_err(|err| Error::LockAcquire {
                     err,
                     full_name: "borrowchk wont allow change.name()".into(),
                 })?;
-                let existing_ref = existing_ref?;
-                match (&expected, &existing_ref) {
-                    (PreviousValue::MustNotExist, _) => {
-                        panic!("BUG: MustNotExist constraint makes no sense if references are to be deleted")
-                    }
-                    (PreviousValue::ExistingMustMatch(_), None)
-                    | (PreviousValue::MustExist, Some(_))
-                    | (PreviousValue::Any, None | Some(_)) => {}
-                    (PreviousValue::MustExist | PreviousValue::MustExistAndMatch(_), None) => {
-                        return Err(Error::DeleteReferenceMustExist {
-                            full_name: change.name(),
-                        })
-                    }
-                    (
-                        PreviousValue::MustExistAndMatch(previous) | PreviousValue::ExistingMustMatch(previous),
-                        Some(existing),
-                    ) => {
-                        let actual = existing.target.clone();
-                        if *previous != actual {
-                            let expected = previous.clone();
-                            return Err(Error::ReferenceOutOfDate {
-                                full_name: change.name(),
-                                expected,
-                                actual,
-                            });
-                        }
-                    }
-                }
-
-                // Keep the previous value for the caller and ourselves. Maybe they want to keep a log of sorts.
-                if let Some(existing) = existing_ref {
-                    *expected = PreviousValue::MustExistAndMatch(existing.target);
-                }
-
+                check_and_update_expected(
+                    &mut change.update.change,
+                    existing_ref?.map(|r| r.target),
+                    change.relocated_from.is_some(),
+                )
+                .map_err(|mismatch| to_error(mismatch, change.name()))?;
                 lock
             }
-            Change::Update { expected, new, .. } => {
+            Change::Rename { .. } | Change::Copy { .. } => {
+                unreachable!("BUG: renames and copies are split during pre-processing")
+            }
+            Change::Update { .. } => {
                 let mut lock = git_lock::File::acquire_to_update_resource(
                     store.reference_path(&relative_path),
                     lock_fail_mode,
-                    Some(store.base.to_owned()),
+                    Some(store.to_base_dir_and_relative_name(&relative_path).0.into_owned()),
                 )
                 .map_err(|err| Error::LockAcquire {
                     err,
                     full_name: "borrowchk wont allow change.name() and this will be corrected by caller".into(),
                 })?;
+                check_and_update_expected(
+                    &mut change.update.change,
+                    existing_ref?.map(|r| r.target),
+                    change.relocated_from.is_some(),
+                )
+                .map_err(|mismatch| to_error(mismatch, change.name()))?;
 
-                let existing_ref = existing_ref?;
-                match (&expected, &existing_ref) {
-                    (PreviousValue::Any, _)
-                    | (PreviousValue::MustExist, Some(_))
-                    | (PreviousValue::MustNotExist | PreviousValue::ExistingMustMatch(_), None) => {}
-                    (PreviousValue::MustExist, None) => {
-                        let expected = Target::Peeled(git_hash::ObjectId::null_sha1());
-                        let full_name = change.name();
-                        return Err(Error::MustExist { full_name, expected });
-                    }
-                    (PreviousValue::MustNotExist, Some(existing)) => {
-                        if existing.target != *new {
-                            let new = new.clone();
-                            return Err(Error::MustNotExist {
-                                full_name: change.name(),
-                                actual: existing.target.clone(),
-                                new,
-                            });
-                        }
-                    }
-                    (
-                        PreviousValue::MustExistAndMatch(previous) | PreviousValue::ExistingMustMatch(previous),
-                        Some(existing),
-                    ) => {
-                        if *previous != existing.target {
-                            let actual = existing.target.clone();
-                            let expected = previous.to_owned();
-                            let full_name = change.name();
-                            return Err(Error::ReferenceOutOfDate {
-                                full_name,
-                                actual,
-                                expected,
-                            });
-                        }
-                    }
-
-                    (PreviousValue::MustExistAndMatch(previous), None) => {
-                        let expected = previous.to_owned();
-                        let full_name = change.name();
-                        return Err(Error::MustExist { full_name, expected });
-                    }
-                };
-
-                if let Some(existing) = existing_ref {
-                    *expected = PreviousValue::MustExistAndMatch(existing.target);
-                };
-
-                lock.with_mut(|file| match new {
-                    Target::Peeled(oid) => write!(file, "{}", oid),
-                    Target::Symbolic(name) => write!(file, "ref: {}", name.0),
-                })?;
+                if let Change::Update { new, .. } = &change.update.change {
+                    lock.with_mut(|file| match new {
+                        Target::Peeled(oid) => write!(file, "{}", oid),
+                        Target::Symbolic(name) => write!(file, "ref: {}", name.0),
+                    })?;
+                }
 
                 lock.close()?
             }
@@ -180,7 +122,17 @@ impl<'s> Transaction<'s> {
         lock_fail_mode: git_lock::acquire::Fail,
     ) -> Result<Self, Error> {
         assert!(self.updates.is_none(), "BUG: Must not call prepare(…) multiple times");
+        if is_quarantined() {
+            return Err(Error::Quarantined);
+        }
         let store = self.store;
+        let initial = matches!(self.packed_refs, PackedRefs::InitialTransaction(_));
+        if initial
+            && (store.packed_refs_path().is_file()
+                || store.loose_iter().map_or(false, |mut refs| refs.next().is_some()))
+        {
+            return Err(Error::InitialTransactionOnNonEmptyStore);
+        }
         let mut updates: Vec<_> = edits
             .into_iter()
             .map(|update| Edit {
@@ -188,14 +140,23 @@ impl<'s> Transaction<'s> {
                 lock: None,
                 parent_index: None,
                 leaf_referent_previous_oid: None,
+                relocated_from: None,
             })
             .collect();
+        // Only renamed or copied references may be packed, symbolic references never are.
+        let packed = if updates
+            .iter()
+            .any(|e| matches!(e.update.change, Change::Rename { .. } | Change::Copy { .. }))
+        {
+            store.assure_packed_refs_uptodate()?
+        } else {
+            None
+        };
         updates
-            .pre_process(
+            .pre_process_with_relocations(
                 |name| {
-                    let symbolic_refs_are_never_packed = None;
                     store
-                        .find_existing_inner(name, symbolic_refs_are_never_packed)
+                        .find_existing_inner(name, packed.as_deref())
                         .map(|r| r.target)
                         .ok()
                 },
@@ -204,13 +165,34 @@ impl<'s> Transaction<'s> {
                     lock: None,
                     parent_index: Some(idx),
                     leaf_referent_previous_oid: None,
+                    relocated_from: None,
+                },
+                |source, update| Edit {
+                    update,
+                    lock: None,
+                    parent_index: None,
+                    leaf_referent_previous_oid: None,
+                    relocated_from: Some(source),
                 },
             )
             .map_err(Error::PreprocessingFailed)?;
+        // Loose references and their reflogs can't be files and directories at the same time.
+        if let Some((source, edit)) = updates.iter().find_map(|edit| {
+            edit.relocated_from
+                .as_ref()
+                .filter(|source| is_parent_or_child(source.as_bstr(), edit.update.name.as_bstr()))
+                .map(|source| (source, edit))
+        }) {
+            return Err(Error::RelocationToParentOrChild {
+                full_name: source.as_bstr().to_owned(),
+                new_name: edit.name(),
+            });
+        }
 
         let mut maybe_updates_for_packed_refs = match self.packed_refs {
             PackedRefs::DeletionsAndNonSymbolicUpdates(_)
-            | PackedRefs::DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(_) => Some(0_usize),
+            | PackedRefs::DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(_)
+            | PackedRefs::InitialTransaction(_) => Some(0_usize),
             PackedRefs::DeletionsOnly => None,
         };
         if maybe_updates_for_packed_refs.is_some() || self.store.packed_refs_path().is_file() {
@@ -223,6 +205,9 @@ impl<'s> Transaction<'s> {
                         ..
                     } => mode,
                     Change::Delete { log, .. } => log,
+                    Change::Rename { .. } | Change::Copy { .. } => {
+                        unreachable!("BUG: renames and copies are split during pre-processing")
+                    }
                 };
                 if log_mode == RefLog::Only {
                     continue;
@@ -280,9 +265,8 @@ impl<'s> Transaction<'s> {
                 if let Some(transaction) = packed_transaction {
                     self.packed_transaction = Some(match &mut self.packed_refs {
                         PackedRefs::DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(f)
-                        | PackedRefs::DeletionsAndNonSymbolicUpdates(f) => {
-                            transaction.prepare(edits_for_packed_transaction, f)?
-                        }
+                        | PackedRefs::DeletionsAndNonSymbolicUpdates(f)
+                        | PackedRefs::InitialTransaction(f) => transaction.prepare(edits_for_packed_transaction, f)?,
                         PackedRefs::DeletionsOnly => transaction
                             .prepare(edits_for_packed_transaction, &mut |_, _| {
                                 unreachable!("BUG: deletions never trigger object lookups")
@@ -294,6 +278,37 @@ impl<'s> Transaction<'s> {
 
         for cid in 0..updates.len() {
             let change = &mut updates[cid];
+            if initial {
+                if let Change::Update {
+                    log:
+                        LogChange {
+                            mode: RefLog::AndReference,
+                            ..
+                        },
+                    new: Target::Peeled(_),
+                    expected,
+                } = &change.update.change
+                {
+                    // These only go into the packed-refs file, and there is nothing they could be compared to.
+                    match expected {
+                        PreviousValue::MustExist => {
+                            return Err(Error::ReferenceMustExist {
+                                full_name: change.name(),
+                            })
+                        }
+                        PreviousValue::MustExistAndMatch(previous) => {
+                            let expected = previous.to_owned();
+                            return Err(Error::MustExist {
+                                full_name: change.name(),
+                                expected,
+                            });
+                        }
+                        PreviousValue::Any | PreviousValue::MustNotExist | PreviousValue::ExistingMustMatch(_) => {
+                            continue
+                        }
+                    }
+                }
+            }
             if let Err(err) = Self::lock_ref_and_apply_change(
                 self.store,
                 lock_fail_mode,
@@ -322,18 +337,18 @@ impl<'s> Transaction<'s> {
                 return Err(err);
             };
 
-            // traverse parent chain from leaf/peeled ref and set the leaf previous oid accordingly
-            // to help with their reflog entries
-            if let (Some(crate::TargetRef::Peeled(oid)), Some(parent_idx)) =
-                (change.update.change.previous_value(), change.parent_index)
-            {
-                let oid = oid.to_owned();
-                let mut parent_idx_cursor = Some(parent_idx);
-                while let Some(parent) = parent_idx_cursor.take().map(|idx| &mut updates[idx]) {
-                    parent_idx_cursor = parent.parent_index;
-                    parent.leaf_referent_previous_oid = Some(oid);
+            propagate_leaf_referent_previous_oid(&mut updates, cid);
+        }
+        let packed_transaction = &mut self.packed_transaction;
+        if let Some(hook) = self.hook.as_mut() {
+            let edits: Vec<_> = updates.iter().map(|edit| edit.update.clone()).collect();
+            run_prepared_hook(hook, &edits, || {
+                for edit in updates.iter_mut() {
+                    edit.lock.take();
                 }
-            }
+                packed_transaction.take();
+            })
+            .map_err(Error::HookRejected)?;
         }
         self.updates = Some(updates);
         Ok(self)
@@ -392,12 +407,28 @@ mod error {
             MustNotExist { full_name: BString, actual: Target, new: Target } {
                 display("Reference '{}' was not supposed to exist when writing it with value {}, but actual content was {}", full_name, new, actual)
             }
+            ReferenceMustExist { full_name: BString } {
+                display("Reference '{}' was supposed to exist, but didn't.", full_name)
+            }
             MustExist { full_name: BString, expected: Target } {
                 display("Reference '{}' was supposed to exist with value {}, but didn't.", full_name, expected)
             }
             ReferenceOutOfDate { full_name: BString, expected: Target, actual: Target } {
                 display("The reference '{}' should have content {}, actual content was {}", full_name, expected, actual)
             }
+            Quarantined {
+                display("Reference updates are forbidden inside the quarantine environment of GIT_QUARANTINE_PATH")
+            }
+            HookRejected(err: Box<dyn std::error::Error + Send + Sync + 'static>) {
+                display("The reference-transaction hook rejected the transaction in its 'prepared' state")
+                source(&**err)
+            }
+            RelocationToParentOrChild { full_name: BString, new_name: BString } {
+                display("Reference '{}' cannot be renamed or copied to '{}' as one would have to be a directory containing the other", full_name, new_name)
+            }
+            InitialTransactionOnNonEmptyStore {
+                display("Initial transactions can only be used on stores without any references")
+            }
             ReferenceDecode(err: file::loose::reference::decode::Error) {
                 display("Could not read reference")
                 from()
@@ -409,4 +440,21 @@ mod error {
 
 pub use error::Error;
 
-use crate::transaction::PreviousValue;
+fn is_parent_or_child(lhs: &BStr, rhs: &BStr) -> bool {
+    let (shorter, longer) = if lhs.len() < rhs.len() { (lhs, rhs) } else { (rhs, lhs) };
+    longer.starts_with(shorter) && longer.get(shorter.len()) == Some(&b'/')
+}
+
+fn to_error(mismatch: Mismatch, full_name: BString) -> Error {
+    match mismatch {
+        Mismatch::DeleteReferenceMustExist => Error::DeleteReferenceMustExist { full_name },
+        Mismatch::MustNotExist { actual, new } => Error::MustNotExist { full_name, actual, new },
+        Mismatch::ReferenceMustExist => Error::ReferenceMustExist { full_name },
+        Mismatch::MustExist { expected } => Error::MustExist { full_name, expected },
+        Mismatch::ReferenceOutOfDate { expected, actual } => Error::ReferenceOutOfDate {
+            full_name,
+            expected,
+            actual,
+        },
+    }
+}
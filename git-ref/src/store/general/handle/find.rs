@@ -17,6 +17,11 @@ mod error {
                 from()
                 source(err)
             }
+            Reftable(err: crate::reftable::find::Error) {
+                display("An error occurred while finding a reference in the reftable database")
+                from()
+                source(err)
+            }
             RefnameValidation(err: crate::name::Error) {
                 display("The ref name or path is not a valid ref name")
                 from()
@@ -37,18 +42,20 @@ pub use error::Error;
 use crate::store::handle;
 
 impl store::Handle {
-    /// TODO: actually implement this with handling of the packed buffer.
+    /// Find a single reference by the given `partial` name, with `Ok(None)` being returned if it doesn't exist.
+    ///
+    /// The lookup rules are the same as in [`file::Store::try_find()`][crate::file::Store::try_find()], independently of
+    /// the backend used to store references.
     pub fn try_find<'a, Name, E>(&self, partial: Name) -> Result<Option<Reference>, Error>
     where
         Name: TryInto<PartialNameRef<'a>, Error = E>,
         Error: From<E>,
     {
-        let _name = partial.try_into()?;
-        match &self.state {
-            handle::State::Loose { store: _, .. } => {
-                todo!()
-            }
-        }
+        let name = partial.try_into()?;
+        Ok(match &self.state {
+            handle::State::Loose { store } => store.try_find(name)?,
+            handle::State::Reftable { store } => store.try_find(name)?,
+        })
     }
 }
 
@@ -59,7 +66,7 @@ mod existing {
         use quick_error::quick_error;
 
         quick_error! {
-            /// The error returned by [file::Store::find_existing()][crate::file::Store::find_existing()].
+            /// The error returned by [`Handle::find()`][crate::store::Handle::find()].
             #[derive(Debug)]
             #[allow(missing_docs)]
             pub enum Error {
@@ -83,18 +90,19 @@ mod existing {
 
     impl store::Handle {
         /// Similar to [`crate::file::Store::find()`] but a non-existing ref is treated as error.
-        pub fn find<'a, Name, E>(&self, _partial: Name) -> Result<Reference, Error>
+        pub fn find<'a, Name, E>(&self, partial: Name) -> Result<Reference, Error>
         where
             Name: TryInto<PartialNameRef<'a>, Error = E>,
             crate::name::Error: From<E>,
         {
-            todo!()
-            // match self.try_find(partial) {}
-            // match self.find_one_with_verified_input(path.to_partial_path().as_ref(), packed) {
-            //     Ok(Some(r)) => Ok(r),
-            //     Ok(None) => Err(Error::NotFound(path.to_partial_path().into_owned())),
-            //     Err(err) => Err(err.into()),
-            // }
+            let path = partial
+                .try_into()
+                .map_err(|err| Error::Find(store::find::Error::RefnameValidation(err.into())))?;
+            match self.try_find(path.clone()) {
+                Ok(Some(r)) => Ok(r),
+                Ok(None) => Err(Error::NotFound(path.to_partial_path().into_owned())),
+                Err(err) => Err(err.into()),
+            }
         }
     }
 }
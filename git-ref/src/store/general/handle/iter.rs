@@ -0,0 +1,101 @@
+use std::path::Path;
+
+use crate::{file, reftable, store, store::handle, Reference};
+
+/// An intermediate structure to hold shared state alive long enough for iteration to happen, independently of the backend
+/// used to store references.
+#[must_use = "Iterators should be obtained from this platform"]
+pub enum Platform<'s> {
+    /// A platform for loose and packed references.
+    Loose(file::iter::Platform<'s>),
+    /// A platform for references in a stack of reftables.
+    Reftable(reftable::iter::Platform<'s>),
+}
+
+/// An iterator over references sorted by their name, as obtained by a [`Platform`].
+#[allow(clippy::large_enum_variant)]
+pub enum Iter<'p> {
+    /// Iterate loose references, then packed ones.
+    Loose(file::iter::LooseThenPacked<'p, 'p>),
+    /// Iterate the merged view of all reftables.
+    Reftable(reftable::iter::Iter<'p>),
+}
+
+impl<'p> Iterator for Iter<'p> {
+    type Item = Result<Reference, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Loose(iter) => iter.next().map(|res| res.map_err(Into::into)),
+            Iter::Reftable(iter) => iter.next().map(|res| res.map_err(Into::into)),
+        }
+    }
+}
+
+impl<'s> Platform<'s> {
+    /// Return an iterator over all references, sorted by their name.
+    pub fn all(&self) -> Result<Iter<'_>, Error> {
+        Ok(match self {
+            Platform::Loose(platform) => Iter::Loose(platform.all()?),
+            Platform::Reftable(platform) => Iter::Reftable(platform.all()?),
+        })
+    }
+
+    /// As [`all()`][Platform::all()], but filters by `prefix`, i.e. "refs/heads".
+    pub fn prefixed(&self, prefix: impl AsRef<Path>) -> Result<Iter<'_>, Error> {
+        Ok(match self {
+            Platform::Loose(platform) => Iter::Loose(platform.prefixed(prefix)?),
+            Platform::Reftable(platform) => Iter::Reftable(platform.prefixed(file::path_to_name(prefix.as_ref()))?),
+        })
+    }
+}
+
+impl store::Handle {
+    /// Return a platform to obtain iterators over all references, or prefixed ones, sorted by their name.
+    pub fn iter(&self) -> Result<Platform<'_>, Error> {
+        Ok(match &self.state {
+            handle::State::Loose { store } => Platform::Loose(store.iter()?),
+            handle::State::Reftable { store } => Platform::Reftable(store.iter()?),
+        })
+    }
+}
+
+mod error {
+    use quick_error::quick_error;
+
+    use crate::{file, packed, reftable};
+
+    quick_error! {
+        /// The error returned by [`Handle::iter()`][crate::store::Handle::iter()] and the iterators it produces.
+        #[derive(Debug)]
+        #[allow(missing_docs)]
+        pub enum Error {
+            PackedOpen(err: packed::buffer::open::Error) {
+                display("The packed-refs file could not be opened")
+                from()
+                source(err)
+            }
+            Io(err: std::io::Error) {
+                display("The loose reference directory could not be traversed")
+                from()
+                source(err)
+            }
+            Loose(err: file::iter::loose_then_packed::Error) {
+                display("A loose or packed reference could not be read")
+                from()
+                source(err)
+            }
+            Stack(err: reftable::stack::Error) {
+                display("The stack of tables could not be loaded")
+                from()
+                source(err)
+            }
+            Reftable(err: reftable::decode::Error) {
+                display("A table could not be read")
+                from()
+                source(err)
+            }
+        }
+    }
+}
+pub use error::Error;
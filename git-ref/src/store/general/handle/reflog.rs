@@ -0,0 +1,61 @@
+use std::convert::{Infallible, TryInto};
+
+use crate::{log, store, store::handle, FullNameRef};
+
+impl store::Handle {
+    /// Return all reflog entries of the reference `name`, oldest first, or `None` if it has no reflog.
+    pub fn reflog<'a, Name, E>(&self, name: Name) -> Result<Option<Vec<log::Line>>, Error>
+    where
+        Name: TryInto<FullNameRef<'a>, Error = E>,
+        crate::name::Error: From<E>,
+    {
+        let name: FullNameRef<'_> = name.try_into().map_err(|err| Error::RefnameValidation(err.into()))?;
+        match &self.state {
+            handle::State::Loose { store } => {
+                let mut buf = Vec::new();
+                match store.reflog_iter::<_, Infallible>(name, &mut buf)? {
+                    Some(iter) => Ok(Some(
+                        iter.map(|line| line.map(Into::into)).collect::<Result<Vec<_>, _>>()?,
+                    )),
+                    None => Ok(None),
+                }
+            }
+            handle::State::Reftable { store } => Ok(store.reflog::<_, Infallible>(name)?),
+        }
+    }
+}
+
+mod error {
+    use quick_error::quick_error;
+
+    use crate::{file, reftable};
+
+    quick_error! {
+        /// The error returned by [`Handle::reflog()`][crate::store::Handle::reflog()].
+        #[derive(Debug)]
+        #[allow(missing_docs)]
+        pub enum Error {
+            RefnameValidation(err: crate::name::Error) {
+                display("The reflog name or path is not a valid ref name")
+                from()
+                source(err)
+            }
+            Loose(err: file::log::Error) {
+                display("The reflog file could not be read")
+                from()
+                source(err)
+            }
+            LooseDecode(err: file::log::iter::decode::Error) {
+                display("A line in the reflog file could not be parsed")
+                from()
+                source(err)
+            }
+            Reftable(err: reftable::reflog::Error) {
+                display("The reflog could not be read from the stack of tables")
+                from()
+                source(err)
+            }
+        }
+    }
+}
+pub use error::Error;
@@ -0,0 +1,117 @@
+use crate::{file, reftable, store, store::handle};
+
+/// A transaction on a [`Handle`][store::Handle], applying [edits][crate::transaction::RefEdit] to whichever backend the store uses.
+pub enum Transaction<'s> {
+    /// A transaction on loose and packed references.
+    Loose(file::Transaction<'s>),
+    /// A transaction writing a new table onto the stack of reftables.
+    Reftable(reftable::Transaction<'s>),
+}
+
+impl store::Handle {
+    /// Open a transaction to apply edits to references.
+    ///
+    /// The transaction inherits the namespace of this handle.
+    pub fn transaction(&self) -> Transaction<'_> {
+        match &self.state {
+            handle::State::Loose { store } => Transaction::Loose(store.transaction()),
+            handle::State::Reftable { store } => Transaction::Reftable(store.transaction()),
+        }
+    }
+}
+
+impl<'s> Transaction<'s> {
+    /// Call `hook` once all references are locked, after the transaction was committed, or when the prepared transaction is
+    /// dropped without committing it or rejected by the hook itself, no matter which backend is used.
+    pub fn hook(self, hook: Box<file::transaction::HookFn>) -> Self {
+        match self {
+            Transaction::Loose(t) => Transaction::Loose(t.hook(hook)),
+            Transaction::Reftable(t) => Transaction::Reftable(t.hook(hook)),
+        }
+    }
+}
+
+///
+pub mod prepare {
+    use crate::{store::transaction::Transaction, transaction::RefEdit};
+
+    impl<'s> Transaction<'s> {
+        /// Prepare for calling [`commit(…)`][Transaction::commit()] by locking all affected references and checking the
+        /// preconditions of `edits`, failing according to `lock_fail_mode` if a lock can't be obtained.
+        pub fn prepare(
+            self,
+            edits: impl IntoIterator<Item = RefEdit>,
+            lock_fail_mode: git_lock::acquire::Fail,
+        ) -> Result<Self, Error> {
+            Ok(match self {
+                Transaction::Loose(t) => Transaction::Loose(t.prepare(edits, lock_fail_mode)?),
+                Transaction::Reftable(t) => Transaction::Reftable(t.prepare(edits, lock_fail_mode)?),
+            })
+        }
+    }
+
+    mod error {
+        use quick_error::quick_error;
+
+        use crate::{file, reftable};
+
+        quick_error! {
+            /// The error returned by [`Transaction::prepare()`][crate::store::transaction::Transaction::prepare()].
+            #[derive(Debug)]
+            #[allow(missing_docs)]
+            pub enum Error {
+                Loose(err: file::transaction::prepare::Error) {
+                    display("The transaction on loose references could not be prepared")
+                    from()
+                    source(err)
+                }
+                Reftable(err: reftable::transaction::prepare::Error) {
+                    display("The transaction on the reftable stack could not be prepared")
+                    from()
+                    source(err)
+                }
+            }
+        }
+    }
+    pub use error::Error;
+}
+
+///
+pub mod commit {
+    use crate::{store::transaction::Transaction, transaction::RefEdit};
+
+    impl<'s> Transaction<'s> {
+        /// Make all [prepared][Transaction::prepare()] edits permanent and return them with their previous values filled in.
+        pub fn commit(self, committer: &git_actor::Signature) -> Result<Vec<RefEdit>, Error> {
+            Ok(match self {
+                Transaction::Loose(t) => t.commit(committer)?,
+                Transaction::Reftable(t) => t.commit(committer)?,
+            })
+        }
+    }
+
+    mod error {
+        use quick_error::quick_error;
+
+        use crate::{file, reftable};
+
+        quick_error! {
+            /// The error returned by [`Transaction::commit()`][crate::store::transaction::Transaction::commit()].
+            #[derive(Debug)]
+            #[allow(missing_docs)]
+            pub enum Error {
+                Loose(err: file::transaction::commit::Error) {
+                    display("The transaction on loose references could not be committed")
+                    from()
+                    source(err)
+                }
+                Reftable(err: reftable::transaction::commit::Error) {
+                    display("The transaction on the reftable stack could not be committed")
+                    from()
+                    source(err)
+                }
+            }
+        }
+    }
+    pub use error::Error;
+}
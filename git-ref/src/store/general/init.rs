@@ -1,8 +1,12 @@
-use std::path::PathBuf;
+use std::{borrow::Cow, path::PathBuf};
+
+use git_config::values::Integer;
+use git_object::bstr::ByteSlice;
 
 use crate::store::WriteReflog;
 
 mod error {
+    use git_object::bstr::BString;
     use quick_error::quick_error;
 
     quick_error! {
@@ -15,24 +19,86 @@ mod error {
                 from()
                 source(err)
             }
+            Config(err: git_config::parser::ParserOrIoError<'static>) {
+                display("The repository configuration could not be read")
+                from()
+                source(err)
+            }
+            UnsupportedRefStorage { name: BString } {
+                display("The reference storage format '{}' configured in 'extensions.refStorage' is not supported", name)
+            }
+            RefStorageInVersion0 {
+                display("'extensions.refStorage' is only valid if 'core.repositoryformatversion' is 1 or higher, but it is 0")
+            }
+            ReftableInLinkedWorktree { git_dir: std::path::PathBuf } {
+                display("The linked worktree at '{}' can't be opened as linked worktrees of reftable repositories aren't supported yet", git_dir.display())
+            }
+            InvalidCommonDir { content: BString } {
+                display("The common directory '{}' is not a valid path", content)
+            }
         }
     }
 }
 
 pub use error::Error;
 
-use crate::file;
+use crate::{file, reftable};
 
 impl crate::Store {
     /// Create a new store at the given location, typically the `.git/` directory.
+    ///
+    /// If `extensions.refStorage` in the `config` file of the repository is `reftable`, references are read from and written to a
+    /// [reftable store][reftable::Store]. Otherwise, or if it is `files`, loose and packed references are used.
+    ///
+    /// If `git_dir` belongs to a linked worktree, the configuration is read from the common directory named in its `commondir` file,
+    /// and shared references are stored there as well. Linked worktrees of reftable repositories can't be opened yet.
     pub fn at(git_dir: impl Into<PathBuf>, reflog_mode: WriteReflog) -> Result<Self, Error> {
-        // for now, just try to read the directory - later we will do that naturally as we have to figure out if it's a ref-table or not.
         let git_dir = git_dir.into();
         std::fs::read_dir(&git_dir)?;
-        Ok(crate::Store {
-            inner: crate::store::State::Loose {
-                store: file::Store::at(git_dir, reflog_mode),
+        let common_dir = match std::fs::read(git_dir.join("commondir")) {
+            Ok(content) => {
+                let path = content.trim_end();
+                let path = path
+                    .to_path()
+                    .map_err(|_| Error::InvalidCommonDir { content: path.into() })?;
+                Some(git_dir.join(path))
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        let config_path = common_dir.as_ref().unwrap_or(&git_dir).join("config");
+        let config = if config_path.is_file() {
+            Some(git_config::file::GitConfig::open(&config_path)?)
+        } else {
+            None
+        };
+        let ref_storage = config
+            .as_ref()
+            .and_then(|config| config.value::<Cow<'_, [u8]>>("extensions", None, "refStorage").ok());
+        if ref_storage.is_some() {
+            let format_version = config
+                .as_ref()
+                .and_then(|config| config.value::<Integer>("core", None, "repositoryformatversion").ok())
+                .map_or(0, |version| version.value);
+            if format_version == 0 {
+                return Err(Error::RefStorageInVersion0);
+            }
+        }
+        let inner = match ref_storage.as_deref() {
+            None | Some(b"files") => crate::store::State::Loose {
+                store: match common_dir {
+                    Some(common_dir) => file::Store::for_linked_worktree(git_dir, common_dir, reflog_mode),
+                    None => file::Store::at(git_dir, reflog_mode),
+                },
+            },
+            Some(b"reftable") => match common_dir {
+                Some(_) => return Err(Error::ReftableInLinkedWorktree { git_dir }),
+                None => crate::store::State::Reftable {
+                    store: reftable::Store::at(git_dir, reflog_mode),
+                },
             },
-        })
+            Some(name) => return Err(Error::UnsupportedRefStorage { name: name.into() }),
+        };
+        Ok(crate::Store { inner })
     }
 }
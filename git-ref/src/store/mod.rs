@@ -3,3 +3,8 @@ pub mod file;
 
 ///
 pub mod packed;
+
+///
+pub mod reftable;
+
+pub(crate) mod prepare;
@@ -125,7 +125,9 @@ impl packed::Transaction {
         edits.sort_by(|l, r| l.inner.name.as_bstr().cmp(r.inner.name.as_bstr()));
         let mut peekable_sorted_edits = edits.iter().peekable();
 
-        file.with_mut(|f| f.write_all(HEADER_LINE))?;
+        // Buffer writes as there may be a lot of small lines, like after initial transactions creating many references.
+        let mut out = std::io::BufWriter::new(&mut file);
+        out.write_all(HEADER_LINE)?;
 
         let mut num_written_lines = 0;
         loop {
@@ -140,7 +142,7 @@ impl packed::Transaction {
                 (Some(Ok(_)), None) => {
                     let pref = refs_sorted.next().expect("next").expect("no err");
                     num_written_lines += 1;
-                    file.with_mut(|out| write_packed_ref(out, pref))?;
+                    write_packed_ref(&mut out, pref)?;
                 }
                 (Some(Ok(pref)), Some(edit)) => {
                     use std::cmp::Ordering::*;
@@ -148,26 +150,29 @@ impl packed::Transaction {
                         Less => {
                             let pref = refs_sorted.next().expect("next").expect("valid");
                             num_written_lines += 1;
-                            file.with_mut(|out| write_packed_ref(out, pref))?;
+                            write_packed_ref(&mut out, pref)?;
                         }
                         Greater => {
                             let edit = peekable_sorted_edits.next().expect("next");
-                            file.with_mut(|out| write_edit(out, edit, &mut num_written_lines))?;
+                            write_edit(&mut out, edit, &mut num_written_lines)?;
                         }
                         Equal => {
                             let _pref = refs_sorted.next().expect("next").expect("valid");
                             let edit = peekable_sorted_edits.next().expect("next");
-                            file.with_mut(|out| write_edit(out, edit, &mut num_written_lines))?;
+                            write_edit(&mut out, edit, &mut num_written_lines)?;
                         }
                     }
                 }
                 (None, Some(_)) => {
                     let edit = peekable_sorted_edits.next().expect("next");
-                    file.with_mut(|out| write_edit(out, edit, &mut num_written_lines))?;
+                    write_edit(&mut out, edit, &mut num_written_lines)?;
                 }
             }
         }
 
+        out.flush()?;
+        drop(out);
+
         if num_written_lines == 0 {
             std::fs::remove_file(file.resource_path())?;
         } else {
@@ -207,6 +212,9 @@ fn write_edit(mut out: impl std::io::Write, edit: &Edit, lines_written: &mut i32
             new: Target::Symbolic(_),
             ..
         } => unreachable!("BUG: packed refs cannot contain symbolic refs, catch that in prepare(…)"),
+        Change::Rename { .. } | Change::Copy { .. } => {
+            unreachable!("BUG: renames and copies are split before reaching packed transactions")
+        }
     }
     Ok(())
 }
@@ -0,0 +1,142 @@
+//! Preparation steps shared by the transactions of all stores.
+use git_hash::ObjectId;
+
+use crate::{
+    store_impl::file::transaction::{hook, HookFn},
+    transaction::{Change, PreviousValue, RefEdit},
+    Target,
+};
+
+/// An edit as held by a transaction while it is prepared.
+pub(crate) trait Edit: std::borrow::BorrowMut<RefEdit> {
+    /// The index of the edit of the symbolic reference this edit was split off from, if any.
+    fn parent_index(&self) -> Option<usize>;
+    /// Set the previous value of the leaf referent to use in the reflog of a symbolic reference.
+    fn set_leaf_referent_previous_oid(&mut self, oid: ObjectId);
+}
+
+/// The ways in which the expectations of an edit don't match the actual state of its reference.
+pub(crate) enum Mismatch {
+    DeleteReferenceMustExist,
+    MustNotExist { actual: Target, new: Target },
+    ReferenceMustExist,
+    MustExist { expected: Target },
+    ReferenceOutOfDate { expected: Target, actual: Target },
+}
+
+/// Verify the expectations of `change` against the `existing` target of its reference and replace them with the
+/// actual previous value, if there is one. `is_relocation` is true if `change` creates the target of a rename or copy,
+/// which must never clobber existing references.
+pub(crate) fn check_and_update_expected(
+    change: &mut Change,
+    existing: Option<Target>,
+    is_relocation: bool,
+) -> Result<(), Mismatch> {
+    match change {
+        Change::Delete { expected, .. } => {
+            match (&expected, &existing) {
+                (PreviousValue::MustNotExist, _) => {
+                    panic!("BUG: MustNotExist constraint makes no sense if references are to be deleted")
+                }
+                (PreviousValue::ExistingMustMatch(_), None)
+                | (PreviousValue::MustExist, Some(_))
+                | (PreviousValue::Any, None | Some(_)) => {}
+                (PreviousValue::MustExist | PreviousValue::MustExistAndMatch(_), None) => {
+                    return Err(Mismatch::DeleteReferenceMustExist)
+                }
+                (
+                    PreviousValue::MustExistAndMatch(previous) | PreviousValue::ExistingMustMatch(previous),
+                    Some(actual),
+                ) => {
+                    if previous != actual {
+                        return Err(Mismatch::ReferenceOutOfDate {
+                            expected: previous.clone(),
+                            actual: actual.clone(),
+                        });
+                    }
+                }
+            }
+            // Keep the previous value for the caller and ourselves. Maybe they want to keep a log of sorts.
+            if let Some(existing) = existing {
+                *expected = PreviousValue::MustExistAndMatch(existing);
+            }
+        }
+        Change::Update { expected, new, .. } => {
+            match (&expected, &existing) {
+                (PreviousValue::Any, _)
+                | (PreviousValue::MustExist, Some(_))
+                | (PreviousValue::MustNotExist | PreviousValue::ExistingMustMatch(_), None) => {}
+                (PreviousValue::MustExist, None) => return Err(Mismatch::ReferenceMustExist),
+                (PreviousValue::MustNotExist, Some(actual)) => {
+                    // A reference must never be clobbered by renames or copies, even if it has the same value.
+                    if actual != new || is_relocation {
+                        return Err(Mismatch::MustNotExist {
+                            actual: actual.clone(),
+                            new: new.clone(),
+                        });
+                    }
+                }
+                (
+                    PreviousValue::MustExistAndMatch(previous) | PreviousValue::ExistingMustMatch(previous),
+                    Some(actual),
+                ) => {
+                    if previous != actual {
+                        return Err(Mismatch::ReferenceOutOfDate {
+                            expected: previous.clone(),
+                            actual: actual.clone(),
+                        });
+                    }
+                }
+                (PreviousValue::MustExistAndMatch(previous), None) => {
+                    return Err(Mismatch::MustExist {
+                        expected: previous.clone(),
+                    })
+                }
+            }
+            if let Some(existing) = existing {
+                *expected = PreviousValue::MustExistAndMatch(existing);
+            }
+        }
+        Change::Rename { .. } | Change::Copy { .. } => {
+            unreachable!("BUG: renames and copies are split during pre-processing")
+        }
+    }
+    Ok(())
+}
+
+/// Traverse the parent chain of the split-off edit at `index` to set the previous value of its leaf referent,
+/// which is what the reflogs of symbolic references record.
+pub(crate) fn propagate_leaf_referent_previous_oid(updates: &mut [impl Edit], index: usize) {
+    if let (Some(crate::TargetRef::Peeled(oid)), Some(parent_idx)) = (
+        updates[index].borrow().change.previous_value(),
+        updates[index].parent_index(),
+    ) {
+        let oid = oid.to_owned();
+        let mut parent_idx_cursor = Some(parent_idx);
+        while let Some(parent) = parent_idx_cursor.take().map(|idx| &mut updates[idx]) {
+            parent_idx_cursor = parent.parent_index();
+            parent.set_leaf_referent_previous_oid(oid);
+        }
+    }
+}
+
+/// Return true if reference updates are forbidden as `GIT_QUARANTINE_PATH` is set, which is the case while git runs the
+/// `pre-receive` hook with objects that aren't accepted yet.
+pub(crate) fn is_quarantined() -> bool {
+    std::env::var_os("GIT_QUARANTINE_PATH").is_some()
+}
+
+/// Call `hook` with `edits` once all of their references are locked. If it rejects the transaction, call `release_locks`
+/// before calling `hook` again to tell it about the abort, as git does, and return its error.
+pub(crate) fn run_prepared_hook(
+    hook: &mut HookFn,
+    edits: &[RefEdit],
+    release_locks: impl FnOnce(),
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    if let Err(err) = hook(hook::State::Prepared, edits) {
+        release_locks();
+        hook(hook::State::Aborted, edits).ok();
+        return Err(err);
+    }
+    Ok(())
+}
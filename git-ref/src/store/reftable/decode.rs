@@ -0,0 +1,344 @@
+use std::{borrow::Cow, convert::TryFrom};
+
+use git_hash::ObjectId;
+use git_object::bstr::{BString, ByteSlice};
+
+use crate::{
+    log,
+    store_impl::reftable::{varint, Value},
+    FullName,
+};
+
+pub(crate) const MAGIC: &[u8; 4] = b"REFT";
+pub(crate) const VERSION: u8 = 1;
+pub(crate) const HEADER_LEN: usize = 24;
+pub(crate) const FOOTER_LEN: usize = 68;
+/// The amount of bytes in front of the records of each block, the block type and its 24 bit length.
+pub(crate) const BLOCK_HEADER_LEN: usize = 4;
+
+pub(crate) mod kind {
+    pub const REF: u8 = b'r';
+    pub const OBJ: u8 = b'o';
+    pub const INDEX: u8 = b'i';
+    pub const LOG: u8 = b'g';
+}
+
+mod error {
+    use quick_error::quick_error;
+
+    quick_error! {
+        /// The error returned when decoding the contents of a reftable.
+        #[derive(Debug)]
+        #[allow(missing_docs)]
+        pub enum Error {
+            Corrupt(message: &'static str) {
+                display("The reftable is corrupt: {}", message)
+            }
+            UnsupportedVersion(version: u8) {
+                display("Reftables of version {} are not supported", version)
+            }
+            Checksum { expected: u32, actual: u32 } {
+                display("The footer checksum was {:08x}, but should have been {:08x}", actual, expected)
+            }
+            Inflate(err: git_features::zlib::inflate::Error) {
+                display("A block of reflog entries could not be decompressed")
+                from()
+                source(err)
+            }
+            RefnameValidation(err: git_validate::refname::Error) {
+                display("A reference name in the table is invalid")
+                from()
+                source(err)
+            }
+        }
+    }
+}
+pub use error::Error;
+
+pub(crate) fn be24(data: &[u8]) -> usize {
+    (data[0] as usize) << 16 | (data[1] as usize) << 8 | data[2] as usize
+}
+
+pub(crate) fn be64(data: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&data[..8]);
+    u64::from_be_bytes(buf)
+}
+
+/// A single block of records, with log blocks being decompressed.
+pub(crate) struct Block<'a> {
+    /// The data of the block starting at its beginning in the file, which includes the file header for the first block.
+    pub data: Cow<'a, [u8]>,
+    pub kind: u8,
+    /// The offset at which the first record starts.
+    records_start: usize,
+    /// The offset at which the restart table starts, which is where the records end.
+    restarts_start: usize,
+    restart_count: usize,
+    /// The position of the block following this one in the file.
+    pub next_position: usize,
+}
+
+impl<'a> Block<'a> {
+    /// Parse the block at `position` in `data`, which are all bytes of the table in front of the footer.
+    /// `block_size` is used to skip padding, and `None` is returned if there is no block at `position`.
+    pub fn at(data: &'a [u8], position: usize, block_size: usize) -> Result<Option<Self>, Error> {
+        let header_len = if position == 0 { HEADER_LEN } else { 0 };
+        let start = position + header_len;
+        if start + BLOCK_HEADER_LEN > data.len() || data[start] == 0 {
+            return Ok(None);
+        }
+        let kind = data[start];
+        let len = be24(&data[start + 1..]);
+        let records_start = start + BLOCK_HEADER_LEN - position;
+        let (data, next_position) = if kind == kind::LOG {
+            let mut inflated = Vec::with_capacity(len);
+            inflated.extend_from_slice(&data[position..start + BLOCK_HEADER_LEN]);
+            inflated.resize(len.max(inflated.len()), 0);
+            let mut inflate = git_features::zlib::Inflate::default();
+            let (status, consumed_in, written_out) =
+                inflate.once(&data[start + BLOCK_HEADER_LEN..], &mut inflated[records_start..])?;
+            if status != git_features::zlib::Status::StreamEnd || written_out != len - records_start {
+                return Err(Error::Corrupt(
+                    "log block did not inflate to the size stated in its header",
+                ));
+            }
+            (Cow::Owned(inflated), start + BLOCK_HEADER_LEN + consumed_in)
+        } else {
+            let end = position + len;
+            if end > data.len() || len < records_start {
+                return Err(Error::Corrupt("block length exceeds the table"));
+            }
+            let is_padded = len < block_size && data.get(end) == Some(&0);
+            (
+                Cow::Borrowed(&data[position..end]),
+                if is_padded { position + block_size } else { end },
+            )
+        };
+        if data.len() < records_start + 2 {
+            return Err(Error::Corrupt("block is too small to hold restart points"));
+        }
+        let restart_count = u16::from_be_bytes([data[data.len() - 2], data[data.len() - 1]]) as usize;
+        let restarts_start = (data.len() - 2)
+            .checked_sub(restart_count * 3)
+            .filter(|pos| *pos >= records_start)
+            .ok_or(Error::Corrupt("restart points exceed the block"))?;
+        Ok(Some(Block {
+            data,
+            kind,
+            records_start,
+            restarts_start,
+            restart_count,
+            next_position,
+        }))
+    }
+
+    /// Return a cursor positioned at the first record of the block.
+    pub fn cursor(&self) -> Cursor {
+        Cursor {
+            pos: self.records_start,
+            end: self.restarts_start,
+            key: Vec::new(),
+        }
+    }
+
+    fn restart_offset(&self, idx: usize) -> usize {
+        be24(&self.data[self.restarts_start + idx * 3..])
+    }
+
+    /// Return a cursor positioned such that the next record has a key that is at most `key`, but as close to it as possible
+    /// as determined by a binary search through the restart points.
+    pub fn seek(&self, key: &[u8]) -> Result<Cursor, Error> {
+        let (mut lo, mut hi) = (0, self.restart_count);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let mut cursor = Cursor {
+                pos: self.restart_offset(mid),
+                end: self.restarts_start,
+                key: Vec::new(),
+            };
+            cursor.next_key(&self.data)?;
+            if cursor.key.as_slice() <= key {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        Ok(match lo {
+            0 => self.cursor(),
+            restart => Cursor {
+                pos: self.restart_offset(restart - 1),
+                end: self.restarts_start,
+                key: Vec::new(),
+            },
+        })
+    }
+
+    /// Return the key of the first record in this block.
+    pub fn first_key(&self) -> Result<Vec<u8>, Error> {
+        let mut cursor = self.cursor();
+        cursor.next_key(&self.data)?;
+        Ok(cursor.key)
+    }
+}
+
+/// The position within a block's records, keeping track of the prefix-compressed key of the current record.
+#[derive(Default)]
+pub(crate) struct Cursor {
+    pos: usize,
+    end: usize,
+    /// The key of the record that was last read.
+    pub key: Vec<u8>,
+}
+
+impl Cursor {
+    /// Read the key of the next record into our `key` field and return the type bits stored along with it,
+    /// or `None` if there are no more records in the block.
+    pub fn next_key(&mut self, data: &[u8]) -> Result<Option<u8>, Error> {
+        if self.pos >= self.end {
+            return Ok(None);
+        }
+        let prefix_len = self.varint(data)? as usize;
+        let suffix_len_and_type = self.varint(data)?;
+        let suffix = self.bytes(data, (suffix_len_and_type >> 3) as usize)?;
+        if prefix_len > self.key.len() {
+            return Err(Error::Corrupt("key prefix is longer than the previous key"));
+        }
+        self.key.truncate(prefix_len);
+        self.key.extend_from_slice(suffix);
+        Ok(Some((suffix_len_and_type & 0x7) as u8))
+    }
+
+    pub fn varint(&mut self, data: &[u8]) -> Result<u64, Error> {
+        let (value, consumed) = data
+            .get(self.pos..self.end)
+            .and_then(varint::decode)
+            .ok_or(Error::Corrupt("invalid or truncated varint"))?;
+        self.pos += consumed;
+        Ok(value)
+    }
+
+    pub fn bytes<'a>(&mut self, data: &'a [u8], len: usize) -> Result<&'a [u8], Error> {
+        let bytes = data
+            .get(self.pos..self.pos + len)
+            .filter(|_| self.pos + len <= self.end)
+            .ok_or(Error::Corrupt("record exceeds the block"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn object_id(&mut self, data: &[u8]) -> Result<ObjectId, Error> {
+        Ok(ObjectId::from(self.bytes(data, 20)?))
+    }
+
+    fn string(&mut self, data: &[u8]) -> Result<BString, Error> {
+        let len = self.varint(data)? as usize;
+        Ok(self.bytes(data, len)?.into())
+    }
+
+    /// Decode the value of a reference record with `value_type`, returning its update index and value.
+    pub fn ref_value(&mut self, data: &[u8], value_type: u8, min_update_index: u64) -> Result<(u64, Value), Error> {
+        let update_index = min_update_index
+            .checked_add(self.varint(data)?)
+            .ok_or(Error::Corrupt("update index overflow"))?;
+        let value = match value_type {
+            0 => Value::Deletion,
+            1 => Value::Object {
+                target: self.object_id(data)?,
+                peeled: None,
+            },
+            2 => Value::Object {
+                target: self.object_id(data)?,
+                peeled: Some(self.object_id(data)?),
+            },
+            3 => Value::Symbolic(FullName::try_from(self.string(data)?)?),
+            _ => return Err(Error::Corrupt("unknown reference value type")),
+        };
+        Ok((update_index, value))
+    }
+
+    /// Decode the value of an index record, the position of the block it refers to.
+    pub fn index_value(&mut self, data: &[u8]) -> Result<usize, Error> {
+        Ok(self.varint(data)? as usize)
+    }
+
+    /// Decode the value of an object record with the count stored in the type bits, returning the positions
+    /// of all reference blocks containing the object. These may be empty if the writer chose not to store them.
+    pub fn obj_value(&mut self, data: &[u8], count: u8) -> Result<Vec<usize>, Error> {
+        let count = match count {
+            0 => self.varint(data)? as usize,
+            count => count as usize,
+        };
+        let mut positions = Vec::with_capacity(count.min(1024));
+        let mut position = 0;
+        for idx in 0..count {
+            let delta = self.varint(data)? as usize;
+            position = if idx == 0 { delta } else { position + delta };
+            positions.push(position);
+        }
+        Ok(positions)
+    }
+
+    /// Decode the value of a log record with `value_type`, which is `None` for deletions.
+    pub fn log_value(&mut self, data: &[u8], value_type: u8) -> Result<Option<log::Line>, Error> {
+        Ok(match value_type {
+            0 => None,
+            1 => {
+                let previous_oid = self.object_id(data)?;
+                let new_oid = self.object_id(data)?;
+                let name = self.string(data)?;
+                let email = self.string(data)?;
+                let time = self.varint(data)?;
+                let tz_offset_minutes = {
+                    let bytes = self.bytes(data, 2)?;
+                    i16::from_be_bytes([bytes[0], bytes[1]])
+                };
+                let mut message = self.string(data)?;
+                if message.last() == Some(&b'\n') {
+                    message.pop();
+                }
+                Some(log::Line {
+                    previous_oid,
+                    new_oid,
+                    signature: git_actor::Signature {
+                        name,
+                        email,
+                        time: git_actor::Time {
+                            time: time as u32,
+                            offset: tz_offset_minutes as i32 * 60,
+                            sign: if tz_offset_minutes < 0 {
+                                git_actor::Sign::Minus
+                            } else {
+                                git_actor::Sign::Plus
+                            },
+                        },
+                    },
+                    message,
+                })
+            }
+            _ => return Err(Error::Corrupt("unknown log record type")),
+        })
+    }
+}
+
+/// Split a log record `key` into the reference name and its update index.
+pub(crate) fn log_key(key: &[u8]) -> Result<(&[u8], u64), Error> {
+    if key.len() < 9 || key[key.len() - 9] != 0 {
+        return Err(Error::Corrupt("log record key is malformed"));
+    }
+    let (name, update_index) = key.split_at(key.len() - 9);
+    Ok((name, !be64(&update_index[1..])))
+}
+
+/// Return the key a log record of `name` and `update_index` is stored under.
+pub(crate) fn to_log_key(name: &[u8], update_index: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(name.len() + 9);
+    key.extend_from_slice(name);
+    key.push(0);
+    key.extend_from_slice(&(!update_index).to_be_bytes());
+    key
+}
+
+pub(crate) fn to_full_name(name: &[u8]) -> Result<FullName, Error> {
+    Ok(FullName::try_from(name.as_bstr())?)
+}
@@ -0,0 +1,128 @@
+use std::convert::TryInto;
+
+use git_object::bstr::{BStr, BString, ByteSlice, ByteVec};
+
+use crate::{
+    store_impl::reftable::{decode, Record, Stack, Store, Value},
+    FullNameRef, Namespace, PartialNameRef, Reference, Target,
+};
+
+impl Store {
+    /// Find a single reference by the given partial `name`, following the same lookup rules as
+    /// [`file::Store::try_find()`][crate::file::Store::try_find()].
+    ///
+    /// Returns `Ok(None)` if no such ref exists. The stack of tables is reloaded each time the method is called, use
+    /// [`Store::try_find_in()`] with a [`Stack`] snapshot for more control.
+    pub fn try_find<'a, Name, E>(&self, partial: Name) -> Result<Option<Reference>, Error>
+    where
+        Name: TryInto<PartialNameRef<'a>, Error = E>,
+        Error: From<E>,
+    {
+        let name = partial.try_into()?;
+        let stack = self.stack()?;
+        Ok(self.try_find_in(&stack, name.as_bstr())?)
+    }
+
+    /// Find a single reference by its `partial` name in the given `stack`.
+    pub fn try_find_in(&self, stack: &Stack, partial: &BStr) -> Result<Option<Reference>, decode::Error> {
+        for candidate in candidates(partial) {
+            let name = self.namespaced(candidate);
+            if let Some(record) = stack.try_find_record(FullNameRef(name.as_bstr()))? {
+                return Ok(to_reference(record, self.namespace.as_ref()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Prefix `name` with our namespace, if one is set.
+    pub(crate) fn namespaced(&self, name: impl Into<BString>) -> BString {
+        let name = name.into();
+        match &self.namespace {
+            Some(namespace) => {
+                let mut prefixed = namespace.as_bstr().to_owned();
+                prefixed.push_str(name);
+                prefixed
+            }
+            None => name,
+        }
+    }
+}
+
+/// Turn `record` into a reference with `namespace` stripped, or `None` if it is a deletion.
+pub(crate) fn to_reference(record: Record, namespace: Option<&Namespace>) -> Option<Reference> {
+    let (target, peeled) = match record.value {
+        Value::Object { target, peeled } => (Target::Peeled(target), peeled),
+        Value::Symbolic(name) => (Target::Symbolic(name), None),
+        Value::Deletion => return None,
+    };
+    let mut reference = Reference {
+        name: record.name,
+        target,
+        peeled,
+    };
+    if let Some(namespace) = namespace {
+        reference.strip_namespace(namespace);
+    }
+    Some(reference)
+}
+
+/// The full names to try in order when looking up `partial`, as described in the documentation of `git rev-parse`.
+fn candidates(partial: &BStr) -> Vec<BString> {
+    let mut out = Vec::new();
+    if !partial.is_empty() && partial.iter().all(|b| b.is_ascii_uppercase()) {
+        out.push(partial.to_owned());
+    }
+    if partial == "refs" || partial.starts_with(b"refs/") {
+        out.push(partial.to_owned());
+    } else {
+        for inbetween in &["", "tags/", "heads/", "remotes/"] {
+            let mut name = BString::from("refs/");
+            name.push_str(inbetween);
+            name.push_str(partial);
+            out.push(name);
+        }
+        let mut name = BString::from("refs/remotes/");
+        name.push_str(partial);
+        name.push_str("/HEAD");
+        out.push(name);
+    }
+    out
+}
+
+mod error {
+    use std::convert::Infallible;
+
+    use quick_error::quick_error;
+
+    use crate::store_impl::reftable::{decode, stack};
+
+    quick_error! {
+        /// The error returned by [`Store::try_find()`][crate::reftable::Store::try_find()].
+        #[derive(Debug)]
+        #[allow(missing_docs)]
+        pub enum Error {
+            Stack(err: stack::Error) {
+                display("The stack of tables could not be loaded")
+                from()
+                source(err)
+            }
+            Decode(err: decode::Error) {
+                display("A table could not be read")
+                from()
+                source(err)
+            }
+            RefnameValidation(err: crate::name::Error) {
+                display("The ref name or path is not a valid ref name")
+                from()
+                source(err)
+            }
+        }
+    }
+
+    impl From<Infallible> for Error {
+        fn from(_: Infallible) -> Self {
+            unreachable!("this impl is needed to allow passing a known valid partial path as parameter")
+        }
+    }
+}
+pub use error::Error;
@@ -0,0 +1,65 @@
+use git_object::bstr::ByteSlice;
+
+use crate::{
+    store_impl::reftable::{decode, find::to_reference, stack, Stack, Store},
+    Namespace, Reference,
+};
+
+/// An intermediate structure to hold a snapshot of the stack alive long enough for iteration to happen.
+#[must_use = "Iterators should be obtained from this platform"]
+pub struct Platform<'s> {
+    store: &'s Store,
+    stack: Stack,
+}
+
+/// An iterator over all references of a [`Stack`], sorted by their name.
+pub struct Iter<'s> {
+    inner: stack::Merged<'s>,
+    namespace: Option<&'s Namespace>,
+}
+
+impl<'s> Iterator for Iter<'s> {
+    type Item = Result<Reference, decode::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for record in &mut self.inner {
+            match record {
+                Ok(record) => {
+                    if let Some(reference) = to_reference(record, self.namespace) {
+                        return Some(Ok(reference));
+                    }
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        None
+    }
+}
+
+impl<'s> Platform<'s> {
+    /// Return an iterator over all references, sorted by their name.
+    pub fn all(&self) -> Result<Iter<'_>, decode::Error> {
+        self.prefixed("")
+    }
+
+    /// As [`all()`][Platform::all()], but filters by `prefix`, i.e. "refs/heads/".
+    ///
+    /// Unlike with loose references, the prefix is matched byte by byte, hence "refs/heads" also matches "refs/headsup".
+    pub fn prefixed(&self, prefix: impl AsRef<[u8]>) -> Result<Iter<'_>, decode::Error> {
+        let prefix = self.store.namespaced(prefix.as_ref());
+        Ok(Iter {
+            inner: self.stack.iter_prefixed(prefix.as_bstr())?,
+            namespace: self.store.namespace.as_ref(),
+        })
+    }
+}
+
+impl Store {
+    /// Return a platform to obtain iterators over all references, or prefixed ones, sorted by their name.
+    pub fn iter(&self) -> Result<Platform<'_>, stack::Error> {
+        Ok(Platform {
+            store: self,
+            stack: self.stack()?,
+        })
+    }
+}
@@ -0,0 +1,127 @@
+//! An implementation of the [reftable format][spec] along with a store which keeps a stack of tables in `$GIT_DIR/reftable`.
+//!
+//! Each table is an immutable, sorted file of blocks holding reference records, an optional index for them, blocks mapping
+//! objects back to the blocks of references pointing to them, as well as compressed blocks of reflog entries.
+//! Changes are made by adding a new table to the top of the stack which is listed in `tables.list`, with the most recent table
+//! winning. The stack is compacted automatically to keep the amount of tables logarithmic to the amount of updates.
+//!
+//! [spec]: https://git-scm.com/docs/reftable
+use std::path::PathBuf;
+
+use filebuffer::FileBuffer;
+use git_features::threading::{MutableOnDemand, OwnShared};
+use git_hash::ObjectId;
+
+use crate::{log, store::WriteReflog, FullName, Namespace};
+
+/// The value of a reference [record][Record] as stored in a table.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub enum Value {
+    /// The reference was deleted, shadowing all values in older tables.
+    Deletion,
+    /// The reference points to an object.
+    Object {
+        /// The object the reference points to.
+        target: ObjectId,
+        /// The object `target` peels to if it is an annotated tag.
+        peeled: Option<ObjectId>,
+    },
+    /// The reference points to another reference.
+    Symbolic(FullName),
+}
+
+/// A reference record as stored in a table.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Record {
+    /// The full name of the reference.
+    pub name: FullName,
+    /// The index of the update that produced this record.
+    pub update_index: u64,
+    /// The value of the reference.
+    pub value: Value,
+}
+
+/// A reflog record as stored in a table.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct LogRecord {
+    /// The full name of the reference the log entry belongs to.
+    pub name: FullName,
+    /// The index of the update which produced this log entry, acting as its identity.
+    pub update_index: u64,
+    /// The log entry itself, or `None` if an entry with the same `name` and `update_index` in older tables should be deleted.
+    pub line: Option<log::Line>,
+}
+
+#[derive(Debug)]
+enum Backing {
+    /// The table is loaded entirely into memory.
+    InMemory(Vec<u8>),
+    /// The table is memory-mapped from a file on disk.
+    Mapped(FileBuffer),
+}
+
+/// A single reftable, either loaded from disk or from memory.
+#[derive(Debug)]
+pub struct Table {
+    data: Backing,
+    footer: table::Footer,
+}
+
+/// A store for references using a stack of [tables][Table] as described by `tables.list` in the `reftable` directory.
+#[derive(Debug, Clone)]
+pub struct Store {
+    /// The `.git` directory containing the `reftable` directory.
+    git_dir: PathBuf,
+    /// The way to handle reflog edits
+    pub write_reflog: WriteReflog,
+    /// The namespace to use for edits and reads
+    pub namespace: Option<Namespace>,
+    /// Tables we have opened previously, keyed by their file name. As tables never change once written, they can be reused
+    /// across snapshots of the stack.
+    tables: OwnShared<MutableOnDemand<NamedTables>>,
+}
+
+/// A snapshot of all tables in a [`Store`], ordered from oldest to newest.
+#[derive(Debug, Clone)]
+pub struct Stack {
+    tables: NamedTables,
+}
+
+/// Tables along with their file name in the `reftable` directory.
+type NamedTables = Vec<(String, OwnShared<Table>)>;
+
+/// A transaction on a reftable store, writing all of its edits into a single new table.
+pub struct Transaction<'s> {
+    store: &'s Store,
+    lock: Option<git_lock::File>,
+    stack: Option<Stack>,
+    updates: Option<Vec<transaction::Edit>>,
+    hook: Option<Box<crate::file::transaction::HookFn>>,
+}
+
+mod varint;
+
+///
+pub mod decode;
+
+///
+pub mod table;
+
+///
+pub mod write;
+pub use write::Writer;
+
+///
+pub mod stack;
+
+///
+pub mod find;
+
+///
+pub mod iter;
+
+///
+pub mod reflog;
+
+///
+pub mod transaction;
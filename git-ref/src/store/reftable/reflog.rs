@@ -0,0 +1,73 @@
+use std::convert::TryInto;
+
+use git_object::bstr::ByteSlice;
+
+use crate::{
+    log,
+    store_impl::reftable::{Stack, Store},
+    FullNameRef,
+};
+
+impl Store {
+    /// Returns true if a reflog with at least one entry exists for the given reference `name`.
+    pub fn reflog_exists<'a, Name, E>(&self, name: Name) -> Result<bool, Error>
+    where
+        Name: TryInto<FullNameRef<'a>, Error = E>,
+        crate::name::Error: From<E>,
+    {
+        Ok(self.reflog(name)?.is_some())
+    }
+
+    /// Return all reflog entries of the reference `name`, oldest first, or `None` if it has no reflog.
+    pub fn reflog<'a, Name, E>(&self, name: Name) -> Result<Option<Vec<log::Line>>, Error>
+    where
+        Name: TryInto<FullNameRef<'a>, Error = E>,
+        crate::name::Error: From<E>,
+    {
+        let name: FullNameRef<'_> = name.try_into().map_err(|err| Error::RefnameValidation(err.into()))?;
+        let stack = self.stack()?;
+        self.reflog_in(&stack, name)
+    }
+
+    /// Return all reflog entries of the reference `name` in `stack`, oldest first, or `None` if it has no reflog.
+    pub fn reflog_in(&self, stack: &Stack, name: FullNameRef<'_>) -> Result<Option<Vec<log::Line>>, Error> {
+        let name = self.namespaced(name.as_bstr());
+        let lines: Vec<_> = stack
+            .log_records_of(FullNameRef(name.as_bstr()))?
+            .into_iter()
+            .rev()
+            .filter_map(|record| record.line)
+            .collect();
+        Ok((!lines.is_empty()).then(|| lines))
+    }
+}
+
+mod error {
+    use quick_error::quick_error;
+
+    use crate::store_impl::reftable::{decode, stack};
+
+    quick_error! {
+        /// The error returned by [`Store::reflog()`][crate::reftable::Store::reflog()].
+        #[derive(Debug)]
+        #[allow(missing_docs)]
+        pub enum Error {
+            Stack(err: stack::Error) {
+                display("The stack of tables could not be loaded")
+                from()
+                source(err)
+            }
+            Decode(err: decode::Error) {
+                display("A table could not be read")
+                from()
+                source(err)
+            }
+            RefnameValidation(err: crate::name::Error) {
+                display("The reflog name or path is not a valid ref name")
+                from()
+                source(err)
+            }
+        }
+    }
+}
+pub use error::Error;
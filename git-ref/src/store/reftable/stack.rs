@@ -0,0 +1,450 @@
+use std::{
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use git_features::threading::{get_mut, get_ref, MutableOnDemand, OwnShared};
+use git_object::bstr::{BStr, ByteSlice};
+
+use crate::{
+    store::WriteReflog,
+    store_impl::reftable::{decode, table, write, LogRecord, Record, Stack, Store, Table, Value, Writer},
+    FullNameRef,
+};
+
+/// The name of the file listing all tables of the stack, oldest first.
+pub(crate) const TABLES_LIST: &str = "tables.list";
+/// How often we try to load the stack if tables disappear while we are loading them due to concurrent compactions.
+const MAX_LOAD_ATTEMPTS: usize = 5;
+/// Each table in the stack should be at least this many times larger than the next newer one.
+const GEOMETRIC_FACTOR: u64 = 2;
+
+/// Initialization
+impl Store {
+    /// Create a new store for the reftable stack in `git_dir`, typically the `.git` directory of a repository,
+    /// which handles reflogs according to `write_reflog`.
+    pub fn at(git_dir: impl Into<PathBuf>, write_reflog: WriteReflog) -> Self {
+        Store {
+            git_dir: git_dir.into(),
+            write_reflog,
+            namespace: None,
+            tables: OwnShared::new(MutableOnDemand::new(Vec::new())),
+        }
+    }
+
+    /// Create the `reftable` directory along with an empty `tables.list` file, unless they already exist.
+    pub fn init(&self) -> std::io::Result<()> {
+        std::fs::create_dir_all(self.reftable_dir())?;
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(self.tables_list_path())
+        {
+            Ok(_) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Access
+impl Store {
+    /// The directory containing the `reftable` directory.
+    pub fn git_dir(&self) -> &Path {
+        &self.git_dir
+    }
+
+    /// The directory containing all tables of the stack.
+    pub fn reftable_dir(&self) -> PathBuf {
+        self.git_dir.join("reftable")
+    }
+
+    /// The path to the file listing all tables of the stack.
+    pub fn tables_list_path(&self) -> PathBuf {
+        self.reftable_dir().join(TABLES_LIST)
+    }
+
+    /// Return a snapshot of the current stack of tables, which won't change anymore once obtained.
+    ///
+    /// Tables loaded previously are reused, as they are immutable.
+    pub fn stack(&self) -> Result<Stack, Error> {
+        let mut attempt = 0;
+        'retry: loop {
+            attempt += 1;
+            let names = match std::fs::read(self.tables_list_path()) {
+                Ok(list) => list
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(|line| line.to_str().map(ToOwned::to_owned))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| Error::TableName)?,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                Err(err) => return Err(err.into()),
+            };
+
+            let mut tables = Vec::with_capacity(names.len());
+            {
+                let cache = get_ref(&self.tables);
+                for name in names {
+                    let cached = cache.iter().find(|(cached, _)| *cached == name).map(|(_, t)| t.clone());
+                    let table = match cached {
+                        Some(table) => table,
+                        None => {
+                            let path = self.reftable_dir().join(&name);
+                            match Table::at(&path) {
+                                Ok(table) => OwnShared::new(table),
+                                Err(table::open::Error::Io(err))
+                                    if err.kind() == std::io::ErrorKind::NotFound && attempt < MAX_LOAD_ATTEMPTS =>
+                                {
+                                    continue 'retry;
+                                }
+                                Err(err) => return Err(Error::Open { err, path }),
+                            }
+                        }
+                    };
+                    tables.push((name, table));
+                }
+            }
+            *get_mut(&self.tables) = tables.clone();
+            return Ok(Stack { tables });
+        }
+    }
+}
+
+/// Access
+impl Stack {
+    /// Return all tables, from oldest to newest.
+    pub fn tables(&self) -> impl ExactSizeIterator<Item = &Table> + DoubleEndedIterator {
+        self.tables.iter().map(|(_, table)| &**table)
+    }
+
+    /// The update index of the most recent table, or 0 if there is no table.
+    pub fn max_update_index(&self) -> u64 {
+        self.tables().last().map_or(0, |table| table.max_update_index())
+    }
+
+    /// Return the most recent record of the reference `name`, or `None` if it doesn't exist or was deleted.
+    pub fn try_find_record(&self, name: FullNameRef<'_>) -> Result<Option<Record>, decode::Error> {
+        for table in self.tables().rev() {
+            if let Some(record) = table.try_find(name)? {
+                return Ok(match record.value {
+                    Value::Deletion => None,
+                    _ => Some(record),
+                });
+            }
+        }
+        Ok(None)
+    }
+
+    /// Return the reference records of all tables starting with `prefix`, merged and sorted by name,
+    /// with deletions and shadowed records removed.
+    pub fn iter_prefixed(&self, prefix: &BStr) -> Result<Merged<'_>, decode::Error> {
+        Ok(Merged {
+            iters: self
+                .tables()
+                .map(|table| table.iter_prefixed(prefix).map(Iterator::peekable))
+                .collect::<Result<_, _>>()?,
+            keep_deletions: false,
+        })
+    }
+
+    /// Return all reflog records of `name` from all tables, most recent first, with deleted entries removed.
+    pub fn log_records_of(&self, name: FullNameRef<'_>) -> Result<Vec<LogRecord>, decode::Error> {
+        let mut records = Vec::<LogRecord>::new();
+        for table in self.tables().rev() {
+            for record in table.log_iter_of(name)? {
+                let record = record?;
+                match records.binary_search_by(|r| record.update_index.cmp(&r.update_index)) {
+                    Ok(_) => {} // shadowed by a more recent table
+                    Err(pos) => records.insert(pos, record),
+                }
+            }
+        }
+        records.retain(|r| r.line.is_some());
+        Ok(records)
+    }
+
+    /// Return the file names of all tables, oldest first.
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.tables.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+/// An iterator over the merged reference records of multiple tables, sorted by name.
+pub struct Merged<'s> {
+    /// Iterators of all tables, oldest first.
+    iters: Vec<std::iter::Peekable<table::Iter<'s>>>,
+    keep_deletions: bool,
+}
+
+impl<'s> Iterator for Merged<'s> {
+    type Item = Result<Record, decode::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut newest: Option<(usize, crate::FullName)> = None;
+            for idx in 0..self.iters.len() {
+                match self.iters[idx].peek() {
+                    Some(Err(_)) => return self.iters[idx].next(),
+                    // later tables are more recent and win if names are equal
+                    Some(Ok(record)) if newest.as_ref().map_or(true, |(_, name)| record.name <= *name) => {
+                        newest = Some((idx, record.name.clone()));
+                    }
+                    Some(Ok(_)) | None => {}
+                }
+            }
+            let (newest, name) = newest?;
+            for iter in &mut self.iters[..newest] {
+                if let Some(Ok(shadowed)) = iter.peek() {
+                    if shadowed.name == name {
+                        iter.next();
+                    }
+                }
+            }
+            let record = self.iters[newest].next().expect("peeked").expect("no error");
+            if record.value == Value::Deletion && !self.keep_deletions {
+                continue;
+            }
+            return Some(Ok(record));
+        }
+    }
+}
+
+/// Compaction
+impl Store {
+    /// Merge all tables of the stack into a single one, removing all deletions and shadowed records in the process,
+    /// and fail according to `lock_fail_mode` if the stack is locked.
+    pub fn compact(&self, lock_fail_mode: git_lock::acquire::Fail) -> Result<(), compact::Error> {
+        let lock = git_lock::File::acquire_to_update_resource(self.tables_list_path(), lock_fail_mode, None)?;
+        let stack = self.stack()?;
+        let num_tables = stack.tables.len();
+        self.compact_locked(lock, stack, 0..num_tables)
+    }
+
+    /// Compact the most recent tables to restore the geometric sequence of table sizes, if the stack isn't locked.
+    pub(crate) fn auto_compact(&self) -> Result<(), compact::Error> {
+        let lock = match git_lock::File::acquire_to_update_resource(
+            self.tables_list_path(),
+            git_lock::acquire::Fail::Immediately,
+            None,
+        ) {
+            Ok(lock) => lock,
+            Err(_) => return Ok(()),
+        };
+        let stack = self.stack()?;
+        let sizes = stack
+            .tables
+            .iter()
+            .map(|(name, _)| {
+                std::fs::metadata(self.reftable_dir().join(name))
+                    .map(|m| m.len())
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+        match segment_to_compact(&sizes) {
+            Some(segment) => self.compact_locked(lock, stack, segment),
+            None => Ok(()),
+        }
+    }
+
+    fn compact_locked(
+        &self,
+        mut lock: git_lock::File,
+        stack: Stack,
+        segment: Range<usize>,
+    ) -> Result<(), compact::Error> {
+        if segment.len() < 2 {
+            return Ok(());
+        }
+        let tables = &stack.tables[segment.clone()];
+        let min_update_index = tables[0].1.min_update_index();
+        let max_update_index = tables[tables.len() - 1].1.max_update_index();
+        let keep_deletions = segment.start != 0;
+        let mut writer = Writer::new(min_update_index, max_update_index);
+
+        let merged = Merged {
+            iters: tables
+                .iter()
+                .map(|(_, table)| table.iter().map(Iterator::peekable))
+                .collect::<Result<_, _>>()?,
+            keep_deletions,
+        };
+        for record in merged {
+            writer.add_ref(record?);
+        }
+
+        let mut logs = Vec::<(Vec<u8>, LogRecord)>::new();
+        for (_, table) in tables.iter().rev() {
+            for record in table.log_iter()? {
+                let record = record?;
+                let key = decode::to_log_key(record.name.as_bstr(), record.update_index);
+                match logs.binary_search_by(|(k, _)| k.cmp(&key)) {
+                    Ok(_) => {}
+                    Err(pos) => logs.insert(pos, (key, record)),
+                }
+            }
+        }
+        for (_, record) in logs {
+            if record.line.is_some() || keep_deletions {
+                writer.add_log(record);
+            }
+        }
+
+        let name = self.write_table(writer, min_update_index, max_update_index)?;
+        let names: Vec<_> = stack.names().collect();
+        let mut list = Vec::new();
+        for existing in &names[..segment.start] {
+            writeln!(list, "{}", existing)?;
+        }
+        writeln!(list, "{}", name)?;
+        for existing in &names[segment.end..] {
+            writeln!(list, "{}", existing)?;
+        }
+        lock.with_mut(|out| out.write_all(&list))?;
+        lock.commit().map_err(|err| err.error)?;
+
+        for obsolete in &names[segment] {
+            std::fs::remove_file(self.reftable_dir().join(obsolete)).ok();
+        }
+        Ok(())
+    }
+
+    /// Write the table produced by `writer` into the reftable directory and return its file name.
+    pub(crate) fn write_table(
+        &self,
+        writer: Writer,
+        min_update_index: u64,
+        max_update_index: u64,
+    ) -> Result<String, write::Error> {
+        let name = table_name(min_update_index, max_update_index);
+        let mut lock = git_lock::File::acquire_to_update_resource(
+            self.reftable_dir().join(&name),
+            git_lock::acquire::Fail::Immediately,
+            None,
+        )
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+        let mut res = Ok(());
+        lock.with_mut(|out| {
+            res = writer.write_to(out);
+            Ok(())
+        })?;
+        res?;
+        lock.commit().map_err(|err| err.error)?;
+        Ok(name)
+    }
+}
+
+/// Find the range of the most recent tables that need to be merged to assure each table is at least
+/// [`GEOMETRIC_FACTOR`] times larger than the next newer one.
+fn segment_to_compact(sizes: &[u64]) -> Option<Range<usize>> {
+    let end = (1..sizes.len())
+        .rev()
+        .find(|&idx| sizes[idx - 1] < sizes[idx] * GEOMETRIC_FACTOR)?
+        + 1;
+    let mut start = end - 1;
+    let mut bytes = sizes[start];
+    while start > 0 && sizes[start - 1] < bytes * GEOMETRIC_FACTOR {
+        start -= 1;
+        bytes += sizes[start];
+    }
+    Some(start..end)
+}
+
+/// Like git, use a random suffix to keep table names unique even if they cover the same update indices.
+fn table_name(min_update_index: u64, max_update_index: u64) -> String {
+    format!(
+        "0x{:012x}-0x{:012x}-{:08x}.ref",
+        min_update_index,
+        max_update_index,
+        fastrand::u32(..)
+    )
+}
+
+mod error {
+    use std::path::PathBuf;
+
+    use quick_error::quick_error;
+
+    use crate::store_impl::reftable::table;
+
+    quick_error! {
+        /// The error returned by [`Store::stack()`][crate::reftable::Store::stack()].
+        #[derive(Debug)]
+        #[allow(missing_docs)]
+        pub enum Error {
+            Io(err: std::io::Error) {
+                display("The list of tables could not be read")
+                from()
+                source(err)
+            }
+            TableName {
+                display("The list of tables contained a name that wasn't valid UTF-8")
+            }
+            Open { err: table::open::Error, path: PathBuf } {
+                display("The table at '{}' could not be opened", path.display())
+                source(err)
+            }
+        }
+    }
+}
+pub use error::Error;
+
+///
+pub mod compact {
+    use quick_error::quick_error;
+
+    use crate::store_impl::reftable::{decode, stack, write};
+
+    quick_error! {
+        /// The error returned by [`Store::compact()`][crate::reftable::Store::compact()].
+        #[derive(Debug)]
+        #[allow(missing_docs)]
+        pub enum Error {
+            LockAcquire(err: git_lock::acquire::Error) {
+                display("The lock for the list of tables could not be obtained")
+                from()
+                source(err)
+            }
+            Stack(err: stack::Error) {
+                display("The stack of tables could not be loaded")
+                from()
+                source(err)
+            }
+            Decode(err: decode::Error) {
+                display("A table could not be read")
+                from()
+                source(err)
+            }
+            Write(err: write::Error) {
+                display("The compacted table could not be written")
+                from()
+                source(err)
+            }
+            Io(err: std::io::Error) {
+                display("The list of tables could not be updated")
+                from()
+                source(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::segment_to_compact;
+
+    #[test]
+    fn geometric_sequences_are_left_alone() {
+        assert_eq!(segment_to_compact(&[]), None);
+        assert_eq!(segment_to_compact(&[100]), None);
+        assert_eq!(segment_to_compact(&[400, 200, 100]), None);
+    }
+
+    #[test]
+    fn the_most_recent_tables_are_merged_until_the_sequence_is_restored() {
+        assert_eq!(segment_to_compact(&[400, 200, 100, 100]), Some(0..4));
+        assert_eq!(segment_to_compact(&[1000, 100, 100]), Some(1..3));
+        assert_eq!(segment_to_compact(&[1000, 300, 100, 60]), Some(1..4));
+    }
+}
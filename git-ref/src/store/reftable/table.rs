@@ -0,0 +1,497 @@
+use git_hash::oid;
+use git_object::bstr::BStr;
+
+use crate::{
+    store_impl::reftable::{
+        decode::{self, be24, be64, kind, Block, Cursor, Error, FOOTER_LEN, HEADER_LEN, MAGIC, VERSION},
+        Backing, LogRecord, Record, Table, Value,
+    },
+    FullNameRef,
+};
+
+/// Information about the layout of a table as stored in its footer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Footer {
+    pub block_size: usize,
+    pub min_update_index: u64,
+    pub max_update_index: u64,
+    pub ref_index_position: usize,
+    pub obj_position: usize,
+    pub obj_id_len: usize,
+    pub obj_index_position: usize,
+    pub log_position: usize,
+    pub log_index_position: usize,
+}
+
+impl Footer {
+    fn from_table(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < HEADER_LEN + FOOTER_LEN {
+            return Err(Error::Corrupt("the table is too small to hold a header and a footer"));
+        }
+        let header = &data[..HEADER_LEN];
+        if &header[..4] != MAGIC {
+            return Err(Error::Corrupt("the reftable signature is missing"));
+        }
+        if header[4] != VERSION {
+            return Err(Error::UnsupportedVersion(header[4]));
+        }
+        let footer = &data[data.len() - FOOTER_LEN..];
+        if &footer[..HEADER_LEN] != header {
+            return Err(Error::Corrupt("the footer doesn't repeat the header"));
+        }
+        let expected = u32::from_be_bytes([footer[64], footer[65], footer[66], footer[67]]);
+        let actual = git_features::hash::crc32(&footer[..64]);
+        if expected != actual {
+            return Err(Error::Checksum { expected, actual });
+        }
+        let obj_position_and_id_len = be64(&footer[32..]);
+        Ok(Footer {
+            block_size: be24(&header[5..]),
+            min_update_index: be64(&header[8..]),
+            max_update_index: be64(&header[16..]),
+            ref_index_position: be64(&footer[24..]) as usize,
+            obj_position: (obj_position_and_id_len >> 5) as usize,
+            obj_id_len: (obj_position_and_id_len & 0x1f) as usize,
+            obj_index_position: be64(&footer[40..]) as usize,
+            log_position: be64(&footer[48..]) as usize,
+            log_index_position: be64(&footer[56..]) as usize,
+        })
+    }
+}
+
+impl AsRef<[u8]> for Backing {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            Backing::InMemory(data) => data,
+            Backing::Mapped(map) => map,
+        }
+    }
+}
+
+///
+pub mod open {
+    use std::path::Path;
+
+    use filebuffer::FileBuffer;
+
+    use crate::store_impl::reftable::{decode, table::Footer, Backing, Table};
+
+    /// Initialization
+    impl Table {
+        /// Open the table at `path`, mapping it into memory if it is larger than a few pages.
+        pub fn at(path: impl AsRef<Path>) -> Result<Self, Error> {
+            let path = path.as_ref();
+            let need_more_than_this_many_bytes_to_use_mmap = 32 * 1024;
+            let data = if std::fs::metadata(path)?.len() <= need_more_than_this_many_bytes_to_use_mmap {
+                Backing::InMemory(std::fs::read(path)?)
+            } else {
+                Backing::Mapped(FileBuffer::open(path)?)
+            };
+            Ok(Table {
+                footer: Footer::from_table(data.as_ref())?,
+                data,
+            })
+        }
+
+        /// Use the serialized table in `data`, as produced by a [`Writer`][crate::reftable::Writer] for example.
+        pub fn from_bytes(data: Vec<u8>) -> Result<Self, decode::Error> {
+            Ok(Table {
+                footer: Footer::from_table(&data)?,
+                data: Backing::InMemory(data),
+            })
+        }
+    }
+
+    mod error {
+        use quick_error::quick_error;
+
+        use crate::store_impl::reftable::decode;
+
+        quick_error! {
+            /// The error returned by [`Table::at()`][super::Table::at()].
+            #[derive(Debug)]
+            #[allow(missing_docs)]
+            pub enum Error {
+                Io(err: std::io::Error) {
+                    display("The table file could not be read")
+                    from()
+                    source(err)
+                }
+                Decode(err: decode::Error) {
+                    display("The table could not be decoded")
+                    from()
+                    source(err)
+                }
+            }
+        }
+    }
+    pub use error::Error;
+}
+
+/// Access
+impl Table {
+    /// The smallest update index of all records in this table.
+    pub fn min_update_index(&self) -> u64 {
+        self.footer.min_update_index
+    }
+
+    /// The largest update index of all records in this table.
+    pub fn max_update_index(&self) -> u64 {
+        self.footer.max_update_index
+    }
+
+    /// The size of blocks holding references, which may be padded to fill them completely.
+    pub fn block_size(&self) -> usize {
+        self.footer.block_size
+    }
+
+    /// Return the record of the reference with the given `name`, which may be a deletion, or `None` if this table
+    /// doesn't know about it.
+    pub fn try_find(&self, name: FullNameRef<'_>) -> Result<Option<Record>, Error> {
+        let name: &BStr = name.into();
+        let mut iter = self.iter_from(name, None)?;
+        Ok(match iter.next().transpose()? {
+            Some(record) if record.name.as_bstr() == name => Some(record),
+            _ => None,
+        })
+    }
+
+    /// Return an iterator over all reference records in this table, including deletions, sorted by name.
+    pub fn iter(&self) -> Result<Iter<'_>, Error> {
+        Ok(Iter {
+            records: Records::new(self, self.ref_section().map(|_| 0), kind::REF)?,
+        })
+    }
+
+    /// Return an iterator over all reference records in this table whose names start with `prefix`, including deletions.
+    pub fn iter_prefixed(&self, prefix: &BStr) -> Result<Iter<'_>, Error> {
+        self.iter_from(prefix, Some(prefix))
+    }
+
+    fn iter_from(&self, key: &[u8], prefix: Option<&[u8]>) -> Result<Iter<'_>, Error> {
+        let records = match self.ref_section() {
+            Some(section) => Records::seek(
+                self,
+                section,
+                self.footer.ref_index_position,
+                kind::REF,
+                key,
+                prefix.unwrap_or(&[]),
+            )?,
+            None => Records::new(self, None, kind::REF)?,
+        };
+        Ok(Iter { records })
+    }
+
+    /// Return an iterator over all reflog records in this table, sorted by name and then by update index, with the most
+    /// recent entry first.
+    pub fn log_iter(&self) -> Result<LogIter<'_>, Error> {
+        Ok(LogIter {
+            records: Records::new(self, self.log_section(), kind::LOG)?,
+        })
+    }
+
+    /// Return an iterator over all reflog records of the reference named `name`, most recent first.
+    pub fn log_iter_of(&self, name: FullNameRef<'_>) -> Result<LogIter<'_>, Error> {
+        let name: &BStr = name.into();
+        let mut key = name.to_vec();
+        key.push(0);
+        let records = match self.log_section() {
+            Some(section) => Records::seek(self, section, self.footer.log_index_position, kind::LOG, &key, &key)?,
+            None => Records::new(self, None, kind::LOG)?,
+        };
+        Ok(LogIter { records })
+    }
+
+    /// Return all reference records whose target or peeled object is `id`, using the object index if available.
+    pub fn refs_with_object(&self, id: &oid) -> Result<Vec<Record>, Error> {
+        let positions = match self.obj_section() {
+            Some(section) => {
+                let abbrev = &id.as_bytes()[..self.footer.obj_id_len.min(id.as_bytes().len())];
+                let mut records =
+                    Records::seek(self, section, self.footer.obj_index_position, kind::OBJ, abbrev, abbrev)?;
+                match records.next_record(|cursor, data, count| cursor.obj_value(data, count))? {
+                    Some(positions) if records.key() == abbrev => (!positions.is_empty()).then(|| positions),
+                    _ => return Ok(Vec::new()),
+                }
+            }
+            None => None,
+        };
+
+        let matches = |record: &Record| match &record.value {
+            Value::Object { target, peeled } => *target == id || peeled.map_or(false, |peeled| peeled == id),
+            Value::Deletion | Value::Symbolic(_) => false,
+        };
+        let mut out = Vec::new();
+        match positions {
+            Some(positions) => {
+                for position in positions {
+                    let block = self
+                        .block_at(position)?
+                        .filter(|b| b.kind == kind::REF)
+                        .ok_or(Error::Corrupt("object record points to a non-existing reference block"))?;
+                    let mut records = Records::in_block(self, block);
+                    while let Some(record) = records.next_ref()? {
+                        if matches(&record) {
+                            out.push(record);
+                        }
+                    }
+                }
+            }
+            None => {
+                for record in self.iter()? {
+                    let record = record?;
+                    if matches(&record) {
+                        out.push(record);
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl Table {
+    /// All bytes in front of the footer.
+    fn data(&self) -> &[u8] {
+        let data = self.data.as_ref();
+        &data[..data.len() - FOOTER_LEN]
+    }
+
+    fn first_block_kind(&self) -> Option<u8> {
+        self.data().get(HEADER_LEN).copied()
+    }
+
+    fn ref_section(&self) -> Option<usize> {
+        (self.first_block_kind() == Some(kind::REF)).then(|| 0)
+    }
+
+    fn obj_section(&self) -> Option<usize> {
+        (self.footer.obj_position != 0).then(|| self.footer.obj_position)
+    }
+
+    fn log_section(&self) -> Option<usize> {
+        if self.footer.log_position != 0 {
+            Some(self.footer.log_position)
+        } else {
+            (self.first_block_kind() == Some(kind::LOG)).then(|| 0)
+        }
+    }
+
+    fn block_at(&self, position: usize) -> Result<Option<Block<'_>>, Error> {
+        Block::at(self.data(), position, self.footer.block_size)
+    }
+
+    /// Find the block of `kind` in the section starting at `section` which would contain `key` if it existed, using the
+    /// index at `index_position` if it isn't zero, or `None` if all keys of the section are smaller than `key`.
+    fn locate(&self, section: usize, index_position: usize, kind: u8, key: &[u8]) -> Result<Option<Block<'_>>, Error> {
+        if index_position != 0 {
+            let mut position = index_position;
+            loop {
+                let index = self
+                    .block_at(position)?
+                    .filter(|b| b.kind == kind::INDEX)
+                    .ok_or(Error::Corrupt("an index block was expected"))?;
+                let mut cursor = index.seek(key)?;
+                // The key of each index record is the last key of the block it points to.
+                let target = loop {
+                    match cursor.next_key(&index.data)? {
+                        Some(_) => {
+                            let target = cursor.index_value(&index.data)?;
+                            if cursor.key.as_slice() >= key {
+                                break target;
+                            }
+                        }
+                        None => return Ok(None),
+                    }
+                };
+                let block = self
+                    .block_at(target)?
+                    .ok_or(Error::Corrupt("an index record points to a non-existing block"))?;
+                match block.kind {
+                    kind::INDEX => position = target,
+                    actual if actual == kind => return Ok(Some(block)),
+                    _ => return Err(Error::Corrupt("an index record points to a block of unexpected type")),
+                }
+            }
+        }
+
+        let mut current = match self.block_at(section)?.filter(|b| b.kind == kind) {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        loop {
+            let next = match self.block_at(current.next_position)?.filter(|b| b.kind == kind) {
+                Some(block) => block,
+                None => return Ok(Some(current)),
+            };
+            if next.first_key()?.as_slice() > key {
+                return Ok(Some(current));
+            }
+            current = next;
+        }
+    }
+}
+
+/// A position within the records of all consecutive blocks of the same kind.
+struct Records<'a> {
+    table: &'a Table,
+    block: Option<Block<'a>>,
+    cursor: Cursor,
+    /// Records with keys smaller than this one are skipped.
+    skip_below: Vec<u8>,
+    /// Records must start with this prefix, or the iteration ends.
+    prefix: Vec<u8>,
+    /// If set, the iteration ends with the current block instead of moving on to the next one.
+    single_block: bool,
+}
+
+impl<'a> Records<'a> {
+    fn new(table: &'a Table, section: Option<usize>, kind: u8) -> Result<Self, Error> {
+        let block = match section {
+            Some(section) => table.block_at(section)?.filter(|b| b.kind == kind),
+            None => None,
+        };
+        Ok(Records {
+            table,
+            cursor: block.as_ref().map(Block::cursor).unwrap_or_default(),
+            block,
+            skip_below: Vec::new(),
+            prefix: Vec::new(),
+            single_block: false,
+        })
+    }
+
+    fn in_block(table: &'a Table, block: Block<'a>) -> Self {
+        Records {
+            table,
+            cursor: block.cursor(),
+            block: Some(block),
+            skip_below: Vec::new(),
+            prefix: Vec::new(),
+            single_block: true,
+        }
+    }
+
+    fn seek(
+        table: &'a Table,
+        section: usize,
+        index_position: usize,
+        kind: u8,
+        key: &[u8],
+        prefix: &[u8],
+    ) -> Result<Self, Error> {
+        let block = table.locate(section, index_position, kind, key)?;
+        Ok(Records {
+            table,
+            cursor: match &block {
+                Some(block) => block.seek(key)?,
+                None => Cursor::default(),
+            },
+            block,
+            skip_below: key.to_owned(),
+            prefix: prefix.to_owned(),
+            single_block: false,
+        })
+    }
+
+    fn key(&self) -> &[u8] {
+        &self.cursor.key
+    }
+
+    /// Decode the next record using `decode`, and move on to the next block of the same kind when needed.
+    fn next_record<T>(
+        &mut self,
+        mut decode: impl FnMut(&mut Cursor, &[u8], u8) -> Result<T, Error>,
+    ) -> Result<Option<T>, Error> {
+        loop {
+            let block = match &self.block {
+                Some(block) => block,
+                None => return Ok(None),
+            };
+            match self.cursor.next_key(&block.data)? {
+                Some(value_type) => {
+                    let value = decode(&mut self.cursor, &block.data, value_type)?;
+                    if self.cursor.key < self.skip_below {
+                        continue;
+                    }
+                    if !self.cursor.key.starts_with(&self.prefix) {
+                        self.block = None;
+                        return Ok(None);
+                    }
+                    return Ok(Some(value));
+                }
+                None if self.single_block => {
+                    self.block = None;
+                    return Ok(None);
+                }
+                None => {
+                    let (next_position, kind) = (block.next_position, block.kind);
+                    self.block = self.table.block_at(next_position)?.filter(|b| b.kind == kind);
+                    self.cursor = self.block.as_ref().map(Block::cursor).unwrap_or_default();
+                }
+            }
+        }
+    }
+
+    fn next_ref(&mut self) -> Result<Option<Record>, Error> {
+        let min_update_index = self.table.footer.min_update_index;
+        match self.next_record(|cursor, data, value_type| cursor.ref_value(data, value_type, min_update_index))? {
+            Some((update_index, value)) => Ok(Some(Record {
+                name: decode::to_full_name(&self.cursor.key)?,
+                update_index,
+                value,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    fn next_log(&mut self) -> Result<Option<LogRecord>, Error> {
+        match self.next_record(|cursor, data, value_type| cursor.log_value(data, value_type))? {
+            Some(line) => {
+                let (name, update_index) = decode::log_key(&self.cursor.key)?;
+                Ok(Some(LogRecord {
+                    name: decode::to_full_name(name)?,
+                    update_index,
+                    line,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn fuse<T>(&mut self, res: Result<Option<T>, Error>) -> Option<Result<T, Error>> {
+        if res.is_err() {
+            self.block = None;
+        }
+        res.transpose()
+    }
+}
+
+/// An iterator over reference records of a [`Table`], sorted by name.
+pub struct Iter<'a> {
+    records: Records<'a>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.records.next_ref();
+        self.records.fuse(res)
+    }
+}
+
+/// An iterator over reflog records of a [`Table`], sorted by name and with the most recent entries first.
+pub struct LogIter<'a> {
+    records: Records<'a>,
+}
+
+impl<'a> Iterator for LogIter<'a> {
+    type Item = Result<LogRecord, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.records.next_log();
+        self.records.fuse(res)
+    }
+}
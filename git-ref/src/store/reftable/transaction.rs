@@ -0,0 +1,457 @@
+use git_hash::ObjectId;
+use git_object::bstr::BString;
+
+use crate::{
+    store_impl::{
+        file::transaction::{hook, HookFn},
+        reftable::{Store, Transaction},
+    },
+    transaction::RefEdit,
+    FullName,
+};
+
+#[derive(Debug)]
+pub(in crate::store_impl::reftable) struct Edit {
+    update: RefEdit,
+    /// Set if this update is coming from a symbolic reference and used to make it appear like it is the one that is handled,
+    /// instead of the referent reference.
+    parent_index: Option<usize>,
+    /// For symbolic refs, this is the previous OID to put into the reflog instead of our own previous value. It's the
+    /// peeled value of the leaf referent.
+    leaf_referent_previous_oid: Option<ObjectId>,
+    /// Set if this update creates a reference renamed or copied from the given reference, whose reflog is to be copied.
+    relocated_from: Option<FullName>,
+}
+
+impl Edit {
+    fn name(&self) -> BString {
+        self.update.name.0.clone()
+    }
+}
+
+impl std::borrow::Borrow<RefEdit> for Edit {
+    fn borrow(&self) -> &RefEdit {
+        &self.update
+    }
+}
+
+impl std::borrow::BorrowMut<RefEdit> for Edit {
+    fn borrow_mut(&mut self) -> &mut RefEdit {
+        &mut self.update
+    }
+}
+
+impl crate::store_impl::prepare::Edit for Edit {
+    fn parent_index(&self) -> Option<usize> {
+        self.parent_index
+    }
+
+    fn set_leaf_referent_previous_oid(&mut self, oid: ObjectId) {
+        self.leaf_referent_previous_oid = Some(oid);
+    }
+}
+
+/// Edits
+impl Store {
+    /// Open a transaction which writes all of its edits into a single new table on top of the stack.
+    ///
+    /// The transaction inherits the parent namespace.
+    pub fn transaction(&self) -> Transaction<'_> {
+        Transaction {
+            store: self,
+            lock: None,
+            stack: None,
+            updates: None,
+            hook: None,
+        }
+    }
+}
+
+impl<'s> Transaction<'s> {
+    /// Call `hook` once the stack is locked, after the transaction was committed, or when the prepared transaction is
+    /// dropped without committing it or rejected by the hook itself. Use [`hook::executable()`] to run the `reference-transaction` hook like git does.
+    pub fn hook(mut self, hook: Box<HookFn>) -> Self {
+        self.hook = Some(hook);
+        self
+    }
+}
+
+impl<'s> Drop for Transaction<'s> {
+    fn drop(&mut self) {
+        if let (Some(hook), Some(updates)) = (self.hook.as_mut(), self.updates.take()) {
+            // Release the lock before telling the hook, as git does.
+            self.lock.take();
+            let edits: Vec<_> = updates.into_iter().map(|edit| edit.update).collect();
+            // The transaction is rolled back no matter what the hook has to say.
+            hook(hook::State::Aborted, &edits).ok();
+        }
+    }
+}
+
+fn should_autocreate_reflog(name: &[u8]) -> bool {
+    name.starts_with(b"refs/heads/")
+        || name.starts_with(b"refs/remotes/")
+        || name.starts_with(b"refs/notes/")
+        || name == b"HEAD"
+}
+
+///
+pub mod prepare {
+    use git_object::bstr::BString;
+
+    use crate::{
+        store_impl::{
+            prepare::{
+                check_and_update_expected, is_quarantined, propagate_leaf_referent_previous_oid, run_prepared_hook,
+                Mismatch,
+            },
+            reftable::{find::to_reference, transaction::Edit, Transaction},
+        },
+        transaction::{RefEdit, RefEditsExt},
+        FullNameRef,
+    };
+
+    impl<'s> Transaction<'s> {
+        /// Prepare for calling [`commit(…)`][Transaction::commit()] by locking the stack of tables and verifying all
+        /// preconditions of `edits` against its current state. Fail according to `lock_fail_mode` if the stack is locked
+        /// already.
+        ///
+        /// If the operation succeeds, the transaction can be committed or dropped to cause a rollback automatically.
+        pub fn prepare(
+            mut self,
+            edits: impl IntoIterator<Item = RefEdit>,
+            lock_fail_mode: git_lock::acquire::Fail,
+        ) -> Result<Self, Error> {
+            assert!(self.updates.is_none(), "BUG: Must not call prepare(…) multiple times");
+            if is_quarantined() {
+                return Err(Error::Quarantined);
+            }
+            let store = self.store;
+            let lock = git_lock::File::acquire_to_update_resource(store.tables_list_path(), lock_fail_mode, None)?;
+            let stack = store.stack()?;
+
+            let mut updates: Vec<_> = edits
+                .into_iter()
+                .map(|update| Edit {
+                    update,
+                    parent_index: None,
+                    leaf_referent_previous_oid: None,
+                    relocated_from: None,
+                })
+                .collect();
+            updates
+                .pre_process_with_relocations(
+                    |name| {
+                        store
+                            .try_find_in(&stack, name.as_bstr())
+                            .ok()
+                            .flatten()
+                            .map(|r| r.target)
+                    },
+                    |idx, update| Edit {
+                        update,
+                        parent_index: Some(idx),
+                        leaf_referent_previous_oid: None,
+                        relocated_from: None,
+                    },
+                    |source, update| Edit {
+                        update,
+                        parent_index: None,
+                        leaf_referent_previous_oid: None,
+                        relocated_from: Some(source),
+                    },
+                )
+                .map_err(Error::PreprocessingFailed)?;
+
+            for cid in 0..updates.len() {
+                let change = &mut updates[cid];
+                let name = store.namespaced(change.update.name.as_bstr());
+                let existing = stack
+                    .try_find_record(FullNameRef(name.as_ref()))?
+                    .and_then(|record| to_reference(record, store.namespace.as_ref()));
+                check_and_update_expected(
+                    &mut change.update.change,
+                    existing.map(|r| r.target),
+                    change.relocated_from.is_some(),
+                )
+                .map_err(|mismatch| to_error(mismatch, change.name()))?;
+                propagate_leaf_referent_previous_oid(&mut updates, cid);
+            }
+            let mut lock = Some(lock);
+            if let Some(hook) = self.hook.as_mut() {
+                let edits: Vec<_> = updates.iter().map(|edit| edit.update.clone()).collect();
+                run_prepared_hook(hook, &edits, || drop(lock.take())).map_err(Error::HookRejected)?;
+            }
+            self.lock = lock;
+            self.stack = Some(stack);
+            self.updates = Some(updates);
+            Ok(self)
+        }
+    }
+
+    fn to_error(mismatch: Mismatch, full_name: BString) -> Error {
+        match mismatch {
+            Mismatch::DeleteReferenceMustExist => Error::DeleteReferenceMustExist { full_name },
+            Mismatch::MustNotExist { actual, new } => Error::MustNotExist { full_name, actual, new },
+            Mismatch::ReferenceMustExist => Error::ReferenceMustExist { full_name },
+            Mismatch::MustExist { expected } => Error::MustExist { full_name, expected },
+            Mismatch::ReferenceOutOfDate { expected, actual } => Error::ReferenceOutOfDate {
+                full_name,
+                expected,
+                actual,
+            },
+        }
+    }
+
+    mod error {
+        use git_object::bstr::BString;
+        use quick_error::quick_error;
+
+        use crate::{
+            store_impl::reftable::{decode, stack},
+            Target,
+        };
+
+        quick_error! {
+            /// The error returned by [`Transaction::prepare()`][crate::reftable::Transaction::prepare()].
+            #[derive(Debug)]
+            #[allow(missing_docs)]
+            pub enum Error {
+                LockAcquire(err: git_lock::acquire::Error) {
+                    display("The lock for the list of tables could not be obtained")
+                    from()
+                    source(err)
+                }
+                Stack(err: stack::Error) {
+                    display("The stack of tables could not be loaded")
+                    from()
+                    source(err)
+                }
+                Decode(err: decode::Error) {
+                    display("A table could not be read")
+                    from()
+                    source(err)
+                }
+                PreprocessingFailed(err: std::io::Error) {
+                    display("Edit preprocessing failed with error: {}", err.to_string())
+                    source(err)
+                }
+                DeleteReferenceMustExist { full_name: BString } {
+                    display("The reference '{}' for deletion did not exist", full_name)
+                }
+                MustNotExist { full_name: BString, actual: Target, new: Target } {
+                    display("Reference '{}' was not supposed to exist when writing it with value {}, but actual content was {}", full_name, new, actual)
+                }
+                ReferenceMustExist { full_name: BString } {
+                    display("Reference '{}' was supposed to exist, but didn't.", full_name)
+                }
+                MustExist { full_name: BString, expected: Target } {
+                    display("Reference '{}' was supposed to exist with value {}, but didn't.", full_name, expected)
+                }
+                ReferenceOutOfDate { full_name: BString, expected: Target, actual: Target } {
+                    display("The reference '{}' should have content {}, actual content was {}", full_name, expected, actual)
+                }
+                Quarantined {
+                    display("Reference updates are forbidden inside the quarantine environment of GIT_QUARANTINE_PATH")
+                }
+                HookRejected(err: Box<dyn std::error::Error + Send + Sync + 'static>) {
+                    display("The reference-transaction hook rejected the transaction in its 'prepared' state")
+                    source(&**err)
+                }
+            }
+        }
+    }
+    pub use error::Error;
+}
+
+///
+pub mod commit {
+    use std::io::Write;
+
+    use crate::{
+        bstr::BStr,
+        log,
+        store::WriteReflog,
+        store_impl::{
+            file::transaction::hook,
+            reftable::{transaction::should_autocreate_reflog, LogRecord, Record, Stack, Transaction, Value, Writer},
+        },
+        transaction::{Change, PreviousValue, RefEdit, RefLog},
+        FullName, FullNameRef, Target,
+    };
+
+    impl<'s> Transaction<'s> {
+        /// Make all [prepared][Transaction::prepare()] edits permanent by writing them into a new table on top of the stack,
+        /// and return the performed edits which represent the current state of the affected refs in the ref store in that instant.
+        /// `committer` is used in the reflog.
+        ///
+        /// All reference updates, deletions and reflog entries are written atomically. Afterwards the stack is compacted
+        /// automatically if needed, which happens on a best-effort basis.
+        pub fn commit(mut self, committer: &git_actor::Signature) -> Result<Vec<RefEdit>, Error> {
+            let updates = self.updates.take().expect("BUG: must call prepare before commit");
+            let stack = self.stack.take().expect("prepared");
+            let mut lock = self.lock.take().expect("prepared");
+            let store = self.store;
+
+            let update_index = stack.max_update_index() + 1;
+            let mut writer = Writer::new(update_index, update_index);
+            let mut num_records = 0;
+            for change in &updates {
+                assert!(!change.update.deref, "Deref mode is turned into splits and turned off");
+                let name = FullName(store.namespaced(change.update.name.as_bstr()));
+                match &change.update.change {
+                    Change::Update { log, new, expected } => {
+                        if log.mode == RefLog::AndReference {
+                            writer.add_ref(Record {
+                                name: name.clone(),
+                                update_index,
+                                value: match new {
+                                    Target::Peeled(oid) => Value::Object {
+                                        target: *oid,
+                                        peeled: None,
+                                    },
+                                    Target::Symbolic(name) => Value::Symbolic(name.clone()),
+                                },
+                            });
+                            num_records += 1;
+                        }
+                        if store.write_reflog == WriteReflog::Disable {
+                            continue;
+                        }
+                        let has_log = match &change.relocated_from {
+                            Some(source) => {
+                                let source = store.namespaced(source.as_bstr());
+                                copy_log(&stack, &mut writer, source.as_ref(), &name, &mut num_records)?
+                            }
+                            None => !stack.log_records_of(FullNameRef(name.as_bstr()))?.is_empty(),
+                        };
+                        let new_oid = match new {
+                            Target::Symbolic(_) => continue, // no reflog for symref changes
+                            Target::Peeled(oid) => *oid,
+                        };
+                        let previous = match expected {
+                            PreviousValue::MustExistAndMatch(Target::Peeled(oid)) => Some(*oid),
+                            // renamed or copied references keep their value
+                            _ if change.relocated_from.is_some() => Some(new_oid),
+                            _ => None,
+                        }
+                        .or(change.leaf_referent_previous_oid);
+                        if previous == Some(new_oid) && change.relocated_from.is_none() {
+                            continue;
+                        }
+                        let write_log = log.force_create_reflog
+                            || should_autocreate_reflog(change.update.name.as_bstr())
+                            || has_log;
+                        if write_log {
+                            writer.add_log(LogRecord {
+                                name,
+                                update_index,
+                                line: Some(log::Line {
+                                    previous_oid: previous.unwrap_or_else(git_hash::ObjectId::null_sha1),
+                                    new_oid,
+                                    signature: committer.to_owned(),
+                                    message: log.message.clone(),
+                                }),
+                            });
+                            num_records += 1;
+                        }
+                    }
+                    Change::Rename { .. } | Change::Copy { .. } => {
+                        unreachable!("BUG: renames and copies are split during pre-processing")
+                    }
+                    Change::Delete { log: mode, .. } => {
+                        // Deleting a reference also deletes its reflog.
+                        for record in stack.log_records_of(FullNameRef(name.as_bstr()))? {
+                            writer.add_log(LogRecord { line: None, ..record });
+                            num_records += 1;
+                        }
+                        if *mode == RefLog::AndReference {
+                            writer.add_ref(Record {
+                                name,
+                                update_index,
+                                value: Value::Deletion,
+                            });
+                            num_records += 1;
+                        }
+                    }
+                }
+            }
+
+            if num_records != 0 {
+                let table_name = store.write_table(writer, update_index, update_index)?;
+                let mut list = Vec::new();
+                for name in stack.names().chain(std::iter::once(table_name.as_str())) {
+                    writeln!(list, "{}", name)?;
+                }
+                lock.with_mut(|out| out.write_all(&list))?;
+                lock.commit().map_err(|err| err.error)?;
+                // Compaction is an optimization which may fail if another process holds the lock, or for other reasons
+                // which shouldn't affect the outcome of this transaction.
+                store.auto_compact().ok();
+            }
+            let edits: Vec<_> = updates.into_iter().map(|edit| edit.update).collect();
+            if let Some(hook) = self.hook.as_mut() {
+                // It's too late to change anything, so errors are ignored just like git does.
+                hook(hook::State::Committed, &edits).ok();
+            }
+            Ok(edits)
+        }
+    }
+
+    /// Replace the reflog of `target` with the one of `source` by adding records to `writer`, incrementing `num_records`
+    /// for each of them. Return true if `source` has a reflog.
+    fn copy_log(
+        stack: &Stack,
+        writer: &mut Writer,
+        source: &BStr,
+        target: &FullName,
+        num_records: &mut usize,
+    ) -> Result<bool, Error> {
+        let records = stack.log_records_of(FullNameRef(source))?;
+        for record in stack.log_records_of(target.to_ref())? {
+            if records.iter().all(|r| r.update_index != record.update_index) {
+                writer.add_log(LogRecord { line: None, ..record });
+                *num_records += 1;
+            }
+        }
+        let has_log = !records.is_empty();
+        for record in records {
+            writer.add_log(LogRecord {
+                name: target.clone(),
+                ..record
+            });
+            *num_records += 1;
+        }
+        Ok(has_log)
+    }
+
+    mod error {
+        use quick_error::quick_error;
+
+        use crate::store_impl::reftable::{decode, write};
+
+        quick_error! {
+            /// The error returned by [`Transaction::commit()`][crate::reftable::Transaction::commit()].
+            #[derive(Debug)]
+            #[allow(missing_docs)]
+            pub enum Error {
+                Decode(err: decode::Error) {
+                    display("A table could not be read")
+                    from()
+                    source(err)
+                }
+                Write(err: write::Error) {
+                    display("The new table could not be written")
+                    from()
+                    source(err)
+                }
+                Io(err: std::io::Error) {
+                    display("The list of tables could not be updated")
+                    from()
+                    source(err)
+                }
+            }
+        }
+    }
+    pub use error::Error;
+}
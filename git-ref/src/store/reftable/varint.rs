@@ -0,0 +1,63 @@
+/// Append `value` to `out` using the variable-length encoding also used for offsets in packs, which stores 7 bits
+/// per byte with the most significant bits first while adding one to every continuation.
+pub(crate) fn encode(mut value: u64, out: &mut Vec<u8>) {
+    let mut buf = [0u8; 10];
+    let mut idx = buf.len() - 1;
+    buf[idx] = (value & 0x7f) as u8;
+    value >>= 7;
+    while value != 0 {
+        value -= 1;
+        idx -= 1;
+        buf[idx] = 0x80 | (value & 0x7f) as u8;
+        value >>= 7;
+    }
+    out.extend_from_slice(&buf[idx..]);
+}
+
+/// Decode a variable-length integer from the beginning of `data` and return it along with the amount of bytes consumed,
+/// or `None` if the data ended prematurely or the value doesn't fit into 64 bits.
+pub(crate) fn decode(data: &[u8]) -> Option<(u64, usize)> {
+    let mut byte = *data.first()?;
+    let mut value = (byte & 0x7f) as u64;
+    let mut consumed = 1;
+    while byte & 0x80 != 0 {
+        byte = *data.get(consumed)?;
+        consumed += 1;
+        value = value.checked_add(1)?.checked_mul(128)? | (byte & 0x7f) as u64;
+    }
+    Some((value, consumed))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn round_trip() {
+        for value in [
+            0,
+            1,
+            127,
+            128,
+            255,
+            256,
+            16383,
+            16384,
+            16511,
+            16512,
+            1 << 40,
+            u64::MAX >> 1,
+            u64::MAX,
+        ] {
+            let mut buf = Vec::new();
+            super::encode(value, &mut buf);
+            assert_eq!(super::decode(&buf), Some((value, buf.len())), "{}", value);
+        }
+    }
+
+    #[test]
+    fn known_encodings() {
+        let mut buf = Vec::new();
+        super::encode(128, &mut buf);
+        assert_eq!(buf, [0x80, 0x00], "the continuation implies an offset of one");
+        assert_eq!(super::decode(&[0x80]), None, "truncated input");
+    }
+}
@@ -0,0 +1,486 @@
+use std::collections::BTreeMap;
+
+use git_hash::ObjectId;
+
+use crate::{
+    log,
+    store_impl::reftable::{
+        decode::{self, kind, BLOCK_HEADER_LEN, HEADER_LEN, MAGIC, VERSION},
+        varint, LogRecord, Record, Value,
+    },
+};
+
+/// The amount of records after which prefix compression starts over, making the record available for binary searches.
+const RESTART_INTERVAL: usize = 16;
+/// Sections with at least this many blocks receive an index.
+const MIN_BLOCKS_FOR_INDEX: usize = 4;
+/// The shortest abbreviation of object ids we write into object blocks.
+const MIN_OBJ_ID_LEN: usize = 2;
+
+/// A utility to write a single [table][crate::reftable::Table] from reference and reflog records.
+///
+/// Object blocks are written automatically to allow finding references by the objects they point to, along with indices
+/// for all sections that are large enough to benefit from them.
+pub struct Writer {
+    min_update_index: u64,
+    max_update_index: u64,
+    block_size: usize,
+    refs: Vec<Record>,
+    logs: Vec<LogRecord>,
+}
+
+mod error {
+    use git_object::bstr::BString;
+    use quick_error::quick_error;
+
+    quick_error! {
+        /// The error returned by [`Writer::write_to()`][super::Writer::write_to()].
+        #[derive(Debug)]
+        #[allow(missing_docs)]
+        pub enum Error {
+            Io(err: std::io::Error) {
+                display("The table could not be written")
+                from()
+                source(err)
+            }
+            UpdateIndexOutOfRange { name: BString, update_index: u64, min: u64, max: u64 } {
+                display("The record of '{}' has update index {} which is not within {}..={}", name, update_index, min, max)
+            }
+            Duplicate { name: BString } {
+                display("There was more than one record for '{}' with the same key", name)
+            }
+            MultiLineMessage { name: BString } {
+                display("The reflog message for '{}' must not span multiple lines", name)
+            }
+        }
+    }
+}
+pub use error::Error;
+
+/// Lifecycle
+impl Writer {
+    /// Create a new writer for a table whose reference records have update indices between `min_update_index` and
+    /// `max_update_index`, inclusive.
+    pub fn new(min_update_index: u64, max_update_index: u64) -> Self {
+        Writer {
+            min_update_index,
+            max_update_index,
+            block_size: 4096,
+            refs: Vec::new(),
+            logs: Vec::new(),
+        }
+    }
+
+    /// Set the size of blocks to `bytes`, which are padded to that size unless they hold reflog entries.
+    /// It defaults to 4096 bytes and must be smaller than 16MB.
+    pub fn block_size(mut self, bytes: usize) -> Self {
+        assert!(
+            bytes > 64 && bytes < 1 << 24,
+            "block size must fit into 24 bits and hold a few records"
+        );
+        self.block_size = bytes;
+        self
+    }
+}
+
+/// Adding records
+impl Writer {
+    /// Add the reference `record`. Each reference name may only be added once.
+    pub fn add_ref(&mut self, record: Record) -> &mut Self {
+        self.refs.push(record);
+        self
+    }
+
+    /// Add the reflog `record`. Each combination of reference name and update index may only be added once.
+    ///
+    /// Unlike reference records, its update index may lie outside of the range of the table, which allows deleting log
+    /// entries of older tables.
+    pub fn add_log(&mut self, record: LogRecord) -> &mut Self {
+        self.logs.push(record);
+        self
+    }
+}
+
+/// Writing
+impl Writer {
+    /// Write the table with all previously added records to `out`.
+    pub fn write_to(self, mut out: impl std::io::Write) -> Result<(), Error> {
+        let table = self.into_bytes()?;
+        out.write_all(&table)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn into_bytes(mut self) -> Result<Vec<u8>, Error> {
+        let (min, max) = (self.min_update_index, self.max_update_index);
+        let check_range = |name: &crate::FullName, update_index: u64| {
+            if update_index < min || update_index > max {
+                Err(Error::UpdateIndexOutOfRange {
+                    name: name.as_bstr().to_owned(),
+                    update_index,
+                    min,
+                    max,
+                })
+            } else {
+                Ok(())
+            }
+        };
+        self.refs.sort_by(|a, b| a.name.cmp(&b.name));
+        if let Some(dup) = self.refs.windows(2).find(|r| r[0].name == r[1].name) {
+            return Err(Error::Duplicate {
+                name: dup[0].name.as_bstr().to_owned(),
+            });
+        }
+        let mut logs = Vec::with_capacity(self.logs.len());
+        for record in std::mem::take(&mut self.logs) {
+            logs.push((decode::to_log_key(record.name.as_bstr(), record.update_index), record));
+        }
+        logs.sort_by(|a, b| a.0.cmp(&b.0));
+        if let Some(dup) = logs.windows(2).find(|r| r[0].0 == r[1].0) {
+            return Err(Error::Duplicate {
+                name: dup[0].1.name.as_bstr().to_owned(),
+            });
+        }
+
+        let header = self.header();
+        let mut out = header.clone();
+
+        let mut ref_records = Vec::with_capacity(self.refs.len());
+        for record in &self.refs {
+            check_range(&record.name, record.update_index)?;
+            ref_records.push(self.encode_ref(record));
+        }
+        let refs = write_section(&mut out, &header, kind::REF, ref_records, Some(self.block_size), true);
+        let ref_index_position = write_index(&mut out, &refs.blocks);
+
+        let mut obj_position = 0;
+        let mut obj_index_position = 0;
+        let mut obj_id_len = 0;
+        if !self.refs.is_empty() {
+            let mut block_positions_by_id = BTreeMap::<ObjectId, Vec<usize>>::new();
+            for (record, block) in self.refs.iter().zip(refs.block_of_record.iter()) {
+                if let Value::Object { target, peeled } = &record.value {
+                    for id in std::iter::once(target).chain(peeled.iter()) {
+                        let positions = block_positions_by_id.entry(*id).or_default();
+                        let position = refs.blocks[*block].1;
+                        if positions.last() != Some(&position) {
+                            positions.push(position);
+                        }
+                    }
+                }
+            }
+            obj_id_len = shortest_unique_len(block_positions_by_id.keys());
+            let obj_records = block_positions_by_id
+                .into_iter()
+                .map(|(id, positions)| encode_obj(&id.as_bytes()[..obj_id_len], &positions))
+                .collect();
+            obj_position = out.len();
+            let objs = write_section(&mut out, &header, kind::OBJ, obj_records, Some(self.block_size), true);
+            obj_index_position = write_index(&mut out, &objs.blocks);
+        }
+
+        let mut log_position = 0;
+        let mut log_index_position = 0;
+        if !logs.is_empty() {
+            let mut log_records = Vec::with_capacity(logs.len());
+            for (key, record) in logs {
+                log_records.push(encode_log(key, &record)?);
+            }
+            log_position = if out.len() == HEADER_LEN { 0 } else { out.len() };
+            let logs = write_section(&mut out, &header, kind::LOG, log_records, Some(self.block_size), false);
+            log_index_position = write_index(&mut out, &logs.blocks);
+        }
+
+        let footer_start = out.len();
+        out.extend_from_slice(&header);
+        for value in [
+            ref_index_position as u64,
+            (obj_position as u64) << 5 | obj_id_len as u64,
+            obj_index_position as u64,
+            log_position as u64,
+            log_index_position as u64,
+        ] {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        let checksum = git_features::hash::crc32(&out[footer_start..]);
+        out.extend_from_slice(&checksum.to_be_bytes());
+        Ok(out)
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.push(VERSION);
+        header.extend_from_slice(&(self.block_size as u32).to_be_bytes()[1..]);
+        header.extend_from_slice(&self.min_update_index.to_be_bytes());
+        header.extend_from_slice(&self.max_update_index.to_be_bytes());
+        header
+    }
+
+    fn encode_ref(&self, record: &Record) -> (Vec<u8>, u8, Vec<u8>) {
+        let mut value = Vec::new();
+        varint::encode(record.update_index - self.min_update_index, &mut value);
+        let value_type = match &record.value {
+            Value::Deletion => 0,
+            Value::Object { target, peeled: None } => {
+                value.extend_from_slice(target.as_bytes());
+                1
+            }
+            Value::Object {
+                target,
+                peeled: Some(peeled),
+            } => {
+                value.extend_from_slice(target.as_bytes());
+                value.extend_from_slice(peeled.as_bytes());
+                2
+            }
+            Value::Symbolic(name) => {
+                encode_bytes(name.as_bstr(), &mut value);
+                3
+            }
+        };
+        (record.name.as_bstr().to_vec(), value_type, value)
+    }
+}
+
+fn encode_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    varint::encode(bytes.len() as u64, out);
+    out.extend_from_slice(bytes);
+}
+
+fn encode_obj(abbrev: &[u8], positions: &[usize]) -> (Vec<u8>, u8, Vec<u8>) {
+    let mut value = Vec::new();
+    let value_type = if positions.len() < 8 {
+        positions.len() as u8
+    } else {
+        varint::encode(positions.len() as u64, &mut value);
+        0
+    };
+    let mut previous = 0;
+    for (idx, position) in positions.iter().enumerate() {
+        varint::encode(
+            if idx == 0 { *position } else { position - previous } as u64,
+            &mut value,
+        );
+        previous = *position;
+    }
+    (abbrev.to_owned(), value_type, value)
+}
+
+fn encode_log(key: Vec<u8>, record: &LogRecord) -> Result<(Vec<u8>, u8, Vec<u8>), Error> {
+    let mut value = Vec::new();
+    let value_type = match &record.line {
+        None => 0,
+        Some(log::Line {
+            previous_oid,
+            new_oid,
+            signature,
+            message,
+        }) => {
+            let mut message = message.as_slice();
+            while let Some(stripped) = message.strip_suffix(b"\n") {
+                message = stripped;
+            }
+            if message.contains(&b'\n') {
+                return Err(Error::MultiLineMessage {
+                    name: record.name.as_bstr().to_owned(),
+                });
+            }
+            value.extend_from_slice(previous_oid.as_bytes());
+            value.extend_from_slice(new_oid.as_bytes());
+            encode_bytes(&signature.name, &mut value);
+            encode_bytes(&signature.email, &mut value);
+            varint::encode(signature.time.time as u64, &mut value);
+            value.extend_from_slice(&((signature.time.offset / 60) as i16).to_be_bytes());
+            varint::encode(message.len() as u64 + 1, &mut value);
+            value.extend_from_slice(message);
+            value.push(b'\n');
+            1
+        }
+    };
+    Ok((key, value_type, value))
+}
+
+/// The length of the shortest prefix that is unique among all of the sorted `ids`.
+fn shortest_unique_len<'a>(ids: impl Iterator<Item = &'a ObjectId>) -> usize {
+    let mut len = MIN_OBJ_ID_LEN;
+    let mut previous: Option<&ObjectId> = None;
+    for id in ids {
+        if let Some(previous) = previous {
+            let common = common_prefix_len(previous.as_bytes(), id.as_bytes());
+            len = len.max(common + 1);
+        }
+        previous = Some(id);
+    }
+    len
+}
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+struct Section {
+    /// The last key of each block along with its position.
+    blocks: Vec<(Vec<u8>, usize)>,
+    /// The index into `blocks` for each of the written records.
+    block_of_record: Vec<usize>,
+}
+
+/// Write all `records` as blocks of `kind` to `out`, which includes the file `header` in the first block of the file.
+/// Blocks will be no larger than `block_size` unless a single record doesn't fit, and padded to that size if `pad` is set.
+fn write_section(
+    out: &mut Vec<u8>,
+    header: &[u8],
+    kind: u8,
+    records: Vec<(Vec<u8>, u8, Vec<u8>)>,
+    block_size: Option<usize>,
+    pad: bool,
+) -> Section {
+    let mut section = Section {
+        blocks: Vec::new(),
+        block_of_record: Vec::with_capacity(records.len()),
+    };
+    let mut block: Option<BlockWriter> = None;
+    for (key, value_type, value) in records {
+        loop {
+            let writer = block.get_or_insert_with(|| {
+                let is_first_block_in_file = out.len() == HEADER_LEN;
+                if is_first_block_in_file {
+                    out.clear();
+                }
+                BlockWriter::new(kind, is_first_block_in_file.then(|| header), block_size)
+            });
+            if writer.add(&key, value_type, &value) {
+                break;
+            }
+            finish_block(
+                out,
+                block.take().expect("present"),
+                &mut section,
+                block_size.filter(|_| pad),
+            );
+        }
+        section.block_of_record.push(section.blocks.len());
+    }
+    if let Some(block) = block {
+        finish_block(out, block, &mut section, block_size.filter(|_| pad));
+    }
+    section
+}
+
+fn finish_block(out: &mut Vec<u8>, block: BlockWriter, section: &mut Section, pad_to: Option<usize>) {
+    let position = out.len();
+    let last_key = block.last_key.clone();
+    let data = block.finish();
+    out.extend_from_slice(&data);
+    if let Some(block_size) = pad_to {
+        if data.len() < block_size {
+            out.resize(position + block_size, 0);
+        }
+    }
+    section.blocks.push((last_key, position));
+}
+
+/// Write an index over `blocks` if there are enough of them and return its position, or 0 if no index was written.
+fn write_index(out: &mut Vec<u8>, blocks: &[(Vec<u8>, usize)]) -> usize {
+    if blocks.len() < MIN_BLOCKS_FOR_INDEX {
+        return 0;
+    }
+    let records = blocks
+        .iter()
+        .map(|(last_key, position)| {
+            let mut value = Vec::new();
+            varint::encode(*position as u64, &mut value);
+            (last_key.clone(), 0, value)
+        })
+        .collect();
+    let position = out.len();
+    let index = write_section(out, &[], kind::INDEX, records, None, false);
+    debug_assert_eq!(index.blocks.len(), 1, "index blocks aren't limited in size");
+    position
+}
+
+/// A block under construction.
+struct BlockWriter {
+    /// All data of the block, starting with the file header if this is the first block of the file.
+    data: Vec<u8>,
+    kind: u8,
+    header_len: usize,
+    restarts: Vec<usize>,
+    last_key: Vec<u8>,
+    num_records: usize,
+    block_size: Option<usize>,
+}
+
+impl BlockWriter {
+    fn new(kind: u8, file_header: Option<&[u8]>, block_size: Option<usize>) -> Self {
+        let mut data = Vec::with_capacity(block_size.unwrap_or(4096));
+        if let Some(header) = file_header {
+            data.extend_from_slice(header);
+        }
+        let header_len = data.len();
+        data.extend_from_slice(&[kind, 0, 0, 0]);
+        BlockWriter {
+            data,
+            kind,
+            header_len,
+            restarts: Vec::new(),
+            last_key: Vec::new(),
+            num_records: 0,
+            block_size,
+        }
+    }
+
+    /// Add a record and return true, or return false if it doesn't fit into a block that already has records.
+    fn add(&mut self, key: &[u8], value_type: u8, value: &[u8]) -> bool {
+        let is_restart = self.num_records % RESTART_INTERVAL == 0;
+        let prefix_len = if is_restart {
+            0
+        } else {
+            common_prefix_len(&self.last_key, key)
+        };
+        let mut record = Vec::with_capacity(key.len() - prefix_len + value.len() + 4);
+        varint::encode(prefix_len as u64, &mut record);
+        varint::encode(((key.len() - prefix_len) << 3) as u64 | value_type as u64, &mut record);
+        record.extend_from_slice(&key[prefix_len..]);
+        record.extend_from_slice(value);
+
+        let num_restarts = self.restarts.len() + usize::from(is_restart);
+        let block_len = self.data.len() + record.len() + num_restarts * 3 + 2;
+        if self.num_records > 0 && self.block_size.map_or(false, |block_size| block_len > block_size) {
+            return false;
+        }
+        if is_restart {
+            self.restarts.push(self.data.len());
+        }
+        self.data.extend_from_slice(&record);
+        self.last_key.clear();
+        self.last_key.extend_from_slice(key);
+        self.num_records += 1;
+        true
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        for restart in &self.restarts {
+            self.data.extend_from_slice(&(*restart as u32).to_be_bytes()[1..]);
+        }
+        self.data.extend_from_slice(&(self.restarts.len() as u16).to_be_bytes());
+        let len = self.data.len();
+        self.data[self.header_len + 1..self.header_len + BLOCK_HEADER_LEN]
+            .copy_from_slice(&(len as u32).to_be_bytes()[1..]);
+        if self.kind != kind::LOG {
+            return self.data;
+        }
+        let records_start = self.header_len + BLOCK_HEADER_LEN;
+        let mut out = self.data[..records_start].to_vec();
+        {
+            use std::io::Write;
+            let mut deflate = git_features::zlib::stream::deflate::Write::new(&mut out);
+            deflate
+                .write_all(&self.data[records_start..])
+                .and_then(|_| deflate.flush())
+                .expect("writes to memory don't fail");
+        }
+        out
+    }
+}
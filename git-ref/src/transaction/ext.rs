@@ -2,7 +2,7 @@ use git_object::bstr::BString;
 
 use crate::{
     transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog, Target},
-    PartialNameRef,
+    FullName, PartialNameRef,
 };
 
 /// An extension trait to perform commonly used operations on edits across different ref stores.
@@ -13,6 +13,9 @@ where
     /// Return true if each ref `name` has exactly one `edit` across multiple ref edits
     fn assure_one_name_has_one_edit(&self) -> Result<(), BString>;
 
+    /// Return the name of the first reference to be renamed or copied as error, if there is one.
+    fn assure_no_relocations(&self) -> Result<(), BString>;
+
     /// Split all symbolic refs into updates for the symbolic ref as well as all their referents if the `deref` flag is enabled.
     ///
     /// Note no action is performed if deref isn't specified.
@@ -22,24 +25,61 @@ where
         make_entry: impl FnMut(usize, RefEdit) -> T,
     ) -> Result<(), std::io::Error>;
 
+    /// Turn all renames and copies into updates creating the new reference, which are appended after being passed to
+    /// `make_relocation(source_name, edit)`. Renames additionally turn into a deletion of the source reference.
+    ///
+    /// `find` is used to obtain the current value of the source reference, which is checked again for renames once the
+    /// reference is locked. Copies are verified against the value returned by `find` only.
+    fn extend_with_splits_of_renames_and_copies(
+        &mut self,
+        find: impl FnMut(PartialNameRef<'_>) -> Option<Target>,
+        make_relocation: impl FnMut(FullName, RefEdit) -> T,
+    ) -> Result<(), std::io::Error>;
+
     /// All processing steps in one and in the correct order.
     ///
     /// Users call this to assure derefs are honored and duplicate checks are done.
+    /// Renames and copies cause an error, use [`pre_process_with_relocations()`][RefEditsExt::pre_process_with_relocations()]
+    /// to support them as well.
     fn pre_process(
         &mut self,
         find: impl FnMut(PartialNameRef<'_>) -> Option<Target>,
         make_entry: impl FnMut(usize, RefEdit) -> T,
     ) -> Result<(), std::io::Error> {
-        self.extend_with_splits_of_symbolic_refs(find, make_entry)?;
-        self.assure_one_name_has_one_edit().map_err(|name| {
+        self.assure_no_relocations().map_err(|name| {
             std::io::Error::new(
-                std::io::ErrorKind::AlreadyExists,
-                format!("A reference named '{}' has multiple edits", name),
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Reference '{}' cannot be renamed or copied without pre-processing relocations",
+                    name
+                ),
             )
-        })
+        })?;
+        self.extend_with_splits_of_symbolic_refs(find, make_entry)?;
+        self.assure_one_name_has_one_edit().map_err(duplicate_edit_error)
+    }
+
+    /// Like [`pre_process()`][RefEditsExt::pre_process()], but also splits renames and copies, passing the updates that
+    /// create the new references to `make_relocation(source_name, edit)`.
+    fn pre_process_with_relocations(
+        &mut self,
+        mut find: impl FnMut(PartialNameRef<'_>) -> Option<Target>,
+        make_entry: impl FnMut(usize, RefEdit) -> T,
+        make_relocation: impl FnMut(FullName, RefEdit) -> T,
+    ) -> Result<(), std::io::Error> {
+        self.extend_with_splits_of_renames_and_copies(&mut find, make_relocation)?;
+        self.extend_with_splits_of_symbolic_refs(find, make_entry)?;
+        self.assure_one_name_has_one_edit().map_err(duplicate_edit_error)
     }
 }
 
+fn duplicate_edit_error(name: BString) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::AlreadyExists,
+        format!("A reference named '{}' has multiple edits", name),
+    )
+}
+
 impl<E> RefEditsExt<E> for Vec<E>
 where
     E: std::borrow::Borrow<RefEdit> + std::borrow::BorrowMut<RefEdit>,
@@ -53,6 +93,17 @@ where
         }
     }
 
+    fn assure_no_relocations(&self) -> Result<(), BString> {
+        match self
+            .iter()
+            .map(|e| e.borrow())
+            .find(|e| matches!(e.change, Change::Rename { .. } | Change::Copy { .. }))
+        {
+            Some(edit) => Err(edit.name.as_bstr().to_owned()),
+            None => Ok(()),
+        }
+    }
+
     fn extend_with_splits_of_symbolic_refs(
         &mut self,
         mut find: impl FnMut(PartialNameRef<'_>) -> Option<Target>,
@@ -72,6 +123,9 @@ where
                 // In any case, we don't want the following algorithms to try dereffing it and assume they deal with
                 // broken refs gracefully.
                 edit.deref = false;
+                if matches!(edit.change, Change::Rename { .. } | Change::Copy { .. }) {
+                    continue;
+                }
                 if let Some(Target::Symbolic(referent)) = find(edit.name.to_partial()) {
                     new_edits.push(make_entry(
                         eid,
@@ -111,6 +165,7 @@ where
                                     deref: true,
                                 }
                             }
+                            Change::Rename { .. } | Change::Copy { .. } => unreachable!("skipped above"),
                         },
                     ));
                 }
@@ -133,4 +188,93 @@ where
             self.append(&mut new_edits);
         }
     }
+
+    fn extend_with_splits_of_renames_and_copies(
+        &mut self,
+        mut find: impl FnMut(PartialNameRef<'_>) -> Option<Target>,
+        mut make_relocation: impl FnMut(FullName, RefEdit) -> E,
+    ) -> Result<(), std::io::Error> {
+        let mut relocations = Vec::new();
+        let mut eid = 0;
+        while eid < self.len() {
+            let edit = self[eid].borrow_mut();
+            let (expected, new_name, message, is_rename) = match &edit.change {
+                Change::Rename {
+                    expected,
+                    new_name,
+                    message,
+                } => (expected, new_name, message, true),
+                Change::Copy {
+                    expected,
+                    new_name,
+                    message,
+                } => (expected, new_name, message, false),
+                Change::Update { .. } | Change::Delete { .. } => {
+                    eid += 1;
+                    continue;
+                }
+            };
+            let operation = if is_rename { "rename" } else { "copy" };
+            let current = find(edit.name.to_partial()).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!(
+                        "Cannot {} reference '{}' to '{}' as it does not exist",
+                        operation,
+                        edit.name.as_bstr(),
+                        new_name.as_bstr()
+                    ),
+                )
+            })?;
+            let expected = match expected {
+                PreviousValue::MustNotExist => {
+                    panic!("BUG: MustNotExist constraint makes no sense if references are to be renamed or copied")
+                }
+                PreviousValue::MustExistAndMatch(expected) | PreviousValue::ExistingMustMatch(expected) => {
+                    expected.clone()
+                }
+                PreviousValue::Any | PreviousValue::MustExist => current.clone(),
+            };
+            if !is_rename && expected != current {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "Cannot copy reference '{}' to '{}' as it should have content {}, actual content was {}",
+                        edit.name.as_bstr(),
+                        new_name.as_bstr(),
+                        expected,
+                        current
+                    ),
+                ));
+            }
+            relocations.push(make_relocation(
+                edit.name.clone(),
+                RefEdit {
+                    change: Change::Update {
+                        log: LogChange {
+                            mode: RefLog::AndReference,
+                            force_create_reflog: false,
+                            message: message.clone(),
+                        },
+                        expected: PreviousValue::MustNotExist,
+                        new: current,
+                    },
+                    name: new_name.clone(),
+                    deref: false,
+                },
+            ));
+            if is_rename {
+                edit.change = Change::Delete {
+                    expected: PreviousValue::MustExistAndMatch(expected),
+                    log: RefLog::AndReference,
+                };
+                edit.deref = false;
+                eid += 1;
+            } else {
+                self.remove(eid);
+            }
+        }
+        self.append(&mut relocations);
+        Ok(())
+    }
 }
@@ -5,6 +5,7 @@
 //!
 //! * create or update reference
 //! * delete references
+//! * rename or copy references along with their reflog
 //!
 //! The following guarantees are made:
 //!
@@ -80,6 +81,36 @@ pub enum Change {
         /// How to thread the reference log during deletion.
         log: RefLog,
     },
+    /// Rename a reference to `new_name` and move its reflog along with it, adding an entry with `message`.
+    ///
+    /// The reference must exist, and `new_name` must not exist. Symbolic references are renamed themselves, their referent
+    /// isn't touched.
+    /// During [pre-processing][RefEditsExt::pre_process_with_relocations()] this change is split into a deletion of the reference and the
+    /// creation of `new_name`.
+    Rename {
+        /// The expected value of the reference to rename, with the `MustNotExist` variant being invalid.
+        ///
+        /// No matter what's specified here, the reference must exist.
+        expected: PreviousValue,
+        /// The name the reference should have after the rename.
+        new_name: FullName,
+        /// The message to put into the reflog of `new_name`.
+        message: BString,
+    },
+    /// Copy a reference to `new_name` along with its reflog, adding an entry with `message` to the copied reflog.
+    ///
+    /// The reference must exist, and `new_name` must not exist.
+    /// During [pre-processing][RefEditsExt::pre_process_with_relocations()] this change is turned into the creation of `new_name`.
+    Copy {
+        /// The expected value of the reference to copy, with the `MustNotExist` variant being invalid.
+        ///
+        /// No matter what's specified here, the reference must exist.
+        expected: PreviousValue,
+        /// The name of the copy.
+        new_name: FullName,
+        /// The message to put into the reflog of `new_name`.
+        message: BString,
+    },
 }
 
 impl Change {
@@ -93,6 +124,14 @@ impl Change {
             Change::Delete {
                 expected: PreviousValue::MustExistAndMatch(previous) | PreviousValue::ExistingMustMatch(previous),
                 ..
+            }
+            | Change::Rename {
+                expected: PreviousValue::MustExistAndMatch(previous) | PreviousValue::ExistingMustMatch(previous),
+                ..
+            }
+            | Change::Copy {
+                expected: PreviousValue::MustExistAndMatch(previous) | PreviousValue::ExistingMustMatch(previous),
+                ..
             } => previous,
             _ => return None,
         }
@@ -155,3 +155,46 @@ mod loose {
         Ok(())
     }
 }
+
+mod all {
+    use crate::file::{store, store_at};
+
+    #[test]
+    fn ambiguous_names_yield_all_candidates_in_order_of_precedence() -> crate::Result {
+        let store = store()?;
+        for (partial_name, expected) in &[
+            ("dt1", &["refs/tags/dt1", "refs/heads/dt1"][..]),
+            ("d1", &["refs/d1", "refs/heads/d1"]),
+            ("origin", &["refs/remotes/origin/HEAD"]),
+            ("main", &["refs/heads/main"]),
+            ("refs/heads/main", &["refs/heads/main"]),
+            ("HEAD", &["HEAD"]),
+            ("foobar", &[]),
+        ] {
+            let names: Vec<_> = store
+                .try_find_all(*partial_name)?
+                .into_iter()
+                .map(|r| r.name.as_bstr().to_owned())
+                .collect();
+            assert_eq!(names, *expected, "{}", partial_name);
+            assert_eq!(
+                store.try_find(*partial_name)?.map(|r| r.name),
+                store.try_find_all(*partial_name)?.into_iter().next().map(|r| r.name),
+                "the first candidate is the one that is found"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn packed_references_are_candidates_as_well() -> crate::Result {
+        let store = store_at("make_packed_refs_for_lookup_rules.sh")?;
+        let names: Vec<_> = store
+            .try_find_all("head-or-tag")?
+            .into_iter()
+            .map(|r| r.name.as_bstr().to_owned())
+            .collect();
+        assert_eq!(names, vec!["refs/tags/head-or-tag", "refs/heads/head-or-tag"]);
+        Ok(())
+    }
+}
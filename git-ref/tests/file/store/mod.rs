@@ -1,3 +1,5 @@
 mod find;
 mod iter;
+mod pack_refs;
 mod reflog;
+mod worktree;
@@ -0,0 +1,73 @@
+use git_object::bstr::BString;
+use git_ref::file::{pack_refs, transaction::FindObjectFn};
+
+use crate::file::{store_with_packed_refs, store_writable};
+
+fn find_in(store: &git_ref::file::Store) -> crate::Result<Box<FindObjectFn>> {
+    let odb = git_odb::compound::Store::at(store.base().join("objects"), 0)?;
+    Ok(Box::new(move |oid, buf| {
+        odb.try_find(oid, buf, &mut git_odb::pack::cache::Never)
+            .map(|obj| obj.map(|obj| obj.kind))
+            .map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send + Sync>)
+    }))
+}
+
+#[test]
+fn all_references_with_pruning_is_like_git() -> crate::Result {
+    let (_keep, store) = store_writable("make_ref_repository.sh")?;
+    let packed = store.pack_refs(
+        pack_refs::Options {
+            all: true,
+            ..Default::default()
+        },
+        find_in(&store)?,
+    )?;
+    assert_eq!(packed.len(), 8, "symbolic and broken references are not packed");
+    assert!(
+        store
+            .loose_iter()?
+            .filter_map(Result::ok)
+            .all(|r| r.kind() == git_ref::Kind::Symbolic),
+        "only symbolic refs are left"
+    );
+
+    let expected: BString = std::fs::read(store_with_packed_refs()?.packed_refs_path())?.into();
+    let actual: BString = std::fs::read(store.packed_refs_path())?.into();
+    assert_eq!(actual, expected, "tags are peeled and the header is the same as git's");
+
+    assert!(
+        store.pack_refs(Default::default(), find_in(&store)?)?.is_empty(),
+        "there is nothing left to pack"
+    );
+    Ok(())
+}
+
+#[test]
+fn tags_only_by_default_and_loose_refs_are_kept_without_pruning() -> crate::Result {
+    let (_keep, store) = store_writable("make_ref_repository.sh")?;
+    let packed = store.pack_refs(
+        pack_refs::Options {
+            prune: false,
+            ..Default::default()
+        },
+        find_in(&store)?,
+    )?;
+    assert_eq!(
+        packed.iter().map(|name| name.as_bstr().to_owned()).collect::<Vec<_>>(),
+        vec!["refs/tags/dt1", "refs/tags/t1"]
+    );
+    let buffer = store.open_packed_buffer()?.expect("written");
+    let tag = buffer.find("dt1")?;
+    assert!(tag.object.is_some(), "annotated tags are peeled");
+    assert_eq!(buffer.iter()?.count(), 2);
+    assert!(store.find_loose("dt1")?.target.as_id().is_some(), "loose tags remain");
+
+    let packed = store.pack_refs(Default::default(), find_in(&store)?)?;
+    assert_eq!(packed.len(), 2, "references that are already packed are packed again");
+    assert!(store.try_find_loose("refs/tags/t1")?.is_none(), "and pruned this time");
+    assert!(
+        store.try_find_loose("main")?.is_some(),
+        "branches aren't packed by default"
+    );
+    Ok(())
+}
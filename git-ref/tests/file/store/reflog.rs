@@ -64,3 +64,268 @@ mod iter_rev {
         Ok(())
     }
 }
+
+mod expire {
+    use std::{collections::HashMap, convert::Infallible};
+
+    use git_hash::ObjectId;
+    use git_lock::acquire::Fail;
+    use git_object::bstr::ByteSlice;
+    use git_ref::file::log::expire::{self, Options};
+
+    fn id(hex_digit: char) -> ObjectId {
+        ObjectId::from_hex(hex_digit.to_string().repeat(40).as_bytes()).expect("valid hex")
+    }
+
+    fn tree() -> ObjectId {
+        id('e')
+    }
+
+    /// `main` is at commit `3` whose ancestors are `2` and `1`, while `a` is a commit based on `1` which isn't reachable anymore.
+    fn objects() -> HashMap<ObjectId, (git_object::Kind, Vec<u8>)> {
+        let commit = |parent: Option<char>| {
+            let mut data = format!("tree {}\n", tree());
+            if let Some(parent) = parent {
+                data.push_str(&format!("parent {}\n", id(parent)));
+            }
+            data.push_str("author a <a@example.com> 0 +0000\ncommitter a <a@example.com> 0 +0000\n\nmessage\n");
+            (git_object::Kind::Commit, data.into_bytes())
+        };
+        let mut objects = HashMap::new();
+        objects.insert(id('1'), commit(None));
+        objects.insert(id('2'), commit(Some('1')));
+        objects.insert(id('3'), commit(Some('2')));
+        objects.insert(id('a'), commit(Some('1')));
+        objects.insert(tree(), (git_object::Kind::Tree, Vec::new()));
+        objects
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn find(
+        objects: &HashMap<ObjectId, (git_object::Kind, Vec<u8>)>,
+    ) -> impl FnMut(ObjectId, &mut Vec<u8>) -> Result<Option<(git_object::Kind, &[u8])>, Infallible> + '_ {
+        move |id, buf| {
+            Ok(objects.get(&id).map(move |(kind, data)| {
+                buf.clear();
+                buf.extend_from_slice(data);
+                (*kind, buf.as_slice())
+            }))
+        }
+    }
+
+    fn store_with_reflog() -> crate::Result<(tempfile::TempDir, git_ref::file::Store)> {
+        let dir = tempfile::TempDir::new()?;
+        let store = git_ref::file::Store::at(dir.path(), git_ref::store::WriteReflog::Normal);
+        std::fs::create_dir_all(dir.path().join("refs/heads"))?;
+        std::fs::write(dir.path().join("refs/heads/main"), format!("{}\n", id('3')))?;
+        std::fs::create_dir_all(dir.path().join("logs/refs/heads"))?;
+        let line = |previous: ObjectId, new: ObjectId, time: u32, message: &str| {
+            format!(
+                "{} {} committer <committer@example.com> {} +0000\t{}\n",
+                previous, new, time, message
+            )
+        };
+        let null = ObjectId::null_sha1();
+        std::fs::write(
+            dir.path().join("logs/refs/heads/main"),
+            [
+                line(null, id('1'), 100, "commit (initial): c1"),
+                line(id('1'), id('a'), 200, "commit: a"),
+                line(id('a'), id('2'), 300, "reset: moving to 2"),
+                line(id('2'), id('3'), 400, "commit: c3"),
+                line(id('3'), id('3'), 500, "commit (amend): c3"),
+            ]
+            .concat(),
+        )?;
+        Ok((dir, store))
+    }
+
+    fn messages(store: &git_ref::file::Store) -> crate::Result<Vec<String>> {
+        let mut buf = Vec::new();
+        Ok(store
+            .reflog_iter("refs/heads/main", &mut buf)?
+            .expect("exists")
+            .map(|line| line.map(|line| line.message.to_str_lossy().into_owned()))
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn options() -> Options {
+        Options {
+            expire: None,
+            expire_unreachable: None,
+            stale_fix: false,
+            rewrite: false,
+            dry_run: false,
+        }
+    }
+
+    #[test]
+    fn non_existing_reflogs_yield_none() -> crate::Result {
+        let (_keep, store) = store_with_reflog()?;
+        let objects = objects();
+        assert!(store
+            .reflog_expire("refs/heads/other", options(), Fail::Immediately, find(&objects))?
+            .is_none());
+        assert!(store
+            .reflog_delete("refs/heads/other", Some(0), false, Fail::Immediately)?
+            .is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn old_entries_are_removed_and_the_next_previous_oid_is_rewritten() -> crate::Result {
+        let (_keep, store) = store_with_reflog()?;
+        let objects = objects();
+        let outcome = store
+            .reflog_expire(
+                "refs/heads/main",
+                Options {
+                    expire: Some(250),
+                    rewrite: true,
+                    ..options()
+                },
+                Fail::Immediately,
+                find(&objects),
+            )?
+            .expect("reflog exists");
+        assert_eq!(outcome.kept, 3);
+        assert_eq!(
+            outcome.removed.iter().map(|l| l.new_oid).collect::<Vec<_>>(),
+            vec![id('1'), id('a')]
+        );
+        assert_eq!(
+            messages(&store)?,
+            vec!["reset: moving to 2", "commit: c3", "commit (amend): c3"]
+        );
+        let mut buf = Vec::new();
+        let first = store
+            .reflog_iter("refs/heads/main", &mut buf)?
+            .expect("exists")
+            .next()
+            .expect("one line")?;
+        assert!(
+            first.previous_oid().is_null(),
+            "the first entry now looks like a creation"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unreachable_entries_are_removed_if_old_enough() -> crate::Result {
+        let (_keep, store) = store_with_reflog()?;
+        let objects = objects();
+        let outcome = store
+            .reflog_expire(
+                "refs/heads/main",
+                Options {
+                    expire_unreachable: Some(250),
+                    ..options()
+                },
+                Fail::Immediately,
+                find(&objects),
+            )?
+            .expect("reflog exists");
+        assert_eq!(outcome.removed.len(), 1, "the entry at 300 is too young despite 'a'");
+        assert_eq!(
+            messages(&store)?,
+            vec![
+                "commit (initial): c1",
+                "reset: moving to 2",
+                "commit: c3",
+                "commit (amend): c3"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn stale_entries_are_removed_and_dry_runs_change_nothing() -> crate::Result {
+        let (_keep, store) = store_with_reflog()?;
+        let mut objects = objects();
+        objects.remove(&id('a'));
+        for dry_run in [true, false] {
+            let outcome = store
+                .reflog_expire(
+                    "refs/heads/main",
+                    Options {
+                        stale_fix: true,
+                        dry_run,
+                        ..options()
+                    },
+                    Fail::Immediately,
+                    find(&objects),
+                )?
+                .expect("reflog exists");
+            assert_eq!(
+                outcome
+                    .removed
+                    .iter()
+                    .map(|l| l.message.to_string())
+                    .collect::<Vec<_>>(),
+                vec!["commit: a", "reset: moving to 2"],
+                "both entries referring to the missing commit are removed"
+            );
+            assert_eq!(messages(&store)?.len(), if dry_run { 5 } else { 3 });
+        }
+        assert!(
+            !store.base().join("logs/refs/heads/main.lock").exists(),
+            "locks are released"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn delete_individual_entries_by_recency() -> crate::Result {
+        let (_keep, store) = store_with_reflog()?;
+        let outcome = store
+            .reflog_delete("refs/heads/main", [0, 4], false, Fail::Immediately)?
+            .expect("reflog exists");
+        assert_eq!(outcome.kept, 3);
+        assert_eq!(messages(&store)?, vec!["commit: a", "reset: moving to 2", "commit: c3"]);
+
+        let err = store
+            .reflog_delete("refs/heads/main", Some(3), false, Fail::Immediately)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The reflog of 'refs/heads/main' has no entry with index 3"
+        );
+        assert_eq!(messages(&store)?.len(), 3, "nothing changes on error");
+        Ok(())
+    }
+
+    #[test]
+    fn parse_cutoff() -> crate::Result {
+        let now = 10 * 24 * 60 * 60;
+        for (input, expected) in [
+            ("never", None),
+            ("false", None),
+            ("now", Some(u32::MAX)),
+            ("all", Some(u32::MAX)),
+            ("90.days.ago", Some(0)),
+            ("2.days.ago", Some(now - 2 * 24 * 60 * 60)),
+            ("1 week ago", Some(now - 7 * 24 * 60 * 60)),
+            ("30 minutes", Some(now - 30 * 60)),
+            ("1970-01-01", Some(0)),
+            ("2023-01-01", Some(1672531200)),
+            ("2024-02-29 12:30", Some(1709209800)),
+            ("2024-02-29T12:30:15", Some(1709209815)),
+        ] {
+            assert_eq!(expire::parse_cutoff(input.into(), now)?, expected, "{}", input);
+        }
+        for invalid in [
+            "",
+            "yesterday",
+            "2.fortnights.ago",
+            "2.days.ago.really",
+            "2023-02-29",
+            "2023-13-01",
+            "1969-12-31",
+            "2023-01-01 25:00",
+            "2023-01-01-01",
+        ] {
+            assert!(expire::parse_cutoff(invalid.into(), now).is_err(), "{}", invalid);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+use std::convert::TryInto;
+
+use git_lock::acquire::Fail;
+use git_ref::{
+    file,
+    store::WriteReflog,
+    transaction::{Change, LogChange, PreviousValue, RefEdit},
+    Target,
+};
+
+fn main_and_linked_worktree_store(dir: &std::path::Path) -> (file::Store, file::Store) {
+    let common_dir = dir.join(".git");
+    (
+        file::Store::at(&common_dir, WriteReflog::Normal),
+        file::Store::for_linked_worktree(
+            common_dir.join("worktrees").join("wt"),
+            &common_dir,
+            WriteReflog::Normal,
+        ),
+    )
+}
+
+fn names(
+    refs: impl Iterator<Item = Result<git_ref::Reference, impl std::error::Error + 'static>>,
+) -> crate::Result<Vec<String>> {
+    Ok(refs
+        .map(|r| r.map(|r| r.name.as_bstr().to_string()))
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+#[test]
+fn per_worktree_refs_are_private_while_all_others_are_shared() -> crate::Result {
+    let dir = git_testtools::scripted_fixture_repo_read_only("make_worktree_repository.sh")?;
+    let (main, linked) = main_and_linked_worktree_store(&dir);
+    assert_eq!(linked.common_dir(), Some(main.base()));
+    assert_eq!(linked.common_dir_resolved(), main.base());
+    assert_eq!(main.common_dir_resolved(), main.base());
+
+    assert_eq!(
+        linked.find_loose("HEAD")?.target.to_ref().as_name(),
+        Some("refs/heads/other".into())
+    );
+    assert_eq!(
+        main.find_loose("HEAD")?.target.to_ref().as_name(),
+        Some("refs/heads/main".into())
+    );
+
+    assert!(linked.try_find("refs/bisect/good")?.is_some());
+    assert!(main.try_find("refs/bisect/good")?.is_none());
+    assert!(linked.try_find("refs/bisect/bad")?.is_none());
+    assert!(main.try_find("refs/bisect/bad")?.is_some());
+
+    for shared in &["refs/heads/shared", "refs/heads/main", "refs/tags/t1"] {
+        assert_eq!(
+            linked.try_find(*shared)?,
+            main.try_find(*shared)?,
+            "loose and packed references are shared"
+        );
+    }
+    assert_eq!(
+        linked.packed_refs_path(),
+        main.packed_refs_path(),
+        "packed refs are shared as well"
+    );
+    Ok(())
+}
+
+#[test]
+fn references_of_other_worktrees_are_accessible_by_prefix() -> crate::Result {
+    let dir = git_testtools::scripted_fixture_repo_read_only("make_worktree_repository.sh")?;
+    let (main, linked) = main_and_linked_worktree_store(&dir);
+
+    let head = linked.find_loose("main-worktree/HEAD")?;
+    assert_eq!(head.name.as_bstr(), "main-worktree/HEAD");
+    assert_eq!(head.target.to_ref().as_name(), Some("refs/heads/main".into()));
+    assert!(linked.try_find("main-worktree/refs/bisect/bad")?.is_some());
+
+    let head = main.find_loose("worktrees/wt/HEAD")?;
+    assert_eq!(head.target.to_ref().as_name(), Some("refs/heads/other".into()));
+    assert!(main.try_find("worktrees/wt/refs/worktree/private")?.is_some());
+    assert!(linked.try_find("worktrees/wt/refs/bisect/good")?.is_some());
+    assert!(main.try_find("worktrees/missing/HEAD")?.is_none());
+    Ok(())
+}
+
+#[test]
+fn iteration_merges_per_worktree_refs_with_shared_ones() -> crate::Result {
+    let dir = git_testtools::scripted_fixture_repo_read_only("make_worktree_repository.sh")?;
+    let (main, linked) = main_and_linked_worktree_store(&dir);
+
+    assert_eq!(
+        names(linked.iter()?.all()?)?,
+        vec![
+            "refs/bisect/good",
+            "refs/heads/main",
+            "refs/heads/other",
+            "refs/heads/shared",
+            "refs/tags/t1",
+            "refs/worktree/private"
+        ]
+    );
+    assert_eq!(
+        names(main.iter()?.all()?)?,
+        vec![
+            "refs/bisect/bad",
+            "refs/heads/main",
+            "refs/heads/other",
+            "refs/heads/shared",
+            "refs/tags/t1"
+        ]
+    );
+    assert_eq!(
+        names(linked.iter()?.prefixed("refs/bisect/")?)?,
+        vec!["refs/bisect/good"]
+    );
+    assert_eq!(
+        names(linked.iter()?.prefixed("refs/worktree")?)?,
+        vec!["refs/worktree/private"]
+    );
+    assert_eq!(
+        linked
+            .loose_iter()?
+            .map(|r| r.map(|r| r.name.as_bstr().to_string()))
+            .collect::<Result<Vec<_>, _>>()?,
+        vec![
+            "refs/bisect/good",
+            "refs/heads/main",
+            "refs/heads/shared",
+            "refs/worktree/private"
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn reflogs_are_kept_next_to_their_references() -> crate::Result {
+    let dir = git_testtools::scripted_fixture_repo_read_only("make_worktree_repository.sh")?;
+    let (main, linked) = main_and_linked_worktree_store(&dir);
+    let mut buf = Vec::new();
+    for (store, branch) in &[(&main, "refs/heads/main"), (&linked, "refs/heads/other")] {
+        let last_head_entry = store
+            .reflog_iter("HEAD", &mut buf)?
+            .expect("present")
+            .last()
+            .expect("non-empty")?;
+        assert_eq!(
+            last_head_entry.new_oid(),
+            store.find(*branch)?.target.into_id(),
+            "each worktree has its own HEAD log"
+        );
+    }
+    assert!(linked.reflog_exists("refs/heads/shared")?);
+    Ok(())
+}
+
+#[test]
+fn transactions_write_per_worktree_refs_into_the_private_git_dir() -> crate::Result {
+    let dir = git_testtools::scripted_fixture_repo_writable("make_worktree_repository.sh")?;
+    let (main, linked) = main_and_linked_worktree_store(dir.path());
+    let id = linked.find_loose("refs/heads/shared")?.target.into_id();
+    let edits = ["refs/bisect/new", "refs/heads/new"]
+        .iter()
+        .map(|name| {
+            Ok(RefEdit {
+                change: Change::Update {
+                    log: LogChange::default(),
+                    expected: PreviousValue::MustNotExist,
+                    new: Target::Peeled(id),
+                },
+                name: (*name).try_into()?,
+                deref: false,
+            })
+        })
+        .collect::<crate::Result<Vec<_>>>()?;
+    let committer = git_actor::Signature {
+        name: "committer".into(),
+        email: "committer@example.com".into(),
+        time: git_actor::Time {
+            time: 1234,
+            offset: 1800,
+            sign: git_actor::Sign::Plus,
+        },
+    };
+    linked
+        .transaction()
+        .prepare(edits, Fail::Immediately)?
+        .commit(&committer)?;
+
+    assert!(linked.base().join("refs/bisect/new").is_file());
+    assert!(main.try_find("refs/bisect/new")?.is_none());
+    assert!(main.base().join("refs/heads/new").is_file());
+    assert!(main.reflog_exists("refs/heads/new")?);
+    assert!(!linked.base().join("logs/refs/heads/new").exists());
+    Ok(())
+}
@@ -44,4 +44,10 @@ mod prepare_and_commit {
     mod create_or_update;
 
     mod delete;
+
+    mod hook;
+
+    mod initial;
+
+    mod rename_or_copy;
 }
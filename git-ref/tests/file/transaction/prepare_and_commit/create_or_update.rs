@@ -84,9 +84,8 @@ fn reference_with_old_value_must_exist_when_creating_it() -> crate::Result {
     );
 
     match res {
-        Err(transaction::prepare::Error::MustExist { full_name, expected }) => {
+        Err(transaction::prepare::Error::ReferenceMustExist { full_name }) => {
             assert_eq!(full_name, "HEAD");
-            assert_eq!(expected, Target::must_exist());
         }
         _ => unreachable!("unexpected result"),
     }
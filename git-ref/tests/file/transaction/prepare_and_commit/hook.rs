@@ -0,0 +1,191 @@
+use std::{
+    convert::TryInto,
+    sync::{Arc, Mutex},
+};
+
+use git_lock::acquire::Fail;
+use git_object::bstr::BString;
+use git_ref::{
+    file::transaction::{self, hook},
+    transaction::{Change, LogChange, PreviousValue, RefEdit},
+    Target,
+};
+use git_testtools::hex_to_id;
+
+use crate::file::transaction::prepare_and_commit::{committer, empty_store};
+
+type Calls = Arc<Mutex<Vec<(hook::State, BString)>>>;
+
+fn recording_hook(reject_prepared: bool) -> (Calls, Box<transaction::HookFn>) {
+    let calls = Calls::default();
+    let hook = {
+        let calls = Arc::clone(&calls);
+        Box::new(move |state, edits: &[RefEdit]| {
+            calls.lock().unwrap().push((state, hook::to_input(edits)));
+            if reject_prepared && state == hook::State::Prepared {
+                Err("rejected".into())
+            } else {
+                Ok(())
+            }
+        })
+    };
+    (calls, hook)
+}
+
+fn update_main(expected: PreviousValue, new: Target) -> crate::Result<RefEdit> {
+    Ok(RefEdit {
+        change: Change::Update {
+            log: LogChange::default(),
+            expected,
+            new,
+        },
+        name: "refs/heads/main".try_into()?,
+        deref: false,
+    })
+}
+
+#[test]
+fn is_called_when_prepared_and_committed_with_previous_and_new_values() -> crate::Result {
+    let (_keep, store) = empty_store()?;
+    let (first, second) = (
+        hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242"),
+        hex_to_id("134385f6d781b7e97062102c6a483440bfda2a03"),
+    );
+    store
+        .transaction()
+        .prepare(
+            Some(update_main(PreviousValue::Any, Target::Peeled(first))?),
+            Fail::Immediately,
+        )?
+        .commit(&committer())?;
+
+    let (calls, hook) = recording_hook(false);
+    store
+        .transaction()
+        .hook(hook)
+        .prepare(
+            vec![
+                update_main(PreviousValue::Any, Target::Peeled(second))?,
+                RefEdit {
+                    change: Change::Update {
+                        log: LogChange::default(),
+                        expected: PreviousValue::MustNotExist,
+                        new: Target::Symbolic("refs/heads/main".try_into()?),
+                    },
+                    name: "HEAD".try_into()?,
+                    deref: false,
+                },
+            ],
+            Fail::Immediately,
+        )?
+        .commit(&committer())?;
+
+    let expected_input = BString::from(format!(
+        "{} {} refs/heads/main\n{} ref:refs/heads/main HEAD\n",
+        first,
+        second,
+        git_hash::ObjectId::null_sha1()
+    ));
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec![
+            (hook::State::Prepared, expected_input.clone()),
+            (hook::State::Committed, expected_input)
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn is_called_when_a_prepared_transaction_is_dropped() -> crate::Result {
+    let (_keep, store) = empty_store()?;
+    let (calls, hook) = recording_hook(false);
+    let transaction = store.transaction().hook(hook).prepare(
+        Some(update_main(
+            PreviousValue::Any,
+            Target::Peeled(git_hash::ObjectId::null_sha1()),
+        )?),
+        Fail::Immediately,
+    )?;
+    drop(transaction);
+
+    assert_eq!(
+        calls.lock().unwrap().iter().map(|t| t.0).collect::<Vec<_>>(),
+        vec![hook::State::Prepared, hook::State::Aborted]
+    );
+    assert!(store.try_find_loose("main")?.is_none(), "nothing was written");
+    assert!(
+        !store.base().join("refs/heads/main.lock").exists(),
+        "locks are released"
+    );
+    Ok(())
+}
+
+#[test]
+fn can_reject_the_transaction_when_it_is_prepared() -> crate::Result {
+    let (_keep, store) = empty_store()?;
+    let (calls, hook) = recording_hook(true);
+    let res = store.transaction().hook(hook).prepare(
+        Some(update_main(
+            PreviousValue::Any,
+            Target::Peeled(git_hash::ObjectId::null_sha1()),
+        )?),
+        Fail::Immediately,
+    );
+    match res {
+        Err(transaction::prepare::Error::HookRejected(err)) => assert_eq!(err.to_string(), "rejected"),
+        _ => unreachable!("the hook rejects the transaction"),
+    }
+    assert_eq!(
+        calls.lock().unwrap().iter().map(|t| t.0).collect::<Vec<_>>(),
+        vec![hook::State::Prepared, hook::State::Aborted],
+        "like git, the hook is told about the abort it caused"
+    );
+    assert!(!store.base().join("refs/heads/main.lock").exists());
+    assert!(store.try_find_loose("main")?.is_none());
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn executables_receive_the_state_as_argument_and_edits_via_stdin() -> crate::Result {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (dir, store) = empty_store()?;
+    let hook_path = dir.path().join("reference-transaction");
+    std::fs::write(
+        &hook_path,
+        "#!/bin/sh\ncat > \"input-$1\"\ntest \"$1\" != prepared || test ! -f reject\n",
+    )?;
+    std::fs::set_permissions(&hook_path, std::fs::Permissions::from_mode(0o755))?;
+    let edit = update_main(PreviousValue::Any, Target::Peeled(git_hash::ObjectId::null_sha1()))?;
+
+    store
+        .transaction()
+        .hook(hook::executable(&hook_path, dir.path()))
+        .prepare(Some(edit.clone()), Fail::Immediately)?
+        .commit(&committer())?;
+    let expected_input = format!("{0} {0} refs/heads/main\n", git_hash::ObjectId::null_sha1());
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("input-prepared"))?,
+        expected_input
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("input-committed"))?,
+        expected_input
+    );
+
+    std::fs::write(dir.path().join("reject"), b"")?;
+    assert!(matches!(
+        store
+            .transaction()
+            .hook(hook::executable(&hook_path, dir.path()))
+            .prepare(Some(edit), Fail::Immediately),
+        Err(transaction::prepare::Error::HookRejected(_))
+    ));
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("input-aborted"))?,
+        expected_input
+    );
+    Ok(())
+}
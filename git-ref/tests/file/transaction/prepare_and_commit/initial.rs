@@ -0,0 +1,125 @@
+use std::convert::TryInto;
+
+use git_lock::acquire::Fail;
+use git_ref::{
+    file::transaction::{self, PackedRefs},
+    transaction::{Change, LogChange, PreviousValue, RefEdit},
+    Target,
+};
+use git_testtools::hex_to_id;
+
+use crate::file::{
+    store_writable,
+    transaction::prepare_and_commit::{committer, empty_store, reflog_lines},
+};
+
+fn initial() -> PackedRefs {
+    PackedRefs::InitialTransaction(Box::new(|_, _| Ok(Some(git_object::Kind::Commit))))
+}
+
+fn create(name: &str, new: Target, force_create_reflog: bool) -> crate::Result<RefEdit> {
+    Ok(RefEdit {
+        change: Change::Update {
+            log: LogChange {
+                message: "clone: from origin".into(),
+                force_create_reflog,
+                ..Default::default()
+            },
+            expected: PreviousValue::MustNotExist,
+            new,
+        },
+        name: name.try_into()?,
+        deref: false,
+    })
+}
+
+#[test]
+fn peeled_references_are_written_into_packed_refs_only() -> crate::Result {
+    let (_keep, store) = empty_store()?;
+    for dir in &["refs/heads", "refs/tags"] {
+        std::fs::create_dir_all(store.base().join(dir))?;
+    }
+    let id = hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242");
+    let edits = store
+        .transaction()
+        .packed_refs(initial())
+        .prepare(
+            vec![
+                create("refs/tags/v1", Target::Peeled(id), false)?,
+                create("refs/remotes/origin/main", Target::Peeled(id), false)?,
+                create("refs/heads/main", Target::Peeled(id), true)?,
+                create("HEAD", Target::Symbolic("refs/heads/main".try_into()?), false)?,
+            ],
+            Fail::Immediately,
+        )?
+        .commit(&committer())?;
+    assert_eq!(edits.len(), 4);
+
+    assert_eq!(
+        store.loose_iter()?.count(),
+        0,
+        "no loose references are written, they are all packed"
+    );
+    let packed = store.open_packed_buffer()?.expect("packed-refs was created");
+    assert_eq!(
+        packed
+            .iter()?
+            .map(|r| r.map(|r| r.name.as_bstr().to_string()))
+            .collect::<Result<Vec<_>, _>>()?,
+        vec!["refs/heads/main", "refs/remotes/origin/main", "refs/tags/v1"],
+        "references are sorted"
+    );
+    assert_eq!(
+        store.find_loose("HEAD")?.target,
+        Target::Symbolic("refs/heads/main".try_into()?),
+        "symbolic references are written as usual"
+    );
+    assert_eq!(
+        store.find("HEAD")?.target,
+        Target::Symbolic("refs/heads/main".try_into()?)
+    );
+
+    assert_eq!(
+        reflog_lines(&store, "refs/heads/main")?.len(),
+        1,
+        "reflogs are only written if forced"
+    );
+    assert!(!store.reflog_exists("refs/remotes/origin/main")?);
+    Ok(())
+}
+
+#[test]
+fn stores_with_references_are_rejected() -> crate::Result {
+    let (_keep, store) = store_writable("make_ref_repository.sh")?;
+    let id = hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242");
+    match store.transaction().packed_refs(initial()).prepare(
+        Some(create("refs/heads/new", Target::Peeled(id), false)?),
+        Fail::Immediately,
+    ) {
+        Err(transaction::prepare::Error::InitialTransactionOnNonEmptyStore) => {}
+        _ => unreachable!("only empty stores can receive initial transactions"),
+    }
+    Ok(())
+}
+
+#[test]
+fn references_that_must_exist_cannot_be_updated() -> crate::Result {
+    let (_keep, store) = empty_store()?;
+    let id = hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242");
+    let mut edit = create("refs/heads/main", Target::Peeled(id), false)?;
+    if let Change::Update { expected, .. } = &mut edit.change {
+        *expected = PreviousValue::MustExist;
+    }
+    match store
+        .transaction()
+        .packed_refs(initial())
+        .prepare(Some(edit), Fail::Immediately)
+    {
+        Err(transaction::prepare::Error::ReferenceMustExist { full_name }) => {
+            assert_eq!(full_name, "refs/heads/main")
+        }
+        _ => unreachable!("the store is empty"),
+    }
+    assert!(store.open_packed_buffer()?.is_none(), "nothing was written");
+    Ok(())
+}
@@ -0,0 +1,266 @@
+use std::convert::TryInto;
+
+use git_hash::ObjectId;
+use git_lock::acquire::Fail;
+use git_ref::{
+    file::transaction,
+    transaction::{Change, LogChange, PreviousValue, RefEdit},
+    Target,
+};
+use git_testtools::hex_to_id;
+
+use crate::file::{
+    store_writable,
+    transaction::prepare_and_commit::{committer, empty_store, log_line, reflog_lines},
+};
+
+fn create(name: &str, id: ObjectId) -> crate::Result<RefEdit> {
+    Ok(RefEdit {
+        change: Change::Update {
+            log: LogChange {
+                message: "create".into(),
+                ..Default::default()
+            },
+            expected: PreviousValue::MustNotExist,
+            new: Target::Peeled(id),
+        },
+        name: name.try_into()?,
+        deref: false,
+    })
+}
+
+fn rename(name: &str, new_name: &str) -> crate::Result<RefEdit> {
+    Ok(RefEdit {
+        change: Change::Rename {
+            expected: PreviousValue::Any,
+            new_name: new_name.try_into()?,
+            message: "rename".into(),
+        },
+        name: name.try_into()?,
+        deref: false,
+    })
+}
+
+fn copy(name: &str, new_name: &str) -> crate::Result<RefEdit> {
+    Ok(RefEdit {
+        change: Change::Copy {
+            expected: PreviousValue::Any,
+            new_name: new_name.try_into()?,
+            message: "copy".into(),
+        },
+        name: name.try_into()?,
+        deref: false,
+    })
+}
+
+#[test]
+fn rename_moves_the_reference_and_its_reflog() -> crate::Result {
+    let (_keep, store) = empty_store()?;
+    let id = hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242");
+    store
+        .transaction()
+        .prepare(Some(create("refs/heads/main", id)?), Fail::Immediately)?
+        .commit(&committer())?;
+
+    let edits = store
+        .transaction()
+        .prepare(Some(rename("refs/heads/main", "refs/heads/new")?), Fail::Immediately)?
+        .commit(&committer())?;
+    assert_eq!(edits.len(), 2, "the rename is split into a deletion and a creation");
+
+    assert!(store.try_find_loose("refs/heads/main")?.is_none());
+    assert!(!store.reflog_exists("refs/heads/main")?);
+    assert_eq!(
+        store.find_loose("refs/heads/new")?.target,
+        Target::Peeled(id),
+        "the value is retained"
+    );
+    assert_eq!(
+        reflog_lines(&store, "refs/heads/new")?,
+        vec![
+            log_line(ObjectId::null_sha1(), id, "create"),
+            log_line(id, id, "rename")
+        ],
+        "the reflog moves along and records the rename"
+    );
+    Ok(())
+}
+
+#[test]
+fn copy_duplicates_the_reference_and_its_reflog() -> crate::Result {
+    let (_keep, store) = empty_store()?;
+    let id = hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242");
+    store
+        .transaction()
+        .prepare(Some(create("refs/heads/main", id)?), Fail::Immediately)?
+        .commit(&committer())?;
+
+    store
+        .transaction()
+        .prepare(Some(copy("refs/heads/main", "refs/heads/copy")?), Fail::Immediately)?
+        .commit(&committer())?;
+
+    assert_eq!(store.find_loose("refs/heads/main")?.target, Target::Peeled(id));
+    assert_eq!(store.find_loose("refs/heads/copy")?.target, Target::Peeled(id));
+    assert_eq!(
+        reflog_lines(&store, "refs/heads/main")?.len(),
+        1,
+        "the original is untouched"
+    );
+    assert_eq!(
+        reflog_lines(&store, "refs/heads/copy")?,
+        vec![log_line(ObjectId::null_sha1(), id, "create"), log_line(id, id, "copy")]
+    );
+    Ok(())
+}
+
+#[test]
+fn reflogs_are_copied_through_a_lock_file() -> crate::Result {
+    let (_keep, store) = empty_store()?;
+    let id = hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242");
+    store
+        .transaction()
+        .prepare(Some(create("refs/heads/main", id)?), Fail::Immediately)?
+        .commit(&committer())?;
+
+    let reflog_lock = store.base().join("logs/refs/heads/copy.lock");
+    std::fs::write(&reflog_lock, b"")?;
+    assert!(
+        store
+            .transaction()
+            .prepare(Some(copy("refs/heads/main", "refs/heads/copy")?), Fail::Immediately)?
+            .commit(&committer())
+            .is_err(),
+        "the reflog of the copy is locked by someone else"
+    );
+    assert!(
+        store.try_find_loose("refs/heads/copy")?.is_none(),
+        "the reference isn't created without its reflog"
+    );
+    assert!(!store.reflog_exists("refs/heads/copy")?);
+
+    std::fs::remove_file(reflog_lock)?;
+    store
+        .transaction()
+        .prepare(Some(copy("refs/heads/main", "refs/heads/copy")?), Fail::Immediately)?
+        .commit(&committer())?;
+    assert_eq!(reflog_lines(&store, "refs/heads/copy")?.len(), 2);
+    assert!(!store.base().join("logs/refs/heads/copy.lock").exists());
+    Ok(())
+}
+
+#[test]
+fn existing_references_are_never_clobbered() -> crate::Result {
+    let (_keep, store) = empty_store()?;
+    let id = hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242");
+    store
+        .transaction()
+        .prepare(
+            vec![create("refs/heads/main", id)?, create("refs/heads/other", id)?],
+            Fail::Immediately,
+        )?
+        .commit(&committer())?;
+
+    for edit in [
+        rename("refs/heads/main", "refs/heads/other")?,
+        copy("refs/heads/main", "refs/heads/other")?,
+    ] {
+        match store.transaction().prepare(Some(edit), Fail::Immediately) {
+            Err(transaction::prepare::Error::MustNotExist { full_name, .. }) => {
+                assert_eq!(full_name, "refs/heads/other")
+            }
+            _ => unreachable!("the destination exists, even though it has the same value"),
+        }
+    }
+    assert!(
+        store.try_find_loose("refs/heads/main")?.is_some(),
+        "the failed rename was rolled back"
+    );
+    Ok(())
+}
+
+#[test]
+fn relocations_to_parent_or_child_paths_are_rejected() -> crate::Result {
+    let (_keep, store) = empty_store()?;
+    let id = hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242");
+    store
+        .transaction()
+        .prepare(
+            vec![create("refs/heads/a", id)?, create("refs/heads/b/c", id)?],
+            Fail::Immediately,
+        )?
+        .commit(&committer())?;
+
+    for (edit, expected_source, expected_destination) in [
+        (
+            rename("refs/heads/a", "refs/heads/a/b")?,
+            "refs/heads/a",
+            "refs/heads/a/b",
+        ),
+        (
+            rename("refs/heads/b/c", "refs/heads/b")?,
+            "refs/heads/b/c",
+            "refs/heads/b",
+        ),
+        (
+            copy("refs/heads/a", "refs/heads/a/b")?,
+            "refs/heads/a",
+            "refs/heads/a/b",
+        ),
+    ] {
+        match store.transaction().prepare(Some(edit), Fail::Immediately) {
+            Err(transaction::prepare::Error::RelocationToParentOrChild { full_name, new_name }) => {
+                assert_eq!(full_name, expected_source);
+                assert_eq!(new_name, expected_destination);
+            }
+            _ => unreachable!("a reference can't be a directory at the same time"),
+        }
+    }
+    assert_eq!(store.find_loose("refs/heads/a")?.target, Target::Peeled(id));
+    assert_eq!(store.find_loose("refs/heads/b/c")?.target, Target::Peeled(id));
+    assert_eq!(reflog_lines(&store, "refs/heads/a")?.len(), 1, "nothing was changed");
+    Ok(())
+}
+
+#[test]
+fn missing_references_cannot_be_renamed() -> crate::Result {
+    let (_keep, store) = empty_store()?;
+    match store
+        .transaction()
+        .prepare(Some(rename("refs/heads/missing", "refs/heads/new")?), Fail::Immediately)
+    {
+        Err(err) => assert_eq!(
+            err.to_string(),
+            "Edit preprocessing failed with error: Cannot rename reference 'refs/heads/missing' to 'refs/heads/new' as it does not exist"
+        ),
+        Ok(_) => unreachable!("source must exist"),
+    }
+    Ok(())
+}
+
+#[test]
+fn renaming_packed_references_removes_them_from_packed_refs() -> crate::Result {
+    let (_keep, store) = store_writable("make_packed_ref_repository.sh")?;
+    assert!(
+        store.try_find_loose("refs/heads/d1")?.is_none(),
+        "the reference is only packed"
+    );
+    let target = store.find("refs/heads/d1")?.target;
+
+    store
+        .transaction()
+        .prepare(Some(rename("refs/heads/d1", "refs/heads/renamed")?), Fail::Immediately)?
+        .commit(&committer())?;
+
+    assert!(store.try_find("refs/heads/d1")?.is_none());
+    assert!(
+        store
+            .open_packed_buffer()?
+            .expect("still there")
+            .try_find("refs/heads/d1")?
+            .is_none(),
+        "the packed reference was removed"
+    );
+    assert_eq!(store.find_loose("refs/heads/renamed")?.target, target);
+    Ok(())
+}
@@ -0,0 +1,19 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q --ref-format=reftable
+git config commit.gpgsign false
+
+git checkout -q -b main
+git commit -q --allow-empty -m c1
+git branch other
+git tag lightweight
+git tag -m "tag object" annotated
+git commit -q --allow-empty -m c2
+git symbolic-ref refs/heads/symbolic refs/heads/main
+git update-ref -d refs/heads/other
+git branch other HEAD~1
+
+git for-each-ref --format='%(refname) %(objectname) %(*objectname) %(symref)' > refs.list
+git symbolic-ref HEAD > head.symref
+git reflog show --format=%H refs/heads/main > main.reflog
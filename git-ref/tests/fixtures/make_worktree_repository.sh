@@ -0,0 +1,21 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q
+git config commit.gpgsign false
+
+git checkout -q -b main
+git commit -q --allow-empty -m c1
+git branch other
+git tag t1
+git pack-refs --all
+
+git commit -q --allow-empty -m c2
+git update-ref refs/bisect/bad HEAD
+
+git worktree add -q wt other
+(cd wt
+  git update-ref refs/bisect/good HEAD
+  git update-ref refs/worktree/private HEAD
+  git update-ref refs/heads/shared HEAD
+)
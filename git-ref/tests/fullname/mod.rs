@@ -20,3 +20,22 @@ fn prefix_with_namespace_and_stripping() {
         "idempotent stripping"
     );
 }
+
+#[test]
+fn shorten_follows_lookup_rules_from_most_to_least_specific() {
+    for (input, expected) in &[
+        ("refs/heads/main", "main"),
+        ("refs/heads/feature/a", "feature/a"),
+        ("refs/tags/v1.0", "v1.0"),
+        ("refs/remotes/origin/main", "origin/main"),
+        ("refs/remotes/origin/HEAD", "origin"),
+        ("refs/notes/commits", "notes/commits"),
+        ("refs/d1", "d1"),
+        ("HEAD", "HEAD"),
+        ("FETCH_HEAD", "FETCH_HEAD"),
+    ] {
+        let name: git_ref::FullName = (*input).try_into().unwrap();
+        assert_eq!(name.shorten(), *expected, "{}", input);
+        assert_eq!(name.to_ref().shorten(), *expected);
+    }
+}
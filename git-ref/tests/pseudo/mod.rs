@@ -0,0 +1,126 @@
+use git_lock::acquire::Fail;
+use git_ref::pseudo::{self, fetch_head};
+use git_testtools::hex_to_id;
+
+const FETCH_HEAD: &str = "8b31e1b2b4c4e0d7a1b6d2bfa2bdbc9a2f2a4d6b\t\tbranch 'main' of https://example.com/repo
+4b825dc642cb6eb9a060e54bf8d69288fbee4904\tnot-for-merge\tbranch 'dev' of https://example.com/repo
+";
+
+mod fetch_head_entries {
+    use git_object::bstr::ByteSlice;
+
+    use super::*;
+
+    #[test]
+    fn decode_and_round_trip() -> crate::Result {
+        let entries = fetch_head::decode(FETCH_HEAD.as_bytes())?;
+        assert_eq!(
+            entries,
+            vec![
+                fetch_head::Entry {
+                    id: hex_to_id("8b31e1b2b4c4e0d7a1b6d2bfa2bdbc9a2f2a4d6b"),
+                    not_for_merge: false,
+                    description: "branch 'main' of https://example.com/repo".into()
+                },
+                fetch_head::Entry {
+                    id: hex_to_id("4b825dc642cb6eb9a060e54bf8d69288fbee4904"),
+                    not_for_merge: true,
+                    description: "branch 'dev' of https://example.com/repo".into()
+                }
+            ]
+        );
+
+        let mut buf = Vec::new();
+        for entry in &entries {
+            entry.write_to(&mut buf)?;
+        }
+        assert_eq!(buf.as_bstr(), FETCH_HEAD);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_lines_are_reported() {
+        let err = fetch_head::decode(
+            b"4b825dc642cb6eb9a060e54bf8d69288fbee4904\t\tok\n4b825dc642cb6eb9a060e54bf8d69288fbee4904\tfor-merge\tx\n",
+        )
+        .unwrap_err();
+        assert!(matches!(err, pseudo::Error::Decode { line_number: 2, .. }));
+    }
+}
+
+#[test]
+fn merge_head_decode_and_round_trip() -> crate::Result {
+    let data = "8b31e1b2b4c4e0d7a1b6d2bfa2bdbc9a2f2a4d6b\n4b825dc642cb6eb9a060e54bf8d69288fbee4904\n";
+    let ids = pseudo::merge_head::decode(data.as_bytes())?;
+    assert_eq!(
+        ids,
+        vec![
+            hex_to_id("8b31e1b2b4c4e0d7a1b6d2bfa2bdbc9a2f2a4d6b"),
+            hex_to_id("4b825dc642cb6eb9a060e54bf8d69288fbee4904")
+        ]
+    );
+    let mut buf = Vec::new();
+    pseudo::merge_head::write_to(&ids, &mut buf)?;
+    assert_eq!(buf, data.as_bytes());
+    assert!(pseudo::merge_head::decode(b"not a hash\n").is_err());
+    Ok(())
+}
+
+#[test]
+fn pseudo_ref_names() {
+    for name in ["FETCH_HEAD", "ORIG_HEAD", "CHERRY_PICK_HEAD", "AUTO_MERGE_HEAD"] {
+        assert!(pseudo::is_pseudo_ref_name(name.into()), "{}", name);
+    }
+    for name in ["HEAD", "fetch_head", "refs/heads/HEAD", "FETCH-HEAD", "HEADS"] {
+        assert!(!pseudo::is_pseudo_ref_name(name.into()), "{}", name);
+    }
+}
+
+mod file_store {
+    use super::*;
+
+    fn empty_store() -> crate::Result<(tempfile::TempDir, git_ref::file::Store)> {
+        let dir = tempfile::TempDir::new()?;
+        let store = git_ref::file::Store::at(dir.path(), git_ref::store::WriteReflog::Normal);
+        Ok((dir, store))
+    }
+
+    #[test]
+    fn missing_pseudo_refs_yield_none() -> crate::Result {
+        let (_keep, store) = empty_store()?;
+        assert!(store.fetch_head()?.is_none());
+        assert!(store.merge_head()?.is_none());
+        assert!(store.pseudo_ref_id(pseudo::ORIG_HEAD.into())?.is_none());
+        assert!(
+            store.pseudo_ref_id("refs/heads/main".into()).is_err(),
+            "only pseudo-refs can be read"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn write_and_read_back() -> crate::Result {
+        let (_keep, store) = empty_store()?;
+        let entries = fetch_head::decode(FETCH_HEAD.as_bytes())?;
+        store.write_fetch_head(&entries, Fail::Immediately)?;
+        assert_eq!(store.fetch_head()?.expect("written"), entries);
+        assert_eq!(
+            std::fs::read(store.base().join("FETCH_HEAD"))?,
+            FETCH_HEAD.as_bytes(),
+            "the format is the one git writes"
+        );
+        assert_eq!(
+            store.pseudo_ref_id(pseudo::FETCH_HEAD.into())?,
+            Some(entries[0].id),
+            "the first entry is the one to use as single object id"
+        );
+
+        let ids = vec![entries[1].id, entries[0].id];
+        store.write_merge_head(&ids, Fail::Immediately)?;
+        assert_eq!(store.merge_head()?.expect("written"), ids);
+
+        std::fs::write(store.base().join("ORIG_HEAD"), format!("{}\n", entries[1].id))?;
+        assert_eq!(store.pseudo_ref_id(pseudo::ORIG_HEAD.into())?, Some(entries[1].id));
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+//! These tests alter the environment of the whole process, which is why they run in their own binary.
+use std::convert::TryInto;
+
+use git_ref::{
+    file::transaction,
+    reftable,
+    transaction::{Change, LogChange, PreviousValue, RefEdit},
+    Target,
+};
+
+#[test]
+fn transactions_are_refused_in_quarantine_environment() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let store = git_ref::file::Store::at(dir.path(), git_ref::store::WriteReflog::Normal);
+    let edit = RefEdit {
+        change: Change::Update {
+            log: LogChange::default(),
+            expected: PreviousValue::Any,
+            new: Target::Peeled(git_hash::ObjectId::null_sha1()),
+        },
+        name: "refs/heads/main".try_into()?,
+        deref: false,
+    };
+
+    let reftable_dir = tempfile::tempdir()?;
+    let reftable_store = git_ref::reftable::Store::at(reftable_dir.path(), git_ref::store::WriteReflog::Normal);
+    reftable_store.init()?;
+
+    std::env::set_var("GIT_QUARANTINE_PATH", dir.path().join("objects/incoming-123"));
+    let res = store
+        .transaction()
+        .prepare(Some(edit.clone()), git_lock::acquire::Fail::Immediately);
+    let reftable_res = reftable_store
+        .transaction()
+        .prepare(Some(edit.clone()), git_lock::acquire::Fail::Immediately)
+        .map(|_| ());
+    std::env::remove_var("GIT_QUARANTINE_PATH");
+    assert!(matches!(res, Err(transaction::prepare::Error::Quarantined)));
+    assert!(store.try_find_loose("main")?.is_none());
+    assert!(matches!(
+        reftable_res,
+        Err(reftable::transaction::prepare::Error::Quarantined)
+    ));
+
+    store
+        .transaction()
+        .prepare(Some(edit.clone()), git_lock::acquire::Fail::Immediately)?;
+    reftable_store
+        .transaction()
+        .prepare(Some(edit), git_lock::acquire::Fail::Immediately)?;
+    Ok(())
+}
@@ -4,6 +4,8 @@ mod file;
 mod fullname;
 mod namespace;
 mod packed;
+mod pseudo;
 mod reference;
+mod reftable;
 mod store;
 mod transaction;
@@ -0,0 +1,870 @@
+use std::convert::TryInto;
+
+use git_actor::{Sign, Time};
+use git_hash::ObjectId;
+use git_ref::{
+    reftable::{self, LogRecord, Record, Value},
+    store::WriteReflog,
+    transaction::{Change, LogChange, PreviousValue, RefEdit},
+    Target,
+};
+use git_testtools::hex_to_id;
+
+fn committer() -> git_actor::Signature {
+    git_actor::Signature {
+        name: "committer".into(),
+        email: "committer@example.com".into(),
+        time: Time {
+            time: 1234,
+            offset: 1800,
+            sign: Sign::Plus,
+        },
+    }
+}
+
+fn oid(n: u8) -> ObjectId {
+    ObjectId::from_20_bytes(&[n; 20])
+}
+
+fn record(name: &str, update_index: u64, value: Value) -> Record {
+    Record {
+        name: name.try_into().expect("valid name"),
+        update_index,
+        value,
+    }
+}
+
+fn object(target: ObjectId) -> Value {
+    Value::Object { target, peeled: None }
+}
+
+fn empty_store() -> crate::Result<(tempfile::TempDir, reftable::Store)> {
+    let dir = tempfile::TempDir::new()?;
+    let store = reftable::Store::at(dir.path(), WriteReflog::Normal);
+    store.init()?;
+    Ok((dir, store))
+}
+
+/// Configure the repository at `git_dir` to use reftables, like `git init --ref-format=reftable` would.
+fn configure_reftable(git_dir: &std::path::Path) -> std::io::Result<()> {
+    std::fs::write(
+        git_dir.join("config"),
+        "[core]\n\trepositoryformatversion = 1\n[extensions]\n\trefStorage = reftable\n",
+    )
+}
+
+fn update(name: &str, expected: PreviousValue, new: Target) -> RefEdit {
+    RefEdit {
+        change: Change::Update {
+            log: LogChange {
+                message: "an update".into(),
+                ..Default::default()
+            },
+            expected,
+            new,
+        },
+        name: name.try_into().expect("valid name"),
+        deref: false,
+    }
+}
+
+mod table {
+    use std::convert::TryInto;
+
+    use git_object::bstr::ByteSlice;
+    use git_ref::reftable::{LogRecord, Table, Value, Writer};
+
+    use crate::reftable::{committer, object, oid, record};
+
+    fn table(writer: Writer) -> crate::Result<Table> {
+        let mut buf = Vec::new();
+        writer.write_to(&mut buf)?;
+        Ok(Table::from_bytes(buf)?)
+    }
+
+    #[test]
+    fn an_empty_table_has_no_records() -> crate::Result {
+        let table = table(Writer::new(1, 1))?;
+        assert_eq!(table.min_update_index(), 1);
+        assert_eq!(table.max_update_index(), 1);
+        assert_eq!(table.iter()?.count(), 0);
+        assert_eq!(table.log_iter()?.count(), 0);
+        assert!(table.try_find("refs/heads/main".try_into()?)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn all_kinds_of_values_round_trip() -> crate::Result {
+        let mut writer = Writer::new(1, 3);
+        let records = vec![
+            record("HEAD", 1, Value::Symbolic("refs/heads/main".try_into()?)),
+            record("refs/heads/gone", 3, Value::Deletion),
+            record("refs/heads/main", 2, object(oid(1))),
+            record(
+                "refs/tags/v1",
+                1,
+                Value::Object {
+                    target: oid(2),
+                    peeled: Some(oid(1)),
+                },
+            ),
+        ];
+        for record in records.iter().rev() {
+            writer.add_ref(record.clone());
+        }
+        let table = table(writer)?;
+        assert_eq!(table.iter()?.collect::<Result<Vec<_>, _>>()?, records);
+        assert_eq!(table.try_find("refs/tags/v1".try_into()?)?.as_ref(), Some(&records[3]));
+        assert_eq!(
+            table
+                .iter_prefixed(b"refs/heads/".as_bstr())?
+                .map(|r| r.map(|r| r.name))
+                .collect::<Result<Vec<_>, _>>()?,
+            vec![records[1].name.clone(), records[2].name.clone()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn many_references_span_multiple_blocks_and_are_found_through_the_index() -> crate::Result {
+        let mut writer = Writer::new(1, 1).block_size(256);
+        let names: Vec<_> = (0..500).map(|n| format!("refs/heads/branch-{:04}", n)).collect();
+        for (n, name) in names.iter().enumerate() {
+            writer.add_ref(record(name, 1, object(oid((n % 200) as u8))));
+        }
+        let table = table(writer)?;
+        assert_eq!(table.block_size(), 256);
+        assert_eq!(table.iter()?.count(), names.len());
+        for (n, name) in names.iter().enumerate().step_by(37) {
+            let found = table.try_find(name.as_str().try_into()?)?.expect("present");
+            assert_eq!(found.value, object(oid((n % 200) as u8)));
+        }
+        assert!(table.try_find("refs/heads/branch-0500".try_into()?)?.is_none());
+        assert_eq!(
+            table.iter_prefixed(b"refs/heads/branch-01".as_bstr())?.count(),
+            100,
+            "prefixes are matched across block boundaries"
+        );
+
+        let with_object = table.refs_with_object(&oid(3))?;
+        assert_eq!(
+            with_object.into_iter().map(|r| r.name.into_inner()).collect::<Vec<_>>(),
+            vec![
+                "refs/heads/branch-0003",
+                "refs/heads/branch-0203",
+                "refs/heads/branch-0403"
+            ],
+            "the object index points to all blocks containing references to the object"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn log_records_are_sorted_newest_first() -> crate::Result {
+        let mut writer = Writer::new(1, 2);
+        for update_index in 1..=2 {
+            writer.add_log(LogRecord {
+                name: "refs/heads/main".try_into()?,
+                update_index,
+                line: Some(git_ref::log::Line {
+                    previous_oid: oid(update_index as u8 - 1),
+                    new_oid: oid(update_index as u8),
+                    signature: committer(),
+                    message: format!("update {}", update_index).into(),
+                }),
+            });
+        }
+        writer.add_log(LogRecord {
+            name: "refs/heads/other".try_into()?,
+            update_index: 2,
+            line: None,
+        });
+        let table = table(writer)?;
+        let logs = table
+            .log_iter_of("refs/heads/main".try_into()?)?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(logs.iter().map(|r| r.update_index).collect::<Vec<_>>(), vec![2, 1]);
+        let line = logs[0].line.as_ref().expect("not a deletion");
+        assert_eq!(line.message, "update 2");
+        assert_eq!(line.signature, committer());
+        assert_eq!(table.log_iter()?.count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn corrupt_tables_are_rejected() -> crate::Result {
+        let mut buf = Vec::new();
+        let mut writer = Writer::new(1, 1);
+        writer.add_ref(record("refs/heads/main", 1, object(oid(1))));
+        writer.write_to(&mut buf)?;
+        let last = buf.len() - 1;
+        buf[last] ^= 0xff;
+        assert!(Table::from_bytes(buf).is_err(), "the footer checksum doesn't match");
+        Ok(())
+    }
+}
+
+mod stack {
+    use std::convert::TryInto;
+
+    use git_lock::acquire::Fail;
+    use git_ref::{
+        reftable::transaction,
+        transaction::{Change, PreviousValue, RefEdit, RefLog},
+        Target,
+    };
+
+    use crate::reftable::{committer, empty_store, oid, update};
+
+    #[test]
+    fn transactions_add_one_table_each_and_newer_tables_win() -> crate::Result {
+        let (_keep, store) = empty_store()?;
+        store
+            .transaction()
+            .prepare(
+                vec![
+                    update("refs/heads/main", PreviousValue::MustNotExist, Target::Peeled(oid(1))),
+                    update("refs/heads/other", PreviousValue::Any, Target::Peeled(oid(2))),
+                ],
+                Fail::Immediately,
+            )?
+            .commit(&committer())?;
+        store
+            .transaction()
+            .prepare(
+                Some(update(
+                    "refs/heads/main",
+                    PreviousValue::MustExistAndMatch(Target::Peeled(oid(1))),
+                    Target::Peeled(oid(3)),
+                )),
+                Fail::Immediately,
+            )?
+            .commit(&committer())?;
+
+        let main = store.try_find("main")?.expect("present");
+        assert_eq!(main.target, Target::Peeled(oid(3)));
+        assert_eq!(store.stack()?.max_update_index(), 2);
+        assert_eq!(
+            store
+                .iter()?
+                .all()?
+                .map(|r| r.map(|r| r.name.into_inner()))
+                .collect::<Result<Vec<_>, _>>()?,
+            vec!["refs/heads/main", "refs/heads/other"]
+        );
+
+        let log = store.reflog("refs/heads/main")?.expect("reflog was created");
+        assert_eq!(
+            log.iter().map(|l| (l.previous_oid, l.new_oid)).collect::<Vec<_>>(),
+            vec![(git_hash::ObjectId::null_sha1(), oid(1)), (oid(1), oid(3))],
+            "reflogs are returned oldest first"
+        );
+        assert_eq!(log[1].message, "an update");
+        Ok(())
+    }
+
+    #[test]
+    fn preconditions_are_checked_against_the_current_stack() -> crate::Result {
+        let (_keep, store) = empty_store()?;
+        store
+            .transaction()
+            .prepare(
+                Some(update("refs/heads/main", PreviousValue::Any, Target::Peeled(oid(1)))),
+                Fail::Immediately,
+            )?
+            .commit(&committer())?;
+
+        let res = store.transaction().prepare(
+            Some(update(
+                "refs/heads/main",
+                PreviousValue::MustNotExist,
+                Target::Peeled(oid(2)),
+            )),
+            Fail::Immediately,
+        );
+        assert!(matches!(res, Err(transaction::prepare::Error::MustNotExist { .. })));
+
+        let res = store.transaction().prepare(
+            Some(update(
+                "refs/heads/main",
+                PreviousValue::MustExistAndMatch(Target::Peeled(oid(2))),
+                Target::Peeled(oid(3)),
+            )),
+            Fail::Immediately,
+        );
+        assert!(matches!(
+            res,
+            Err(transaction::prepare::Error::ReferenceOutOfDate { .. })
+        ));
+
+        let res = store.transaction().prepare(
+            Some(update(
+                "refs/heads/missing",
+                PreviousValue::MustExist,
+                Target::Peeled(oid(3)),
+            )),
+            Fail::Immediately,
+        );
+        match res {
+            Err(transaction::prepare::Error::ReferenceMustExist { full_name }) => {
+                assert_eq!(full_name, "refs/heads/missing")
+            }
+            _ => unreachable!("the reference doesn't exist"),
+        }
+
+        let res = store.transaction().prepare(
+            Some(RefEdit {
+                change: Change::Delete {
+                    expected: PreviousValue::MustExist,
+                    log: RefLog::AndReference,
+                },
+                name: "refs/heads/missing".try_into()?,
+                deref: false,
+            }),
+            Fail::Immediately,
+        );
+        assert!(matches!(
+            res,
+            Err(transaction::prepare::Error::DeleteReferenceMustExist { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn deletions_shadow_older_tables_along_with_their_reflog() -> crate::Result {
+        let (_keep, store) = empty_store()?;
+        store
+            .transaction()
+            .prepare(
+                Some(update("refs/heads/main", PreviousValue::Any, Target::Peeled(oid(1)))),
+                Fail::Immediately,
+            )?
+            .commit(&committer())?;
+        assert!(store.reflog_exists("refs/heads/main")?);
+
+        let edits = store
+            .transaction()
+            .prepare(
+                Some(RefEdit {
+                    change: Change::Delete {
+                        expected: PreviousValue::MustExist,
+                        log: RefLog::AndReference,
+                    },
+                    name: "refs/heads/main".try_into()?,
+                    deref: false,
+                }),
+                Fail::Immediately,
+            )?
+            .commit(&committer())?;
+        assert_eq!(
+            edits[0].change.previous_value(),
+            Some(git_ref::TargetRef::Peeled(&oid(1))),
+            "the previous value is filled in"
+        );
+        assert!(store.try_find("refs/heads/main")?.is_none());
+        assert!(!store.reflog_exists("refs/heads/main")?);
+        assert_eq!(store.iter()?.all()?.count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn symbolic_refs_are_followed_when_deref_is_set() -> crate::Result {
+        let (_keep, store) = empty_store()?;
+        store
+            .transaction()
+            .prepare(
+                vec![
+                    update(
+                        "HEAD",
+                        PreviousValue::Any,
+                        Target::Symbolic("refs/heads/main".try_into()?),
+                    ),
+                    update("refs/heads/main", PreviousValue::Any, Target::Peeled(oid(1))),
+                ],
+                Fail::Immediately,
+            )?
+            .commit(&committer())?;
+
+        let mut edit = update("HEAD", PreviousValue::Any, Target::Peeled(oid(2)));
+        edit.deref = true;
+        store
+            .transaction()
+            .prepare(Some(edit), Fail::Immediately)?
+            .commit(&committer())?;
+
+        assert_eq!(
+            store.try_find("HEAD")?.expect("present").target,
+            Target::Symbolic("refs/heads/main".try_into()?),
+            "HEAD stays symbolic"
+        );
+        assert_eq!(store.try_find("main")?.expect("present").target, Target::Peeled(oid(2)));
+        let head_log = store
+            .reflog("HEAD")?
+            .expect("HEAD gets a log entry for the referent update");
+        assert_eq!(head_log.last().expect("an entry").new_oid, oid(2));
+        Ok(())
+    }
+
+    #[test]
+    fn renames_move_the_reflog_and_refuse_to_clobber() -> crate::Result {
+        let (_keep, store) = empty_store()?;
+        store
+            .transaction()
+            .prepare(
+                vec![
+                    update("refs/heads/main", PreviousValue::Any, Target::Peeled(oid(1))),
+                    update("refs/heads/other", PreviousValue::Any, Target::Peeled(oid(2))),
+                ],
+                Fail::Immediately,
+            )?
+            .commit(&committer())?;
+
+        let rename = |new_name: &str| -> crate::Result<RefEdit> {
+            Ok(RefEdit {
+                change: Change::Rename {
+                    expected: PreviousValue::Any,
+                    new_name: new_name.try_into()?,
+                    message: "rename".into(),
+                },
+                name: "refs/heads/main".try_into()?,
+                deref: false,
+            })
+        };
+        let res = store
+            .transaction()
+            .prepare(Some(rename("refs/heads/other")?), Fail::Immediately);
+        assert!(matches!(res, Err(transaction::prepare::Error::MustNotExist { .. })));
+
+        store
+            .transaction()
+            .prepare(Some(rename("refs/heads/new")?), Fail::Immediately)?
+            .commit(&committer())?;
+        assert!(store.try_find("refs/heads/main")?.is_none());
+        assert!(!store.reflog_exists("refs/heads/main")?);
+        assert_eq!(
+            store.try_find("refs/heads/new")?.expect("present").target,
+            Target::Peeled(oid(1))
+        );
+        let log = store.reflog("refs/heads/new")?.expect("moved along");
+        assert_eq!(
+            log.iter().map(|l| (l.new_oid, l.message.clone())).collect::<Vec<_>>(),
+            vec![(oid(1), "an update".into()), (oid(1), "rename".into())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn the_reference_transaction_hook_is_called_and_may_reject_the_transaction() -> crate::Result {
+        use std::sync::{Arc, Mutex};
+
+        use git_ref::file::transaction::hook;
+
+        let (_keep, store) = empty_store()?;
+        for (reject_prepared, name) in [(false, "refs/heads/main"), (true, "refs/heads/rejected")] {
+            let calls = Arc::new(Mutex::new(Vec::new()));
+            let hook = {
+                let calls = Arc::clone(&calls);
+                Box::new(move |state, edits: &[RefEdit]| {
+                    calls.lock().unwrap().push((state, hook::to_input(edits)));
+                    if reject_prepared && state == hook::State::Prepared {
+                        Err("rejected".into())
+                    } else {
+                        Ok(())
+                    }
+                })
+            };
+            let res = store
+                .transaction()
+                .hook(hook)
+                .prepare(
+                    Some(update(name, PreviousValue::Any, Target::Peeled(oid(1)))),
+                    Fail::Immediately,
+                )
+                .and_then(|t| Ok(t.commit(&committer()).expect("commit works")));
+            let input = format!("{} {} {}\n", git_hash::ObjectId::null_sha1(), oid(1), name);
+            let calls = calls.lock().unwrap().clone();
+            if reject_prepared {
+                assert!(matches!(res, Err(transaction::prepare::Error::HookRejected(_))));
+                assert_eq!(
+                    calls,
+                    vec![
+                        (hook::State::Prepared, input.clone().into()),
+                        (hook::State::Aborted, input.into())
+                    ]
+                );
+                assert_eq!(store.stack()?.max_update_index(), 1, "nothing was written");
+                store
+                    .transaction()
+                    .prepare(
+                        Some(update("refs/heads/other", PreviousValue::Any, Target::Peeled(oid(2)))),
+                        Fail::Immediately,
+                    )
+                    .map(|_| ())
+                    .expect("the lock was released");
+            } else {
+                res?;
+                assert_eq!(
+                    calls,
+                    vec![
+                        (hook::State::Prepared, input.clone().into()),
+                        (hook::State::Committed, input.into())
+                    ]
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn the_stack_is_compacted_automatically() -> crate::Result {
+        let (_keep, store) = empty_store()?;
+        for n in 1..=20 {
+            store
+                .transaction()
+                .prepare(
+                    Some(update(
+                        &format!("refs/heads/branch-{}", n),
+                        PreviousValue::MustNotExist,
+                        Target::Peeled(oid(n)),
+                    )),
+                    Fail::Immediately,
+                )?
+                .commit(&committer())?;
+        }
+        let stack = store.stack()?;
+        assert!(
+            stack.tables().len() < 6,
+            "the amount of tables stays logarithmic, got {}",
+            stack.tables().len()
+        );
+        assert_eq!(stack.max_update_index(), 20);
+        assert_eq!(store.iter()?.all()?.count(), 20);
+
+        store.compact(Fail::Immediately)?;
+        assert_eq!(
+            store.stack()?.tables().len(),
+            1,
+            "a full compaction leaves a single table"
+        );
+        assert_eq!(store.iter()?.all()?.count(), 20);
+        for n in 1..=20 {
+            assert_eq!(
+                store
+                    .reflog(format!("refs/heads/branch-{}", n).as_str())?
+                    .map(|l| l.len()),
+                Some(1),
+                "logs are retained"
+            );
+        }
+        Ok(())
+    }
+}
+
+mod handle {
+    use git_lock::acquire::Fail;
+    use git_ref::{transaction::PreviousValue, Target};
+
+    use crate::reftable::{committer, configure_reftable, empty_store, oid, update};
+
+    #[test]
+    fn the_general_store_uses_reftables_if_configured() -> crate::Result {
+        let (dir, _store) = empty_store()?;
+        configure_reftable(dir.path())?;
+        let store = git_ref::Store::at(dir.path(), git_ref::store::WriteReflog::Normal)?;
+        let handle = store.to_handle();
+        handle
+            .transaction()
+            .prepare(
+                Some(update(
+                    "refs/heads/main",
+                    PreviousValue::MustNotExist,
+                    Target::Peeled(oid(1)),
+                )),
+                Fail::Immediately,
+            )?
+            .commit(&committer())?;
+        assert!(
+            !dir.path().join("refs").join("heads").join("main").exists(),
+            "no loose refs are written"
+        );
+
+        assert_eq!(handle.find("main")?.target, Target::Peeled(oid(1)));
+        assert!(handle.try_find("other")?.is_none());
+        assert!(handle.find("other").is_err());
+        assert_eq!(handle.iter()?.prefixed("refs/heads/")?.count(), 1);
+        assert_eq!(handle.reflog("refs/heads/main")?.map(|l| l.len()), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn the_general_store_uses_loose_references_unless_configured_otherwise() -> crate::Result {
+        let (dir, _store) = empty_store()?;
+        let store = git_ref::Store::at(dir.path(), git_ref::store::WriteReflog::Normal)?;
+        store
+            .to_handle()
+            .transaction()
+            .prepare(
+                Some(update("refs/heads/main", PreviousValue::Any, Target::Peeled(oid(1)))),
+                Fail::Immediately,
+            )?
+            .commit(&committer())?;
+        assert!(
+            dir.path().join("refs").join("heads").join("main").is_file(),
+            "tables are ignored without 'extensions.refStorage'"
+        );
+
+        std::fs::write(
+            dir.path().join("config"),
+            "[core]\n\trepositoryformatversion = 1\n[extensions]\n\trefStorage = unknown\n",
+        )?;
+        assert!(matches!(
+            git_ref::Store::at(dir.path(), git_ref::store::WriteReflog::Normal),
+            Err(git_ref::store::init::Error::UnsupportedRefStorage { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn the_ref_storage_extension_is_rejected_in_repositories_of_version_0() -> crate::Result {
+        let (dir, _store) = empty_store()?;
+        for config in &[
+            "[extensions]\n\trefStorage = reftable\n",
+            "[core]\n\trepositoryformatversion = 0\n[extensions]\n\trefStorage = files\n",
+        ] {
+            std::fs::write(dir.path().join("config"), config)?;
+            assert!(matches!(
+                git_ref::Store::at(dir.path(), git_ref::store::WriteReflog::Normal),
+                Err(git_ref::store::init::Error::RefStorageInVersion0)
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn linked_worktrees_use_the_configuration_of_the_common_dir() -> crate::Result {
+        let (dir, _store) = empty_store()?;
+        let git_dir = dir.path().join("worktrees").join("wt");
+        std::fs::create_dir_all(&git_dir)?;
+        std::fs::write(git_dir.join("commondir"), "../..\n")?;
+
+        let store = git_ref::Store::at(&git_dir, git_ref::store::WriteReflog::Normal)?;
+        store
+            .to_handle()
+            .transaction()
+            .prepare(
+                vec![
+                    update("refs/heads/main", PreviousValue::Any, Target::Peeled(oid(1))),
+                    update("HEAD", PreviousValue::Any, Target::Peeled(oid(1))),
+                ],
+                Fail::Immediately,
+            )?
+            .commit(&committer())?;
+        assert!(
+            dir.path().join("refs").join("heads").join("main").is_file(),
+            "shared references are written into the common dir"
+        );
+        assert!(git_dir.join("HEAD").is_file(), "HEAD belongs to the worktree");
+
+        configure_reftable(dir.path())?;
+        assert!(
+            matches!(
+                git_ref::Store::at(&git_dir, git_ref::store::WriteReflog::Normal),
+                Err(git_ref::store::init::Error::ReftableInLinkedWorktree { .. })
+            ),
+            "instead of writing loose references into a reftable repository, we refuse to open it"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn namespaces_are_applied_to_reads_and_writes() -> crate::Result {
+        let (dir, store) = empty_store()?;
+        configure_reftable(dir.path())?;
+        let general = git_ref::Store::at(dir.path(), git_ref::store::WriteReflog::Normal)?;
+        let handle = general.to_handle_namespaced(Some(git_ref::namespace::expand("foo")?));
+        handle
+            .transaction()
+            .prepare(
+                Some(update("refs/heads/main", PreviousValue::Any, Target::Peeled(oid(1)))),
+                Fail::Immediately,
+            )?
+            .commit(&committer())?;
+
+        assert!(store.try_find("refs/heads/main")?.is_none());
+        assert!(store.try_find("refs/namespaces/foo/refs/heads/main")?.is_some());
+        let names: Vec<_> = handle
+            .iter()?
+            .all()?
+            .map(|r| r.map(|r| r.name.into_inner()))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(names, vec!["refs/heads/main"], "namespaces are stripped");
+        assert_eq!(handle.find("main")?.name.as_bstr(), "refs/heads/main");
+        Ok(())
+    }
+}
+
+/// Tests which need git v2.45 or newer to read and write reftables, and which thus have to be run explicitly.
+mod git_interop {
+    use std::convert::TryInto;
+
+    use git_hash::ObjectId;
+    use git_lock::acquire::Fail;
+    use git_ref::{
+        store::WriteReflog,
+        transaction::{Change, PreviousValue, RefEdit, RefLog},
+        Target,
+    };
+
+    use crate::reftable::{committer, update};
+
+    #[test]
+    #[ignore = "needs git v2.45 or newer to write reftables"]
+    fn references_and_reflogs_are_read_like_git_reads_them() -> crate::Result {
+        let dir = git_testtools::scripted_fixture_repo_read_only("make_reftable_repository.sh")?;
+        let store = git_ref::Store::at(dir.join(".git"), WriteReflog::Normal)?;
+        let handle = store.to_handle();
+
+        let mut expected_names = Vec::new();
+        for line in std::fs::read_to_string(dir.join("refs.list"))?.lines() {
+            let mut tokens = line.split(' ');
+            let (name, id, peeled, symref) = (
+                tokens.next().expect("name"),
+                tokens.next().expect("id"),
+                tokens.next().expect("peeled id, possibly empty"),
+                tokens.next().expect("symbolic target, possibly empty"),
+            );
+            let reference = handle.find(name)?;
+            let expected_target = if symref.is_empty() {
+                Target::Peeled(ObjectId::from_hex(id.as_bytes())?)
+            } else {
+                Target::Symbolic(symref.try_into()?)
+            };
+            assert_eq!(reference.target, expected_target, "{}", name);
+            if !peeled.is_empty() {
+                assert_eq!(
+                    reference.peeled,
+                    Some(ObjectId::from_hex(peeled.as_bytes())?),
+                    "{}",
+                    name
+                );
+            }
+            expected_names.push(name.to_owned());
+        }
+        let names: Vec<_> = handle
+            .iter()?
+            .prefixed("refs/")?
+            .map(|r| r.map(|r| r.name.as_bstr().to_string()))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(names, expected_names, "deleted references don't show up");
+
+        let head = std::fs::read_to_string(dir.join("head.symref"))?;
+        assert_eq!(handle.find("HEAD")?.target, Target::Symbolic(head.trim().try_into()?));
+
+        let reflog: Vec<_> = handle
+            .reflog("refs/heads/main")?
+            .expect("present")
+            .into_iter()
+            .rev()
+            .map(|line| line.new_oid.to_string())
+            .collect();
+        let expected: Vec<_> = std::fs::read_to_string(dir.join("main.reflog"))?
+            .lines()
+            .map(ToOwned::to_owned)
+            .collect();
+        assert_eq!(reflog, expected, "git lists the most recent entry first");
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "needs git v2.45 or newer to read reftables"]
+    fn references_and_reflogs_written_by_the_store_are_read_by_git() -> crate::Result {
+        let dir = git_testtools::scripted_fixture_repo_writable("make_reftable_repository.sh")?;
+        let store = git_ref::Store::at(dir.path().join(".git"), WriteReflog::Normal)?;
+        let handle = store.to_handle();
+        let main = handle.find("main")?.target.as_id().expect("peeled").to_owned();
+        handle
+            .transaction()
+            .prepare(
+                vec![
+                    update("refs/heads/written", PreviousValue::MustNotExist, Target::Peeled(main)),
+                    update(
+                        "refs/heads/written-symbolic",
+                        PreviousValue::MustNotExist,
+                        Target::Symbolic("refs/heads/written".try_into()?),
+                    ),
+                    RefEdit {
+                        change: Change::Delete {
+                            expected: PreviousValue::MustExist,
+                            log: RefLog::AndReference,
+                        },
+                        name: "refs/heads/other".try_into()?,
+                        deref: false,
+                    },
+                ],
+                Fail::Immediately,
+            )?
+            .commit(&committer())?;
+
+        let git = |args: &[&str]| -> crate::Result<String> {
+            let out = std::process::Command::new("git")
+                .args(args)
+                .current_dir(dir.path())
+                .output()?;
+            assert!(
+                out.status.success(),
+                "git {:?} failed: {}",
+                args,
+                String::from_utf8_lossy(&out.stderr)
+            );
+            Ok(String::from_utf8(out.stdout)?)
+        };
+        assert_eq!(
+            git(&[
+                "for-each-ref",
+                "--format=%(refname) %(objectname) %(symref)",
+                "refs/heads/"
+            ])?,
+            format!(
+                "refs/heads/main {id} \n\
+                 refs/heads/symbolic {id} refs/heads/main\n\
+                 refs/heads/written {id} \n\
+                 refs/heads/written-symbolic {id} refs/heads/written\n",
+                id = main
+            ),
+            "the deleted reference is gone, and the new ones are visible"
+        );
+        assert_eq!(
+            git(&["reflog", "show", "--format=%H %gs", "refs/heads/written"])?,
+            format!("{} an update\n", main)
+        );
+        Ok(())
+    }
+}
+
+#[test]
+fn tables_are_written_to_disk_and_read_back() -> crate::Result {
+    let (_keep, store) = empty_store()?;
+    let mut writer = reftable::Writer::new(1, 1);
+    writer
+        .add_ref(record(
+            "refs/heads/main",
+            1,
+            object(hex_to_id("134385f6d781b7e97062102c6a483440bfda2a03")),
+        ))
+        .add_log(LogRecord {
+            name: "refs/heads/main".try_into()?,
+            update_index: 1,
+            line: None,
+        });
+    let path = store.reftable_dir().join("0x000000000001-0x000000000001-00000000.ref");
+    writer.write_to(std::fs::File::create(&path)?)?;
+    std::fs::write(store.tables_list_path(), "0x000000000001-0x000000000001-00000000.ref\n")?;
+
+    let table = reftable::Table::at(&path)?;
+    assert_eq!(table.iter()?.count(), 1);
+    assert_eq!(
+        store.try_find("main")?.expect("present").target,
+        Target::Peeled(hex_to_id("134385f6d781b7e97062102c6a483440bfda2a03"))
+    );
+    Ok(())
+}
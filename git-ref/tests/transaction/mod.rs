@@ -356,5 +356,126 @@ mod refedit_ext {
             );
             Ok(())
         }
+
+        #[test]
+        fn renames_and_copies_are_split_into_deletions_and_creations() -> crate::Result {
+            let id = hex_to_id("e69de29bb2d1d6434b8b29ae775ad8c2e48c5391");
+            let store = MockStore::with(vec![
+                ("refs/heads/to-rename", Target::Peeled(id)),
+                ("refs/heads/to-copy", Target::Peeled(id)),
+            ]);
+            let mut edits = vec![
+                RefEdit {
+                    change: Change::Rename {
+                        expected: PreviousValue::Any,
+                        new_name: "refs/heads/renamed".try_into()?,
+                        message: "rename".into(),
+                    },
+                    name: "refs/heads/to-rename".try_into()?,
+                    deref: true,
+                },
+                RefEdit {
+                    change: Change::Copy {
+                        expected: PreviousValue::MustExistAndMatch(Target::Peeled(id)),
+                        new_name: "refs/heads/copied".try_into()?,
+                        message: "copy".into(),
+                    },
+                    name: "refs/heads/to-copy".try_into()?,
+                    deref: false,
+                },
+            ];
+
+            let mut sources = Vec::new();
+            edits.extend_with_splits_of_renames_and_copies(
+                |n| store.find_existing(n),
+                |source, e| {
+                    sources.push(source);
+                    e
+                },
+            )?;
+            assert_eq!(
+                sources,
+                vec!["refs/heads/to-rename".try_into()?, "refs/heads/to-copy".try_into()?],
+                "the source of each relocation is passed along"
+            );
+            let creation = |name: &str, message: &str| -> crate::Result<RefEdit> {
+                Ok(RefEdit {
+                    change: Change::Update {
+                        log: LogChange {
+                            mode: RefLog::AndReference,
+                            force_create_reflog: false,
+                            message: message.into(),
+                        },
+                        expected: PreviousValue::MustNotExist,
+                        new: Target::Peeled(id),
+                    },
+                    name: name.try_into()?,
+                    deref: false,
+                })
+            };
+            assert_eq!(
+                edits,
+                vec![
+                    RefEdit {
+                        change: Change::Delete {
+                            expected: PreviousValue::MustExistAndMatch(Target::Peeled(id)),
+                            log: RefLog::AndReference,
+                        },
+                        name: "refs/heads/to-rename".try_into()?,
+                        deref: false,
+                    },
+                    creation("refs/heads/renamed", "rename")?,
+                    creation("refs/heads/copied", "copy")?,
+                ],
+                "renames delete their source and copies leave it alone"
+            );
+            store.assert_empty();
+            Ok(())
+        }
+
+        #[test]
+        fn renames_and_copies_of_missing_references_fail() -> crate::Result {
+            let store = MockStore::default();
+            let mut edits = vec![RefEdit {
+                change: Change::Rename {
+                    expected: PreviousValue::Any,
+                    new_name: "refs/heads/new".try_into()?,
+                    message: Default::default(),
+                },
+                name: "refs/heads/missing".try_into()?,
+                deref: false,
+            }];
+            let err = edits
+                .extend_with_splits_of_renames_and_copies(|n| store.find_existing(n), |_, e| e)
+                .expect_err("source must exist");
+            assert_eq!(
+                err.to_string(),
+                "Cannot rename reference 'refs/heads/missing' to 'refs/heads/new' as it does not exist"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn renames_and_copies_are_rejected_without_relocations() -> crate::Result {
+            let store = MockStore::default();
+            let mut edits = vec![RefEdit {
+                change: Change::Copy {
+                    expected: PreviousValue::Any,
+                    new_name: "refs/heads/new".try_into()?,
+                    message: Default::default(),
+                },
+                name: "refs/heads/source".try_into()?,
+                deref: false,
+            }];
+            let err = edits
+                .pre_process(|n| store.find_existing(n), |_, e| e)
+                .expect_err("relocations need their own pre-processing");
+            assert_eq!(
+                err.to_string(),
+                "Reference 'refs/heads/source' cannot be renamed or copied without pre-processing relocations"
+            );
+            store.assert_empty();
+            Ok(())
+        }
     }
 }
@@ -12,7 +12,7 @@ use crate::{
     bstr::BString,
     easy,
     easy::{reference, Reference},
-    ext::ReferenceExt,
+    ext::{ObjectIdExt, ReferenceExt},
 };
 
 const DEFAULT_LOCK_MODE: git_lock::acquire::Fail = git_lock::acquire::Fail::Immediately;
@@ -149,6 +149,9 @@ impl easy::Handle {
     ///
     /// Returns all reference edits, which might be more than where provided due the splitting of symbolic references, and
     /// whose previous (_old_) values are the ones seen on in storage after the reference was locked.
+    ///
+    /// If present in the directory configured with `core.hooksPath`, or in `hooks` otherwise, the `reference-transaction` hook is run
+    /// and may reject the edits before they are committed.
     pub fn edit_references(
         &self,
         edits: impl IntoIterator<Item = RefEdit>,
@@ -163,8 +166,13 @@ impl easy::Handle {
                 &committer_storage
             }
         };
-        self.refs
-            .transaction()
+        let mut transaction = self.refs.transaction();
+        let hook = self.hooks_dir.join("reference-transaction");
+        if is_executable(&hook) {
+            let working_dir = self.work_tree().unwrap_or_else(|| self.refs.base()).to_owned();
+            transaction = transaction.hook(git_ref::file::transaction::hook::executable(hook, working_dir));
+        }
+        transaction
             .prepare(edits, lock_mode)?
             .commit(committer)
             .map_err(Into::into)
@@ -218,18 +226,80 @@ impl easy::Handle {
     ///
     /// Otherwise return `None` if the reference wasn't found.
     /// If the reference is expected to exist, use [`find_reference()`][easy::Handle::find_reference()].
+    ///
+    /// Partial names are expanded using git's disambiguation rules, trying `refs/<name>`, `refs/tags/<name>`,
+    /// `refs/heads/<name>`, `refs/remotes/<name>` and `refs/remotes/<name>/HEAD` in that order.
+    /// If `name` refers to more than one reference, a warning is logged and the first one is returned, just like git does.
     pub fn try_find_reference<'a, Name, E>(&self, name: Name) -> Result<Option<Reference<'_>>, reference::find::Error>
     where
         Name: TryInto<PartialNameRef<'a>, Error = E>,
         git_ref::file::find::Error: From<E>,
     {
-        let state = self;
-        match state.refs.try_find(name) {
-            Ok(r) => match r {
-                Some(r) => Ok(Some(Reference::from_ref(r, self))),
-                None => Ok(None),
-            },
-            Err(err) => Err(err.into()),
+        let name = name.try_into().map_err(git_ref::file::find::Error::from)?;
+        let mut candidates = self.refs.try_find_all::<_, std::convert::Infallible>(name.clone())?;
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+        let first = candidates.remove(0);
+        if !candidates.is_empty() {
+            log::warn!(
+                "refname '{}' is ambiguous, using '{}' over {}",
+                name.as_bstr(),
+                first.name.as_bstr(),
+                candidates
+                    .iter()
+                    .map(|r| format!("'{}'", r.name.as_bstr()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
         }
+        Ok(Some(Reference::from_ref(first, self)))
+    }
+}
+
+/// Access pseudo-references like `FETCH_HEAD` and `MERGE_HEAD`
+impl easy::Handle {
+    /// Return all entries of `FETCH_HEAD` as written by the most recent fetch, or `None` if there was none.
+    pub fn fetch_head(&self) -> Result<Option<Vec<git_ref::pseudo::fetch_head::Entry>>, reference::pseudo::Error> {
+        Ok(self.refs.fetch_head()?)
     }
+
+    /// Return the commits listed in `MERGE_HEAD` to be merged into `HEAD`, or `None` if no merge is in progress.
+    pub fn merge_heads(&self) -> Result<Option<Vec<easy::Oid<'_>>>, reference::pseudo::Error> {
+        Ok(self
+            .refs
+            .merge_head()?
+            .map(|ids| ids.into_iter().map(|id| id.attach(self)).collect()))
+    }
+
+    /// Return the commit `HEAD` pointed to before it was moved by a reset, rebase or merge, or `None` if `ORIG_HEAD` doesn't exist.
+    pub fn orig_head(&self) -> Result<Option<easy::Oid<'_>>, reference::pseudo::Error> {
+        self.pseudo_ref_id(git_ref::pseudo::ORIG_HEAD)
+    }
+
+    /// Return the commit being cherry-picked, or `None` if no cherry-pick is in progress.
+    pub fn cherry_pick_head(&self) -> Result<Option<easy::Oid<'_>>, reference::pseudo::Error> {
+        self.pseudo_ref_id(git_ref::pseudo::CHERRY_PICK_HEAD)
+    }
+
+    /// Return the commit being reverted, or `None` if no revert is in progress.
+    pub fn revert_head(&self) -> Result<Option<easy::Oid<'_>>, reference::pseudo::Error> {
+        self.pseudo_ref_id(git_ref::pseudo::REVERT_HEAD)
+    }
+
+    fn pseudo_ref_id(&self, name: &str) -> Result<Option<easy::Oid<'_>>, reference::pseudo::Error> {
+        Ok(self.refs.pseudo_ref_id(name.into())?.map(|id| id.attach(self)))
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map_or(false, |m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &std::path::Path) -> bool {
+    path.is_file()
 }
@@ -13,6 +13,7 @@ impl Clone for easy::Handle {
             self.objects.clone(),
             self.hash_kind,
             self.work_tree.clone(),
+            self.hooks_dir.clone(),
         )
     }
 }
@@ -31,11 +32,13 @@ impl easy::Handle {
         objects: crate::OdbHandle,
         hash_kind: git_hash::Kind,
         work_tree: Option<PathBuf>,
+        hooks_dir: PathBuf,
     ) -> Self {
         easy::Handle {
             buf: RefCell::new(vec![]),
             hash_kind,
             work_tree,
+            hooks_dir,
             objects: {
                 #[cfg(feature = "max-performance")]
                 {
@@ -58,6 +61,7 @@ impl From<&crate::Repository> for easy::Handle {
             repo.objects.to_handle_shared(),
             repo.hash_kind,
             repo.work_tree.clone(),
+            repo.hooks_dir.clone(),
         )
     }
 }
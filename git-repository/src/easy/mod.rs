@@ -92,6 +92,7 @@ pub struct Handle {
     /// A way to access objects.
     pub objects: crate::OdbHandle,
     work_tree: Option<PathBuf>,
+    hooks_dir: PathBuf,
     /// The kind of hash that is used or should be used for object ids
     hash_kind: git_hash::Kind,
     buf: RefCell<Vec<u8>>,
@@ -14,6 +14,17 @@ pub mod edit {
     }
 }
 
+///
+pub mod pseudo {
+    /// The error returned by [fetch_head()][crate::easy::Handle::fetch_head()] and other accessors of pseudo-references.
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error(transparent)]
+        ReadPseudoReference(#[from] git_ref::pseudo::Error),
+    }
+}
+
 ///
 pub mod peel {
     use crate::easy;
@@ -12,7 +12,7 @@ pub mod iter;
 
 mod errors;
 
-pub use errors::{edit, find, peel};
+pub use errors::{edit, find, peel, pseudo};
 
 use crate::ext::ObjectIdExt;
 
@@ -40,7 +40,8 @@
 //!
 //! - Only a single `easy::Object` or derivatives can be held in memory at a time, _per `Easy*`_.
 //! - Changes made to the configuration, packs, and alternates aren't picked up automatically, but the current object store
-//!   needs a manual refresh.
+//!   needs a manual refresh. Long-running processes can use a [`git_odb::general::Store`] instead, which picks up changes
+//!   to packs and alternates whenever an object can't be found.
 //!
 //! ### Design Sketch
 //!
@@ -90,6 +91,7 @@
 //! * [`odb`]
 //!   * [`pack`][odb::pack]
 //! * [`refs`]
+//! * [`index`]
 //! * [`interrupt`]
 //! * [`tempfile`]
 //! * [`lock`]
@@ -118,6 +120,8 @@ pub use git_features::{parallel, progress, progress::Progress, threading};
 pub use git_hash as hash;
 #[doc(inline)]
 pub use git_hash::{oid, ObjectId};
+#[cfg(feature = "unstable")]
+pub use git_index as index;
 pub use git_lock as lock;
 pub use git_object as objs;
 pub use git_object::bstr;
@@ -184,6 +188,10 @@ pub struct Repository {
     /// The path to the worktree at which to find checked out files
     pub work_tree: Option<PathBuf>,
     pub(crate) hash_kind: git_hash::Kind,
+    /// The directory to find hooks in, as configured with `core.hooksPath`.
+    pub(crate) hooks_dir: PathBuf,
+    /// The remote to fetch missing objects from if this repository is a partial clone.
+    pub(crate) promisor_remote: Option<promisor::Remote>,
     // TODO: git-config should be here - it's read a lot but not written much in must applications, so shouldn't be in `State`.
     //       Probably it's best reload it on signal (in servers) or refresh it when it's known to have been changed similar to how
     //       packs are refreshed. This would be `git_config::fs::Config` when ready.
@@ -198,6 +206,12 @@ pub mod commit;
 ///
 pub mod reference;
 
+///
+pub mod worktree;
+
+///
+pub mod promisor;
+
 /// The kind of `Repository`
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum Kind {
@@ -13,6 +13,8 @@ pub enum Error {
     MissingObjectsDirectory { missing: PathBuf },
     #[error("Expected a refs directory at '{}'", .missing.display())]
     MissingRefsDirectory { missing: PathBuf },
+    #[error("Could not read the path to the git directory or common directory from '{}'", .path.display())]
+    ReadPath { source: std::io::Error, path: PathBuf },
 }
 
 /// Returns true if the given `git_dir` seems to be a bare repository.
@@ -26,13 +28,32 @@ pub fn bare(git_dir: impl AsRef<Path>) -> bool {
 /// purely based on the presence of files. Note that the git-config ultimately decides what's bare.
 ///
 /// * [x] a valid head
-/// * [ ] git common directory
+/// * [x] git common directory
 ///   * [ ] respect GIT_COMMON_DIR
 /// * [x] an objects directory
 ///   * [x] respect GIT_OBJECT_DIRECTORY
 /// * [x] a refs directory
+/// * [x] a `.git` file pointing to the private git directory of a linked worktree
 pub fn git(git_dir: impl AsRef<Path>) -> Result<crate::Kind, Error> {
     let dot_git = git_dir.as_ref();
+    let (dot_git, is_gitdir_file) = if dot_git.is_file() {
+        let git_dir = crate::path::from_gitdir_file(dot_git).map_err(|source| Error::ReadPath {
+            source,
+            path: dot_git.to_owned(),
+        })?;
+        (git_dir, true)
+    } else {
+        (dot_git.to_owned(), false)
+    };
+    let common_dir_file = dot_git.join("commondir");
+    let common_dir = crate::path::from_plain_file(&common_dir_file)
+        .transpose()
+        .map_err(|source| Error::ReadPath {
+            source,
+            path: common_dir_file,
+        })?;
+    let is_linked_worktree = is_gitdir_file || common_dir.is_some();
+    let common_dir = common_dir.unwrap_or_else(|| dot_git.clone());
 
     {
         let refs = crate::RefStore::at(&dot_git, git_ref::store::WriteReflog::Normal);
@@ -47,19 +68,19 @@ pub fn git(git_dir: impl AsRef<Path>) -> Result<crate::Kind, Error> {
     {
         let objects_path = std::env::var("GIT_OBJECT_DIRECTORY")
             .map(PathBuf::from)
-            .unwrap_or_else(|_| dot_git.join("objects"));
+            .unwrap_or_else(|_| common_dir.join("objects"));
         if !objects_path.is_dir() {
             return Err(Error::MissingObjectsDirectory { missing: objects_path });
         }
     }
     {
-        let refs_path = dot_git.join("refs");
+        let refs_path = common_dir.join("refs");
         if !refs_path.is_dir() {
             return Err(Error::MissingRefsDirectory { missing: refs_path });
         }
     }
 
-    Ok(if bare(git_dir) {
+    Ok(if !is_linked_worktree && bare(&dot_git) {
         crate::Kind::Bare
     } else {
         crate::Kind::WorkTree
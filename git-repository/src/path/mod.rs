@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use crate::{Kind, Path};
+use crate::{bstr::ByteSlice, Kind, Path};
 
 ///
 pub mod create;
@@ -18,10 +18,21 @@ impl AsRef<std::path::Path> for Path {
 impl Path {
     /// Instantiate a new path from `dir` which is expected to be the `.git` directory, with `kind` indicating
     /// whether it's a bare repository or not.
+    ///
+    /// If `dir` is the private git directory of a linked worktree, typically `.git/worktrees/<id>`, the work tree is
+    /// the one it was registered for.
     pub fn from_dot_git_dir(dir: impl Into<PathBuf>, kind: Kind) -> Self {
         let dir = dir.into();
         match kind {
-            Kind::WorkTree => Path::WorkTree(dir.parent().expect("this is a sub-directory").to_owned()),
+            Kind::WorkTree => match from_plain_file(dir.join("gitdir")).and_then(Result::ok) {
+                Some(dot_git_file) => Path::WorkTree(
+                    dot_git_file
+                        .parent()
+                        .expect("the .git file is always in a work tree")
+                        .to_owned(),
+                ),
+                None => Path::WorkTree(dir.parent().expect("this is a sub-directory").to_owned()),
+            },
             Kind::Bare => Path::Repository(dir),
         }
     }
@@ -41,3 +52,46 @@ impl Path {
         }
     }
 }
+
+/// Read the `gitdir: <path>` indirection from the `.git` file at `path`, as used in the work trees of linked worktrees
+/// and submodules, and return the git directory it points to.
+///
+/// Relative paths are resolved against the directory containing the file at `path`.
+pub fn from_gitdir_file(path: impl AsRef<std::path::Path>) -> std::io::Result<PathBuf> {
+    let path = path.as_ref();
+    let content = std::fs::read(path)?;
+    let git_dir = content
+        .strip_prefix(b"gitdir: ")
+        .map(|git_dir| git_dir.trim_end())
+        .filter(|git_dir| !git_dir.is_empty())
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Expected 'gitdir: <path>' in '{}'", path.display()),
+            )
+        })?;
+    resolve_relative_to_parent_of(path, git_dir)
+}
+
+/// Read the single path in the plain file at `path`, like `.git/worktrees/<id>/commondir`, and resolve relative ones
+/// against the directory containing that file.
+///
+/// Return `None` if there is no such file.
+pub(crate) fn from_plain_file(path: impl AsRef<std::path::Path>) -> Option<std::io::Result<PathBuf>> {
+    let path = path.as_ref();
+    match std::fs::read(path) {
+        Ok(content) => Some(resolve_relative_to_parent_of(path, content.trim_end())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => Some(Err(err)),
+    }
+}
+
+fn resolve_relative_to_parent_of(file: &std::path::Path, path: &[u8]) -> std::io::Result<PathBuf> {
+    let path = path
+        .to_path()
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+    Ok(match file.parent() {
+        Some(parent) if path.is_relative() => parent.join(path),
+        _ => path.to_owned(),
+    })
+}
@@ -0,0 +1,244 @@
+//! Partial clones as configured with `extensions.partialClone`, which obtain missing objects from a promisor remote on demand.
+use std::borrow::Cow;
+
+use git_config::{file::GitConfig, values::Boolean};
+
+use crate::bstr::{BStr, BString};
+
+/// The remote from which objects missing in a partial clone can be fetched, along with the filter used to omit them.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Remote {
+    /// The name of the remote as configured with `extensions.partialClone`.
+    pub name: BString,
+    /// The url of the remote as configured with `remote.<name>.url`, if set.
+    pub url: Option<BString>,
+    /// The object filter used when fetching from the remote as configured with `remote.<name>.partialCloneFilter`,
+    /// like `blob:none` for blobless clones or `tree:0` for treeless clones.
+    pub filter: Option<BString>,
+}
+
+impl Remote {
+    /// Read the promisor remote from `config`, or return `None` if the repository isn't a partial clone.
+    ///
+    /// Besides `extensions.partialClone`, the first remote with `remote.<name>.promisor` set is used as newer versions
+    /// of git don't write the extension anymore.
+    pub(crate) fn from_config(config: &GitConfig<'_>) -> Option<Self> {
+        let name = match config.value::<Cow<'_, [u8]>>("extensions", None, "partialClone") {
+            Ok(name) => name.into_owned(),
+            Err(_) => first_promisor_remote_name(config)?.into_bytes(),
+        };
+        let value = |key: &str| {
+            std::str::from_utf8(&name)
+                .ok()
+                .and_then(|subsection| config.value::<Cow<'_, [u8]>>("remote", Some(subsection), key).ok())
+                .map(|v| BString::from(v.into_owned()))
+        };
+        Some(Remote {
+            url: value("url"),
+            filter: value("partialCloneFilter"),
+            name: name.into(),
+        })
+    }
+
+    /// The name of the remote, like `origin`.
+    pub fn name(&self) -> &BStr {
+        self.name.as_ref()
+    }
+}
+
+fn first_promisor_remote_name(config: &GitConfig<'_>) -> Option<String> {
+    // Subsection names can't be listed through `GitConfig`, so we obtain them from the parsed sections instead.
+    let bytes = Vec::<u8>::from(config);
+    let parsed = git_config::parser::parse_from_bytes(&bytes).ok()?;
+    parsed
+        .sections()
+        .iter()
+        .filter(|section| section.section_header.name.0.eq_ignore_ascii_case("remote"))
+        .filter_map(|section| section.section_header.subsection_name.as_deref())
+        .find(|name| {
+            config
+                .value::<Boolean<'_>>("remote", Some(name), "promisor")
+                .map_or(false, |b| matches!(b, Boolean::True(_)))
+        })
+        .map(ToOwned::to_owned)
+}
+
+///
+#[cfg(feature = "blocking-network-client")]
+pub mod fetch {
+    use std::{
+        io,
+        path::{Path, PathBuf},
+    };
+
+    use git_features::{progress, progress::Progress, threading::OwnShared};
+    use git_hash::ObjectId;
+    use git_odb::general;
+    use git_protocol::{
+        fetch::{Action, Arguments, LsRefsAction, Ref, Response},
+        transport,
+        transport::client::Capabilities,
+    };
+
+    use crate::{
+        bstr::{BString, ByteSlice},
+        Repository,
+    };
+
+    /// The error returned by [`Remote::to_fetch_fn()`][super::Remote::to_fetch_fn()] and [`Repository::general_objects()`].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("The promisor remote '{}' has no url configured", .name)]
+        MissingUrl { name: BString },
+        #[error("The partial clone filter '{}' isn't valid UTF-8", .filter)]
+        InvalidFilter { filter: BString },
+        #[error(transparent)]
+        Init(#[from] general::init::Error),
+        #[error(transparent)]
+        AlternatesFromEnvironment(#[from] git_odb::alternate::Error),
+    }
+
+    /// Fetches the objects it wants from a promisor remote, writing them into a pack marked as promisor pack.
+    struct Delegate<'a> {
+        ids: &'a [ObjectId],
+        filter: Option<&'a str>,
+        pack_directory: &'a Path,
+    }
+
+    impl<'a> git_protocol::fetch::DelegateBlocking for Delegate<'a> {
+        fn prepare_ls_refs(
+            &mut self,
+            _server: &Capabilities,
+            _arguments: &mut Vec<BString>,
+            _features: &mut Vec<(&str, Option<&str>)>,
+        ) -> io::Result<LsRefsAction> {
+            Ok(LsRefsAction::Skip)
+        }
+
+        fn prepare_fetch(
+            &mut self,
+            _version: transport::Protocol,
+            _server: &Capabilities,
+            _features: &mut Vec<(&str, Option<&str>)>,
+            _refs: &[Ref],
+        ) -> io::Result<Action> {
+            Ok(Action::Continue)
+        }
+
+        fn negotiate(
+            &mut self,
+            _refs: &[Ref],
+            arguments: &mut Arguments,
+            _previous_response: Option<&Response>,
+        ) -> io::Result<Action> {
+            for id in self.ids {
+                arguments.want(id);
+            }
+            if let Some(filter) = self.filter {
+                if arguments.can_use_filter() {
+                    arguments.filter(filter);
+                }
+            }
+            Ok(Action::Cancel)
+        }
+    }
+
+    impl<'a> git_protocol::fetch::Delegate for Delegate<'a> {
+        fn receive_pack(
+            &mut self,
+            input: impl io::BufRead,
+            progress: impl Progress,
+            _refs: &[Ref],
+            _previous_response: &Response,
+        ) -> io::Result<()> {
+            // The pack only becomes visible once its index is moved into place, at which point it must be marked already.
+            let tmp_dir = tempfile::Builder::new()
+                .prefix("tmp_promisor_")
+                .tempdir_in(self.pack_directory)?;
+            let outcome = git_pack::Bundle::write_to_directory(
+                input,
+                Some(tmp_dir.path()),
+                progress,
+                &crate::interrupt::IS_INTERRUPTED,
+                None,
+                git_pack::bundle::write::Options {
+                    thread_limit: None,
+                    index_kind: git_pack::index::Version::V2,
+                    iteration_mode: git_pack::data::input::Mode::Verify,
+                },
+            )
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            if let (Some(data_path), Some(index_path)) = (outcome.data_path, outcome.index_path) {
+                let destination = |path: &Path| self.pack_directory.join(path.file_name().expect("files have names"));
+                // Like git, mark the pack so that objects referenced by its objects are allowed to be missing.
+                std::fs::write(destination(&index_path).with_extension("promisor"), b"")?;
+                std::fs::rename(&data_path, destination(&data_path))?;
+                std::fs::rename(&index_path, destination(&index_path))?;
+            }
+            Ok(())
+        }
+    }
+
+    impl super::Remote {
+        /// Return a function to fetch missing objects from this remote into the `objects_directory` of a partial clone,
+        /// to be set with [`general::Store::set_promisor_fetch()`].
+        ///
+        /// Objects are requested explicitly along with the remote's partial clone filter, so that fetching a tree doesn't
+        /// pull in all of its blobs in blobless clones. Fetches can be interrupted with [`crate::interrupt::trigger()`].
+        pub fn to_fetch_fn(
+            &self,
+            objects_directory: impl Into<PathBuf>,
+        ) -> Result<Box<general::promisor::FetchFn>, Error> {
+            let url = self.url.clone().ok_or_else(|| Error::MissingUrl {
+                name: self.name.clone(),
+            })?;
+            let filter = self
+                .filter
+                .as_ref()
+                .map(|filter| {
+                    filter
+                        .to_str()
+                        .map(ToOwned::to_owned)
+                        .map_err(|_| Error::InvalidFilter { filter: filter.clone() })
+                })
+                .transpose()?;
+            let pack_directory = objects_directory.into().join("pack");
+            Ok(Box::new(move |ids: &[ObjectId]| {
+                let transport = git_protocol::transport::connect(url.as_slice(), transport::Protocol::V2)?;
+                git_protocol::fetch(
+                    transport,
+                    Delegate {
+                        ids,
+                        filter: filter.as_deref(),
+                        pack_directory: &pack_directory,
+                    },
+                    git_protocol::credentials::helper,
+                    progress::Discard,
+                    git_protocol::FetchConnection::TerminateOnSuccessfulCompletion,
+                )?;
+                Ok(())
+            }))
+        }
+    }
+
+    impl Repository {
+        /// Open our objects directory as [general object store][general::Store], which fetches objects missing in
+        /// partial clones from the [promisor remote][Repository::promisor_remote()] on demand.
+        ///
+        /// Like when opening the repository, alternates in `GIT_ALTERNATE_OBJECT_DIRECTORIES` are used as well.
+        pub fn general_objects(&self) -> Result<OwnShared<general::Store>, Error> {
+            let store = general::Store::at_opts(
+                self.objects_dir(),
+                general::init::Options {
+                    alternates: git_odb::alternate::from_environment()?,
+                    ..Default::default()
+                },
+            )?;
+            if let Some(remote) = self.promisor_remote() {
+                store.set_promisor_fetch(Some(remote.to_fetch_fn(self.objects_dir())?));
+            }
+            Ok(store)
+        }
+    }
+}
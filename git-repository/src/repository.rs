@@ -10,6 +10,14 @@ mod access {
             }
         }
 
+        /// Return the remote to fetch missing objects from if this repository is a partial clone, as configured with
+        /// `extensions.partialClone`.
+        ///
+        /// Such objects can be fetched on demand by the [general object store][git_odb::general::Store::set_promisor_fetch()].
+        pub fn promisor_remote(&self) -> Option<&crate::promisor::Remote> {
+            self.promisor_remote.as_ref()
+        }
+
         /// Add thread-local state to an easy-to-use handle for the most convenient API.
         pub fn to_easy(&self) -> easy::Handle {
             self.into()
@@ -39,7 +47,7 @@ pub mod open {
     use git_config::values::{Boolean, Integer};
     use git_features::threading::OwnShared;
 
-    use crate::Repository;
+    use crate::{bstr::ByteSlice, Repository};
 
     /// The error returned by [`Repository::open()`].
     #[derive(Debug, thiserror::Error)]
@@ -53,10 +61,14 @@ pub mod open {
         ObjectStoreInitialization(#[from] git_odb::linked::init::Error),
         #[error("Cannot handle objects formatted as {:?}", .name)]
         UnsupportedObjectFormat { name: crate::bstr::BString },
+        #[error("Could not read the path to the git directory or common directory from '{}'", .path.display())]
+        ReadPath { source: std::io::Error, path: PathBuf },
     }
 
     impl Repository {
         /// Open a git repository at the given `path`, possibly expanding it to `path/.git` if `path` is a work tree dir.
+        ///
+        /// Alternate object directories in `GIT_ALTERNATE_OBJECT_DIRECTORIES` are used in addition to those in `alternates` files.
         pub fn open(path: impl Into<std::path::PathBuf>) -> Result<Self, Error> {
             let path = path.into();
             let (path, kind) = match crate::path::is::git(&path) {
@@ -71,17 +83,37 @@ pub mod open {
             Repository::open_from_paths(git_dir, worktree_dir)
         }
 
-        pub(in crate::repository) fn open_from_paths(
-            git_dir: PathBuf,
-            mut worktree_dir: Option<PathBuf>,
-        ) -> Result<Self, Error> {
-            let config = git_config::file::GitConfig::open(git_dir.join("config"))?;
+        pub(crate) fn open_from_paths(git_dir: PathBuf, mut worktree_dir: Option<PathBuf>) -> Result<Self, Error> {
+            let git_dir = if git_dir.is_file() {
+                crate::path::from_gitdir_file(&git_dir).map_err(|source| Error::ReadPath { source, path: git_dir })?
+            } else {
+                git_dir
+            };
+            let common_dir_file = git_dir.join("commondir");
+            let common_dir = crate::path::from_plain_file(&common_dir_file)
+                .transpose()
+                .map_err(|source| Error::ReadPath {
+                    source,
+                    path: common_dir_file,
+                })?;
+            let config = git_config::file::GitConfig::open(common_dir.as_ref().unwrap_or(&git_dir).join("config"))?;
             if worktree_dir.is_none() {
                 let is_bare = config
                     .value::<Boolean<'_>>("core", None, "bare")
                     .map_or(false, |b| matches!(b, Boolean::True(_)));
                 if !is_bare {
-                    worktree_dir = Some(git_dir.parent().expect("parent is always available").to_owned());
+                    let dot_git = match common_dir {
+                        Some(_) => crate::path::from_plain_file(git_dir.join("gitdir")).and_then(Result::ok),
+                        None => None,
+                    };
+                    worktree_dir = Some(
+                        dot_git
+                            .as_deref()
+                            .unwrap_or(&git_dir)
+                            .parent()
+                            .expect("parent is always available")
+                            .to_owned(),
+                    );
                 }
             }
             let hash_kind = if config
@@ -105,18 +137,34 @@ pub mod open {
                 git_hash::Kind::Sha1
             };
 
+            // Relative paths are relative to where hooks are run, which is the work tree unless the repository is bare.
+            let hooks_dir = match config
+                .value::<Cow<'_, [u8]>>("core", None, "hooksPath")
+                .ok()
+                .and_then(|path| path.to_path().ok().map(ToOwned::to_owned))
+            {
+                Some(path) => worktree_dir.as_ref().unwrap_or(&git_dir).join(path),
+                None => common_dir.as_ref().unwrap_or(&git_dir).join("hooks"),
+            };
+            let write_reflog = if worktree_dir.is_none() {
+                git_ref::store::WriteReflog::Disable
+            } else {
+                git_ref::store::WriteReflog::Normal
+            };
+            let alternates = git_odb::alternate::from_environment().map_err(git_odb::linked::init::Error::from)?;
             Ok(crate::Repository {
-                objects: OwnShared::new(git_odb::linked::Store::at(git_dir.join("objects"))?),
-                refs: crate::RefStore::at(
-                    git_dir,
-                    if worktree_dir.is_none() {
-                        git_ref::store::WriteReflog::Disable
-                    } else {
-                        git_ref::store::WriteReflog::Normal
-                    },
-                ),
+                objects: OwnShared::new(git_odb::linked::Store::at_opts(
+                    common_dir.as_ref().unwrap_or(&git_dir).join("objects"),
+                    git_odb::linked::init::Options { alternates },
+                )?),
+                refs: match common_dir {
+                    Some(common_dir) => crate::RefStore::for_linked_worktree(git_dir, common_dir, write_reflog),
+                    None => crate::RefStore::at(git_dir, write_reflog),
+                },
                 work_tree: worktree_dir,
                 hash_kind,
+                hooks_dir,
+                promisor_remote: crate::promisor::Remote::from_config(&config),
             })
         }
     }
@@ -166,6 +214,13 @@ mod location {
             self.refs.base()
         }
 
+        /// Return the path to the directory containing objects, references and configuration shared among all worktrees.
+        ///
+        /// It's the same as [`git_dir()`][Repository::git_dir()] unless this is a linked worktree.
+        pub fn common_dir(&self) -> &std::path::Path {
+            self.refs.common_dir_resolved()
+        }
+
         /// Return the path to the working directory if this is not a bare repository.
         pub fn workdir(&self) -> Option<&std::path::Path> {
             self.work_tree.as_deref()
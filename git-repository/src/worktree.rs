@@ -0,0 +1,532 @@
+//! Linked worktrees as managed by `git worktree`, registered in `$GIT_COMMON_DIR/worktrees/<id>`.
+use std::path::{Path, PathBuf};
+
+use crate::{
+    bstr::{BStr, BString, ByteSlice},
+    Repository,
+};
+
+/// A handle to a linked worktree by its private git directory, usually `$GIT_COMMON_DIR/worktrees/<id>`.
+///
+/// It's called proxy as it only refers to the worktree without loading it, use [`into_repo()`][Proxy::into_repo()] for that.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Proxy {
+    git_dir: PathBuf,
+}
+
+impl Proxy {
+    /// Create a new instance from the private `git_dir` of a linked worktree, typically `$GIT_COMMON_DIR/worktrees/<id>`.
+    pub fn at(git_dir: impl Into<PathBuf>) -> Self {
+        Proxy {
+            git_dir: git_dir.into(),
+        }
+    }
+
+    /// The identifier of this worktree, which is the name of its private git directory.
+    pub fn id(&self) -> &BStr {
+        <[u8]>::from_os_str(
+            self.git_dir
+                .file_name()
+                .expect("worktree directories always have a name"),
+        )
+        .expect("no illformed UTF-8 on windows")
+        .as_bstr()
+    }
+
+    /// The private git directory of this worktree containing its `HEAD` and index, among other per-worktree files.
+    pub fn git_dir(&self) -> &Path {
+        &self.git_dir
+    }
+
+    /// Return the path to the work tree with the checked out files, as registered in the `gitdir` file.
+    ///
+    /// Note that it might not exist anymore if it was deleted without `git worktree remove`.
+    pub fn base(&self) -> std::io::Result<PathBuf> {
+        let dot_git = crate::path::from_plain_file(self.git_dir.join("gitdir"))
+            .unwrap_or_else(|| Err(std::io::ErrorKind::NotFound.into()))?;
+        Ok(dot_git
+            .parent()
+            .expect("the .git file is always in a work tree")
+            .to_owned())
+    }
+
+    /// Return true if this worktree is locked, which protects it from being pruned or removed.
+    pub fn is_locked(&self) -> bool {
+        self.git_dir.join("locked").is_file()
+    }
+
+    /// Return the reason for why this worktree is locked, or `None` if it is not locked.
+    ///
+    /// An empty reason indicates that the worktree was locked without providing one.
+    pub fn lock_reason(&self) -> Option<BString> {
+        std::fs::read(self.git_dir.join("locked"))
+            .ok()
+            .map(|reason| reason.trim_end().into())
+    }
+
+    /// Lock this worktree with the given `reason`, which may be empty, to protect it from being pruned or removed.
+    pub fn lock(&self, reason: &BStr) -> std::io::Result<()> {
+        std::fs::write(self.git_dir.join("locked"), reason)
+    }
+
+    /// Unlock this worktree if it was locked.
+    pub fn unlock(&self) -> std::io::Result<()> {
+        match std::fs::remove_file(self.git_dir.join("locked")) {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            res => res,
+        }
+    }
+
+    /// Return true if this worktree can be pruned as its work tree is gone, and it isn't locked.
+    pub fn is_prunable(&self) -> bool {
+        !self.is_locked() && self.base().map_or(true, |base| !base.is_dir())
+    }
+
+    /// Open this worktree as repository.
+    pub fn into_repo(self) -> Result<Repository, crate::open::Error> {
+        let base = self.base().map_err(|source| crate::open::Error::ReadPath {
+            source,
+            path: self.git_dir.join("gitdir"),
+        })?;
+        Repository::open_from_paths(self.git_dir, Some(base))
+    }
+
+    /// Delete the work tree along with all of its files as well as its private git directory, unless `force` is false
+    /// and it is locked or its files don't match the tree checked out in it.
+    ///
+    /// Note that the index isn't consulted, and that all files not contained in the checked out tree count as changes,
+    /// including ignored ones.
+    pub fn remove(self, force: bool) -> Result<(), remove::Error> {
+        if !force {
+            if let Some(reason) = self.lock_reason() {
+                return Err(remove::Error::Locked {
+                    id: self.id().to_owned(),
+                    reason,
+                });
+            }
+            if self.base().map_or(false, |base| base.is_dir()) && !self.clone().into_repo()?.work_tree_matches_head()? {
+                return Err(remove::Error::Modified {
+                    id: self.id().to_owned(),
+                });
+            }
+        }
+        if let Ok(base) = self.base() {
+            match std::fs::remove_dir_all(&base) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(remove::Error::Io {
+                        source: err,
+                        path: base,
+                    })
+                }
+                _ => {}
+            }
+        }
+        std::fs::remove_dir_all(&self.git_dir).map_err(|source| remove::Error::Io {
+            source,
+            path: self.git_dir,
+        })
+    }
+}
+
+impl Repository {
+    /// Return true if the files in our work tree are exactly those of the tree checked out at `HEAD`, ignoring empty directories.
+    fn work_tree_matches_head(&self) -> Result<bool, remove::Error> {
+        let work_tree = match self.workdir() {
+            Some(work_tree) => work_tree,
+            None => return Ok(true),
+        };
+        let handle = self.to_easy();
+        let tree_id = match handle.head()?.into_fully_peeled_id() {
+            Some(id) => Some(handle.find_object(id?)?.peel_to_kind(git_object::Kind::Tree)?.id),
+            None => None,
+        };
+        dir_matches_tree(&handle, work_tree, tree_id, true)
+    }
+
+    /// Return all linked worktrees of this repository sorted by their id, which doesn't include the main worktree.
+    pub fn worktrees(&self) -> std::io::Result<Vec<Proxy>> {
+        let mut worktrees = match std::fs::read_dir(self.common_dir().join("worktrees")) {
+            Ok(entries) => entries
+                .filter_map(|entry| {
+                    entry
+                        .map(|entry| {
+                            let git_dir = entry.path();
+                            git_dir.join("gitdir").is_file().then(|| Proxy::at(git_dir))
+                        })
+                        .transpose()
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        worktrees.sort();
+        Ok(worktrees)
+    }
+
+    /// Create a new linked worktree whose work tree is at `path` with its `HEAD` set to `head`, which is either an existing
+    /// branch to check out or the id of a commit, or of a tag pointing to one, to detach at.
+    ///
+    /// `path` must not exist or be an empty directory. Its id is derived from the last component of `path`.
+    /// The files of the tree of the commit are checked out into it, and an index matching them is written.
+    ///
+    /// Note that no `.gitattributes` or filters are applied, symbolic links are written as files containing their target on
+    /// platforms other than unix, and submodules are checked out as empty directories.
+    pub fn worktree_add(&self, path: impl AsRef<Path>, head: &git_ref::Target) -> Result<Proxy, add::Error> {
+        let path = path.as_ref();
+        let path = if path.is_relative() {
+            std::env::current_dir()
+                .map_err(|source| add::Error::Io {
+                    source,
+                    path: path.to_owned(),
+                })?
+                .join(path)
+        } else {
+            path.to_owned()
+        };
+        if path.read_dir().map_or(false, |mut entries| entries.next().is_some()) {
+            return Err(add::Error::DestinationNotEmpty { path });
+        }
+        if let git_ref::Target::Symbolic(name) = head {
+            if let Some(id) = self.worktree_with_branch_checked_out(name.as_bstr())? {
+                return Err(add::Error::BranchCheckedOut {
+                    name: name.as_bstr().to_owned(),
+                    id,
+                });
+            }
+        }
+        let handle = self.to_easy();
+        let commit_id = match head {
+            git_ref::Target::Symbolic(name) => handle
+                .try_find_reference(name.to_partial())?
+                .filter(|r| r.name() == name.to_ref())
+                .ok_or_else(|| add::Error::BranchNotFound {
+                    name: name.as_bstr().to_owned(),
+                })?
+                .into_fully_peeled_id()?
+                .detach(),
+            git_ref::Target::Peeled(id) => *id,
+        };
+        let commit = handle.find_object(commit_id)?.peel_to_kind(git_object::Kind::Commit)?;
+        let commit_id = commit.id;
+        let tree_id = commit.peel_to_kind(git_object::Kind::Tree)?.id;
+
+        let worktrees_dir = self.common_dir().join("worktrees");
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| add::Error::InvalidPath { path: path.clone() })?;
+        let git_dir = std::iter::once(name.to_owned())
+            .chain((1..).map(|counter| format!("{}{}", name, counter)))
+            .map(|id| worktrees_dir.join(id))
+            .find(|git_dir| !git_dir.exists())
+            .expect("there are enough ids");
+
+        let io_err = |path: &Path| {
+            let path = path.to_owned();
+            move |source| add::Error::Io { source, path }
+        };
+        std::fs::create_dir_all(&git_dir).map_err(io_err(&git_dir))?;
+        let git_dir = git_dir.canonicalize().map_err(io_err(&git_dir))?;
+        let worktree = Proxy::at(&git_dir);
+        worktree
+            .lock("initializing".into())
+            .map_err(io_err(&git_dir.join("locked")))?;
+
+        std::fs::create_dir_all(&path).map_err(io_err(&path))?;
+        // Both links must stay valid no matter from where the worktree is accessed.
+        let path = path.canonicalize().map_err(io_err(&path))?;
+        let dot_git = path.join(".git");
+        let write = |file: &Path, content: &[u8]| std::fs::write(file, content).map_err(io_err(file));
+        write(&git_dir.join("gitdir"), format!("{}\n", dot_git.display()).as_bytes())?;
+        write(&git_dir.join("commondir"), b"../..\n")?;
+        let head_content = match head {
+            git_ref::Target::Symbolic(name) => format!("ref: {}\n", name.as_bstr()),
+            git_ref::Target::Peeled(_) => format!("{}\n", commit_id),
+        };
+        write(&git_dir.join("HEAD"), head_content.as_bytes())?;
+        write(&dot_git, format!("gitdir: {}\n", git_dir.display()).as_bytes())?;
+
+        let mut entries = Vec::new();
+        checkout_tree(&handle, tree_id, &path, "".into(), &mut entries)?;
+        let mut index = Vec::new();
+        git_index::write::entries(&mut entries, &mut index).map_err(io_err(&git_dir.join("index")))?;
+        write(&git_dir.join("index"), &index)?;
+
+        worktree.unlock().map_err(io_err(&git_dir.join("locked")))?;
+        Ok(worktree)
+    }
+
+    /// Delete the private git directories of all linked worktrees whose work tree is gone, unless they are locked,
+    /// and return the ids of the pruned worktrees.
+    pub fn worktree_prune(&self) -> std::io::Result<Vec<BString>> {
+        let mut pruned = Vec::new();
+        for worktree in self.worktrees()?.into_iter().filter(Proxy::is_prunable) {
+            std::fs::remove_dir_all(worktree.git_dir())?;
+            pruned.push(worktree.id().to_owned());
+        }
+        Ok(pruned)
+    }
+
+    /// Return the id of the worktree that has the branch `name` checked out, or `"main-worktree"` if it's the main worktree.
+    fn worktree_with_branch_checked_out(&self, name: &BStr) -> Result<Option<BString>, add::Error> {
+        let main_git_dir = self.common_dir().to_owned();
+        let worktrees = self.worktrees().map_err(|source| add::Error::Io {
+            source,
+            path: main_git_dir.join("worktrees"),
+        })?;
+        for (git_dir, id) in std::iter::once((main_git_dir, BString::from("main-worktree"))).chain(
+            worktrees
+                .into_iter()
+                .map(|wt| (wt.git_dir().to_owned(), wt.id().to_owned())),
+        ) {
+            let refs = crate::RefStore::at(git_dir, git_ref::store::WriteReflog::Disable);
+            let head = refs.find_loose("HEAD")?;
+            if head.target.as_name() == Some(name) {
+                return Ok(Some(id));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Write all files of the tree with `tree_id` into `dir` and add them to `entries`, with their paths prefixed by `prefix`.
+fn checkout_tree(
+    handle: &crate::easy::Handle,
+    tree_id: git_hash::ObjectId,
+    dir: &Path,
+    prefix: &BStr,
+    entries: &mut Vec<git_index::Entry>,
+) -> Result<(), add::Error> {
+    use git_object::tree::EntryMode;
+
+    let io_err = |path: &Path| {
+        let path = path.to_owned();
+        move |source| add::Error::Io { source, path }
+    };
+    let tree = handle.find_object(tree_id)?.detach();
+    for entry in git_object::TreeRefIter::from_bytes(&tree.data) {
+        let entry = entry.map_err(|_| add::Error::DecodeTree { id: tree_id })?;
+        let path = entry
+            .filename
+            .to_path()
+            .ok()
+            .filter(|name| name.components().count() == 1 && entry.filename != ".git")
+            .map(|name| dir.join(name))
+            .ok_or_else(|| add::Error::InvalidTreeEntry {
+                name: entry.filename.to_owned(),
+                id: tree_id,
+            })?;
+        let mut entry_path = prefix.to_owned();
+        if !entry_path.is_empty() {
+            entry_path.push(b'/');
+        }
+        entry_path.extend_from_slice(entry.filename);
+        match entry.mode {
+            EntryMode::Tree => {
+                std::fs::create_dir(&path).map_err(io_err(&path))?;
+                checkout_tree(handle, entry.oid.to_owned(), &path, entry_path.as_bstr(), entries)?;
+                continue;
+            }
+            EntryMode::Blob | EntryMode::BlobExecutable => {
+                let blob = handle.find_object(entry.oid.to_owned())?.detach();
+                std::fs::write(&path, &blob.data).map_err(io_err(&path))?;
+                #[cfg(unix)]
+                if entry.mode == EntryMode::BlobExecutable {
+                    use std::os::unix::fs::PermissionsExt;
+                    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).map_err(io_err(&path))?;
+                }
+            }
+            EntryMode::Link => {
+                let blob = handle.find_object(entry.oid.to_owned())?.detach();
+                #[cfg(unix)]
+                {
+                    let target = blob.data.to_path().map_err(|_| add::Error::InvalidTreeEntry {
+                        name: entry.filename.to_owned(),
+                        id: tree_id,
+                    })?;
+                    std::os::unix::fs::symlink(target, &path).map_err(io_err(&path))?;
+                }
+                #[cfg(not(unix))]
+                std::fs::write(&path, &blob.data).map_err(io_err(&path))?;
+            }
+            EntryMode::Commit => std::fs::create_dir(&path).map_err(io_err(&path))?,
+        }
+        entries.push(git_index::Entry {
+            path: entry_path,
+            mode: entry.mode as u32,
+            id: entry.oid.to_owned(),
+            stage: 0,
+            is_intent_to_add: false,
+        });
+    }
+    Ok(())
+}
+
+fn dir_matches_tree(
+    handle: &crate::easy::Handle,
+    dir: &Path,
+    tree_id: Option<git_hash::ObjectId>,
+    is_work_tree_root: bool,
+) -> Result<bool, remove::Error> {
+    use git_object::tree::EntryMode;
+    use git_odb::Write;
+
+    let io_err = |path: &Path| {
+        let path = path.to_owned();
+        move |source| remove::Error::Io { source, path }
+    };
+    let mut untracked = std::fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.file_name()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(io_err(dir))?;
+    if is_work_tree_root {
+        untracked.retain(|name| name != ".git");
+    }
+
+    let entries = match tree_id {
+        Some(tree_id) => {
+            let tree = handle.find_object(tree_id)?.detach();
+            git_object::TreeRefIter::from_bytes(&tree.data)
+                .filter_map(Result::ok)
+                .map(|entry| (entry.filename.to_owned(), entry.mode, entry.oid.to_owned()))
+                .collect()
+        }
+        None => Vec::new(),
+    };
+    for (name, mode, id) in entries {
+        let path = match name.to_path() {
+            Ok(name) => dir.join(name),
+            Err(_) => return Ok(false),
+        };
+        match untracked
+            .iter()
+            .position(|untracked| untracked == path.file_name().expect("joined a name"))
+        {
+            Some(pos) => untracked.remove(pos),
+            None => return Ok(false),
+        };
+        let meta = std::fs::symlink_metadata(&path).map_err(io_err(&path))?;
+        let matches = match mode {
+            EntryMode::Tree => meta.is_dir() && dir_matches_tree(handle, &path, Some(id), false)?,
+            EntryMode::Blob | EntryMode::BlobExecutable => {
+                meta.is_file() && {
+                    let file = std::fs::File::open(&path).map_err(io_err(&path))?;
+                    git_odb::sink()
+                        .write_stream(git_object::Kind::Blob, meta.len(), file, handle.hash_kind())
+                        .map_err(io_err(&path))?
+                        == id
+                }
+            }
+            EntryMode::Link => {
+                meta.file_type().is_symlink() && {
+                    let target = std::fs::read_link(&path).map_err(io_err(&path))?;
+                    <[u8]>::from_path(&target).map_or(false, |target| {
+                        git_odb::sink()
+                            .write_buf(git_object::Kind::Blob, target, handle.hash_kind())
+                            .map_or(false, |target_id| target_id == id)
+                    })
+                }
+            }
+            // Submodules are not checked.
+            EntryMode::Commit => meta.is_dir(),
+        };
+        if !matches {
+            return Ok(false);
+        }
+    }
+    for name in untracked {
+        if !is_empty_dir(&dir.join(name))? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Return true if `path` is a directory without any files in it, which git wouldn't be able to track.
+fn is_empty_dir(path: &Path) -> Result<bool, remove::Error> {
+    if !std::fs::symlink_metadata(path).map_or(false, |meta| meta.is_dir()) {
+        return Ok(false);
+    }
+    for entry in std::fs::read_dir(path).map_err(|source| remove::Error::Io {
+        source,
+        path: path.to_owned(),
+    })? {
+        let entry = entry.map_err(|source| remove::Error::Io {
+            source,
+            path: path.to_owned(),
+        })?;
+        if !is_empty_dir(&entry.path())? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+///
+pub mod add {
+    use std::path::PathBuf;
+
+    use crate::bstr::BString;
+
+    /// The error returned by [`Repository::worktree_add()`][crate::Repository::worktree_add()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("Refusing to create a worktree in the non-empty directory at '{}'", .path.display())]
+        DestinationNotEmpty { path: PathBuf },
+        #[error("Cannot derive a worktree id from the path '{}'", .path.display())]
+        InvalidPath { path: PathBuf },
+        #[error("The branch '{}' is already checked out in worktree '{}'", .name, .id)]
+        BranchCheckedOut { name: BString, id: BString },
+        #[error("The branch '{}' to check out doesn't exist", .name)]
+        BranchNotFound { name: BString },
+        #[error(transparent)]
+        FindHead(#[from] git_ref::file::find::existing::Error),
+        #[error(transparent)]
+        FindBranch(#[from] crate::easy::reference::find::Error),
+        #[error(transparent)]
+        PeelBranch(#[from] crate::easy::reference::peel::Error),
+        #[error(transparent)]
+        FindObject(#[from] crate::easy::object::find::existing::Error),
+        #[error(transparent)]
+        PeelToTree(#[from] crate::easy::object::peel::to_kind::Error),
+        #[error("The tree {} could not be decoded", .id)]
+        DecodeTree { id: git_hash::ObjectId },
+        #[error("The tree {} contains the entry '{}' which can't be checked out", .id, .name)]
+        InvalidTreeEntry { name: BString, id: git_hash::ObjectId },
+        #[error("Could not create or write '{}'", .path.display())]
+        Io { source: std::io::Error, path: PathBuf },
+    }
+}
+
+///
+pub mod remove {
+    use std::path::PathBuf;
+
+    use crate::bstr::BString;
+
+    /// The error returned by [`Proxy::remove()`][super::Proxy::remove()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("Worktree '{}' is locked: {}", .id, .reason)]
+        Locked { id: BString, reason: BString },
+        #[error("Worktree '{}' contains modified or untracked files", .id)]
+        Modified { id: BString },
+        #[error(transparent)]
+        Open(#[from] crate::open::Error),
+        #[error(transparent)]
+        FindHead(#[from] crate::easy::reference::find::existing::Error),
+        #[error(transparent)]
+        PeelHead(#[from] crate::easy::head::peel::Error),
+        #[error(transparent)]
+        PeelToTree(#[from] crate::easy::object::peel::to_kind::Error),
+        #[error(transparent)]
+        FindObject(#[from] crate::easy::object::find::existing::Error),
+        #[error("Could not delete '{}'", .path.display())]
+        Io { source: std::io::Error, path: PathBuf },
+    }
+}
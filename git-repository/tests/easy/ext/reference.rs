@@ -80,6 +80,71 @@ mod set_namespace {
     }
 }
 
+#[cfg(unix)]
+mod reference_transaction_hook {
+    use std::os::unix::fs::PermissionsExt;
+
+    use git_repository as git;
+    use git_repository::refs::transaction::PreviousValue;
+
+    #[test]
+    fn is_run_with_edits_and_may_reject_them() -> crate::Result {
+        let (repo, _keep) = crate::easy_repo_rw("make_references_repo.sh")?;
+        let hooks_dir = repo.refs.base().join("hooks");
+        std::fs::create_dir_all(&hooks_dir)?;
+        let hook = hooks_dir.join("reference-transaction");
+        // Hooks run in the work tree.
+        std::fs::write(
+            &hook,
+            "#!/bin/sh\ncat >> \".git/hook-$1\"\n! grep -q rejected \".git/hook-$1\"\n",
+        )?;
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+
+        let id = git::ObjectId::empty_tree(git::hash::Kind::Sha1);
+        repo.tag_reference("accepted", id, PreviousValue::MustNotExist)?;
+        let committed = std::fs::read_to_string(repo.refs.base().join("hook-committed"))?;
+        assert_eq!(
+            committed,
+            format!("{} {} refs/tags/accepted\n", git::ObjectId::null_sha1(), id)
+        );
+
+        assert!(
+            repo.tag_reference("rejected", id, PreviousValue::MustNotExist).is_err(),
+            "the hook fails in the prepared state"
+        );
+        assert!(repo.try_find_reference("rejected")?.is_none());
+        assert_eq!(
+            std::fs::read_to_string(repo.refs.base().join("hook-aborted"))?,
+            format!("{} {} refs/tags/rejected\n", git::ObjectId::null_sha1(), id),
+            "the hook is told about the abort it caused"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn is_found_in_the_configured_hooks_path() -> crate::Result {
+        let (_repo, dir) = crate::repo_rw("make_references_repo.sh")?;
+        let mut config = std::fs::OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(".git/config"))?;
+        std::io::Write::write_all(&mut config, b"[core]\n\thooksPath = custom-hooks\n")?;
+        let hooks_dir = dir.path().join("custom-hooks");
+        std::fs::create_dir_all(&hooks_dir)?;
+        let hook = hooks_dir.join("reference-transaction");
+        std::fs::write(&hook, "#!/bin/sh\ntest \"$1\" != prepared\n")?;
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+
+        let repo = git::Repository::open(dir.path())?.to_easy();
+        let id = git::ObjectId::empty_tree(git::hash::Kind::Sha1);
+        assert!(
+            repo.tag_reference("rejected", id, PreviousValue::MustNotExist).is_err(),
+            "relative hook paths are relative to the work tree"
+        );
+        assert!(repo.try_find_reference("rejected")?.is_none());
+        Ok(())
+    }
+}
+
 mod iter_references {
     use git_repository as git;
     use git_testtools::hex_to_id;
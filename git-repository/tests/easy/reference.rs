@@ -40,4 +40,81 @@ mod find {
         assert_eq!(symbolic_ref.into_fully_peeled_id()?, the_commit, "idempotency");
         Ok(())
     }
+
+    #[test]
+    fn ambiguous_names_resolve_to_the_first_candidate_like_git() -> crate::Result {
+        let repo = repo()?;
+        for (partial_name, expected) in &[
+            ("dt1", "refs/tags/dt1"),
+            ("d1", "refs/d1"),
+            ("heads/d1", "refs/heads/d1"),
+            ("origin", "refs/remotes/origin/HEAD"),
+        ] {
+            let r = repo.find_reference(*partial_name)?;
+            assert_eq!(r.name().as_bstr(), *expected);
+        }
+        assert!(repo.try_find_reference("does-not-exist")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn shortened_names_find_the_same_reference() -> crate::Result {
+        let repo = repo()?;
+        for full_name in &[
+            "refs/heads/main",
+            "refs/tags/t1",
+            "refs/remotes/origin/main",
+            "refs/remotes/origin/HEAD",
+        ] {
+            let r = repo.find_reference(*full_name)?;
+            let short = r.name().shorten();
+            assert_ne!(short, *full_name);
+            assert_eq!(repo.find_reference(short)?.name(), r.name());
+        }
+        Ok(())
+    }
+}
+
+mod pseudo {
+    use git_testtools::hex_to_id;
+
+    #[test]
+    fn accessors_read_the_respective_files() -> crate::Result {
+        let (repo, _keep) = crate::repo_rw("make_basic_repo.sh")?;
+        let repo: git_repository::easy::Handle = repo.into();
+        assert!(repo.fetch_head()?.is_none());
+        assert!(repo.merge_heads()?.is_none());
+        assert!(repo.orig_head()?.is_none());
+        assert!(repo.cherry_pick_head()?.is_none());
+        assert!(repo.revert_head()?.is_none());
+
+        let id = hex_to_id("134385f6d781b7e97062102c6a483440bfda2a03");
+        let entries = vec![git_ref::pseudo::fetch_head::Entry {
+            id,
+            not_for_merge: false,
+            description: "branch 'main' of https://example.com/repo".into(),
+        }];
+        repo.refs
+            .write_fetch_head(&entries, git_lock::acquire::Fail::Immediately)?;
+        assert_eq!(repo.fetch_head()?, Some(entries));
+
+        repo.refs
+            .write_merge_head(&[id], git_lock::acquire::Fail::Immediately)?;
+        assert_eq!(
+            repo.merge_heads()?
+                .expect("written")
+                .into_iter()
+                .map(|id| id.detach())
+                .collect::<Vec<_>>(),
+            vec![id]
+        );
+
+        for name in ["ORIG_HEAD", "CHERRY_PICK_HEAD", "REVERT_HEAD"] {
+            std::fs::write(repo.refs.base().join(name), format!("{}\n", id))?;
+        }
+        assert_eq!(repo.orig_head()?.expect("written").detach(), id);
+        assert_eq!(repo.cherry_pick_head()?.expect("written").detach(), id);
+        assert_eq!(repo.revert_head()?.expect("written").detach(), id);
+        Ok(())
+    }
 }
@@ -0,0 +1,19 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q upstream
+(cd upstream
+  git config commit.gpgsign false
+
+  echo content > file
+  git add file
+  git commit -q -m c1
+)
+
+git clone -q --bare upstream server.git
+(cd server.git
+  git config uploadpack.allowFilter true
+  git config uploadpack.allowAnySHA1InWant true
+)
+
+git clone -q --bare --filter=blob:none "file://$PWD/server.git" partial.git
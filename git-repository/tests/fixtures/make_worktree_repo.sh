@@ -0,0 +1,34 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q
+git config commit.gpgsign false
+
+git checkout -q -b main
+touch this
+git add this
+git commit -q -m c1
+
+git worktree add -q wt -b linked
+git worktree add -q --detach wt-locked
+git worktree lock --reason "on a removable drive" wt-locked
+git worktree add -q wt-gone -b gone
+rm -rf wt-gone
+
+# make all links relative so the fixture can be moved
+for id in wt wt-locked wt-gone; do
+  echo "../../../$id/.git" > .git/worktrees/$id/gitdir
+done
+for id in wt wt-locked; do
+  echo "gitdir: ../.git/worktrees/$id" > $id/.git
+done
+
+mkdir -p dir/nested
+echo "content" > dir/nested/file
+echo "#!/bin/sh" > exe && chmod +x exe
+ln -s dir/nested/file link
+git add dir exe link
+git commit -q -m c2
+git branch other
+
+mkdir -p wt/some/nested/dir
@@ -0,0 +1,31 @@
+use git_odb::Find;
+use git_repository::{bstr::ByteSlice, Repository};
+
+#[test]
+fn missing_blobs_are_fetched_from_the_file_remote_into_a_promisor_pack() -> crate::Result {
+    let dir = git_testtools::scripted_fixture_repo_writable("make_partial_clone_repo.sh")?;
+    let repo = Repository::open(dir.path().join("partial.git"))?;
+    let remote = repo.promisor_remote().expect("partial clone");
+    assert_eq!(remote.name(), "origin");
+    assert_eq!(remote.filter.as_ref().map(|f| f.as_bstr()), Some("blob:none".into()));
+
+    let promisor_packs = || -> std::io::Result<usize> {
+        Ok(std::fs::read_dir(repo.objects_dir().join("pack"))?
+            .filter_map(Result::ok)
+            .filter(|e| e.path().extension().map_or(false, |ext| ext == "promisor"))
+            .count())
+    };
+    assert_eq!(promisor_packs()?, 1, "the clone itself is a promisor pack");
+
+    let store = repo.general_objects()?;
+    let handle = store.to_handle();
+    let blob_id = git_hash::ObjectId::from_hex(b"d95f3ad14dee633a758d2e331151e950dd13e4ed")?;
+    assert!(!handle.contains(blob_id), "the blob was filtered when cloning");
+
+    let mut buf = Vec::new();
+    let blob = handle.try_find(blob_id, &mut buf)?.expect("fetched on demand");
+    assert_eq!(blob.data.as_bstr(), "content\n");
+    assert!(handle.is_in_promisor_pack(blob_id));
+    assert_eq!(promisor_packs()?, 2, "fetched packs are marked as promisor packs, too");
+    Ok(())
+}
@@ -27,4 +27,7 @@ fn basic_rw_repo() -> crate::Result<(Handle, tempfile::TempDir)> {
 mod discover;
 mod easy;
 mod init;
+#[cfg(feature = "blocking-network-client")]
+mod promisor;
 mod reference;
+mod worktree;
@@ -0,0 +1,251 @@
+use std::path::{Path, PathBuf};
+
+use git_repository::{Kind, Repository};
+
+fn repo_path() -> crate::Result<PathBuf> {
+    git_testtools::scripted_fixture_repo_read_only("make_worktree_repo.sh")
+}
+
+/// Links in the fixture are relative, so paths must be resolved before comparing them.
+fn canonical(path: impl AsRef<Path>) -> PathBuf {
+    path.as_ref().canonicalize().expect("path exists")
+}
+
+mod open {
+    use super::*;
+
+    #[test]
+    fn from_work_tree_with_dot_git_file() -> crate::Result {
+        let dir = repo_path()?;
+        let repo = Repository::open(dir.join("wt"))?;
+        assert_eq!(repo.kind(), Kind::WorkTree);
+        assert_eq!(repo.workdir(), Some(dir.join("wt").as_path()));
+        assert_eq!(canonical(repo.git_dir()), canonical(dir.join(".git/worktrees/wt")));
+        assert_eq!(canonical(repo.common_dir()), canonical(dir.join(".git")));
+        assert_eq!(
+            canonical(repo.objects_dir()),
+            canonical(dir.join(".git/objects")),
+            "objects are shared"
+        );
+
+        let head = repo.refs.find_loose("HEAD")?;
+        assert_eq!(head.target.as_name(), Some("refs/heads/linked".into()));
+        let main_head = repo.refs.find_loose("main-worktree/HEAD")?;
+        assert_eq!(main_head.target.as_name(), Some("refs/heads/main".into()));
+        assert!(repo.refs.try_find("main")?.is_some(), "branches are shared");
+        Ok(())
+    }
+
+    #[test]
+    fn from_private_git_dir() -> crate::Result {
+        let dir = repo_path()?;
+        let repo = Repository::open(dir.join(".git/worktrees/wt"))?;
+        assert_eq!(repo.kind(), Kind::WorkTree);
+        assert_eq!(
+            canonical(repo.workdir().expect("non-bare")),
+            canonical(dir.join("wt")),
+            "the work tree is the one registered in the private git dir"
+        );
+        assert_eq!(canonical(repo.git_dir()), canonical(dir.join(".git/worktrees/wt")));
+        Ok(())
+    }
+
+    #[test]
+    fn by_discovery_from_nested_directory() -> crate::Result {
+        let dir = repo_path()?;
+        let path = git_repository::path::discover::existing(dir.join("wt/some/nested/dir"))?;
+        assert_eq!(path.kind(), Kind::WorkTree);
+        assert_eq!(path.as_ref(), dir.join("wt"));
+
+        let repo = git_repository::discover(dir.join("wt/some/nested/dir"))?;
+        assert_eq!(canonical(repo.git_dir()), canonical(dir.join(".git/worktrees/wt")));
+        Ok(())
+    }
+
+    #[test]
+    fn main_worktree_is_unaffected() -> crate::Result {
+        let dir = repo_path()?;
+        let repo = Repository::open(&dir)?;
+        assert_eq!(repo.git_dir(), dir.join(".git"));
+        assert_eq!(repo.common_dir(), repo.git_dir());
+        let head = repo.refs.find_loose("HEAD")?;
+        assert_eq!(head.target.as_name(), Some("refs/heads/main".into()));
+        Ok(())
+    }
+}
+
+#[test]
+fn list_and_inspect() -> crate::Result {
+    let dir = repo_path()?;
+    let repo = Repository::open(&dir)?;
+    let worktrees = repo.worktrees()?;
+    assert_eq!(
+        worktrees.iter().map(|wt| wt.id().to_owned()).collect::<Vec<_>>(),
+        vec!["wt", "wt-gone", "wt-locked"]
+    );
+    let (wt, gone, locked) = (&worktrees[0], &worktrees[1], &worktrees[2]);
+    assert_eq!(canonical(wt.base()?), canonical(dir.join("wt")));
+    assert!(!wt.is_locked());
+    assert_eq!(wt.lock_reason(), None);
+    assert!(!wt.is_prunable());
+
+    assert!(gone.is_prunable(), "its work tree doesn't exist anymore");
+
+    assert!(locked.is_locked());
+    assert_eq!(locked.lock_reason().expect("locked"), "on a removable drive");
+    assert!(!locked.is_prunable(), "locked worktrees are never prunable");
+
+    let linked = Repository::open(dir.join("wt"))?;
+    assert_eq!(
+        linked
+            .worktrees()?
+            .iter()
+            .map(|wt| canonical(wt.git_dir()))
+            .collect::<Vec<_>>(),
+        worktrees.iter().map(|wt| canonical(wt.git_dir())).collect::<Vec<_>>(),
+        "all worktrees see the same list"
+    );
+
+    let repo = wt.clone().into_repo()?;
+    assert_eq!(repo.git_dir(), wt.git_dir());
+    assert_eq!(repo.workdir(), Some(wt.base()?.as_path()));
+    Ok(())
+}
+
+mod add {
+    use std::convert::TryInto;
+
+    use git_repository::{bstr::ByteSlice, refs::Target, worktree};
+
+    use super::*;
+
+    #[test]
+    fn with_branch_and_detached() -> crate::Result {
+        let (repo, dir) = crate::repo_rw("make_worktree_repo.sh")?;
+        let head_id = repo.refs.find("main")?.target.into_id();
+        let branch = repo.worktree_add(
+            dir.path().join("new"),
+            &Target::Symbolic("refs/heads/other".try_into()?),
+        )?;
+        assert_eq!(branch.id(), "new");
+        assert_eq!(branch.base()?, canonical(dir.path().join("new")));
+        assert!(
+            branch.base()?.is_absolute() && branch.git_dir().is_absolute(),
+            "both links are absolute"
+        );
+        assert_eq!(
+            std::fs::read(dir.path().join("new/.git"))?,
+            format!("gitdir: {}\n", canonical(branch.git_dir()).display()).into_bytes(),
+            "the link to the private git dir is canonical"
+        );
+
+        let detached = repo.worktree_add(dir.path().join("nested").join("new"), &Target::Peeled(head_id))?;
+        assert_eq!(detached.id(), "new1", "ids are made unique");
+
+        let repo = Repository::open(dir.path().join("nested/new"))?;
+        assert_eq!(repo.git_dir(), detached.git_dir());
+        assert_eq!(repo.refs.find_loose("HEAD")?.target.into_id(), head_id);
+
+        let repo = branch.clone().into_repo()?;
+        assert_eq!(
+            repo.refs.find_loose("HEAD")?.target.as_name(),
+            Some("refs/heads/other".into())
+        );
+
+        for worktree in ["new", "nested/new"] {
+            let work_tree = dir.path().join(worktree);
+            assert_eq!(std::fs::read(work_tree.join("dir/nested/file"))?, b"content\n");
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                assert_ne!(
+                    std::fs::metadata(work_tree.join("exe"))?.permissions().mode() & 0o111,
+                    0
+                );
+                assert_eq!(
+                    std::fs::read_link(work_tree.join("link"))?,
+                    Path::new("dir/nested/file")
+                );
+            }
+            let status = std::process::Command::new("git")
+                .args(["status", "--porcelain", "--untracked-files=all"])
+                .current_dir(&work_tree)
+                .output()?;
+            assert!(status.status.success());
+            assert_eq!(
+                status.stdout.as_bstr(),
+                "",
+                "git sees the files that were checked out and the index that was written"
+            );
+        }
+        branch.remove(false)?;
+        detached.remove(false)?;
+        Ok(())
+    }
+
+    #[test]
+    fn fails_if_branch_is_checked_out_or_missing_or_destination_is_not_empty() -> crate::Result {
+        let (repo, dir) = crate::repo_rw("make_worktree_repo.sh")?;
+        assert!(matches!(
+            repo.worktree_add(dir.path().join("new"), &Target::Symbolic("refs/heads/main".try_into()?)),
+            Err(worktree::add::Error::BranchCheckedOut { id, .. }) if id == "main-worktree"
+        ));
+        assert!(matches!(
+            repo.worktree_add(dir.path().join("new"), &Target::Symbolic("refs/heads/linked".try_into()?)),
+            Err(worktree::add::Error::BranchCheckedOut { id, .. }) if id == "wt"
+        ));
+        assert!(matches!(
+            repo.worktree_add(dir.path().join("wt"), &Target::Symbolic("refs/heads/other".try_into()?)),
+            Err(worktree::add::Error::DestinationNotEmpty { .. })
+        ));
+        assert!(matches!(
+            repo.worktree_add(dir.path().join("new"), &Target::Symbolic("refs/heads/missing".try_into()?)),
+            Err(worktree::add::Error::BranchNotFound { name }) if name == "refs/heads/missing"
+        ));
+        assert!(!dir.path().join("new").exists());
+        assert_eq!(repo.worktrees()?.len(), 3, "nothing was added");
+        Ok(())
+    }
+}
+
+#[test]
+fn lock_prune_and_remove() -> crate::Result {
+    let (repo, dir) = crate::repo_rw("make_worktree_repo.sh")?;
+    let locked = repo.worktrees()?.pop().expect("three worktrees");
+
+    assert_eq!(repo.worktree_prune()?, vec!["wt-gone"]);
+    assert_eq!(repo.worktrees()?.len(), 2);
+
+    assert!(matches!(
+        locked.clone().remove(false),
+        Err(git_repository::worktree::remove::Error::Locked { .. })
+    ));
+    locked.unlock()?;
+    assert!(!locked.is_locked());
+    locked.lock("".into())?;
+    assert_eq!(locked.lock_reason().expect("locked"), "", "locks may have no reason");
+    locked.clone().remove(true)?;
+    assert!(!locked.git_dir().exists());
+    assert!(!dir.path().join("wt-locked").exists(), "the work tree is gone as well");
+
+    let wt = repo.worktrees()?.pop().expect("one left");
+    for (path, content) in [("this", "modified"), ("untracked", "")] {
+        std::fs::write(dir.path().join("wt").join(path), content)?;
+        assert!(matches!(
+            wt.clone().remove(false),
+            Err(git_repository::worktree::remove::Error::Modified { id }) if id == "wt"
+        ));
+        assert!(wt.git_dir().is_dir(), "nothing was removed");
+    }
+    std::fs::remove_file(dir.path().join("wt/untracked"))?;
+    std::fs::write(dir.path().join("wt/this"), "")?;
+    wt.clone().remove(false)?;
+    assert!(
+        !dir.path().join("wt").exists(),
+        "empty directories don't prevent removal"
+    );
+    assert!(repo.worktrees()?.is_empty());
+    assert!(repo.worktree_prune()?.is_empty());
+    assert!(dir.path().join("this").is_file(), "the main worktree is untouched");
+    Ok(())
+}
@@ -1,12 +1,13 @@
 use std::{io::Write, path::Path};
 
-use tempfile::{NamedTempFile, TempPath};
+use tempfile::{NamedTempFile, TempDir, TempPath};
 
 use crate::{handle, AutoRemove};
 
 enum TempfileOrTemppath {
     Tempfile(NamedTempFile),
     Temppath(TempPath),
+    Tempdir(TempDir),
 }
 
 pub(crate) struct ForksafeTempfile {
@@ -27,13 +28,28 @@ impl ForksafeTempfile {
             owning_process_id: std::process::id(),
         }
     }
+
+    pub fn new_dir(tempdir: TempDir, cleanup: AutoRemove) -> Self {
+        ForksafeTempfile {
+            inner: TempfileOrTemppath::Tempdir(tempdir),
+            cleanup,
+            owning_process_id: std::process::id(),
+        }
+    }
 }
 
 impl ForksafeTempfile {
     pub fn as_mut_tempfile(&mut self) -> Option<&mut NamedTempFile> {
         match &mut self.inner {
             TempfileOrTemppath::Tempfile(file) => Some(file),
-            TempfileOrTemppath::Temppath(_) => None,
+            TempfileOrTemppath::Temppath(_) | TempfileOrTemppath::Tempdir(_) => None,
+        }
+    }
+    pub fn path(&self) -> &Path {
+        match &self.inner {
+            TempfileOrTemppath::Tempfile(file) => file.path(),
+            TempfileOrTemppath::Temppath(path) => path,
+            TempfileOrTemppath::Tempdir(dir) => dir.path(),
         }
     }
     pub fn close(self) -> Self {
@@ -63,6 +79,7 @@ impl ForksafeTempfile {
                     self
                 })),
             },
+            TempfileOrTemppath::Tempdir(_) => unreachable!("directories are never persisted"),
         }
     }
 
@@ -70,18 +87,26 @@ impl ForksafeTempfile {
         match self.inner {
             TempfileOrTemppath::Tempfile(file) => file.into_temp_path(),
             TempfileOrTemppath::Temppath(path) => path,
+            TempfileOrTemppath::Tempdir(_) => unreachable!("directories are never turned into paths"),
         }
     }
     pub fn into_tempfile(self) -> Option<NamedTempFile> {
         match self.inner {
             TempfileOrTemppath::Tempfile(file) => Some(file),
-            TempfileOrTemppath::Temppath(_) => None,
+            TempfileOrTemppath::Temppath(_) | TempfileOrTemppath::Tempdir(_) => None,
+        }
+    }
+    pub fn into_tempdir(self) -> Option<TempDir> {
+        match self.inner {
+            TempfileOrTemppath::Tempdir(dir) => Some(dir),
+            TempfileOrTemppath::Tempfile(_) | TempfileOrTemppath::Temppath(_) => None,
         }
     }
     pub fn drop_impl(self) {
         let file_path = match self.inner {
             TempfileOrTemppath::Tempfile(file) => file.path().to_owned(),
             TempfileOrTemppath::Temppath(path) => path.to_path_buf(),
+            TempfileOrTemppath::Tempdir(dir) => dir.path().to_owned(),
         };
         let parent_directory = file_path.parent().expect("every tempfile has a parent directory");
         self.cleanup.execute_best_effort(parent_directory);
@@ -95,6 +120,16 @@ impl ForksafeTempfile {
                 temppath
             }
             TempfileOrTemppath::Temppath(path) => path,
+            TempfileOrTemppath::Tempdir(dir) => {
+                // Removing all contents needs to allocate, which we accept as there is no other way to clean up.
+                std::fs::remove_dir_all(dir.path()).ok();
+                std::mem::forget(
+                    self.cleanup
+                        .execute_best_effort(dir.path().parent().expect("every directory has a parent")),
+                );
+                std::mem::forget(dir);
+                return;
+            }
         };
         std::fs::remove_file(&temppath).ok();
         std::mem::forget(
@@ -1,7 +1,10 @@
 //!
-use std::{io, path::Path};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
 
-use tempfile::{NamedTempFile, TempPath};
+use tempfile::{NamedTempFile, TempDir, TempPath};
 
 use crate::{AutoRemove, ContainingDirectory, ForksafeTempfile, Handle, NEXT_MAP_INDEX, REGISTER};
 
@@ -15,6 +18,10 @@ pub struct Writable;
 #[derive(Debug)]
 pub struct Closed;
 
+/// Marker to signal the Registration is a directory which is removed along with all of its contents.
+#[derive(Debug)]
+pub struct Directory;
+
 pub(crate) enum Mode {
     Writable,
     Closed,
@@ -151,6 +158,54 @@ impl Handle<Writable> {
     }
 }
 
+/// Creation and ownership transfer
+impl Handle<Directory> {
+    /// Create a registered temporary directory within `containing_directory` whose name starts with `prefix` and won't clash.
+    /// Control how to deal with intermediate directories with `directory`.
+    ///
+    /// The directory is removed along with all of its contents on drop or when the program receives a termination signal.
+    pub fn new(
+        containing_directory: impl AsRef<Path>,
+        prefix: &str,
+        directory: ContainingDirectory,
+    ) -> io::Result<Self> {
+        let containing_directory = directory.resolve(containing_directory.as_ref())?;
+        let tempdir = tempfile::Builder::new()
+            .prefix(prefix)
+            .tempdir_in(containing_directory)?;
+        let id = NEXT_MAP_INDEX.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        expect_none(REGISTER.insert(id, Some(ForksafeTempfile::new_dir(tempdir, AutoRemove::Tempfile))));
+        Ok(Handle {
+            id,
+            _marker: Default::default(),
+        })
+    }
+
+    /// Return the path to the temporary directory.
+    ///
+    /// It's a theoretical possibility that the directory isn't present anymore if signals interfere, hence the `Option`
+    pub fn path(&self) -> Option<PathBuf> {
+        // Like in `with_mut()`, the registry must not stay locked as signal handlers would dead-lock on it.
+        match REGISTER.remove(&self.id) {
+            Some((id, Some(t))) => {
+                let path = t.path().to_owned();
+                expect_none(REGISTER.insert(id, Some(t)));
+                Some(path)
+            }
+            None | Some((_, None)) => None,
+        }
+    }
+
+    /// Take ownership of the temporary directory, which deletes it along with its contents when dropped.
+    ///
+    /// It's a theoretical possibility that the directory isn't present anymore if signals interfere, hence the `Option`
+    pub fn take(self) -> Option<TempDir> {
+        let res = REGISTER.remove(&self.id);
+        std::mem::forget(self);
+        res.and_then(|(_k, v)| v.map(|v| v.into_tempdir().expect("correct runtime typing")))
+    }
+}
+
 /// Mutation
 impl Handle<Writable> {
     /// Obtain a mutable handler to the underlying named tempfile and call `f(&mut named_tempfile)` on it.
@@ -67,6 +67,16 @@ mod tests {
                 "the signal triggers removal but won't terminate the process (anymore)"
             );
         }
+
+        // Signals affect all tempfiles of the process, hence directories are tested here to not interfere with the above.
+        let tempdir = crate::new_directory(dir.path(), "incoming-", ContainingDirectory::Exists)?;
+        let path = tempdir.path().expect("still registered");
+        std::fs::write(path.join("file"), b"content")?;
+        assert_eq!(filecount_in(dir.path()), 1);
+
+        signal_hook::low_level::raise(signal_hook::consts::SIGTERM)?;
+        assert_eq!(filecount_in(dir.path()), 0, "the directory was removed recursively");
+        assert!(tempdir.path().is_none(), "it's not registered anymore");
         Ok(())
     }
 }
@@ -1,4 +1,4 @@
-//! git-style registered tempfiles that are removed upon typical termination signals.
+//! git-style registered tempfiles and directories that are removed upon typical termination signals.
 //!
 //! This crate installs signal handlers the first time its facilities are used.
 //! These are powered by [`signal-hook`] to get notified when the application is told to shut down
@@ -22,6 +22,7 @@
 //! * Uninterruptible signals are received like `SIGKILL`
 //! * The application is performing a write operation on the tempfile when a signal arrives, preventing this tempfile to be removed,
 //!   but not others. Any other operation dealing with the tempfile suffers from the same issue.
+//! * Temporary directories are removed recursively, which allocates within the signal handler and thus might not succeed.
 //!
 //! [signal-hook]: https://docs.rs/signal-hook
 #![deny(missing_docs, unsafe_code, rust_2018_idioms)]
@@ -45,7 +46,7 @@ mod forksafe;
 use forksafe::ForksafeTempfile;
 
 pub mod handle;
-use crate::handle::{Closed, Writable};
+use crate::handle::{Closed, Directory, Writable};
 
 static SIGNAL_HANDLER_MODE: AtomicUsize = AtomicUsize::new(SignalHandlerMode::default() as usize);
 static NEXT_MAP_INDEX: AtomicUsize = AtomicUsize::new(0);
@@ -180,6 +181,16 @@ pub fn mark_at(
     Handle::<Closed>::at(path, directory, cleanup)
 }
 
+/// A shortcut to [`Handle::<Directory>::new()`], creating a temporary directory with non-clashing name starting with `prefix`
+/// which is removed along with all of its contents.
+pub fn new_directory(
+    containing_directory: impl AsRef<Path>,
+    prefix: &str,
+    directory: ContainingDirectory,
+) -> io::Result<Handle<Directory>> {
+    Handle::<Directory>::new(containing_directory, prefix, directory)
+}
+
 /// Explicitly (instead of lazily) initialize signal handlers and other state to keep track of tempfiles.
 /// Only has an effect the first time it is called and furthermore allows to set the `mode` in which signal handlers
 /// are installed.
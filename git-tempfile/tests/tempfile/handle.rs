@@ -242,3 +242,42 @@ mod new {
         Ok(())
     }
 }
+
+mod directory {
+    use git_tempfile::ContainingDirectory;
+
+    #[test]
+    fn it_is_removed_with_all_contents_when_dropped() -> crate::Result {
+        let dir = tempfile::tempdir()?;
+        let tempdir = git_tempfile::new_directory(dir.path(), "incoming-", ContainingDirectory::Exists)?;
+        let path = tempdir.path().expect("still registered");
+        assert!(path.starts_with(dir.path()));
+        assert!(path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map_or(false, |name| name.starts_with("incoming-")));
+        std::fs::create_dir(path.join("subdir"))?;
+        std::fs::write(path.join("subdir").join("file"), b"content")?;
+
+        drop(tempdir);
+        assert!(!path.exists(), "the directory and its contents are gone");
+        assert!(dir.path().is_dir(), "it won't touch the containing directory");
+        Ok(())
+    }
+
+    #[test]
+    fn it_can_be_taken_to_remove_it_explicitly() -> crate::Result {
+        let dir = tempfile::tempdir()?;
+        let tempdir = git_tempfile::new_directory(
+            dir.path().join("a").join("b"),
+            "",
+            ContainingDirectory::CreateAllRaceProof(Default::default()),
+        )?;
+        let path = tempdir.path().expect("still registered");
+        let tempdir = tempdir.take().expect("still there");
+        assert!(path.is_dir(), "taking it keeps it around");
+        tempdir.close()?;
+        assert!(!path.exists());
+        Ok(())
+    }
+}
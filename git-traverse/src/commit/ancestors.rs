@@ -0,0 +1,532 @@
+use std::{
+    borrow::BorrowMut,
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, BinaryHeap, VecDeque},
+};
+
+use git_hash::{oid, ObjectId};
+use git_object::{commit::ref_iter::Token, CommitRefIter};
+use quick_error::quick_error;
+
+use crate::commit::{Ancestors, Parents, Sorting};
+
+quick_error! {
+    /// The error is part of the item returned by the [Ancestors] iterator.
+    #[derive(Debug)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        NotFound{oid: ObjectId} {
+            display("The commit {} could not be found", oid)
+        }
+        ObjectDecode(err: git_object::decode::Error) {
+            display("An object could not be decoded")
+            source(err)
+            from()
+        }
+    }
+}
+
+/// The state used and potentially shared by multiple graph traversals.
+#[derive(Default, Clone)]
+pub struct State {
+    next: VecDeque<ObjectId>,
+    buf: Vec<u8>,
+    seen: BTreeSet<ObjectId>,
+    parents: Vec<ObjectId>,
+    by_time: BinaryHeap<Queued>,
+    sequence: usize,
+    topo: Topo,
+    reversed: Option<Vec<ObjectId>>,
+}
+
+impl State {
+    fn clear(&mut self) {
+        self.next.clear();
+        self.buf.clear();
+        self.seen.clear();
+        self.parents.clear();
+        self.by_time.clear();
+        self.sequence = 0;
+        self.topo.clear();
+        self.reversed = None;
+    }
+
+    fn push_by_time(&mut self, id: ObjectId, time: u64) {
+        self.sequence += 1;
+        self.by_time.push(Queued {
+            time,
+            sequence: self.sequence,
+            id,
+        });
+    }
+
+    /// Make `id` available for being returned by a topological traversal, which happens when all of its children were returned.
+    fn mark_ready(&mut self, id: ObjectId, sorted_by_time: bool) {
+        if sorted_by_time {
+            let time = self.topo.explored.get(&id).map(|e| e.time).unwrap_or_default();
+            self.push_by_time(id, time);
+        } else {
+            self.topo.stack.push(id);
+        }
+    }
+}
+
+/// A commit waiting to be returned, ordered by time with the newest commit first, and by the order of insertion
+/// if times are equal.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct Queued {
+    pub(crate) time: u64,
+    pub(crate) sequence: usize,
+    pub(crate) id: ObjectId,
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.time
+            .cmp(&other.time)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The state needed for topological sorting, which explores commits by generation to learn about the amount of children
+/// of each commit before returning it.
+#[derive(Default, Clone)]
+struct Topo {
+    /// All commits that were discovered but not yet returned.
+    indegree: BTreeMap<ObjectId, InDegree>,
+    /// Commits which still have to be explored, highest generation first.
+    explore: BinaryHeap<(u64, ObjectId)>,
+    /// Commits that were explored but not yet returned.
+    explored: BTreeMap<ObjectId, Explored>,
+    /// Commits ready to be returned if sorting is purely topological, the last one is returned first.
+    stack: Vec<ObjectId>,
+}
+
+impl Topo {
+    fn clear(&mut self) {
+        self.indegree.clear();
+        self.explore.clear();
+        self.explored.clear();
+        self.stack.clear();
+    }
+}
+
+#[derive(Clone, Copy)]
+struct InDegree {
+    /// The amount of children that weren't returned yet, plus one. Thus the commit can be returned once it reaches one.
+    count: u32,
+    /// The generation of the commit as provided by the commit-graph, or [`GENERATION_UNKNOWN`] if it isn't part of it.
+    generation: u64,
+}
+
+#[derive(Clone)]
+struct Explored {
+    parents: Vec<ObjectId>,
+    time: u64,
+}
+
+#[derive(Clone, Copy)]
+enum TimeKind {
+    Author,
+    Committer,
+}
+
+impl<Find, Predicate, StateMut> Ancestors<Find, Predicate, StateMut> {
+    /// Change our commit parent handling mode to the given one.
+    pub fn mode(mut self, mode: Parents) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Change the order in which commits are returned to the given `sorting`.
+    pub fn sorting(mut self, sorting: Sorting) -> Self {
+        self.sorting = sorting;
+        self
+    }
+
+    /// If `toggle` is true, return commits in the reverse order of the configured [sorting][Ancestors::sorting()],
+    /// like `git log --reverse`.
+    ///
+    /// Note that this requires the entire traversal to be performed before the first commit can be returned.
+    pub fn reverse(mut self, toggle: bool) -> Self {
+        self.reverse = toggle;
+        self
+    }
+
+    /// Set the commit-graph to use as `cache` for generation numbers and commit times, which avoids visiting all commits
+    /// upfront when sorting topologically and avoids additional lookups when sorting by commit time.
+    pub fn commit_graph(mut self, cache: Option<git_commitgraph::Graph>) -> Self {
+        self.cache = cache;
+        self
+    }
+}
+
+impl<Find, StateMut> Ancestors<Find, fn(&oid) -> bool, StateMut>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Option<CommitRefIter<'a>>,
+    StateMut: BorrowMut<State>,
+{
+    /// Create a new instance.
+    ///
+    /// * `find` - a way to lookup new object data during traversal by their ObjectId, writing their data into buffer and returning
+    ///    an iterator over commit tokens if the object is present and is a commit. Caching should be implemented within this function
+    ///    as needed. The return value is `Option<CommitIter>` which degenerates all error information. Not finding a commit should also
+    ///    be considered an errors as all objects in the commit graph should be present in the database. Hence [`Error::NotFound`] should
+    ///    be escalated into a more specific error if its encountered by the caller.
+    /// * `state` - all state used for the traversal. If multiple traversals are performed, allocations can be minimized by reusing
+    ///   this state.
+    /// * `tips`
+    ///   * the starting points of the iteration, usually commits
+    ///   * each commit they lead to will only be returned once, including the tip that started it
+    pub fn new(tips: impl IntoIterator<Item = impl Into<ObjectId>>, state: StateMut, find: Find) -> Self {
+        Self::filtered(tips, state, find, |_| true)
+    }
+}
+
+impl<Find, Predicate, StateMut> Ancestors<Find, Predicate, StateMut>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Option<CommitRefIter<'a>>,
+    Predicate: FnMut(&oid) -> bool,
+    StateMut: BorrowMut<State>,
+{
+    /// Create a new instance with commit filtering enabled.
+    ///
+    /// * `find` - a way to lookup new object data during traversal by their ObjectId, writing their data into buffer and returning
+    ///    an iterator over commit tokens if the object is present and is a commit. Caching should be implemented within this function
+    ///    as needed. The return value is `Option<CommitIter>` which degenerates all error information. Not finding a commit should also
+    ///    be considered an errors as all objects in the commit graph should be present in the database. Hence [`Error::NotFound`] should
+    ///    be escalated into a more specific error if its encountered by the caller.
+    /// * `state` - all state used for the traversal. If multiple traversals are performed, allocations can be minimized by reusing
+    ///   this state.
+    /// * `tips`
+    ///   * the starting points of the iteration, usually commits
+    ///   * each commit they lead to will only be returned once, including the tip that started it
+    /// * `predicate` - indicate whether a given commit should be included in the result as well
+    ///   as whether its parent commits should be traversed.
+    pub fn filtered(
+        tips: impl IntoIterator<Item = impl Into<ObjectId>>,
+        mut state: StateMut,
+        find: Find,
+        mut predicate: Predicate,
+    ) -> Self {
+        let tips = tips.into_iter();
+        {
+            let state = state.borrow_mut();
+            state.clear();
+            state.next.reserve(tips.size_hint().0);
+            for tip in tips.map(Into::into) {
+                let was_inserted = state.seen.insert(tip);
+                if was_inserted && predicate(&tip) {
+                    state.next.push_back(tip);
+                }
+            }
+        }
+        Self {
+            find,
+            predicate,
+            state,
+            mode: Default::default(),
+            sorting: Default::default(),
+            reverse: false,
+            cache: None,
+        }
+    }
+
+    fn next_sorted(&mut self) -> Option<Result<ObjectId, Error>> {
+        match self.sorting {
+            Sorting::BreadthFirst => self.next_by_discovery(),
+            Sorting::ByCommitTimeNewestFirst => self.next_by_commit_time(),
+            Sorting::Topological => self.next_topological(None),
+            Sorting::TopologicalByCommitTime => self.next_topological(Some(TimeKind::Committer)),
+            Sorting::TopologicalByAuthorTime => self.next_topological(Some(TimeKind::Author)),
+        }
+    }
+
+    fn next_by_discovery(&mut self) -> Option<Result<ObjectId, Error>> {
+        let state = self.state.borrow_mut();
+        let res = state.next.pop_front();
+        if let Some(oid) = res {
+            match (self.find)(&oid, &mut state.buf) {
+                Some(mut commit_iter) => {
+                    if let Some(Err(decode_tree_err)) = commit_iter.next() {
+                        return Some(Err(decode_tree_err.into()));
+                    }
+                    for token in commit_iter {
+                        match token {
+                            Ok(Token::Parent { id }) => {
+                                let was_inserted = state.seen.insert(id);
+                                if was_inserted && (self.predicate)(&id) {
+                                    state.next.push_back(id);
+                                }
+                                if matches!(self.mode, Parents::First) {
+                                    break;
+                                }
+                            }
+                            Ok(_a_token_past_the_parents) => break,
+                            Err(err) => return Some(Err(err.into())),
+                        }
+                    }
+                }
+                None => return Some(Err(Error::NotFound { oid })),
+            }
+        }
+        res.map(Ok)
+    }
+
+    fn next_by_commit_time(&mut self) -> Option<Result<ObjectId, Error>> {
+        let state = self.state.borrow_mut();
+        let cache = self.cache.as_ref();
+        while let Some(tip) = state.next.pop_front() {
+            match commit_time(&mut self.find, &mut state.buf, cache, &tip) {
+                Ok(time) => state.push_by_time(tip, time),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+
+        let id = state.by_time.pop()?.id;
+        state.parents.clear();
+        if let Err(err) = parse_commit(
+            &mut self.find,
+            &mut state.buf,
+            &id,
+            &self.mode,
+            Some(&mut state.parents),
+            None,
+        ) {
+            return Some(Err(err));
+        }
+        let mut parents = std::mem::take(&mut state.parents);
+        for parent in parents.drain(..) {
+            let was_inserted = state.seen.insert(parent);
+            if was_inserted && (self.predicate)(&parent) {
+                match commit_time(&mut self.find, &mut state.buf, cache, &parent) {
+                    Ok(time) => state.push_by_time(parent, time),
+                    Err(err) => return Some(Err(err)),
+                }
+            }
+        }
+        state.parents = parents;
+        Some(Ok(id))
+    }
+
+    fn next_topological(&mut self, time: Option<TimeKind>) -> Option<Result<ObjectId, Error>> {
+        let state = self.state.borrow_mut();
+        let cache = self.cache.as_ref();
+        if !state.next.is_empty() {
+            let mut min_generation = GENERATION_UNKNOWN;
+            for tip in &state.next {
+                let generation = generation_of(cache, tip);
+                min_generation = min_generation.min(generation);
+                state.topo.indegree.insert(*tip, InDegree { count: 1, generation });
+                state.topo.explore.push((generation, *tip));
+            }
+            if let Err(err) = explore_to_depth(
+                &mut self.find,
+                &mut self.predicate,
+                cache,
+                &self.mode,
+                time,
+                state,
+                min_generation,
+            ) {
+                return Some(Err(err));
+            }
+            // Tips go onto the stack in reverse so that the first tip is returned first, just like it would be when sorting by time.
+            let sorted_by_time = time.is_some();
+            while let Some(tip) = if sorted_by_time {
+                state.next.pop_front()
+            } else {
+                state.next.pop_back()
+            } {
+                if state.topo.indegree.get(&tip).map_or(false, |d| d.count == 1) {
+                    state.mark_ready(tip, sorted_by_time);
+                }
+            }
+        }
+
+        let id = match time {
+            Some(_) => state.by_time.pop().map(|queued| queued.id),
+            None => state.topo.stack.pop(),
+        }?;
+        state.topo.indegree.remove(&id);
+        let parents = state
+            .topo
+            .explored
+            .remove(&id)
+            .map(|explored| explored.parents)
+            .unwrap_or_default();
+        for parent in parents {
+            let generation = match state.topo.indegree.get(&parent) {
+                Some(indegree) => indegree.generation,
+                None => continue,
+            };
+            if let Err(err) = explore_to_depth(
+                &mut self.find,
+                &mut self.predicate,
+                cache,
+                &self.mode,
+                time,
+                state,
+                generation,
+            ) {
+                return Some(Err(err));
+            }
+            if let Some(indegree) = state.topo.indegree.get_mut(&parent) {
+                indegree.count -= 1;
+                if indegree.count == 1 {
+                    state.mark_ready(parent, time.is_some());
+                }
+            }
+        }
+        Some(Ok(id))
+    }
+}
+
+impl<Find, Predicate, StateMut> Iterator for Ancestors<Find, Predicate, StateMut>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Option<CommitRefIter<'a>>,
+    Predicate: FnMut(&oid) -> bool,
+    StateMut: BorrowMut<State>,
+{
+    type Item = Result<ObjectId, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.reverse {
+            return self.next_sorted();
+        }
+        if self.state.borrow_mut().reversed.is_none() {
+            let mut ids = Vec::new();
+            while let Some(res) = self.next_sorted() {
+                match res {
+                    Ok(id) => ids.push(id),
+                    Err(err) => {
+                        self.state.borrow_mut().reversed = Some(Vec::new());
+                        return Some(Err(err));
+                    }
+                }
+            }
+            self.state.borrow_mut().reversed = Some(ids);
+        }
+        self.state.borrow_mut().reversed.as_mut().and_then(Vec::pop).map(Ok)
+    }
+}
+
+/// Explore all commits with a generation of at least `min_generation` to learn about all of their children, making it safe to
+/// return them once all of their children were returned.
+fn explore_to_depth<Find, Predicate>(
+    find: &mut Find,
+    predicate: &mut Predicate,
+    cache: Option<&git_commitgraph::Graph>,
+    mode: &Parents,
+    time: Option<TimeKind>,
+    state: &mut State,
+    min_generation: u64,
+) -> Result<(), Error>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Option<CommitRefIter<'a>>,
+    Predicate: FnMut(&oid) -> bool,
+{
+    while let Some(&(generation, _)) = state.topo.explore.peek() {
+        if generation < min_generation {
+            break;
+        }
+        let (_, id) = state.topo.explore.pop().expect("peeked before");
+        let mut parents = Vec::new();
+        let sort_time = parse_commit(find, &mut state.buf, &id, mode, Some(&mut parents), time)?;
+        for parent in &parents {
+            if state.seen.insert(*parent) {
+                if predicate(parent) {
+                    let generation = generation_of(cache, parent);
+                    state.topo.indegree.insert(*parent, InDegree { count: 2, generation });
+                    state.topo.explore.push((generation, *parent));
+                }
+            } else if let Some(indegree) = state.topo.indegree.get_mut(parent) {
+                indegree.count += 1;
+            }
+        }
+        state.topo.explored.insert(
+            id,
+            Explored {
+                parents,
+                time: sort_time,
+            },
+        );
+    }
+    Ok(())
+}
+
+/// The generation of commits which aren't part of the commit-graph, which are thus explored before all others.
+const GENERATION_UNKNOWN: u64 = u64::MAX;
+
+/// Obtain the generation of the commit with `id` from the commit-graph `cache`, which are corrected commit dates if available
+/// to be more accurate on repositories with skewed clocks.
+fn generation_of(cache: Option<&git_commitgraph::Graph>, id: &oid) -> u64 {
+    cache
+        .and_then(|graph| graph.generation_of(id))
+        .unwrap_or(GENERATION_UNKNOWN)
+}
+
+/// Obtain the committer time of the commit with `id`, preferably from the commit-graph `cache`.
+fn commit_time<Find>(
+    find: &mut Find,
+    buf: &mut Vec<u8>,
+    cache: Option<&git_commitgraph::Graph>,
+    id: &oid,
+) -> Result<u64, Error>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Option<CommitRefIter<'a>>,
+{
+    match cache.and_then(|graph| graph.commit_by_id(id)) {
+        Some(commit) => Ok(commit.committer_timestamp()),
+        None => parse_commit(find, buf, id, &Parents::All, None, Some(TimeKind::Committer)),
+    }
+}
+
+/// Lookup the commit with `id` and push its parents according to `mode` into `parents` if set.
+/// Return the commit's time of the given kind, or 0 if `time` is `None`.
+fn parse_commit<Find>(
+    find: &mut Find,
+    buf: &mut Vec<u8>,
+    id: &oid,
+    mode: &Parents,
+    mut parents: Option<&mut Vec<ObjectId>>,
+    time: Option<TimeKind>,
+) -> Result<u64, Error>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Option<CommitRefIter<'a>>,
+{
+    let commit_iter = find(id, buf).ok_or_else(|| Error::NotFound { oid: id.to_owned() })?;
+    let mut num_parents = 0;
+    for token in commit_iter {
+        match token? {
+            Token::Tree { .. } => {}
+            Token::Parent { id } => {
+                if let Some(parents) = parents.as_mut() {
+                    if num_parents == 0 || matches!(mode, Parents::All) {
+                        parents.push(id);
+                    }
+                }
+                num_parents += 1;
+            }
+            Token::Author { signature } => match time {
+                Some(TimeKind::Author) => return Ok(signature.time.time.into()),
+                Some(TimeKind::Committer) => {}
+                None => break,
+            },
+            Token::Committer { signature } => {
+                if let Some(TimeKind::Committer) = time {
+                    return Ok(signature.time.time.into());
+                }
+                break;
+            }
+            _ => break,
+        }
+    }
+    Ok(0)
+}
@@ -0,0 +1,96 @@
+/// An iterator over the ancestors one or more starting commits
+pub struct Ancestors<Find, Predicate, StateMut> {
+    find: Find,
+    predicate: Predicate,
+    state: StateMut,
+    mode: Parents,
+    sorting: Sorting,
+    reverse: bool,
+    cache: Option<git_commitgraph::Graph>,
+}
+
+/// An iterator over the ancestors of one or more starting commits which only yields commits that changed at least one of
+/// the given paths, like `git log -- <path>…`.
+pub struct PathLimited<Find, StateMut> {
+    find: Find,
+    state: StateMut,
+    paths: Vec<git_object::bstr::BString>,
+    mode: Parents,
+    simplification: Simplification,
+    cache: Option<git_commitgraph::Graph>,
+}
+
+/// Specify how to handle commit parents during traversal.
+pub enum Parents {
+    /// Traverse all parents, useful for traversing the entire ancestry.
+    All,
+    /// Only traverse along the first parent, which commonly ignores all branches.
+    First,
+}
+
+impl Default for Parents {
+    fn default() -> Self {
+        Parents::All
+    }
+}
+
+/// Specify how to sort commits during traversal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Sorting {
+    /// Commits are returned in the order in which they are discovered, breadth-first, which is the order in which their
+    /// ids are mentioned in the respective commit.
+    ///
+    /// This is the fastest mode as it doesn't need to look at more than the commits themselves.
+    BreadthFirst,
+    /// Commits are returned by their committer time, newest first, like `git log` without any ordering flags.
+    ///
+    /// Note that parents may be returned before their children if the clocks of the committers were skewed.
+    /// The time of each newly discovered commit needs to be known, which requires an additional lookup if
+    /// no [commit-graph][Ancestors::commit_graph()] is set.
+    ByCommitTimeNewestFirst,
+    /// No parent is returned before all of its children, and commits of different lines of history aren't intermixed,
+    /// like `git log --topo-order`.
+    ///
+    /// Without generation numbers from a [commit-graph][Ancestors::commit_graph()], all commits reachable from the tips
+    /// have to be visited before the first commit can be returned.
+    Topological,
+    /// No parent is returned before all of its children, but otherwise commits are returned by their committer time,
+    /// newest first, like `git log --date-order`.
+    TopologicalByCommitTime,
+    /// No parent is returned before all of its children, but otherwise commits are returned by their author time,
+    /// newest first, like `git log --author-date-order`.
+    TopologicalByAuthorTime,
+}
+
+impl Default for Sorting {
+    fn default() -> Self {
+        Sorting::BreadthFirst
+    }
+}
+
+/// Specify how history is simplified when traversing it with [path limits][PathLimited].
+///
+/// A commit is called _TREESAME_ to one of its parents if the paths we are limited to are the same in both of them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Simplification {
+    /// If a commit is TREESAME to one of its parents, only that parent is followed and the commit itself is not returned,
+    /// which is the default of `git log -- <path>`.
+    ///
+    /// This hides side branches which didn't contribute to the final state of the paths.
+    Default,
+    /// Follow all parents of merge commits, and return commits unless they are TREESAME to all of their parents,
+    /// like `git log --full-history -- <path>`.
+    FullHistory,
+}
+
+impl Default for Simplification {
+    fn default() -> Self {
+        Simplification::Default
+    }
+}
+
+///
+pub mod ancestors;
+
+///
+pub mod path_limited;
@@ -0,0 +1,323 @@
+use std::{
+    borrow::BorrowMut,
+    collections::{BTreeSet, BinaryHeap, VecDeque},
+};
+
+use git_hash::{oid, ObjectId};
+use git_object::{
+    bstr::{BString, ByteSlice},
+    commit::ref_iter::Token,
+    tree::EntryMode,
+};
+use quick_error::quick_error;
+
+use crate::commit::{ancestors::Queued, Parents, PathLimited, Simplification};
+
+quick_error! {
+    /// The error is part of the item returned by the [PathLimited] iterator.
+    #[derive(Debug)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        NotFound{oid: ObjectId} {
+            display("The object {} could not be found", oid)
+        }
+        ObjectDecode(err: git_object::decode::Error) {
+            display("An object could not be decoded")
+            source(err)
+            from()
+        }
+        MissingCommitter{oid: ObjectId} {
+            display("The commit {} could not be decoded as it has no committer", oid)
+        }
+    }
+}
+
+/// The state used and potentially shared by multiple path-limited graph traversals.
+#[derive(Default, Clone)]
+pub struct State {
+    tips: VecDeque<ObjectId>,
+    queue: BinaryHeap<Queued>,
+    sequence: usize,
+    seen: BTreeSet<ObjectId>,
+    buf: Vec<u8>,
+    lhs_tree_buf: Vec<u8>,
+    rhs_tree_buf: Vec<u8>,
+}
+
+impl State {
+    fn clear(&mut self) {
+        self.tips.clear();
+        self.queue.clear();
+        self.sequence = 0;
+        self.seen.clear();
+        self.buf.clear();
+        self.lhs_tree_buf.clear();
+        self.rhs_tree_buf.clear();
+    }
+
+    fn push(&mut self, id: ObjectId, time: u64) {
+        self.sequence += 1;
+        self.queue.push(Queued {
+            time,
+            sequence: self.sequence,
+            id,
+        });
+    }
+}
+
+impl<Find, StateMut> PathLimited<Find, StateMut> {
+    /// Change our commit parent handling mode to the given one.
+    pub fn mode(mut self, mode: Parents) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Change the way history is simplified to the given `simplification`.
+    pub fn simplification(mut self, simplification: Simplification) -> Self {
+        self.simplification = simplification;
+        self
+    }
+
+    /// Set the commit-graph to use as `cache` for its changed-path Bloom filters, which avoids comparing the trees of commits
+    /// and their first parent if none of our paths can have changed between them.
+    pub fn commit_graph(mut self, cache: Option<git_commitgraph::Graph>) -> Self {
+        self.cache = cache;
+        self
+    }
+}
+
+impl<Find, StateMut> PathLimited<Find, StateMut>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Option<git_object::Data<'a>>,
+    StateMut: BorrowMut<State>,
+{
+    /// Create a new instance which returns commits by their commit time, newest first, like `git log` does.
+    ///
+    /// * `tips`
+    ///   * the starting points of the iteration, usually commits
+    ///   * each commit they lead to will only be returned once, including the tip that started it
+    /// * `paths` - the slash-separated paths relative to the root of the repository which are used to limit the traversal
+    ///    to commits changing them. A path matches itself and everything beneath it if it is a directory. If empty, all commits
+    ///    are returned unless their tree is the same as the one of their parent.
+    /// * `state` - all state used for the traversal. If multiple traversals are performed, allocations can be minimized by reusing
+    ///   this state.
+    /// * `find` - a way to lookup new object data during traversal by their ObjectId, writing their data into buffer. It must be
+    ///    able to return commits as well as trees, as trees of commits and their parents are compared to learn whether a path changed.
+    ///    Caching should be implemented within this function as needed as parent commits are looked up more than once.
+    ///    Not finding an object should be considered an error, which is why [`Error::NotFound`] should be escalated into a more
+    ///    specific error if it is encountered by the caller.
+    pub fn new(
+        tips: impl IntoIterator<Item = impl Into<ObjectId>>,
+        paths: impl IntoIterator<Item = impl Into<BString>>,
+        mut state: StateMut,
+        find: Find,
+    ) -> Self {
+        {
+            let state = state.borrow_mut();
+            state.clear();
+            for tip in tips.into_iter().map(Into::into) {
+                if state.seen.insert(tip) {
+                    state.tips.push_back(tip);
+                }
+            }
+        }
+        PathLimited {
+            find,
+            state,
+            paths: paths.into_iter().map(Into::into).collect(),
+            mode: Default::default(),
+            simplification: Default::default(),
+            cache: None,
+        }
+    }
+
+    /// Return the next commit to look at along with a flag telling whether it should be shown.
+    fn next_inner(&mut self) -> Result<Option<(ObjectId, bool)>, Error> {
+        let state = self.state.borrow_mut();
+        while let Some(tip) = state.tips.pop_front() {
+            let time = lookup_commit(&mut self.find, &mut state.buf, &tip, &self.mode, None)?.1;
+            state.push(tip, time);
+        }
+
+        let id = match state.queue.pop() {
+            Some(queued) => queued.id,
+            None => return Ok(None),
+        };
+        let mut parents = Vec::new();
+        let tree = lookup_commit(&mut self.find, &mut state.buf, &id, &self.mode, Some(&mut parents))?.0;
+
+        let unchanged_from_first_parent = definitely_unchanged(self.cache.as_ref(), &id, &self.paths);
+        if parents.is_empty() {
+            let treesame = unchanged_from_first_parent || is_treesame(&mut self.find, state, &self.paths, None, &tree)?;
+            return Ok(Some((id, !treesame)));
+        }
+
+        let mut follow = Vec::with_capacity(parents.len());
+        let (mut any_treesame, mut all_treesame) = (false, true);
+        for (parent_index, parent_id) in parents.into_iter().enumerate() {
+            let (parent_tree, parent_time) =
+                lookup_commit(&mut self.find, &mut state.buf, &parent_id, &self.mode, None)?;
+            let treesame = (parent_index == 0 && unchanged_from_first_parent)
+                || is_treesame(&mut self.find, state, &self.paths, Some(&parent_tree), &tree)?;
+            if treesame && self.simplification == Simplification::Default {
+                follow.clear();
+                follow.push((parent_id, parent_time));
+                any_treesame = true;
+                break;
+            }
+            any_treesame |= treesame;
+            all_treesame &= treesame;
+            follow.push((parent_id, parent_time));
+        }
+
+        for (parent_id, parent_time) in follow {
+            if state.seen.insert(parent_id) {
+                state.push(parent_id, parent_time);
+            }
+        }
+        let show = match self.simplification {
+            Simplification::Default => !any_treesame,
+            Simplification::FullHistory => !all_treesame,
+        };
+        Ok(Some((id, show)))
+    }
+}
+
+impl<Find, StateMut> Iterator for PathLimited<Find, StateMut>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Option<git_object::Data<'a>>,
+    StateMut: BorrowMut<State>,
+{
+    type Item = Result<ObjectId, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.next_inner() {
+                Ok(Some((id, true))) => return Some(Ok(id)),
+                Ok(Some((_hidden, false))) => continue,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// Lookup the commit with `id` to return its tree and committer time, and to push its parents according to `mode` into `parents`
+/// if set.
+fn lookup_commit<Find>(
+    find: &mut Find,
+    buf: &mut Vec<u8>,
+    id: &oid,
+    mode: &Parents,
+    mut parents: Option<&mut Vec<ObjectId>>,
+) -> Result<(ObjectId, u64), Error>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Option<git_object::Data<'a>>,
+{
+    let commit_iter = find(id, buf)
+        .and_then(git_object::Data::try_into_commit_iter)
+        .ok_or_else(|| Error::NotFound { oid: id.to_owned() })?;
+    let mut tree = None;
+    for token in commit_iter {
+        match token? {
+            Token::Tree { id } => tree = Some(id),
+            Token::Parent { id } => {
+                if let Some(parents) = parents.as_mut() {
+                    if parents.is_empty() || matches!(mode, Parents::All) {
+                        parents.push(id);
+                    }
+                }
+            }
+            Token::Author { .. } => {}
+            Token::Committer { signature } => {
+                let tree = tree.expect("the tree is always the first token");
+                return Ok((tree, signature.time.time.into()));
+            }
+            _ => break,
+        }
+    }
+    Err(Error::MissingCommitter { oid: id.to_owned() })
+}
+
+/// Return true if the changed-path Bloom filter of commit `id` in `cache` proves that none of the `paths` changed compared to its
+/// first parent, or to the empty tree if it has none.
+fn definitely_unchanged(cache: Option<&git_commitgraph::Graph>, id: &oid, paths: &[BString]) -> bool {
+    match cache {
+        Some(cache) if !paths.is_empty() => paths
+            .iter()
+            .all(|path| cache.maybe_contains_path(id, path.as_bstr()) == Some(false)),
+        _ => false,
+    }
+}
+
+/// Return true if none of the `paths` changed between the tree `lhs` and the tree `rhs`. If `lhs` is `None`, it is assumed
+/// to be the empty tree.
+///
+/// Only the trees leading to our `paths` are looked at, so the cost doesn't depend on the amount of changes elsewhere.
+fn is_treesame<Find>(
+    find: &mut Find,
+    state: &mut State,
+    paths: &[BString],
+    lhs: Option<&oid>,
+    rhs: &oid,
+) -> Result<bool, Error>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Option<git_object::Data<'a>>,
+{
+    if lhs == Some(rhs) {
+        return Ok(true);
+    }
+    if paths.is_empty() {
+        return match lhs {
+            Some(_) => Ok(false),
+            None => Ok(lookup_tree(find, &mut state.rhs_tree_buf, rhs)?.next().is_none()),
+        };
+    }
+    for path in paths {
+        let mut lhs = lhs.map(|id| (EntryMode::Tree, id.to_owned()));
+        let mut rhs = Some((EntryMode::Tree, rhs.to_owned()));
+        for component in path.split(|b| *b == b'/').filter(|c| !c.is_empty()) {
+            if lhs == rhs {
+                break;
+            }
+            lhs = lookup_entry(find, &mut state.lhs_tree_buf, lhs, component)?;
+            rhs = lookup_entry(find, &mut state.rhs_tree_buf, rhs, component)?;
+        }
+        if lhs != rhs {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Return the entry named `name` in `tree` if it is a tree at all.
+fn lookup_entry<Find>(
+    find: &mut Find,
+    buf: &mut Vec<u8>,
+    tree: Option<(EntryMode, ObjectId)>,
+    name: &[u8],
+) -> Result<Option<(EntryMode, ObjectId)>, Error>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Option<git_object::Data<'a>>,
+{
+    let id = match tree {
+        Some((mode, id)) if mode.is_tree() => id,
+        _ => return Ok(None),
+    };
+    for entry in lookup_tree(find, buf, &id)? {
+        let entry = entry?;
+        if entry.filename == name {
+            return Ok(Some((entry.mode, entry.oid.to_owned())));
+        }
+    }
+    Ok(None)
+}
+
+fn lookup_tree<'a, Find>(find: &mut Find, buf: &'a mut Vec<u8>, id: &oid) -> Result<git_object::TreeRefIter<'a>, Error>
+where
+    Find: for<'b> FnMut(&oid, &'b mut Vec<u8>) -> Option<git_object::Data<'b>>,
+{
+    find(id, buf)
+        .and_then(git_object::Data::try_into_tree_iter)
+        .ok_or_else(|| Error::NotFound { oid: id.to_owned() })
+}
@@ -181,4 +181,190 @@ mod ancestor {
             },
         )
     }
+
+    mod sorting {
+        use git_odb::{linked::Store, pack::FindExt};
+        use git_traverse::commit::{self, Sorting};
+
+        use crate::hex_to_id;
+
+        fn db_and_commit_graph_dir() -> crate::Result<(Store, std::path::PathBuf)> {
+            let dir = git_testtools::scripted_fixture_repo_read_only("make_traversal_repo_for_commits_with_dates.sh")?;
+            let objects_dir = dir.join(".git").join("objects");
+            let db = Store::at(&objects_dir)?;
+            Ok((db, objects_dir.join("info")))
+        }
+
+        fn check_traversal(sorting: Sorting, reverse: bool, expected: &[&str]) -> crate::Result {
+            for use_commit_graph in [false, true] {
+                let (db, info_dir) = db_and_commit_graph_dir()?;
+                let cache = if use_commit_graph {
+                    Some(git_commitgraph::Graph::from_info_dir(info_dir)?)
+                } else {
+                    None
+                };
+                let oids: Result<Vec<_>, _> = commit::Ancestors::new(
+                    Some(hex_to_id("b8c050b4ca4f11634b2475a669c96d56132f5e81")),
+                    commit::ancestors::State::default(),
+                    move |oid, buf| db.find_commit_iter(oid, buf).ok().map(|t| t.0),
+                )
+                .sorting(sorting)
+                .reverse(reverse)
+                .commit_graph(cache)
+                .collect();
+                let expected: Vec<_> = expected.iter().copied().map(hex_to_id).collect();
+                assert_eq!(oids?, expected, "with commit-graph: {}", use_commit_graph);
+            }
+            Ok(())
+        }
+
+        const M1B1: &str = "b8c050b4ca4f11634b2475a669c96d56132f5e81";
+        const B1C2: &str = "82ad83fee3c87b311e43699217f2ce7a03bdeaf3";
+        const B1C1: &str = "34fcb5127d27ca954e8a957ffce61e1d8dbfd74f";
+        const C4: &str = "2b38d07e12f0c56f71dbaa9c0aa2bcb47966952c";
+        const C3: &str = "31279144bcc41811cd291cc9a7f7b2b3c2e14638";
+        const C2: &str = "f13af7b40c97dc69741fa06cefba4e98142ce91a";
+        const C1: &str = "dc423ef5f5988c894a090d89258b29d5f4d50750";
+
+        #[test]
+        fn breadth_first() -> crate::Result {
+            check_traversal(Sorting::BreadthFirst, false, &[M1B1, C4, B1C2, C3, B1C1, C2, C1])
+        }
+
+        #[test]
+        fn by_commit_time_shows_parents_before_children_with_skewed_clocks() -> crate::Result {
+            check_traversal(
+                Sorting::ByCommitTimeNewestFirst,
+                false,
+                &[M1B1, B1C2, C4, C3, C2, C1, B1C1],
+            )
+        }
+
+        #[test]
+        fn topological() -> crate::Result {
+            check_traversal(Sorting::Topological, false, &[M1B1, B1C2, B1C1, C4, C3, C2, C1])
+        }
+
+        #[test]
+        fn topological_reversed() -> crate::Result {
+            check_traversal(Sorting::Topological, true, &[C1, C2, C3, C4, B1C1, B1C2, M1B1])
+        }
+
+        #[test]
+        fn topological_by_commit_time() -> crate::Result {
+            check_traversal(
+                Sorting::TopologicalByCommitTime,
+                false,
+                &[M1B1, B1C2, C4, C3, B1C1, C2, C1],
+            )
+        }
+
+        #[test]
+        fn topological_by_author_time() -> crate::Result {
+            check_traversal(
+                Sorting::TopologicalByAuthorTime,
+                false,
+                &[M1B1, C4, C3, B1C2, B1C1, C2, C1],
+            )
+        }
+    }
+}
+
+mod path_limited {
+    use git_odb::{linked::Store, pack::FindExt};
+    use git_traverse::commit::{self, Simplification};
+
+    use crate::hex_to_id;
+
+    const M1B1: &str = "926abcc5a544f0085f19ca8834ac50d37d57e4ae";
+    const C4: &str = "c721df7f19a8e20dfb2633871ade68ec46ee6789";
+    const B1C2: &str = "a53674ce2f7764473e5eeee6547443d802c4c3c4";
+    const B1C1: &str = "7104773c79bdd143031507f663d9e1fd179a911d";
+    const C3: &str = "ff05a961ffb4a56ac178c374eaf2009cb02c3417";
+    const C2: &str = "66b18ef6646a5701b8e552e017c244dfc08e16ca";
+    const C1: &str = "bdc14697ba7ec3e75b97c299dd05a3806dcbdb93";
+
+    fn check_traversal(paths: &[&str], simplification: Simplification, expected: &[&str]) -> crate::Result {
+        let dir = git_testtools::scripted_fixture_repo_read_only("make_traversal_repo_for_commits_with_paths.sh")?;
+        let db = Store::at(dir.join(".git").join("objects"))?;
+        let oids: Result<Vec<_>, _> = commit::PathLimited::new(
+            Some(hex_to_id(M1B1)),
+            paths.iter().copied(),
+            commit::path_limited::State::default(),
+            move |oid, buf| db.find(oid, buf).ok().map(|t| t.0),
+        )
+        .simplification(simplification)
+        .collect();
+        let expected: Vec<_> = expected.iter().copied().map(hex_to_id).collect();
+        assert_eq!(oids?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn merge_treesame_to_first_parent_is_simplified_away() -> crate::Result {
+        check_traversal(&["a"], Simplification::Default, &[C4, C3, C1])
+    }
+
+    #[test]
+    fn full_history_shows_merges_differing_from_any_parent() -> crate::Result {
+        check_traversal(&["a"], Simplification::FullHistory, &[M1B1, C4, C3, C1])
+    }
+
+    #[test]
+    fn merge_treesame_to_second_parent_only_follows_it() -> crate::Result {
+        check_traversal(&["d"], Simplification::Default, &[B1C1, C2])?;
+        check_traversal(&["d/b"], Simplification::Default, &[B1C1, C2])
+    }
+
+    #[test]
+    fn full_history_with_directories() -> crate::Result {
+        check_traversal(&["d"], Simplification::FullHistory, &[M1B1, B1C1, C2])
+    }
+
+    #[test]
+    fn multiple_paths() -> crate::Result {
+        check_traversal(&["a", "c"], Simplification::Default, &[M1B1, C4, B1C2, C3, C1])
+    }
+
+    #[test]
+    fn paths_match_from_the_root_and_by_whole_components() -> crate::Result {
+        check_traversal(&["b"], Simplification::Default, &[])?;
+        check_traversal(&["d/b/c"], Simplification::Default, &[])
+    }
+
+    #[test]
+    fn without_paths_all_commits_changing_the_tree_are_returned() -> crate::Result {
+        check_traversal(&[], Simplification::Default, &[M1B1, C4, B1C2, B1C1, C3, C2, C1])
+    }
+
+    #[test]
+    fn trees_not_leading_to_any_path_are_not_looked_at() -> crate::Result {
+        let dir = git_testtools::scripted_fixture_repo_read_only("make_traversal_repo_for_commits_with_paths.sh")?;
+        let db = Store::at(dir.join(".git").join("objects"))?;
+        let mut looked_up = Vec::new();
+        let oids: Result<Vec<_>, _> = commit::PathLimited::new(
+            Some(hex_to_id(M1B1)),
+            Some("a"),
+            commit::path_limited::State::default(),
+            |oid, buf| {
+                looked_up.push(oid.to_owned());
+                db.find(oid, buf).ok().map(|t| t.0)
+            },
+        )
+        .collect();
+        assert_eq!(oids?, [C4, C3, C1].iter().copied().map(hex_to_id).collect::<Vec<_>>());
+
+        let mut buf = Vec::new();
+        for id in looked_up {
+            if let Some(tree) = db.find(id, &mut buf)?.0.try_into_tree_iter() {
+                for entry in tree {
+                    assert_ne!(
+                        entry?.filename, "b",
+                        "the tree of 'd' is never needed to learn if 'a' changed"
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
 }
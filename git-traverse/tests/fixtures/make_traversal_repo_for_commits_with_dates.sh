@@ -0,0 +1,30 @@
+#!/bin/bash
+set -eu -o pipefail
+
+function commit_at() {
+  local message=${1:?first argument is the commit message}
+  local committer_time=${2:?second argument is the committer time in seconds since epoch}
+  local author_time=${3:-$committer_time}
+  GIT_COMMITTER_DATE="$committer_time +0000" GIT_AUTHOR_DATE="$author_time +0000" \
+    git commit -q --allow-empty -m "$message"
+}
+
+git init -q
+git config commit.gpgsign false
+
+git checkout -q -b main
+commit_at c1 946684900
+commit_at c2 946685000
+
+git checkout -q -b branch1
+# a clock-skewed commit which is older than its parent
+commit_at b1c1 946684850
+commit_at b1c2 946685400 946684950
+
+git checkout -q main
+commit_at c3 946685100
+commit_at c4 946685200
+
+GIT_COMMITTER_DATE="946685500 +0000" GIT_AUTHOR_DATE="946685500 +0000" git merge -q --no-ff branch1 -m m1b1
+
+git commit-graph write --no-progress --reachable
@@ -0,0 +1,31 @@
+#!/bin/bash
+set -eu -o pipefail
+
+function commit_at() {
+  local message=${1:?first argument is the commit message}
+  local time=${2:?second argument is the commit time in seconds since epoch}
+  GIT_COMMITTER_DATE="$time +0000" GIT_AUTHOR_DATE="$time +0000" git commit -q -m "$message"
+}
+
+git init -q
+git config commit.gpgsign false
+
+git checkout -q -b main
+echo a > a && git add a
+commit_at c1 946684900
+mkdir d && echo b > d/b && git add d
+commit_at c2 946685000
+echo a2 > a && git add a
+commit_at c3 946685100
+
+git checkout -q -b branch1
+echo b2 > d/b && git add d
+commit_at b1c1 946685200
+echo c > c && git add c
+commit_at b1c2 946685300
+
+git checkout -q main
+echo a3 > a && git add a
+commit_at c4 946685400
+
+GIT_COMMITTER_DATE="946685500 +0000" GIT_AUTHOR_DATE="946685500 +0000" git merge -q --no-ff branch1 -m m1b1
@@ -0,0 +1,188 @@
+use std::{collections::HashMap, ffi::OsStr, io, path::Path, str::FromStr};
+
+use anyhow::{Context as AnyhowContext, Result};
+use git_commitgraph::{bloom, file::write::Entry, graph::write::Split, Graph};
+use git_repository as git;
+use git_repository::{hash::ObjectId, objs::bstr::ByteVec, odb::pack::FindExt, traverse, Progress};
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SplitStrategy {
+    No,
+    NoMerge,
+    SizeMultiple,
+    Replace,
+}
+
+impl SplitStrategy {
+    pub fn variants() -> &'static [&'static str] {
+        &["no", "no-merge", "size-multiple", "replace"]
+    }
+}
+
+impl Default for SplitStrategy {
+    fn default() -> Self {
+        SplitStrategy::No
+    }
+}
+
+impl FromStr for SplitStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use SplitStrategy::*;
+        let slc = s.to_ascii_lowercase();
+        Ok(match slc.as_str() {
+            "no" => No,
+            "no-merge" => NoMerge,
+            "size-multiple" => SizeMultiple,
+            "replace" => Replace,
+            _ => return Err("invalid value".into()),
+        })
+    }
+}
+
+/// A general purpose context for many operations provided here
+pub struct Context<W: io::Write> {
+    /// How to split the commit graph into multiple files
+    pub split: SplitStrategy,
+    /// When using the 'size-multiple' strategy, the factor by which a file must be larger than the ones above it to not be merged.
+    pub size_multiple: Option<u32>,
+    /// When using the 'size-multiple' strategy, always merge files if the new file would contain more than this amount of commits.
+    pub max_commits: Option<u32>,
+    /// If true, compute changed-path Bloom filters for all commits to accelerate path-limited traversals.
+    pub changed_paths: bool,
+    /// A stream to which to output operation results
+    pub out: W,
+}
+
+/// Add all commits reachable from `tips`, or from all references if `tips` is empty, to the commit graph of the repository
+/// at `repository_path`.
+pub fn write<W>(
+    repository_path: impl AsRef<Path>,
+    tips: impl IntoIterator<Item = impl AsRef<OsStr>>,
+    mut progress: impl Progress,
+    Context {
+        split,
+        size_multiple,
+        max_commits,
+        changed_paths,
+        mut out,
+    }: Context<W>,
+) -> Result<()>
+where
+    W: io::Write,
+{
+    use git::bstr::ByteSlice;
+    use os_str_bytes::OsStrBytes;
+
+    let repo = git::discover(repository_path)?;
+    let handle = repo.to_easy();
+    let odb = &repo.objects;
+    let mut buf = Vec::new();
+
+    let mut tip_ids = tips
+        .into_iter()
+        .map(|tip| {
+            ObjectId::from_hex(&Vec::from_os_str_lossy(tip.as_ref())).or_else(|_| {
+                handle
+                    .find_reference(tip.as_ref().to_raw_bytes().as_bstr())
+                    .map_err(anyhow::Error::from)
+                    .and_then(|r| r.into_fully_peeled_id().map(|oid| oid.detach()).map_err(Into::into))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if tip_ids.is_empty() {
+        for reference in handle.references()?.all()?.peeled() {
+            let reference = reference.map_err(|err| anyhow::anyhow!(err))?;
+            if let git::refs::TargetRef::Peeled(id) = reference.target() {
+                tip_ids.push(id.to_owned());
+            }
+        }
+    }
+    tip_ids.retain(|id| {
+        odb.find(id, &mut buf)
+            .map(|(data, _)| data.kind == git::objs::Kind::Commit)
+            .unwrap_or(false)
+    });
+
+    progress.init(None, git::progress::count("commits"));
+    let mut entries = Vec::new();
+    for id in traverse::commit::Ancestors::new(tip_ids, traverse::commit::ancestors::State::default(), |oid, buf| {
+        odb.find_commit_iter(oid, buf).ok().map(|t| t.0)
+    }) {
+        let id = id?;
+        let commit = odb.find_commit(id, &mut buf)?.0;
+        entries.push(Entry {
+            id,
+            tree: commit.tree(),
+            parents: commit.parents().collect(),
+            committer_timestamp: commit.committer.time.time.into(),
+            bloom_filter: None,
+        });
+        progress.inc();
+    }
+
+    if changed_paths {
+        let trees: HashMap<_, _> = entries.iter().map(|e| (e.id, e.tree)).collect();
+        let mut progress = progress.add_child("changed paths");
+        progress.init(Some(entries.len()), git::progress::count("commits"));
+        let mut state = git::diff::tree::State::default();
+        let (mut lhs_buf, mut rhs_buf) = (Vec::new(), Vec::new());
+        for entry in entries.iter_mut() {
+            // Parents may be missing in shallow or partial clones, in which case all paths are considered changed, which is
+            // safe as filters may yield false positives.
+            let parent_tree = entry.parents.first().and_then(|parent| {
+                trees.get(parent).copied().or_else(|| {
+                    odb.find_commit(parent, &mut lhs_buf)
+                        .ok()
+                        .map(|(commit, _)| commit.tree())
+                })
+            });
+            let lhs = match parent_tree {
+                Some(tree) => Some(odb.find_tree_iter(tree, &mut lhs_buf)?.0),
+                None => None,
+            };
+            let rhs = odb.find_tree_iter(entry.tree, &mut rhs_buf)?.0;
+            let mut recorder = git::diff::tree::Recorder::default();
+            git::diff::tree::Changes::from(lhs).needed_to_obtain(
+                rhs,
+                &mut state,
+                |oid, buf| odb.find_tree_iter(oid, buf).ok().map(|t| t.0),
+                &mut recorder,
+            )?;
+            let paths = recorder.records.into_iter().filter_map(|change| {
+                use git::diff::tree::recorder::Change::*;
+                match change {
+                    Addition { entry_mode, path, .. }
+                    | Deletion { entry_mode, path, .. }
+                    | Modification { entry_mode, path, .. } => (!entry_mode.is_tree()).then(|| path),
+                }
+            });
+            entry.bloom_filter = Some(bloom::Filter::from_changed_paths(paths));
+            progress.inc();
+        }
+    }
+
+    let split = match split {
+        SplitStrategy::No => Split::No,
+        SplitStrategy::NoMerge => Split::NoMerge,
+        SplitStrategy::SizeMultiple => Split::SizeMultiple {
+            factor: size_multiple.unwrap_or(2),
+            max_commits,
+        },
+        SplitStrategy::Replace => Split::Replace,
+    };
+    let outcome = Graph::write_to_info_dir(repo.objects_dir().join("info"), entries, split)
+        .with_context(|| "Could not write commit graph")?;
+    match outcome.path {
+        Some(path) => writeln!(
+            out,
+            "Wrote {} commits to {}, the commit graph now consists of {} file(s)",
+            outcome.num_commits,
+            path.display(),
+            outcome.num_files
+        )?,
+        None => writeln!(out, "The commit graph is up to date")?,
+    }
+    Ok(())
+}
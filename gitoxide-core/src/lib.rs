@@ -40,9 +40,11 @@ pub mod net;
 pub mod commitgraph;
 #[cfg(feature = "estimate-hours")]
 pub mod hours;
+pub mod odb;
 #[cfg(feature = "organize")]
 pub mod organize;
 pub mod pack;
+pub mod refs;
 #[cfg(any(feature = "async-client", feature = "blocking-client"))]
 pub mod remote;
 pub mod repository;
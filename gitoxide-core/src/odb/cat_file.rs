@@ -0,0 +1,379 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use git_repository as git;
+use git_repository::{
+    bstr::{BStr, BString, ByteSlice},
+    easy,
+    hash::ObjectId,
+    objs::Kind,
+    odb::{linked, Find},
+    refs::file::ReferenceExt,
+};
+
+/// The format git uses if none is specified, which is also the only format of the header line preceding object contents.
+pub const DEFAULT_FORMAT: &str = "%(objectname) %(objecttype) %(objectsize)";
+
+/// Determines what to print for each requested object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Print a line according to the format, followed by the object contents and a newline, like `git cat-file --batch`.
+    Batch,
+    /// Print a line according to the format only, like `git cat-file --batch-check`.
+    BatchCheck,
+}
+
+/// A general purpose context for many operations provided here
+pub struct Context<W: io::Write> {
+    /// Whether or not to print object contents.
+    pub mode: Mode,
+    /// The format of the line printed for each object, with `%(objectname)`, `%(objecttype)`, `%(objectsize)` and `%(rest)`
+    /// as placeholders. If `None`, the [default][DEFAULT_FORMAT] is used.
+    pub format: Option<String>,
+    /// If true, print all objects in the object database in the order of their ids instead of reading object names from the input.
+    pub all_objects: bool,
+    /// If true, don't flush the output after each object, which is faster but unsuitable when driven interactively.
+    pub buffer: bool,
+    /// The size of the cache to store full objects by their ID, bypassing any lookup in the object database.
+    ///
+    /// If 0, the cache is disabled entirely.
+    pub object_cache_size_in_bytes: usize,
+    /// A stream to which to output the requested objects
+    pub out: W,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Atom {
+    Name,
+    Type,
+    Size,
+    Rest,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Part {
+    Literal(BString),
+    Atom(Atom),
+}
+
+/// A parsed format string, like [`DEFAULT_FORMAT`].
+#[derive(Debug)]
+struct Format(Vec<Part>);
+
+impl Format {
+    fn parse(format: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut literal = BString::default();
+        let mut input = format;
+        while let Some(pos) = input.find('%') {
+            literal.extend_from_slice(&input.as_bytes()[..pos]);
+            input = &input[pos + 1..];
+            if let Some(stripped) = input.strip_prefix('%') {
+                literal.push(b'%');
+                input = stripped;
+                continue;
+            }
+            let (name, remaining) = match input.strip_prefix('(').and_then(|input| input.split_once(')')) {
+                Some(name_and_remaining) => name_and_remaining,
+                None => {
+                    literal.push(b'%');
+                    continue;
+                }
+            };
+            let atom = match name {
+                "objectname" => Atom::Name,
+                "objecttype" => Atom::Type,
+                "objectsize" => Atom::Size,
+                "rest" => Atom::Rest,
+                "objectsize:disk" | "deltabase" => bail!("The format element '{}' is not yet supported", name),
+                _ => bail!("unknown format element: {}", name),
+            };
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(Part::Atom(atom));
+            input = remaining;
+        }
+        literal.extend_from_slice(input.as_bytes());
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Format(parts))
+    }
+
+    fn uses(&self, atom: Atom) -> bool {
+        self.0.contains(&Part::Atom(atom))
+    }
+
+    fn write_to(&self, mut out: impl io::Write, id: &ObjectId, kind: Kind, size: u64, rest: &BStr) -> io::Result<()> {
+        for part in &self.0 {
+            match part {
+                Part::Literal(literal) => out.write_all(literal)?,
+                Part::Atom(Atom::Name) => write!(out, "{}", id)?,
+                Part::Atom(Atom::Type) => write!(out, "{}", kind)?,
+                Part::Atom(Atom::Size) => write!(out, "{}", size)?,
+                Part::Atom(Atom::Rest) => out.write_all(rest)?,
+            }
+        }
+        out.write_all(b"\n")
+    }
+}
+
+/// The outcome of resolving an object name.
+enum Resolution {
+    Found(ObjectId),
+    Missing,
+    /// An abbreviated id matches more than one object.
+    Ambiguous,
+}
+
+/// Resolves abbreviated object ids using the ids of all objects in the database, which are only listed once needed.
+struct AbbreviatedIds<'a> {
+    odb: &'a linked::Store,
+    all: Option<BTreeSet<ObjectId>>,
+}
+
+impl AbbreviatedIds<'_> {
+    /// Find the only object whose id starts with the hexadecimal `prefix`.
+    fn resolve(&mut self, prefix: &str) -> Result<Resolution> {
+        let all = match &mut self.all {
+            Some(all) => all,
+            None => self.all.insert(
+                self.odb
+                    .iter()
+                    .collect::<Result<_, _>>()
+                    .context("Could not list all objects to resolve abbreviated ids")?,
+            ),
+        };
+        let prefix = prefix.to_ascii_lowercase();
+        let lowest_candidate =
+            ObjectId::from_hex(format!("{:0<40}", prefix).as_bytes()).expect("valid hex of the right size");
+        let mut candidates = all
+            .range(lowest_candidate..)
+            .take_while(|id| id.to_string().starts_with(&prefix));
+        Ok(match (candidates.next(), candidates.next()) {
+            (Some(id), None) => Resolution::Found(*id),
+            (Some(_), Some(_)) => Resolution::Ambiguous,
+            (None, _) => Resolution::Missing,
+        })
+    }
+}
+
+/// Resolve `name` as full hexadecimal object id, as possibly partial reference name following symbolic references but
+/// without peeling tags, or as abbreviated hexadecimal object id, just like git does.
+fn resolve(handle: &easy::Handle, name: &BStr, abbreviated_ids: &mut AbbreviatedIds<'_>) -> Result<Resolution> {
+    if let Ok(id) = ObjectId::from_hex(name) {
+        return Ok(Resolution::Found(id));
+    }
+    let name = match name.to_str() {
+        Ok(name) if !name.is_empty() => name,
+        _ => return Ok(Resolution::Missing),
+    };
+    if name == "@" || name.contains(|c| matches!(c, '~' | '^' | ':')) || name.contains("@{") {
+        bail!("Revision expressions like '{}' are not supported yet", name)
+    }
+    match handle.try_find_reference(name) {
+        Ok(Some(reference)) => return follow(handle, reference.detach()),
+        Ok(None) | Err(easy::reference::find::Error::Find(git::refs::file::find::Error::RefnameValidation(_))) => {}
+        Err(err) => return Err(err.into()),
+    };
+
+    let is_abbreviated_id = (4..40).contains(&name.len()) && name.bytes().all(|b| b.is_ascii_hexdigit());
+    if is_abbreviated_id {
+        abbreviated_ids.resolve(name)
+    } else {
+        Ok(Resolution::Missing)
+    }
+}
+
+/// Follow `reference` until it points to an object.
+fn follow(handle: &easy::Handle, mut reference: git::refs::Reference) -> Result<Resolution> {
+    loop {
+        if let Some(id) = reference.target.as_id() {
+            return Ok(Resolution::Found(id.to_owned()));
+        }
+        reference = match reference
+            .follow(&handle.refs)
+            .expect("symbolic references can be followed")
+        {
+            Ok(next) => next,
+            Err(git::refs::file::find::existing::Error::NotFound(_)) => return Ok(Resolution::Missing),
+            Err(err) => return Err(err.into()),
+        };
+    }
+}
+
+/// Print the object `id` according to `format` and `mode`, or return false if it doesn't exist.
+fn print_object(
+    handle: &easy::Handle,
+    id: ObjectId,
+    format: &Format,
+    mode: Mode,
+    rest: &BStr,
+    mut out: impl io::Write,
+) -> Result<bool> {
+    match mode {
+        Mode::Batch => {
+            let object = match handle.try_find_object(id)? {
+                Some(object) => object,
+                None => return Ok(false),
+            };
+            format.write_to(&mut out, &id, object.kind, object.data.len() as u64, rest)?;
+            out.write_all(&object.data)?;
+            out.write_all(b"\n")?;
+        }
+        Mode::BatchCheck => {
+            let header = match handle.objects.try_header(id)? {
+                Some(header) => header,
+                None => return Ok(false),
+            };
+            format.write_to(&mut out, &id, header.kind, header.size, rest)?;
+        }
+    }
+    Ok(true)
+}
+
+/// Read object names, one per line, from `input` and print each object as configured, following the protocol of
+/// `git cat-file --batch` and `git cat-file --batch-check`.
+///
+/// Names may be full or abbreviated hexadecimal object ids or reference names, and objects which can't be found are printed as
+/// `<name> missing`, while abbreviated ids matching more than one object are printed as `<name> ambiguous`. Revision expressions
+/// like `HEAD~1` or `HEAD:path` aren't supported yet and cause an error.
+/// If the format uses `%(rest)`, the name ends at the first whitespace and everything after it is substituted for `%(rest)`.
+/// Lookups of object type and size only decode object headers unless object contents are printed as well.
+pub fn batch<W>(
+    repository_path: impl AsRef<Path>,
+    input: impl BufRead,
+    Context {
+        mode,
+        format,
+        all_objects,
+        buffer,
+        object_cache_size_in_bytes,
+        out,
+    }: Context<W>,
+) -> Result<()>
+where
+    W: io::Write,
+{
+    let format = Format::parse(format.as_deref().unwrap_or(DEFAULT_FORMAT))?;
+    let mut out = io::BufWriter::new(out);
+    let repo = git::discover(repository_path)?;
+    let mut handle = repo.to_easy().apply_environment();
+    if object_cache_size_in_bytes > 0 {
+        handle.object_cache_size(object_cache_size_in_bytes);
+    }
+
+    if all_objects {
+        let ids = repo
+            .objects
+            .iter()
+            .collect::<Result<BTreeSet<_>, _>>()
+            .context("Could not list all objects")?;
+        for id in ids {
+            if !print_object(&handle, id, &format, mode, "".into(), &mut out)? {
+                bail!("Object {} vanished while printing all objects", id)
+            }
+        }
+        return out.flush().map_err(Into::into);
+    }
+
+    let split_at_whitespace = format.uses(Atom::Rest);
+    let mut abbreviated_ids = AbbreviatedIds {
+        odb: &repo.objects,
+        all: None,
+    };
+    for line in input.split(b'\n') {
+        let line = line?;
+        let line = line.as_bstr();
+        let (name, rest) = match line.find_byteset(b" \t") {
+            Some(pos) if split_at_whitespace => (
+                line[..pos].as_bstr(),
+                line[pos..].trim_start_with(|c| c == ' ' || c == '\t').as_bstr(),
+            ),
+            _ => (line, "".into()),
+        };
+        let problem: &[u8] = match resolve(&handle, name, &mut abbreviated_ids)? {
+            Resolution::Found(id) if print_object(&handle, id, &format, mode, rest, &mut out)? => b"",
+            Resolution::Found(_) | Resolution::Missing => b" missing\n",
+            Resolution::Ambiguous => b" ambiguous\n",
+        };
+        if !problem.is_empty() {
+            out.write_all(name)?;
+            out.write_all(problem)?;
+        }
+        if !buffer {
+            out.flush()?;
+        }
+    }
+    out.flush().map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    mod format {
+        use crate::odb::cat_file::{Atom, Format, Part, DEFAULT_FORMAT};
+
+        fn literal(s: &str) -> Part {
+            Part::Literal(s.into())
+        }
+
+        #[test]
+        fn default_format() {
+            assert_eq!(
+                Format::parse(DEFAULT_FORMAT).unwrap().0,
+                vec![
+                    Part::Atom(Atom::Name),
+                    literal(" "),
+                    Part::Atom(Atom::Type),
+                    literal(" "),
+                    Part::Atom(Atom::Size)
+                ]
+            );
+        }
+
+        #[test]
+        fn double_percent_signs_are_literal_percent_signs() {
+            assert_eq!(
+                Format::parse("%%(objectname) 100%%").unwrap().0,
+                vec![literal("%(objectname) 100%")]
+            );
+        }
+
+        #[test]
+        fn percent_signs_without_atom_are_kept() {
+            assert_eq!(
+                Format::parse("%objectname %(objectsize").unwrap().0,
+                vec![literal("%objectname %(objectsize")]
+            );
+        }
+
+        #[test]
+        fn rest_is_an_atom_surrounded_by_literals() {
+            let format = Format::parse("<%(rest)>").unwrap();
+            assert_eq!(format.0, vec![literal("<"), Part::Atom(Atom::Rest), literal(">")]);
+            assert!(format.uses(Atom::Rest));
+            assert!(!format.uses(Atom::Name));
+        }
+
+        #[test]
+        fn unknown_atoms_are_an_error() {
+            assert_eq!(
+                Format::parse("%(objectname) %(foo)").unwrap_err().to_string(),
+                "unknown format element: foo"
+            );
+        }
+
+        #[test]
+        fn known_but_unsupported_atoms_are_an_error() {
+            assert_eq!(
+                Format::parse("%(deltabase)").unwrap_err().to_string(),
+                "The format element 'deltabase' is not yet supported"
+            );
+        }
+    }
+}
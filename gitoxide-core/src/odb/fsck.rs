@@ -0,0 +1,457 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    path::Path,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use anyhow::{bail, Result};
+use git_repository as git;
+use git_repository::{
+    bstr::BStr,
+    hash::{oid, ObjectId},
+    objs::{tree::EntryMode, Kind, ObjectRef, TreeRefIter},
+    odb::{linked, pack, pack::FindExt},
+    refs::file::ReferenceExt,
+    traverse, Progress,
+};
+
+/// A general purpose context for many operations provided here
+pub struct Context<W1: io::Write, W2: io::Write> {
+    /// If true, only check that all reachable objects exist and can be decoded, without verifying the integrity of all packs
+    /// and loose objects.
+    pub connectivity_only: bool,
+    /// If true, report all unreachable objects instead of only the dangling ones.
+    pub unreachable: bool,
+    /// If set, don't use more than this amount of threads when verifying packs.
+    pub thread_limit: Option<usize>,
+    pub should_interrupt: Arc<AtomicBool>,
+    /// A stream to which to output the objects found to be missing, corrupt, dangling or unreachable
+    pub out: W1,
+    /// A stream to which to output packs that failed verification
+    pub err: W2,
+}
+
+/// The result of checking which objects are reachable from references, their reflogs and the index.
+#[derive(Default)]
+pub struct Connectivity {
+    /// All objects which are reachable, exist and can be decoded.
+    pub reachable: BTreeSet<ObjectId>,
+    /// Objects which are reachable but don't exist, along with the kind they are expected to be if known.
+    pub missing: BTreeMap<ObjectId, Option<Kind>>,
+    /// Objects which are reachable but can't be read or decoded, along with a description of the problem.
+    pub corrupt: BTreeMap<ObjectId, String>,
+}
+
+impl Connectivity {
+    /// Return true if no reachable object is missing or corrupt.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty()
+    }
+}
+
+/// The objects pointed to by an object, as far as they are relevant for continuing the walk.
+enum Checked {
+    Commit { tree: ObjectId },
+    Tag { target: ObjectId, kind: Kind },
+    Tree,
+    Blob,
+}
+
+struct Checker<'a> {
+    odb: &'a linked::Store,
+    /// Objects which were found to be reachable along with all objects reachable from them before, and aren't checked again.
+    known: &'a BTreeSet<ObjectId>,
+    buf: Vec<u8>,
+    outcome: Connectivity,
+}
+
+impl<'a> Checker<'a> {
+    /// Read and decode the object with `id`, expected to be of the `expected` kind if known, and mark it as reachable, or record
+    /// it as missing or corrupt. Objects which are already known to be reachable are skipped.
+    fn check(&mut self, id: &oid, expected: Option<Kind>) -> Option<Checked> {
+        if self.known.contains(id) {
+            return None;
+        }
+        let data = match pack::Find::try_find(self.odb, id, &mut self.buf) {
+            Ok(Some((data, _location))) => data,
+            Ok(None) => {
+                self.outcome.missing.insert(id.to_owned(), expected);
+                return None;
+            }
+            Err(err) => {
+                self.outcome.corrupt.insert(id.to_owned(), err.to_string());
+                return None;
+            }
+        };
+        if let Some(expected) = expected.filter(|expected| *expected != data.kind) {
+            self.outcome.corrupt.insert(
+                id.to_owned(),
+                format!("expected a {} but found a {}", expected, data.kind),
+            );
+            return None;
+        }
+        let checked = match data.decode() {
+            Ok(ObjectRef::Commit(commit)) => Checked::Commit { tree: commit.tree() },
+            Ok(ObjectRef::Tag(tag)) => Checked::Tag {
+                target: tag.target(),
+                kind: tag.target_kind,
+            },
+            Ok(ObjectRef::Tree(_)) => Checked::Tree,
+            Ok(ObjectRef::Blob(_)) => Checked::Blob,
+            Err(err) => {
+                self.outcome.corrupt.insert(id.to_owned(), err.to_string());
+                return None;
+            }
+        };
+        self.outcome.reachable.insert(id.to_owned());
+        Some(checked)
+    }
+}
+
+/// Descends into all trees which weren't seen before, checking each of their entries.
+struct TreeVisitor<'a, 'b> {
+    checker: &'b mut Checker<'a>,
+    traversed_trees: &'b mut BTreeSet<ObjectId>,
+}
+
+impl<'a, 'b> traverse::tree::Visit for TreeVisitor<'a, 'b> {
+    fn pop_front_tracked_path_and_set_current(&mut self) {}
+
+    fn push_back_tracked_path_component(&mut self, _component: &BStr) {}
+
+    fn push_path_component(&mut self, _component: &BStr) {}
+
+    fn pop_path_component(&mut self) {}
+
+    fn visit_tree(&mut self, entry: &git::objs::tree::EntryRef<'_>) -> traverse::tree::visit::Action {
+        use traverse::tree::visit::Action;
+        if self.traversed_trees.contains(entry.oid) {
+            return Action::Skip;
+        }
+        match self.checker.check(entry.oid, Some(Kind::Tree)) {
+            Some(_) => {
+                self.traversed_trees.insert(entry.oid.to_owned());
+                Action::Continue
+            }
+            None => Action::Skip,
+        }
+    }
+
+    fn visit_nontree(&mut self, entry: &git::objs::tree::EntryRef<'_>) -> traverse::tree::visit::Action {
+        // Submodule commits are stored in another repository.
+        if entry.mode != EntryMode::Commit && !self.checker.outcome.reachable.contains(entry.oid) {
+            self.checker.check(entry.oid, Some(Kind::Blob));
+        }
+        traverse::tree::visit::Action::Continue
+    }
+}
+
+/// Add the ids of all objects that references in `refs` point to, including `HEAD` and all previous values recorded
+/// in reflogs, to `tips`.
+fn add_reference_tips(refs: &git::RefStore, tips: &mut BTreeSet<ObjectId>) -> Result<()> {
+    let mut references = Vec::new();
+    if let Some(head) = refs.try_find("HEAD")? {
+        references.push(head);
+    }
+    for reference in refs.iter()?.all()? {
+        references.push(reference?);
+    }
+
+    for reference in &references {
+        if let Some(id) = reference.target.as_id() {
+            tips.insert(id.to_owned());
+        }
+        let mut log = reference.log_iter(refs);
+        if let Some(lines) = log.all()? {
+            for line in lines {
+                let line = line?;
+                tips.extend(
+                    [line.previous_oid(), line.new_oid()]
+                        .iter()
+                        .filter(|id| !id.is_null())
+                        .cloned(),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Add the ids of all entries in the index file at `path` along with the trees recorded in its cache-tree extension to
+/// `tips`, so that staged objects are considered reachable. Submodule commits and entries which are only intended to be
+/// added are skipped, as are missing index files.
+fn add_index_tips(path: &Path, tips: &mut BTreeSet<ObjectId>) -> Result<()> {
+    let index = match git::index::File::at(path) {
+        Ok(index) => index,
+        Err(git::index::decode::Error::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            return Err(anyhow::Error::new(err).context(format!("Could not read the index at '{}'", path.display())))
+        }
+    };
+    tips.extend(
+        index
+            .entries
+            .into_iter()
+            .filter(|entry| entry.mode & 0o170000 != 0o160000 && !entry.is_intent_to_add)
+            .map(|entry| entry.id),
+    );
+    tips.extend(index.tree_ids);
+    Ok(())
+}
+
+/// Return the ids of all objects that references in `repo` and all of its worktrees point to, including each `HEAD` and
+/// all previous values recorded in reflogs, as well as all objects in the index of each worktree.
+fn tips(repo: &git::Repository) -> Result<BTreeSet<ObjectId>> {
+    let mut tips = BTreeSet::new();
+    let common_dir = repo.common_dir();
+    // The references of the main worktree, which are shared with all linked worktrees except for their `HEAD` and other
+    // per-worktree references.
+    add_reference_tips(
+        &git::RefStore::at(common_dir, git::refs::store::WriteReflog::Disable),
+        &mut tips,
+    )?;
+    add_index_tips(&common_dir.join("index"), &mut tips)?;
+    for worktree in repo.worktrees()? {
+        add_reference_tips(
+            &git::RefStore::for_linked_worktree(worktree.git_dir(), common_dir, git::refs::store::WriteReflog::Disable),
+            &mut tips,
+        )?;
+        add_index_tips(&worktree.git_dir().join("index"), &mut tips)?;
+    }
+    Ok(tips)
+}
+
+/// Find all objects reachable from the references of `repo` and their reflogs as well as the index, taking all worktrees
+/// into account, checking that each of them exists and can be decoded.
+///
+/// Commits are traversed with all of their parents, and trees including all of their entries. Objects which are missing
+/// or corrupt are recorded instead of aborting the operation.
+pub fn connectivity(repo: &git::Repository, progress: impl Progress) -> Result<Connectivity> {
+    reachable_from(repo, tips(repo)?, &BTreeSet::new(), progress)
+}
+
+/// Find all objects reachable from `tips` in the object database of `repo` like [`connectivity()`] does, but skip all objects
+/// which are `known` to be reachable along with all objects reachable from them.
+pub fn reachable_from(
+    repo: &git::Repository,
+    tips: impl IntoIterator<Item = ObjectId>,
+    known: &BTreeSet<ObjectId>,
+    mut progress: impl Progress,
+) -> Result<Connectivity> {
+    let odb = &*repo.objects;
+    let mut checker = Checker {
+        odb,
+        known,
+        buf: Vec::new(),
+        outcome: Connectivity::default(),
+    };
+
+    let mut queue: Vec<_> = tips.into_iter().map(|id| (id, None)).collect();
+    let (mut commits, mut trees) = (Vec::new(), Vec::new());
+    while let Some((id, expected)) = queue.pop() {
+        match checker.check(&id, expected) {
+            Some(Checked::Commit { .. }) => commits.push(id),
+            Some(Checked::Tag { target, kind }) => queue.push((target, Some(kind))),
+            Some(Checked::Tree) => trees.push(id),
+            Some(Checked::Blob) | None => {}
+        }
+    }
+
+    progress.init(None, git::progress::count("commits"));
+    for _commit in traverse::commit::Ancestors::filtered(
+        commits,
+        traverse::commit::ancestors::State::default(),
+        |oid, buf| odb.find_commit_iter(oid, buf).ok().map(|t| t.0),
+        |id| match checker.check(id, Some(Kind::Commit)) {
+            Some(Checked::Commit { tree }) => {
+                trees.push(tree);
+                true
+            }
+            _ => false,
+        },
+    ) {
+        // Errors can't happen as only commits which were found and decoded are traversed.
+        progress.inc();
+    }
+
+    progress.init(None, git::progress::count("trees"));
+    let mut traversed_trees = BTreeSet::new();
+    let mut state = traverse::tree::breadthfirst::State::default();
+    let mut buf = Vec::new();
+    for tree in trees {
+        if traversed_trees.contains(&tree) || checker.check(&tree, Some(Kind::Tree)).is_none() {
+            continue;
+        }
+        traversed_trees.insert(tree);
+        let root = odb.find_tree_iter(tree, &mut buf)?.0;
+        let mut visitor = TreeVisitor {
+            checker: &mut checker,
+            traversed_trees: &mut traversed_trees,
+        };
+        traverse::tree::breadthfirst(
+            root,
+            &mut state,
+            |oid, buf| {
+                odb.find_tree_iter(oid, buf)
+                    .ok()
+                    .map(|t| t.0)
+                    // The tree was checked before, so it can only fail if it's removed in the mean time. Pretend it's empty.
+                    .or_else(|| Some(TreeRefIter::from_bytes(&[])))
+            },
+            &mut visitor,
+        )?;
+        progress.inc();
+    }
+    Ok(checker.outcome)
+}
+
+/// Return all objects which are contained in the object database of `repo` but aren't `reachable`, along with their kind and
+/// whether they are dangling, that is not referenced by any other unreachable object.
+pub fn unreachable(repo: &git::Repository, reachable: &BTreeSet<ObjectId>) -> Result<BTreeMap<ObjectId, (Kind, bool)>> {
+    let odb = &*repo.objects;
+    let mut unreachable = BTreeMap::new();
+    let mut referenced = BTreeSet::new();
+    let mut buf = Vec::new();
+    for id in odb.iter() {
+        let id = id?;
+        if reachable.contains(&id) || unreachable.contains_key(&id) {
+            continue;
+        }
+        let data = match pack::Find::try_find(odb, id, &mut buf) {
+            Ok(Some((data, _location))) => data,
+            // Objects which can't be read are reported when verifying packs and loose objects.
+            Ok(None) | Err(_) => continue,
+        };
+        match data.decode() {
+            Ok(ObjectRef::Commit(commit)) => {
+                referenced.insert(commit.tree());
+                referenced.extend(commit.parents());
+            }
+            Ok(ObjectRef::Tag(tag)) => {
+                referenced.insert(tag.target());
+            }
+            Ok(ObjectRef::Tree(tree)) => referenced.extend(
+                tree.entries
+                    .iter()
+                    .filter(|e| e.mode != EntryMode::Commit)
+                    .map(|e| e.oid.to_owned()),
+            ),
+            Ok(ObjectRef::Blob(_)) | Err(_) => {}
+        }
+        unreachable.insert(id, (data.kind, false));
+    }
+    for (id, (_kind, is_dangling)) in unreachable.iter_mut() {
+        *is_dangling = !referenced.contains(id);
+    }
+    Ok(unreachable)
+}
+
+/// Verify all packs and loose objects of `odb` including their object hashes, and return the amount of failures which were
+/// written to `err`.
+fn verify_integrity(
+    odb: &linked::Store,
+    mut progress: impl Progress,
+    thread_limit: Option<usize>,
+    should_interrupt: &Arc<AtomicBool>,
+    mut err: impl io::Write,
+) -> Result<usize> {
+    let mut failures = 0;
+    for db in &odb.dbs {
+        for bundle in &db.bundles {
+            if let Err(e) = bundle.verify_integrity(
+                pack::index::verify::Mode::Sha1Crc32Decode,
+                pack::index::traverse::Algorithm::DeltaTreeLookup,
+                || pack::cache::Never,
+                thread_limit,
+                Some(progress.add_child(format!("{}", bundle.index.path().display()))),
+                Arc::clone(should_interrupt),
+            ) {
+                writeln!(err, "corrupt pack {}: {}", bundle.index.path().display(), e)?;
+                failures += 1;
+            }
+        }
+
+        let mut buf = Vec::new();
+        for id in db.loose.iter() {
+            let id = id?;
+            let result = db
+                .loose
+                .try_find(id, &mut buf)
+                .map_err(|e| e.to_string())
+                .and_then(|data| match data {
+                    Some(data) => data
+                        .verify_checksum(id)
+                        .map_err(|e| e.to_string())
+                        .and_then(|_| data.decode().map(|_| ()).map_err(|e| e.to_string())),
+                    // It was removed in the mean time.
+                    None => Ok(()),
+                });
+            if let Err(e) = result {
+                writeln!(err, "corrupt loose object {}: {}", id, e)?;
+                failures += 1;
+            }
+        }
+    }
+    Ok(failures)
+}
+
+/// Check the object database of the repository at `repository_path` for missing or corrupt objects which are reachable from
+/// any reference, reflog or index, and report dangling or unreachable objects. Unless only connectivity is checked, all packs and
+/// loose objects are verified as well.
+///
+/// Fails if any missing or corrupt object was found.
+pub fn fsck<W1, W2>(
+    repository_path: impl AsRef<Path>,
+    mut progress: impl Progress,
+    Context {
+        connectivity_only,
+        unreachable: report_unreachable,
+        thread_limit,
+        should_interrupt,
+        mut out,
+        mut err,
+    }: Context<W1, W2>,
+) -> Result<()>
+where
+    W1: io::Write,
+    W2: io::Write,
+{
+    let repo = git::discover(repository_path)?;
+    let mut failures = if connectivity_only {
+        0
+    } else {
+        verify_integrity(
+            &repo.objects,
+            progress.add_child("verify"),
+            thread_limit,
+            &should_interrupt,
+            &mut err,
+        )?
+    };
+
+    let connectivity = connectivity(&repo, progress.add_child("connectivity"))?;
+    for (id, kind) in &connectivity.missing {
+        match kind {
+            Some(kind) => writeln!(out, "missing {} {}", kind, id)?,
+            None => writeln!(out, "missing object {}", id)?,
+        }
+    }
+    for (id, reason) in &connectivity.corrupt {
+        writeln!(out, "broken object {}: {}", id, reason)?;
+    }
+    failures += connectivity.missing.len() + connectivity.corrupt.len();
+
+    for (id, (kind, is_dangling)) in unreachable(&repo, &connectivity.reachable)? {
+        if report_unreachable {
+            writeln!(out, "unreachable {} {}", kind, id)?;
+        } else if is_dangling {
+            writeln!(out, "dangling {} {}", kind, id)?;
+        }
+    }
+
+    if failures != 0 {
+        bail!("Found {} problem(s) in the object database", failures)
+    }
+    Ok(())
+}
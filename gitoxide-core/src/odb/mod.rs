@@ -0,0 +1,3 @@
+pub mod cat_file;
+pub mod fsck;
+pub mod prune;
@@ -0,0 +1,117 @@
+use std::{
+    io,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{bail, Result};
+use git_repository as git;
+use git_repository::Progress;
+
+use crate::odb::fsck;
+
+/// A general purpose context for many operations provided here
+pub struct Context<W: io::Write> {
+    /// Only remove unreachable objects which were last modified longer ago than this, or all of them if `None`.
+    pub expire_after: Option<Duration>,
+    /// If true, only print the objects that would be removed.
+    pub dry_run: bool,
+    /// A stream to which to output the ids of all removed objects
+    pub out: W,
+}
+
+/// Like git, only remove unreachable objects older than two weeks by default, as they might be in use by concurrent processes.
+pub const DEFAULT_EXPIRY: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// Turn the amount of seconds after which unreachable objects may be removed into the expiry to use for pruning, using
+/// [`DEFAULT_EXPIRY`] if unset and removing objects regardless of their age if it's 0.
+pub fn expire_after(seconds: Option<u64>) -> Option<Duration> {
+    match seconds {
+        None => Some(DEFAULT_EXPIRY),
+        Some(0) => None,
+        Some(seconds) => Some(Duration::from_secs(seconds)),
+    }
+}
+
+/// Remove all loose objects from the repository at `repository_path` which aren't reachable from any reference, reflog
+/// or index of any of its worktrees, and which are older than the configured expiry time.
+///
+/// Like git, objects reachable from loose objects newer than the expiry time are kept as well, as these may be about to be
+/// referenced. Objects they lead to which are missing or corrupt are ignored.
+///
+/// Objects in alternates are never removed, and nothing is done if any reachable object is missing or corrupt as the
+/// set of reachable objects can't be known in that case.
+pub fn prune<W>(
+    repository_path: impl AsRef<Path>,
+    mut progress: impl Progress,
+    Context {
+        expire_after,
+        dry_run,
+        mut out,
+    }: Context<W>,
+) -> Result<()>
+where
+    W: io::Write,
+{
+    let repo = git::discover(repository_path)?;
+    let connectivity = fsck::connectivity(&repo, progress.add_child("connectivity"))?;
+    if !connectivity.is_complete() {
+        bail!(
+            "Refusing to prune as {} reachable object(s) are missing or corrupt",
+            connectivity.missing.len() + connectivity.corrupt.len()
+        )
+    }
+
+    let cutoff = expire_after.map(|duration| SystemTime::now() - duration);
+    let loose = &repo
+        .objects
+        .dbs
+        .first()
+        .expect("the repository's own object database")
+        .loose;
+    let mut candidates = Vec::new();
+    let mut recent = Vec::new();
+    for id in loose.iter() {
+        let id = id?;
+        if connectivity.reachable.contains(&id) {
+            continue;
+        }
+        let hex = id.to_string();
+        let path = loose.path.join(&hex[..2]).join(&hex[2..]);
+        let modified = match std::fs::metadata(&path).and_then(|md| md.modified()) {
+            Ok(modified) => modified,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err.into()),
+        };
+        if cutoff.map_or(false, |cutoff| modified > cutoff) {
+            recent.push(id);
+        } else {
+            candidates.push((id, path));
+        }
+    }
+    let kept_by_recent = fsck::reachable_from(
+        &repo,
+        recent,
+        &connectivity.reachable,
+        progress.add_child("connectivity of recent objects"),
+    )?
+    .reachable;
+
+    let mut progress = progress.add_child("prune");
+    progress.init(None, git::progress::count("objects"));
+    for (id, path) in candidates {
+        if kept_by_recent.contains(&id) {
+            continue;
+        }
+        if !dry_run {
+            std::fs::remove_file(&path)?;
+            // Remove the fan-out directory if it's empty now, which fails harmlessly otherwise.
+            if let Some(dir) = path.parent() {
+                std::fs::remove_dir(dir).ok();
+            }
+        }
+        writeln!(out, "{}", id)?;
+        progress.inc();
+    }
+    Ok(())
+}
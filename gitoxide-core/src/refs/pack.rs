@@ -0,0 +1,39 @@
+use std::{io, path::Path};
+
+use anyhow::Result;
+use git_repository as git;
+
+/// A general purpose context for many operations provided here
+pub struct Context<W: io::Write> {
+    /// If true, pack all references, not only tags and those which are already packed.
+    pub all: bool,
+    /// If true, delete loose references once they are packed.
+    pub prune: bool,
+    /// A stream to which to output the names of all packed references
+    pub out: W,
+}
+
+/// Pack the loose references of the repository at `repository_path` into its `packed-refs` file, peeling tags along the way.
+pub fn pack<W>(repository_path: impl AsRef<Path>, Context { all, prune, mut out }: Context<W>) -> Result<()>
+where
+    W: io::Write,
+{
+    let repo = git::discover(repository_path)?;
+    let objects = repo.objects.clone();
+    let packed = repo.refs.pack_refs(
+        git::refs::file::pack_refs::Options {
+            all,
+            prune,
+            ..Default::default()
+        },
+        Box::new(move |id, buf| {
+            git::odb::Find::try_find(&*objects, id, buf)
+                .map(|obj| obj.map(|obj| obj.kind))
+                .map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send + Sync>)
+        }),
+    )?;
+    for name in packed {
+        writeln!(out, "{}", name.as_bstr())?;
+    }
+    Ok(())
+}
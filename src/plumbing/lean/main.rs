@@ -222,5 +222,101 @@ pub fn main() -> Result<()> {
             )
             .map(|_| ())
         }
+        SubCommands::CommitGraphWrite(options::CommitGraphWrite {
+            repository,
+            split,
+            size_multiple,
+            max_commits,
+            changed_paths,
+            tips,
+        }) => {
+            let (_handle, progress) = prepare(verbose, "commit-graph-write", None);
+            core::commitgraph::write::write(
+                repository.unwrap_or_else(|| PathBuf::from(".")),
+                tips,
+                DoOrDiscard::from(progress),
+                core::commitgraph::write::Context {
+                    split: split.unwrap_or_default(),
+                    size_multiple,
+                    max_commits,
+                    changed_paths,
+                    out: stdout(),
+                },
+            )
+        }
+        SubCommands::RefsPack(options::RefsPack {
+            repository,
+            all,
+            no_prune,
+        }) => core::refs::pack::pack(
+            repository.unwrap_or_else(|| PathBuf::from(".")),
+            core::refs::pack::Context {
+                all,
+                prune: !no_prune,
+                out: stdout(),
+            },
+        ),
+        SubCommands::Fsck(options::Fsck {
+            repository,
+            connectivity_only,
+            unreachable,
+        }) => {
+            let (_handle, progress) = prepare(verbose, "fsck", None);
+            core::odb::fsck::fsck(
+                repository.unwrap_or_else(|| PathBuf::from(".")),
+                DoOrDiscard::from(progress),
+                core::odb::fsck::Context {
+                    connectivity_only,
+                    unreachable,
+                    thread_limit,
+                    should_interrupt,
+                    out: stdout(),
+                    err: stderr(),
+                },
+            )
+        }
+        SubCommands::Prune(options::Prune {
+            repository,
+            expire_after_seconds,
+            dry_run,
+        }) => {
+            let (_handle, progress) = prepare(verbose, "prune", None);
+            core::odb::prune::prune(
+                repository.unwrap_or_else(|| PathBuf::from(".")),
+                DoOrDiscard::from(progress),
+                core::odb::prune::Context {
+                    expire_after: core::odb::prune::expire_after(expire_after_seconds),
+                    dry_run,
+                    out: stdout(),
+                },
+            )
+        }
+        SubCommands::CatFile(options::CatFile {
+            repository,
+            batch,
+            batch_check,
+            format,
+            batch_all_objects,
+            buffer,
+            object_cache_size_mb,
+        }) => {
+            let mode = match (batch, batch_check) {
+                (true, false) => core::odb::cat_file::Mode::Batch,
+                (false, true) => core::odb::cat_file::Mode::BatchCheck,
+                _ => anyhow::bail!("Either --batch or --batch-check must be specified"),
+            };
+            core::odb::cat_file::batch(
+                repository.unwrap_or_else(|| PathBuf::from(".")),
+                io::BufReader::new(stdin()),
+                core::odb::cat_file::Context {
+                    mode,
+                    format,
+                    all_objects: batch_all_objects,
+                    buffer,
+                    object_cache_size_in_bytes: object_cache_size_mb.unwrap_or(0) * 1_000_000,
+                    out: stdout(),
+                },
+            )
+        }
     }
 }
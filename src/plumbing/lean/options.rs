@@ -37,6 +37,11 @@ pub enum SubCommands {
     #[cfg(any(feature = "gitoxide-core-async-client", feature = "gitoxide-core-blocking-client"))]
     PackReceive(PackReceive),
     CommitGraphVerify(CommitGraphVerify),
+    CommitGraphWrite(CommitGraphWrite),
+    RefsPack(RefsPack),
+    Fsck(Fsck),
+    Prune(Prune),
+    CatFile(CatFile),
 }
 
 /// Create an index from a packfile.
@@ -277,3 +282,126 @@ pub struct CommitGraphVerify {
     #[argh(switch, short = 's')]
     pub statistics: bool,
 }
+
+/// Write or update the commit graph of a repository
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "commit-graph-write")]
+pub struct CommitGraphWrite {
+    #[argh(option, short = 'r')]
+    /// the directory containing the '.git' repository whose commit graph should be written.
+    pub repository: Option<PathBuf>,
+
+    #[argh(option)]
+    /// the way commits are distributed across commit-graph files.
+    ///
+    /// Possible values are "no", "no-merge", "size-multiple" and "replace". Default is "no", which writes a single file.
+    pub split: Option<core::commitgraph::write::SplitStrategy>,
+
+    #[argh(option)]
+    /// when splitting by "size-multiple", a file is merged into the new one unless it has more than this many times
+    /// as many commits. Default is 2.
+    pub size_multiple: Option<u32>,
+
+    #[argh(option)]
+    /// when splitting by "size-multiple", files are merged as long as the new file would have more than this amount of commits.
+    pub max_commits: Option<u32>,
+
+    #[argh(switch)]
+    /// compute changed-path Bloom filters for all commits to speed up path-limited traversals.
+    pub changed_paths: bool,
+
+    /// the tips from which to start the commit graph iteration, either as fully qualified commit hashes
+    /// or as branch names.
+    ///
+    /// If empty, all commits reachable from all references are added.
+    #[argh(positional)]
+    pub tips: Vec<OsString>,
+}
+
+/// Pack loose references into the 'packed-refs' file, peeling tags along the way
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "refs-pack")]
+pub struct RefsPack {
+    #[argh(option, short = 'r')]
+    /// the directory containing the '.git' repository whose references should be packed.
+    pub repository: Option<PathBuf>,
+
+    #[argh(switch)]
+    /// pack all references, not only tags and references which are already packed.
+    pub all: bool,
+
+    #[argh(switch)]
+    /// keep the loose references even though they were packed.
+    pub no_prune: bool,
+}
+
+/// Verify the connectivity and integrity of all objects, reporting missing, corrupt and dangling ones
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "fsck")]
+pub struct Fsck {
+    #[argh(option, short = 'r')]
+    /// the directory containing the '.git' repository whose objects should be checked.
+    pub repository: Option<PathBuf>,
+
+    #[argh(switch)]
+    /// only check that all objects reachable from references and reflogs exist and can be decoded, without verifying all packs
+    /// and loose objects.
+    pub connectivity_only: bool,
+
+    #[argh(switch)]
+    /// report all unreachable objects, not only dangling ones which aren't referenced by any other unreachable object.
+    pub unreachable: bool,
+}
+
+/// Remove loose objects which aren't reachable from any reference, reflog or index
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "prune")]
+pub struct Prune {
+    #[argh(option, short = 'r')]
+    /// the directory containing the '.git' repository whose unreachable objects should be removed.
+    pub repository: Option<PathBuf>,
+
+    #[argh(option, short = 'e')]
+    /// only remove objects which were last modified longer ago than the given amount of seconds, defaulting to two weeks.
+    /// Use 0 to remove all unreachable loose objects.
+    pub expire_after_seconds: Option<u64>,
+
+    #[argh(switch, short = 'n')]
+    /// only print the objects which would be removed.
+    pub dry_run: bool,
+}
+
+/// Print objects named on standard input, one per line, just like `git cat-file --batch` and `git cat-file --batch-check`
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "cat-file")]
+pub struct CatFile {
+    #[argh(option, short = 'r')]
+    /// the directory containing the '.git' repository from which objects should be read.
+    pub repository: Option<PathBuf>,
+
+    #[argh(switch)]
+    /// print a line of the format followed by the contents of each object.
+    pub batch: bool,
+
+    #[argh(switch)]
+    /// print only a line of the format for each object, without its contents.
+    pub batch_check: bool,
+
+    #[argh(option)]
+    /// the format of the line printed for each object, defaulting to "%(objectname) %(objecttype) %(objectsize)".
+    /// "%(rest)" is replaced with everything following the first whitespace of the input line.
+    pub format: Option<String>,
+
+    #[argh(switch)]
+    /// print all objects in the object database ordered by their id instead of reading object names from standard input.
+    pub batch_all_objects: bool,
+
+    #[argh(switch)]
+    /// don't flush the output after each object, which is faster but can't be used to drive the process interactively.
+    pub buffer: bool,
+
+    #[argh(option)]
+    /// the size in megabytes for a cache to speed up accessing entire objects, bypassing object database access when hit.
+    /// If unset, no cache will be used.
+    pub object_cache_size_mb: Option<usize>,
+}
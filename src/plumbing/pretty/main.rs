@@ -251,6 +251,137 @@ pub fn main() -> Result<()> {
             },
         )
         .map(|_| ()),
+        Subcommands::CommitGraphWrite {
+            repository,
+            split,
+            size_multiple,
+            max_commits,
+            changed_paths,
+            tips,
+        } => prepare_and_run(
+            "commit-graph-write",
+            verbose,
+            progress,
+            progress_keep_open,
+            None,
+            move |progress, out, _err| {
+                core::commitgraph::write::write(
+                    repository.unwrap_or_else(|| PathBuf::from(".")),
+                    tips,
+                    progress,
+                    core::commitgraph::write::Context {
+                        split: split.unwrap_or_default(),
+                        size_multiple,
+                        max_commits,
+                        changed_paths,
+                        out,
+                    },
+                )
+            },
+        ),
+        Subcommands::RefsPack {
+            repository,
+            all,
+            no_prune,
+        } => prepare_and_run(
+            "refs-pack",
+            verbose,
+            progress,
+            progress_keep_open,
+            None,
+            move |_progress, out, _err| {
+                core::refs::pack::pack(
+                    repository.unwrap_or_else(|| PathBuf::from(".")),
+                    core::refs::pack::Context {
+                        all,
+                        prune: !no_prune,
+                        out,
+                    },
+                )
+            },
+        ),
+        Subcommands::Fsck {
+            repository,
+            connectivity_only,
+            unreachable,
+        } => prepare_and_run(
+            "fsck",
+            verbose,
+            progress,
+            progress_keep_open,
+            None,
+            move |progress, out, err| {
+                core::odb::fsck::fsck(
+                    repository.unwrap_or_else(|| PathBuf::from(".")),
+                    progress,
+                    core::odb::fsck::Context {
+                        connectivity_only,
+                        unreachable,
+                        thread_limit,
+                        should_interrupt,
+                        out,
+                        err,
+                    },
+                )
+            },
+        ),
+        Subcommands::Prune {
+            repository,
+            expire_after_seconds,
+            dry_run,
+        } => prepare_and_run(
+            "prune",
+            verbose,
+            progress,
+            progress_keep_open,
+            None,
+            move |progress, out, _err| {
+                core::odb::prune::prune(
+                    repository.unwrap_or_else(|| PathBuf::from(".")),
+                    progress,
+                    core::odb::prune::Context {
+                        expire_after: core::odb::prune::expire_after(expire_after_seconds),
+                        dry_run,
+                        out,
+                    },
+                )
+            },
+        ),
+        Subcommands::CatFile {
+            repository,
+            batch,
+            batch_check,
+            batch_all_objects,
+            buffer,
+            object_cache_size_mb,
+        } => {
+            let (mode, format) = match (batch, batch_check) {
+                (Some(format), None) => (core::odb::cat_file::Mode::Batch, format),
+                (None, Some(format)) => (core::odb::cat_file::Mode::BatchCheck, format),
+                _ => anyhow::bail!("Either --batch or --batch-check must be specified"),
+            };
+            prepare_and_run(
+                "cat-file",
+                verbose,
+                progress,
+                progress_keep_open,
+                None,
+                move |_progress, out, _err| {
+                    core::odb::cat_file::batch(
+                        repository.unwrap_or_else(|| PathBuf::from(".")),
+                        BufReader::new(stdin()),
+                        core::odb::cat_file::Context {
+                            mode,
+                            format,
+                            all_objects: batch_all_objects,
+                            buffer,
+                            object_cache_size_in_bytes: object_cache_size_mb.unwrap_or(0) * 1_000_000,
+                            out,
+                        },
+                    )
+                },
+            )
+        }
     }?;
     Ok(())
 }
@@ -258,4 +258,118 @@ pub enum Subcommands {
         #[clap(long, short = 's')]
         statistics: bool,
     },
+    /// Write or update the commit graph of a repository
+    #[clap(setting = AppSettings::DisableVersionFlag)]
+    CommitGraphWrite {
+        #[clap(long, short = 'r')]
+        /// the directory containing the '.git' repository whose commit graph should be written.
+        repository: Option<PathBuf>,
+
+        #[clap(long, possible_values(core::commitgraph::write::SplitStrategy::variants()))]
+        /// the way commits are distributed across commit-graph files.
+        ///
+        /// Possible values are "no", "no-merge", "size-multiple" and "replace". Default is "no", which writes a single file.
+        split: Option<core::commitgraph::write::SplitStrategy>,
+
+        #[clap(long)]
+        /// when splitting by "size-multiple", a file is merged into the new one unless it has more than this many times
+        /// as many commits. Default is 2.
+        size_multiple: Option<u32>,
+
+        #[clap(long)]
+        /// when splitting by "size-multiple", files are merged as long as the new file would have more than this amount of commits.
+        max_commits: Option<u32>,
+
+        #[clap(long)]
+        /// compute changed-path Bloom filters for all commits to speed up path-limited traversals.
+        changed_paths: bool,
+
+        /// The tips from which to start the commit graph iteration, either as fully qualified commit hashes
+        /// or as branch names.
+        ///
+        /// If empty, all commits reachable from all references are added.
+        tips: Vec<OsString>,
+    },
+    /// Pack loose references into the 'packed-refs' file, peeling tags along the way
+    #[clap(setting = AppSettings::DisableVersionFlag)]
+    RefsPack {
+        #[clap(long, short = 'r')]
+        /// the directory containing the '.git' repository whose references should be packed.
+        repository: Option<PathBuf>,
+
+        #[clap(long)]
+        /// pack all references, not only tags and references which are already packed.
+        all: bool,
+
+        #[clap(long)]
+        /// keep the loose references even though they were packed.
+        no_prune: bool,
+    },
+    /// Verify the connectivity and integrity of all objects, reporting missing, corrupt and dangling ones
+    #[clap(setting = AppSettings::DisableVersionFlag)]
+    Fsck {
+        #[clap(long, short = 'r')]
+        /// the directory containing the '.git' repository whose objects should be checked.
+        repository: Option<PathBuf>,
+
+        #[clap(long)]
+        /// only check that all objects reachable from references and reflogs exist and can be decoded, without verifying all packs
+        /// and loose objects.
+        connectivity_only: bool,
+
+        #[clap(long)]
+        /// report all unreachable objects, not only dangling ones which aren't referenced by any other unreachable object.
+        unreachable: bool,
+    },
+    /// Remove loose objects which aren't reachable from any reference, reflog or index
+    #[clap(setting = AppSettings::DisableVersionFlag)]
+    Prune {
+        #[clap(long, short = 'r')]
+        /// the directory containing the '.git' repository whose unreachable objects should be removed.
+        repository: Option<PathBuf>,
+
+        #[clap(long, short = 'e')]
+        /// only remove objects which were last modified longer ago than the given amount of seconds, defaulting to two weeks.
+        ///
+        /// Use 0 to remove all unreachable loose objects.
+        expire_after_seconds: Option<u64>,
+
+        #[clap(long, short = 'n')]
+        /// only print the objects which would be removed.
+        dry_run: bool,
+    },
+    /// Print objects named on standard input, one per line, just like `git cat-file --batch` and `git cat-file --batch-check`
+    #[clap(setting = AppSettings::DisableVersionFlag)]
+    CatFile {
+        #[clap(long, short = 'r')]
+        /// the directory containing the '.git' repository from which objects should be read.
+        repository: Option<PathBuf>,
+
+        #[clap(long, require_equals = true, conflicts_with("batch-check"))]
+        /// print a line of the given format followed by the contents of each object.
+        ///
+        /// The format defaults to "%(objectname) %(objecttype) %(objectsize)", and "%(rest)" is replaced with everything
+        /// following the first whitespace of the input line.
+        batch: Option<Option<String>>,
+
+        #[clap(long, require_equals = true)]
+        /// print only a line of the given format for each object, without its contents.
+        ///
+        /// The format is the same as the one for --batch.
+        batch_check: Option<Option<String>>,
+
+        #[clap(long)]
+        /// print all objects in the object database ordered by their id instead of reading object names from standard input.
+        batch_all_objects: bool,
+
+        #[clap(long)]
+        /// don't flush the output after each object, which is faster but can't be used to drive the process interactively.
+        buffer: bool,
+
+        #[clap(long)]
+        /// The size in megabytes for a cache to speed up accessing entire objects, bypassing object database access when hit.
+        ///
+        /// If unset, no cache will be used.
+        object_cache_size_mb: Option<usize>,
+    },
 }
@@ -516,3 +516,121 @@ title "gix commit-graph-verify"
     )
   )
 )
+title "gix commit-graph-write"
+(when "running 'commit-graph-write'"
+  (small-repo-in-sandbox
+    (with "no existing commit-graph"
+      it "writes a commit-graph file that git considers valid" && {
+        expect_run $SUCCESSFULLY "$exe_plumbing" commit-graph-write
+        expect_run $SUCCESSFULLY git commit-graph verify --no-progress
+      }
+    )
+    (with "the 'no-merge' split strategy"
+      it "writes a commit-graph chain that git considers valid" && {
+        expect_run $SUCCESSFULLY "$exe_plumbing" commit-graph-write --split no-merge
+        expect_run $SUCCESSFULLY git commit-graph verify --no-progress
+      }
+    )
+  )
+)
+
+title "gix refs-pack"
+(when "running 'refs-pack'"
+  (small-repo-in-sandbox
+    (with "all references"
+      it "writes a packed-refs file that git would write as well" && {
+        expect_run $SUCCESSFULLY "$exe_plumbing" refs-pack --all
+        expect_run $WITH_FAILURE test -f .git/refs/tags/annotated
+        cp .git/packed-refs packed-refs-by-gix
+        expect_run $SUCCESSFULLY git pack-refs --all
+        expect_run $SUCCESSFULLY diff packed-refs-by-gix .git/packed-refs
+      }
+    )
+  )
+)
+
+title "gix fsck"
+(when "running 'fsck'"
+  (small-repo-in-sandbox
+    (with "a dangling blob"
+      it "reports it just like git does" && {
+        echo dangling | git hash-object -w --stdin >/dev/null
+        expect_run $SUCCESSFULLY "$exe_plumbing" fsck
+        "$exe_plumbing" fsck > fsck-by-gix
+        git fsck --no-progress > fsck-by-git 2>/dev/null
+        expect_run $SUCCESSFULLY diff fsck-by-gix fsck-by-git
+      }
+    )
+  )
+)
+
+title "gix prune"
+(when "running 'prune'"
+  (small-repo-in-sandbox
+    (with "a dangling blob and no expiry"
+      it "removes it and keeps all reachable objects" && {
+        blob=$(echo dangling | git hash-object -w --stdin)
+        expect_run $SUCCESSFULLY "$exe_plumbing" prune --expire-after-seconds 0
+        expect_run $WITH_FAILURE git cat-file -e $blob
+        expect_run $SUCCESSFULLY git fsck --no-dangling
+      }
+    )
+    (with "a staged blob and a commit only reachable from a detached linked worktree"
+      it "keeps both of them" && {
+        staged=$(echo staged | git hash-object -w --stdin)
+        git update-index --add --cacheinfo 100644,$staged,staged-only
+        git worktree add --detach detached-worktree >/dev/null 2>&1
+        commit=$(cd detached-worktree && git commit --allow-empty -qm "detached only" && git rev-parse HEAD)
+        expect_run $SUCCESSFULLY "$exe_plumbing" prune --expire-after-seconds 0
+        expect_run $SUCCESSFULLY git cat-file -e $staged
+        expect_run $SUCCESSFULLY git cat-file -e $commit
+      }
+    )
+    (with "an old blob only referenced by a recent tree and the default expiry"
+      it "keeps both of them but removes old dangling objects" && {
+        kept=$(echo kept | git hash-object -w --stdin)
+        dangling=$(echo old-dangling | git hash-object -w --stdin)
+        for blob in $kept $dangling; do
+          touch -d "3 weeks ago" .git/objects/${blob:0:2}/${blob:2}
+        done
+        tree=$(printf "100644 blob $kept\tfile\n" | git mktree)
+        expect_run $SUCCESSFULLY "$exe_plumbing" prune
+        expect_run $SUCCESSFULLY git cat-file -e $tree
+        expect_run $SUCCESSFULLY git cat-file -e $kept
+        expect_run $WITH_FAILURE git cat-file -e $dangling
+      }
+    )
+  )
+)
+
+title "gix cat-file"
+(when "running 'cat-file'"
+  (small-repo-in-sandbox
+    (with "object names and references on stdin"
+      it "prints them just like git does" && {
+        names="main
+$(git rev-parse HEAD^{tree})
+$(git rev-parse --short=7 HEAD)
+unannotated
+does-not-exist"
+        for mode in batch batch-check; do
+          echo "$names" | "$exe_plumbing" cat-file --$mode > cat-file-by-gix
+          echo "$names" | git cat-file --$mode > cat-file-by-git
+          expect_run $SUCCESSFULLY diff cat-file-by-gix cat-file-by-git
+        done
+      }
+    )
+    (with "a revision expression"
+      it "fails as these aren't supported yet" && {
+        expect_run $WITH_FAILURE "$exe_plumbing" cat-file --batch-check <<<"HEAD~1"
+      }
+    )
+    (with "--batch-all-objects"
+      it "prints all objects ordered by id just like git does" && {
+        "$exe_plumbing" cat-file --batch --batch-all-objects > cat-file-by-gix
+        git cat-file --batch --batch-all-objects > cat-file-by-git
+        expect_run $SUCCESSFULLY cmp cat-file-by-gix cat-file-by-git
+      }
+    )
+  )
+)